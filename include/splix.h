@@ -0,0 +1,58 @@
+/* C API for splix, the lightning-fast image splitter.
+ * Link against the splix cdylib built by `cargo build --release`. */
+
+#ifndef SPLIX_H
+#define SPLIX_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The image was split and every tile was passed to the callback. */
+#define SPLIX_OK 0
+/* A pointer was null or the grid had no rows or columns. */
+#define SPLIX_ERROR_ARGUMENT 1
+/* The buffer isn't an image in a supported format. */
+#define SPLIX_ERROR_DECODE 2
+/* A tile couldn't be encoded. */
+#define SPLIX_ERROR_ENCODE 3
+/* The callback asked to stop before every tile was passed to it. */
+#define SPLIX_STOPPED 4
+
+typedef struct SplixOptions {
+    /* Number of equal rows to split the image into. */
+    uint32_t rows;
+    /* Number of equal columns to split the image into. */
+    uint32_t cols;
+} SplixOptions;
+
+typedef struct SplixTile {
+    uint32_t row;
+    uint32_t col;
+    uint32_t x;
+    uint32_t y;
+    uint32_t width;
+    uint32_t height;
+    /* The tile, encoded in the same format as the source image.
+     * Only valid until the callback returns. */
+    const uint8_t *data;
+    size_t len;
+} SplixTile;
+
+/* Called with each tile, left to right, top to bottom.
+ * Returning anything other than 0 stops splitting. */
+typedef int (*SplixTileCallback)(void *user_data, const SplixTile *tile);
+
+/* Splits an encoded image into a grid of tiles, passing each one to a callback.
+ * Returns SPLIX_OK, or one of the SPLIX_ERROR_* codes or SPLIX_STOPPED. */
+int splix_split(const uint8_t *buffer, size_t len, const SplixOptions *options,
+                SplixTileCallback callback, void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SPLIX_H */
@@ -0,0 +1,30 @@
+# Messages splix prints, in German.
+
+split-summary = splix: { $processed } von { $scanned } { $scanned ->
+        [one] Bild
+       *[other] Bildern
+    } in { $tiles } { $tiles ->
+        [one] Kachel
+       *[other] Kacheln
+    } geteilt, in { $secs } s
+split-bytes = { $read } gelesen, { $written } geschrieben
+split-stages = Dekodieren { $decode } s, Teilen { $split } s, Kodieren { $encode } s, Schreiben { $write } s, über alle Threads summiert
+
+skipped-files = splix: { $count } { $count ->
+        [one] Datei übersprungen, die nicht geteilt werden konnte:
+       *[other] Dateien übersprungen, die nicht geteilt werden konnten:
+    }
+skipped-small = splix: { $path } wird übersprungen, da es kleiner als das Raster ist
+scaled-up-small = splix: { $path } auf { $width }x{ $height } vergrößert, da es kleiner als das Raster ist
+padded-small = splix: { $path } auf { $width }x{ $height } aufgefüllt, da es kleiner als das Raster ist
+
+interrupting = splix: Unterbrochen. Die Kacheln, die gerade geschrieben werden, werden fertiggestellt. Erneut Strg-C drücken, um sofort zu beenden
+interrupted = splix: Unterbrochen. Die vor dem Abbruch geschriebenen Kacheln wurden behalten
+stopped-early = splix: Wegen eines Fehlers vorzeitig beendet
+resume-hint = splix: resume: Denselben Befehl erneut ausführen, um ab der zuletzt geschriebenen Kachel fortzufahren
+jobs-failed = splix: jobfile: { $failed } von { $count } { $count ->
+        [one] Auftrag
+       *[other] Aufträgen
+    } fehlgeschlagen
+
+help = Hinweis
@@ -0,0 +1,30 @@
+# Messages splix prints, in English. Every other locale falls back to these for any message it leaves out.
+
+split-summary = splix: Split { $processed } of { $scanned } { $scanned ->
+        [one] image
+       *[other] images
+    } into { $tiles } { $tiles ->
+        [one] tile
+       *[other] tiles
+    } in { $secs }s
+split-bytes = Read { $read }, wrote { $written }
+split-stages = Decoding { $decode }s, splitting { $split }s, encoding { $encode }s, writing { $write }s, summed over threads
+
+skipped-files = splix: Skipped { $count } { $count ->
+        [one] file
+       *[other] files
+    } that couldn't be split:
+skipped-small = splix: Skipping { $path }, which is smaller than the grid
+scaled-up-small = splix: Scaled { $path } up to { $width }x{ $height }, since it's smaller than the grid
+padded-small = splix: Padded { $path } to { $width }x{ $height }, since it's smaller than the grid
+
+interrupting = splix: Interrupted. Finishing the tiles being written, press Ctrl-C again to quit at once
+interrupted = splix: Interrupted. Kept the tiles written before stopping
+stopped-early = splix: Stopped early because of an error
+resume-hint = splix: resume: Run the same command again to carry on from the last tile written
+jobs-failed = splix: jobfile: { $failed } of { $count } { $count ->
+        [one] job
+       *[other] jobs
+    } failed
+
+help = help
@@ -0,0 +1,54 @@
+use splix::grid::Cell;
+
+/// Moves every cut between cells to the nearest multiple of a number of pixels, such as the 8 or 16 pixel blocks
+/// of video and JPEG encoders or the texture sizes of a GPU, so every tile starts on the block grid and is a whole
+/// number of blocks, apart from the remainder at the right and bottom edges, which never move.
+/// Bands that round away to nothing are dropped.
+///
+/// # Arguments
+///
+/// * `cells` - Cells to align.
+/// * `(width, height)` - Size of the image.
+/// * `(align_x, align_y)` - Number of pixels each vertical and horizontal cut must be a multiple of.
+/// * `(origin_x, origin_y)` - Where the grid starts, less than the alignment, such as the width of the partial block
+///   a flipped JPEG starts with. Cuts nearer the image's edge than the grid round to the edge.
+///
+/// # Returns
+///
+/// The cells with their cuts moved.
+pub fn align(
+    cells: &[Cell],
+    (width, height): (u32, u32),
+    (align_x, align_y): (u32, u32),
+    (origin_x, origin_y): (u32, u32),
+) -> Vec<Cell> {
+    let snap = |cut: u32, length: u32, align: u32, origin: u32| {
+        if cut >= length {
+            length
+        } else if cut <= origin / 2 {
+            0
+        } else {
+            // Counted from one block before the origin, so cuts before it don't underflow.
+            ((cut + align - origin + align / 2) / align * align + origin)
+                .saturating_sub(align)
+                .min(length)
+        }
+    };
+
+    cells
+        .iter()
+        .filter_map(|cell| {
+            let x = snap(cell.x, width, align_x, origin_x);
+            let y = snap(cell.y, height, align_y, origin_y);
+            let right = snap(cell.x + cell.width, width, align_x, origin_x);
+            let bottom = snap(cell.y + cell.height, height, align_y, origin_y);
+            (right > x && bottom > y).then_some(Cell {
+                x,
+                y,
+                width: right - x,
+                height: bottom - y,
+                ..*cell
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,314 @@
+use crate::aseprite;
+use crate::dicom;
+use crate::rng::Rng;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use image::ImageFormat;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+
+/// Extensions of the archives images can be read from, lowercase: zip and comic book archives, and tarballs.
+const ZIP_EXTS: [&str; 2] = [".zip", ".cbz"];
+const TAR_EXTS: [&str; 2] = [".tar", ".cbt"];
+const TAR_GZ_EXTS: [&str; 2] = [".tar.gz", ".tgz"];
+
+/// Signatures and fields of the zip format, from PKWARE's APPNOTE.TXT.
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP_END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP_STORED: u16 = 0;
+const ZIP_DEFLATE: u16 = 8;
+const ZIP_ENCRYPTED: u16 = 1;
+
+/// The kind of an archive.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Finds the kind of an archive from its extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the archive.
+    ///
+    /// # Returns
+    ///
+    /// The kind, or `None` if the path isn't an archive splix can read or write.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        let ends_with = |exts: &[&str]| exts.iter().any(|ext| name.ends_with(ext));
+        if ends_with(&ZIP_EXTS) {
+            Some(ArchiveKind::Zip)
+        } else if ends_with(&TAR_EXTS) {
+            Some(ArchiveKind::Tar)
+        } else if ends_with(&TAR_GZ_EXTS) {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Images extracted from an archive to a temporary directory, which is removed when this is dropped.
+pub struct Extracted {
+    dir: PathBuf,
+    archive: PathBuf,
+}
+
+impl Extracted {
+    /// The directory the images were extracted to, keeping their paths inside the archive.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The path of an extracted image inside the archive, such as `comic.cbz/01.png`,
+    /// which names it in messages and the manifest, since the extracted copy is removed once the run ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the extracted image.
+    pub fn source_path(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.dir) {
+            Ok(entry) => self.archive.join(entry),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+/// Finds the archive a path names an entry of, such as `comic.cbz` for `comic.cbz/01.png`.
+///
+/// # Arguments
+///
+/// * `path` - Path that may lie inside an archive.
+///
+/// # Returns
+///
+/// The path of the archive, or `None` if no parent of the path is an archive.
+pub fn archive_of(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .skip(1)
+        .find(|parent| ArchiveKind::from_path(parent).is_some() && parent.is_file())
+}
+
+impl Drop for Extracted {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// How many names to try for the temporary directory before giving up.
+const TEMP_DIR_ATTEMPTS: usize = 16;
+
+/// Extracts the images in an archive to a temporary directory, so they can be split like a directory of images.
+/// Other files are left in the archive, as are entries whose paths would leave the directory.
+/// An archive with two images of the same name is refused, since one would replace the other.
+///
+/// # Arguments
+///
+/// * `path` - Path of the archive.
+/// * `kind` - Kind of the archive.
+///
+/// # Returns
+///
+/// The extracted images, or an error if the archive couldn't be read.
+pub fn extract(path: &Path, kind: ArchiveKind) -> io::Result<Extracted> {
+    let extracted = Extracted {
+        dir: create_temp_dir(path)?,
+        archive: path.to_path_buf(),
+    };
+
+    let file = BufReader::new(File::open(path)?);
+    match kind {
+        ArchiveKind::Zip => extract_zip(file, &extracted.dir)?,
+        ArchiveKind::Tar => extract_tar(file, &extracted.dir)?,
+        ArchiveKind::TarGz => extract_tar(GzDecoder::new(file), &extracted.dir)?,
+    }
+
+    Ok(extracted)
+}
+
+/// Creates a new, empty temporary directory for an archive's images, named after the archive and a random suffix.
+/// A directory that already exists is never reused, since another run or user may own it.
+///
+/// # Arguments
+///
+/// * `path` - Path of the archive.
+fn create_temp_dir(path: &Path) -> io::Result<PathBuf> {
+    let mut rng = Rng::new(Rng::random_seed() ^ u64::from(process::id()));
+    for _ in 0..TEMP_DIR_ATTEMPTS {
+        let dir = env::temp_dir().join(format!(
+            "splix-{}-{:016x}-{}",
+            process::id(),
+            rng.next_u64(),
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        match fs::create_dir(&dir) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|_| dir),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "Failed to find an unused temporary directory",
+    ))
+}
+
+/// Whether an entry of an archive is an image splix can split.
+fn is_image(name: &Path) -> bool {
+    ImageFormat::from_path(name).is_ok() || aseprite::is_aseprite(name) || dicom::is_dicom(name)
+}
+
+/// The path an entry is extracted to, or `None` if its path is absolute or climbs out of the directory.
+fn entry_path(dir: &Path, name: &Path) -> Option<PathBuf> {
+    let mut path = dir.to_path_buf();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(path).filter(|path| path != dir)
+}
+
+/// Writes an entry's contents to its file, creating its directory.
+/// An entry whose file was already written, by an earlier entry of the same name, is an error.
+///
+/// # Arguments
+///
+/// * `path` - Path the entry is extracted to.
+/// * `name` - Path of the entry inside the archive.
+/// * `contents` - The entry's contents.
+fn write_entry(path: &Path, name: &Path, contents: &mut impl Read) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create_new(path).map_err(|err| match err.kind() {
+        io::ErrorKind::AlreadyExists => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} appears more than once in the archive", name.display()),
+        ),
+        _ => err,
+    })?;
+    io::copy(contents, &mut file).map(|_| ())
+}
+
+/// Extracts the images in a tar archive.
+fn extract_tar(reader: impl Read, dir: &Path) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.into_owned();
+        if !is_image(&name) {
+            continue;
+        }
+        if let Some(path) = entry_path(dir, &name) {
+            write_entry(&path, &name, &mut entry)?;
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the images in a zip archive, which may be stored or compressed with deflate.
+/// Zip64 archives, larger than 4 GiB or with more than 65,535 entries, aren't supported.
+fn extract_zip(mut reader: impl Read + Seek, dir: &Path) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+    // The end of central directory record is the last 22 bytes, unless the archive has a comment of up to 64 KiB.
+    let len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = len.min(22 + u64::from(u16::MAX));
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0; tail_len as usize];
+    reader.read_exact(&mut tail)?;
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&offset| u32_at(&tail, offset) == ZIP_END_OF_DIRECTORY)
+        .ok_or_else(|| invalid("Not a zip archive"))?;
+    let entries = u16_at(&tail, end + 10);
+    let directory_len = u32_at(&tail, end + 12);
+    let directory_offset = u32_at(&tail, end + 16);
+    if entries == u16::MAX || directory_offset == u32::MAX {
+        return Err(invalid("Zip64 archives aren't supported"));
+    }
+
+    let mut directory = vec![0; directory_len as usize];
+    reader.seek(SeekFrom::Start(u64::from(directory_offset)))?;
+    reader.read_exact(&mut directory)?;
+
+    let mut offset = 0;
+    for _ in 0..entries {
+        if directory.len() < offset + 46 || u32_at(&directory, offset) != ZIP_CENTRAL_HEADER {
+            return Err(invalid("The zip archive's central directory is corrupt"));
+        }
+        let flags = u16_at(&directory, offset + 8);
+        let method = u16_at(&directory, offset + 10);
+        let compressed_len = u32_at(&directory, offset + 20);
+        let name_len = usize::from(u16_at(&directory, offset + 28));
+        let extra_len = usize::from(u16_at(&directory, offset + 30));
+        let comment_len = usize::from(u16_at(&directory, offset + 32));
+        let local_offset = u32_at(&directory, offset + 42);
+        let name = directory
+            .get(offset + 46..offset + 46 + name_len)
+            .ok_or_else(|| invalid("The zip archive's central directory is corrupt"))?;
+        let name = PathBuf::from(String::from_utf8_lossy(name).into_owned());
+        offset += 46 + name_len + extra_len + comment_len;
+
+        if !is_image(&name) {
+            continue;
+        }
+        let Some(path) = entry_path(dir, &name) else {
+            continue;
+        };
+        if flags & ZIP_ENCRYPTED != 0 {
+            return Err(invalid(&format!(
+                "{} is encrypted, which isn't supported",
+                name.display()
+            )));
+        }
+        if compressed_len == u32::MAX || local_offset == u32::MAX {
+            return Err(invalid("Zip64 archives aren't supported"));
+        }
+
+        // The local header's name and extra field may differ in length from the central directory's.
+        let mut local = [0; 30];
+        reader.seek(SeekFrom::Start(u64::from(local_offset)))?;
+        reader.read_exact(&mut local)?;
+        if u32_at(&local, 0) != ZIP_LOCAL_HEADER {
+            return Err(invalid("The zip archive's local headers are corrupt"));
+        }
+        let skip = i64::from(u16_at(&local, 26)) + i64::from(u16_at(&local, 28));
+        reader.seek(SeekFrom::Current(skip))?;
+
+        let mut data = (&mut reader).take(u64::from(compressed_len));
+        match method {
+            ZIP_STORED => write_entry(&path, &name, &mut data)?,
+            ZIP_DEFLATE => write_entry(&path, &name, &mut DeflateDecoder::new(data))?,
+            _ => {
+                return Err(invalid(&format!(
+                    "{} is compressed with method {}, which isn't supported",
+                    name.display(),
+                    method
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
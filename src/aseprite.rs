@@ -0,0 +1,573 @@
+use crate::reduce;
+use flate2::read::ZlibDecoder;
+use image::error::{DecodingError, ImageFormatHint};
+use image::{ImageError, ImageResult, Rgba, RgbaImage};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Magic number of an Aseprite file's header.
+const FILE_MAGIC: u16 = 0xA5E0;
+
+/// Magic number of each frame's header.
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+const OLD_PALETTE_CHUNK: u16 = 0x0004;
+const OLD_PALETTE_6BIT_CHUNK: u16 = 0x0011;
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const PALETTE_CHUNK: u16 = 0x2019;
+
+/// Layer flag for layers shown in the editor.
+const VISIBLE: u16 = 1;
+
+/// Layer flag for the background layer, which is opaque.
+const BACKGROUND: u16 = 8;
+
+/// Header flag set when each layer's opacity is meaningful.
+const LAYER_OPACITY_VALID: u32 = 1;
+
+/// Colors of a palette that pixels can use, since indexed pixels are a byte each.
+const PALETTE_SIZE: usize = 256;
+
+/// Whether a file is an Aseprite sprite, going by its extension.
+pub fn is_aseprite(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("aseprite") || ext.eq_ignore_ascii_case("ase"))
+}
+
+/// Reads the size of an Aseprite sprite from its header.
+///
+/// # Arguments
+///
+/// * `path` - Path of the sprite.
+///
+/// # Returns
+///
+/// The width and height of the sprite, or the error that kept it from being read.
+pub fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    let mut header = [0; 12];
+    File::open(path)?.read_exact(&mut header)?;
+    let mut reader = Reader::new(&header);
+    reader.skip(4)?;
+    if reader.u16()? != FILE_MAGIC {
+        return Err(error("Not an Aseprite file"));
+    }
+    reader.skip(2)?;
+
+    Ok((reader.u16()?.into(), reader.u16()?.into()))
+}
+
+/// Decodes every frame of an Aseprite sprite, with its visible layers flattened as Aseprite shows them.
+/// Hue, saturation, color, and luminosity layers are composited as normal layers, and tilemap layers are left out.
+///
+/// # Arguments
+///
+/// * `path` - Path of the sprite.
+///
+/// # Returns
+///
+/// The frames in order, or the error that kept the sprite from being decoded.
+pub fn decode(path: &Path) -> ImageResult<Vec<RgbaImage>> {
+    let bytes = fs::read(path)?;
+    let mut reader = Reader::new(&bytes);
+
+    reader.skip(4)?;
+    if reader.u16()? != FILE_MAGIC {
+        return Err(error("Not an Aseprite file"));
+    }
+    let frame_count = reader.u16()?;
+    let width = u32::from(reader.u16()?);
+    let height = u32::from(reader.u16()?);
+    let depth = match reader.u16()? {
+        32 => Depth::Rgba,
+        16 => Depth::Grayscale,
+        8 => Depth::Indexed,
+        depth => return Err(error(&format!("Unsupported color depth {}", depth))),
+    };
+    let layer_opacity = reader.u32()? & LAYER_OPACITY_VALID != 0;
+    reader.skip(2 + 4 + 4)?;
+    let transparent_index = reader.u8()?;
+    reader.skip(128 - 29)?;
+    // Every frame is flattened onto a canvas of the sprite's size.
+    let canvas_size = u64::from(width) * u64::from(height) * 4 * u64::from(frame_count);
+    if !reduce::fits_in_memory(canvas_size) {
+        return Err(error("The sprite is too large to decode"));
+    }
+
+    let mut sprite = Sprite {
+        width,
+        height,
+        depth,
+        transparent_index,
+        layer_opacity,
+        layers: Vec::new(),
+        palette: vec![[0, 0, 0, 255]; PALETTE_SIZE],
+    };
+    let mut frames: Vec<Vec<Cel>> = Vec::with_capacity(frame_count.into());
+
+    for _ in 0..frame_count {
+        let frame_size = reader.u32()? as usize;
+        let frame_end = reader.pos - 4 + frame_size;
+        if reader.u16()? != FRAME_MAGIC {
+            return Err(error("Frame header is corrupt"));
+        }
+        let old_chunks = reader.u16()?;
+        reader.skip(2 + 2)?;
+        let chunks = match reader.u32()? {
+            0 => u32::from(old_chunks),
+            chunks => chunks,
+        };
+
+        let mut cels = Vec::new();
+        for _ in 0..chunks {
+            let chunk_size = reader.u32()? as usize;
+            let chunk_type = reader.u16()?;
+            let chunk = Reader::new(reader.take(chunk_size.saturating_sub(6))?);
+            match chunk_type {
+                LAYER_CHUNK => sprite.read_layer(chunk)?,
+                CEL_CHUNK => {
+                    if let Some(cel) = sprite.read_cel(chunk, &frames)? {
+                        cels.push(cel);
+                    }
+                }
+                PALETTE_CHUNK => sprite.read_palette(chunk)?,
+                OLD_PALETTE_CHUNK => sprite.read_old_palette(chunk, 1)?,
+                OLD_PALETTE_6BIT_CHUNK => sprite.read_old_palette(chunk, 4)?,
+                _ => {}
+            }
+        }
+
+        reader.pos = frame_end.max(reader.pos);
+        frames.push(cels);
+    }
+
+    let visible = sprite.visible_layers();
+    Ok(frames
+        .iter()
+        .map(|cels| sprite.flatten(width, height, cels, &visible))
+        .collect())
+}
+
+/// How a sprite stores its pixels.
+#[derive(Clone, Copy)]
+enum Depth {
+    Rgba,
+    Grayscale,
+    Indexed,
+}
+
+/// A layer of a sprite.
+struct Layer {
+    flags: u16,
+    /// How deep the layer is nested in groups, from 0 for top-level layers.
+    child_level: u16,
+    blend_mode: u16,
+    opacity: u8,
+    /// Whether the layer holds pixels, rather than being a group or tilemap.
+    is_image: bool,
+}
+
+/// The pixels of a layer in a frame.
+#[derive(Clone)]
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    opacity: u8,
+    /// Offset of the cel from its layer's place in the stack.
+    z_index: i16,
+    /// The pixels, before the palette is applied.
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+/// Everything about a sprite needed to flatten its frames.
+struct Sprite {
+    width: u32,
+    height: u32,
+    depth: Depth,
+    transparent_index: u8,
+    layer_opacity: bool,
+    layers: Vec<Layer>,
+    palette: Vec<[u8; 4]>,
+}
+
+impl Sprite {
+    fn read_layer(&mut self, mut chunk: Reader) -> ImageResult<()> {
+        let flags = chunk.u16()?;
+        let layer_type = chunk.u16()?;
+        let child_level = chunk.u16()?;
+        chunk.skip(4)?;
+        let blend_mode = chunk.u16()?;
+        let opacity = chunk.u8()?;
+
+        self.layers.push(Layer {
+            flags,
+            child_level,
+            blend_mode,
+            opacity,
+            is_image: layer_type == 0,
+        });
+        Ok(())
+    }
+
+    /// Reads a cel, resolving linked cels to the cel they share pixels with.
+    ///
+    /// # Returns
+    ///
+    /// The cel, or `None` for cels of tilemaps.
+    fn read_cel(&self, mut chunk: Reader, frames: &[Vec<Cel>]) -> ImageResult<Option<Cel>> {
+        let layer = usize::from(chunk.u16()?);
+        let x = i32::from(chunk.i16()?);
+        let y = i32::from(chunk.i16()?);
+        let opacity = chunk.u8()?;
+        let cel_type = chunk.u16()?;
+        let z_index = chunk.i16()?;
+        chunk.skip(5)?;
+
+        let (pixels, width, height) = match cel_type {
+            0 | 2 => {
+                let width = u32::from(chunk.u16()?);
+                let height = u32::from(chunk.u16()?);
+                // Sizes are checked before anything is allocated for them, so a corrupt file can't claim gigabytes.
+                if width > self.width || height > self.height {
+                    return Err(error("Cel is larger than the sprite"));
+                }
+                let len = width as usize * height as usize * self.bytes_per_pixel();
+                if cel_type == 0 && len > chunk.remaining() {
+                    return Err(error("Cel is truncated"));
+                }
+                let pixels = if cel_type == 0 {
+                    chunk.take(len)?.to_vec()
+                } else {
+                    let mut pixels = Vec::with_capacity(len);
+                    ZlibDecoder::new(chunk.rest())
+                        .take(len as u64)
+                        .read_to_end(&mut pixels)?;
+                    pixels
+                };
+                if pixels.len() < len {
+                    return Err(error("Cel is truncated"));
+                }
+                (pixels, width, height)
+            }
+            1 => {
+                let frame = usize::from(chunk.u16()?);
+                let linked = frames
+                    .get(frame)
+                    .and_then(|cels| cels.iter().find(|cel| cel.layer == layer))
+                    .ok_or_else(|| error("Linked cel refers to a missing cel"))?;
+                return Ok(Some(Cel {
+                    x,
+                    y,
+                    opacity,
+                    z_index,
+                    ..linked.clone()
+                }));
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Cel {
+            layer,
+            x,
+            y,
+            opacity,
+            z_index,
+            pixels,
+            width,
+            height,
+        }))
+    }
+
+    fn read_palette(&mut self, mut chunk: Reader) -> ImageResult<()> {
+        // The palette's new size isn't read, since the palette is always kept at 256 colors.
+        chunk.skip(4)?;
+        let first = chunk.u32()? as usize;
+        let last = chunk.u32()? as usize;
+        chunk.skip(8)?;
+
+        // Colors past the first 256 can't be used by pixels, so they're read but not kept.
+        for index in first..=last {
+            let has_name = chunk.u16()? & 1 != 0;
+            let color = [chunk.u8()?, chunk.u8()?, chunk.u8()?, chunk.u8()?];
+            if has_name {
+                let len = usize::from(chunk.u16()?);
+                chunk.skip(len)?;
+            }
+            if let Some(entry) = self.palette.get_mut(index) {
+                *entry = color;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a palette chunk from older versions of Aseprite.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - What to multiply each value by, 4 for palettes of 6-bit values.
+    fn read_old_palette(&mut self, mut chunk: Reader, scale: u8) -> ImageResult<()> {
+        let packets = chunk.u16()?;
+        let mut index = 0;
+        for _ in 0..packets {
+            index += usize::from(chunk.u8()?);
+            let count = match chunk.u8()? {
+                0 => 256,
+                count => usize::from(count),
+            };
+            for _ in 0..count {
+                let color = [
+                    chunk.u8()?.saturating_mul(scale),
+                    chunk.u8()?.saturating_mul(scale),
+                    chunk.u8()?.saturating_mul(scale),
+                    255,
+                ];
+                if let Some(entry) = self.palette.get_mut(index) {
+                    *entry = color;
+                }
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        match self.depth {
+            Depth::Rgba => 4,
+            Depth::Grayscale => 2,
+            Depth::Indexed => 1,
+        }
+    }
+
+    /// Which layers are shown, which takes the layer and every group it's in being visible.
+    fn visible_layers(&self) -> Vec<bool> {
+        let mut visible = Vec::with_capacity(self.layers.len());
+        // Whether each group the current layer is nested in is visible, outermost first.
+        let mut groups: Vec<bool> = Vec::new();
+
+        for layer in &self.layers {
+            groups.truncate(layer.child_level.into());
+            let shown = layer.flags & VISIBLE != 0 && groups.iter().all(|&group| group);
+            visible.push(shown && layer.is_image);
+            groups.push(shown);
+        }
+        visible
+    }
+
+    /// Composites the cels of a frame bottom to top onto a transparent canvas.
+    fn flatten(&self, width: u32, height: u32, cels: &[Cel], visible: &[bool]) -> RgbaImage {
+        let mut canvas = RgbaImage::new(width, height);
+
+        let mut cels: Vec<&Cel> = cels
+            .iter()
+            .filter(|cel| visible.get(cel.layer).copied().unwrap_or(false))
+            .collect();
+        cels.sort_by_key(|cel| (cel.layer as i64 + i64::from(cel.z_index), cel.z_index));
+
+        for cel in cels {
+            let layer = &self.layers[cel.layer];
+            let mut opacity = f32::from(cel.opacity) / 255.0;
+            if self.layer_opacity && layer.flags & BACKGROUND == 0 {
+                opacity *= f32::from(layer.opacity) / 255.0;
+            }
+
+            for cy in 0..cel.height {
+                let y = cel.y + cy as i32;
+                if y < 0 || y >= height as i32 {
+                    continue;
+                }
+                for cx in 0..cel.width {
+                    let x = cel.x + cx as i32;
+                    if x < 0 || x >= width as i32 {
+                        continue;
+                    }
+                    let offset = (cy * cel.width + cx) as usize * self.bytes_per_pixel();
+                    let source = self.color(&cel.pixels[offset..], layer.flags & BACKGROUND != 0);
+                    let backdrop = canvas.get_pixel_mut(x as u32, y as u32);
+                    *backdrop = blend(*backdrop, source, layer.blend_mode, opacity);
+                }
+            }
+        }
+
+        canvas
+    }
+
+    /// The color of a pixel of a cel.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel` - The pixel's bytes, followed by the rest of the cel.
+    /// * `background` - Whether the pixel is on the background layer, where the transparent index is opaque.
+    fn color(&self, pixel: &[u8], background: bool) -> [u8; 4] {
+        match self.depth {
+            Depth::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            Depth::Grayscale => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            Depth::Indexed if pixel[0] == self.transparent_index && !background => [0; 4],
+            Depth::Indexed => self.palette[usize::from(pixel[0])],
+        }
+    }
+}
+
+/// Blends a pixel onto the canvas with one of Aseprite's blend modes, the same way as the W3C compositing spec.
+///
+/// # Arguments
+///
+/// * `backdrop` - Pixel of the canvas.
+/// * `source` - Pixel of the layer.
+/// * `mode` - Blend mode of the layer.
+/// * `opacity` - Opacity of the cel and layer, from 0 to 1.
+fn blend(backdrop: Rgba<u8>, source: [u8; 4], mode: u16, opacity: f32) -> Rgba<u8> {
+    let source_alpha = f32::from(source[3]) / 255.0 * opacity;
+    if source_alpha <= 0.0 {
+        return backdrop;
+    }
+    let backdrop_alpha = f32::from(backdrop[3]) / 255.0;
+    let alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+
+    let mut blended = [0; 4];
+    for channel in 0..3 {
+        let cb = f32::from(backdrop[channel]) / 255.0;
+        let cs = f32::from(source[channel]) / 255.0;
+        let mixed = (1.0 - backdrop_alpha) * cs + backdrop_alpha * blend_channel(mode, cb, cs);
+        let value = (source_alpha * mixed + backdrop_alpha * cb * (1.0 - source_alpha)) / alpha;
+        blended[channel] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    blended[3] = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    Rgba(blended)
+}
+
+/// Blends one channel with a separable blend mode.
+fn blend_channel(mode: u16, cb: f32, cs: f32) -> f32 {
+    let hard_light = |cb: f32, cs: f32| {
+        if cs <= 0.5 {
+            cb * 2.0 * cs
+        } else {
+            let cs = 2.0 * cs - 1.0;
+            cb + cs - cb * cs
+        }
+    };
+
+    match mode {
+        // Multiply.
+        1 => cb * cs,
+        // Screen.
+        2 => cb + cs - cb * cs,
+        // Overlay.
+        3 => hard_light(cs, cb),
+        // Darken.
+        4 => cb.min(cs),
+        // Lighten.
+        5 => cb.max(cs),
+        // Color dodge.
+        6 => match (cb, cs) {
+            (cb, _) if cb <= 0.0 => 0.0,
+            (_, cs) if cs >= 1.0 => 1.0,
+            (cb, cs) => (cb / (1.0 - cs)).min(1.0),
+        },
+        // Color burn.
+        7 => match (cb, cs) {
+            (cb, _) if cb >= 1.0 => 1.0,
+            (_, cs) if cs <= 0.0 => 0.0,
+            (cb, cs) => 1.0 - ((1.0 - cb) / cs).min(1.0),
+        },
+        // Hard light.
+        8 => hard_light(cb, cs),
+        // Soft light.
+        9 => {
+            if cs <= 0.5 {
+                cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+            } else {
+                let d = if cb <= 0.25 {
+                    ((16.0 * cb - 12.0) * cb + 4.0) * cb
+                } else {
+                    cb.sqrt()
+                };
+                cb + (2.0 * cs - 1.0) * (d - cb)
+            }
+        }
+        // Difference.
+        10 => (cb - cs).abs(),
+        // Exclusion.
+        11 => cb + cs - 2.0 * cb * cs,
+        // Addition.
+        16 => (cb + cs).min(1.0),
+        // Subtract.
+        17 => (cb - cs).max(0.0),
+        // Divide.
+        18 => match cs {
+            cs if cs <= 0.0 => {
+                if cb > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            cs => (cb / cs).min(1.0),
+        },
+        // Normal, and the non-separable modes, which are shown as normal.
+        _ => cs,
+    }
+}
+
+/// Reads little-endian values from a byte buffer.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> ImageResult<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or_else(|| ImageError::IoError(io::ErrorKind::UnexpectedEof.into()))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = self.bytes.get(self.pos..).unwrap_or_default();
+        self.pos = self.bytes.len();
+        rest
+    }
+
+    fn skip(&mut self, len: usize) -> ImageResult<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> ImageResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> ImageResult<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> ImageResult<i16> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> ImageResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+/// Builds an error for a sprite that can't be decoded.
+fn error(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("Aseprite".to_string()),
+        message.to_string(),
+    ))
+}
@@ -0,0 +1,88 @@
+use crate::output::{Output, TileAttrs};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::Semaphore;
+
+/// Writes tiles in the background on an async runtime,
+/// so the threads that decode and encode images never wait on slow disks or networks.
+pub struct AsyncWrites {
+    runtime: Runtime,
+    jobs: u32,
+    permits: Arc<Semaphore>,
+    errors: Arc<Mutex<Vec<io::Error>>>,
+}
+
+impl AsyncWrites {
+    /// # Arguments
+    ///
+    /// * `jobs` - Maximum number of writes that may be in flight at once.
+    ///   Encoding waits when this many tiles are waiting to be written, bounding memory use.
+    pub fn new(jobs: usize) -> io::Result<Self> {
+        let jobs = u32::try_from(jobs).unwrap_or(u32::MAX);
+
+        Ok(AsyncWrites {
+            runtime: Builder::new_multi_thread()
+                .max_blocking_threads(jobs as usize)
+                .thread_name("splix-io")
+                .build()?,
+            jobs,
+            permits: Arc::new(Semaphore::new(jobs as usize)),
+            errors: Arc::default(),
+        })
+    }
+
+    /// Starts writing a tile, waiting only if too many writes are already in flight.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Where to write the tile.
+    /// * `name` - Path of the tile relative to the output.
+    /// * `bytes` - Encoded tile.
+    /// * `content_type` - MIME type of the encoded tile.
+    /// * `attrs` - Attributes to give the tile.
+    pub fn write(
+        &self,
+        output: &Arc<Output>,
+        name: PathBuf,
+        bytes: Vec<u8>,
+        content_type: &'static str,
+        attrs: &TileAttrs,
+    ) {
+        let permit = self
+            .runtime
+            .block_on(Arc::clone(&self.permits).acquire_owned())
+            .unwrap();
+        let output = Arc::clone(output);
+        let errors = Arc::clone(&self.errors);
+        let attrs = attrs.clone();
+
+        self.runtime.spawn_blocking(move || {
+            if let Err(err) = output.write(&name, &bytes, content_type, &attrs) {
+                errors.lock().unwrap().push(err);
+            }
+
+            drop(output);
+            drop(permit);
+        });
+    }
+
+    /// Waits for every write to complete.
+    ///
+    /// # Returns
+    ///
+    /// The errors of the writes that failed.
+    pub fn finish(self) -> Vec<io::Error> {
+        drop(
+            self.runtime
+                .block_on(self.permits.acquire_many(self.jobs))
+                .unwrap(),
+        );
+        drop(self.runtime);
+
+        Arc::try_unwrap(self.errors)
+            .map(|errors| errors.into_inner().unwrap())
+            .unwrap_or_default()
+    }
+}
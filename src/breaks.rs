@@ -0,0 +1,193 @@
+use image::{DynamicImage, GrayImage};
+use splix::grid::Cell;
+
+/// Cuts a tall image into rows no taller than a height, moving each cut to the quietest row nearby,
+/// such as a blank gap between lines of text or panels of a comic, so nothing is sliced through the middle.
+/// Each cut aims to split what's left evenly, and is looked for within a quarter of the height of that aim.
+///
+/// # Arguments
+///
+/// * `img` - Image to cut.
+/// * `max_height` - Largest height of each row.
+///
+/// # Returns
+///
+/// The cells of the rows, from top to bottom.
+pub fn smart_rows(img: &DynamicImage, max_height: u32) -> Vec<Cell> {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    let costs = cut_costs(&luma);
+    let window = (max_height / 4).max(1);
+
+    let mut cuts = vec![0];
+    let mut top = 0;
+    while height - top > max_height {
+        let rows_left = (height - top).div_ceil(max_height);
+        let aim = top + (height - top) / rows_left;
+        let earliest = aim.saturating_sub(window).max(top + 1);
+        let latest = (aim + window).min(top + max_height);
+
+        // Of the quietest rows, the one closest to the aim wins.
+        let cut = (earliest..=latest)
+            .min_by_key(|&y| (costs[y as usize], y.abs_diff(aim)))
+            .unwrap_or(latest);
+        cuts.push(cut);
+        top = cut;
+    }
+    cuts.push(height);
+
+    cuts.windows(2)
+        .enumerate()
+        .map(|(row, cut)| Cell {
+            row,
+            col: 0,
+            x: 0,
+            y: cut[0],
+            width,
+            height: cut[1] - cut[0],
+        })
+        .collect()
+}
+
+/// Scores how much cutting above each row would slice through, from the detail in the rows on either side
+/// and the change between them. Rows of a flat background score 0.
+///
+/// # Arguments
+///
+/// * `luma` - Brightness of the image.
+///
+/// # Returns
+///
+/// The score of cutting above each row, with one more entry for the bottom edge.
+fn cut_costs(luma: &GrayImage) -> Vec<u64> {
+    let (width, height) = luma.dimensions();
+    let rows: Vec<&[u8]> = luma.as_raw().chunks_exact(width as usize).collect();
+    let detail: Vec<u64> = rows
+        .iter()
+        .map(|row| {
+            row.windows(2)
+                .map(|pair| pair[0].abs_diff(pair[1]) as u64)
+                .sum()
+        })
+        .collect();
+
+    (0..=height as usize)
+        .map(|y| {
+            if y == 0 || y == height as usize {
+                return 0;
+            }
+
+            let change: u64 = rows[y - 1]
+                .iter()
+                .zip(rows[y])
+                .map(|(&above, &below)| above.abs_diff(below) as u64)
+                .sum();
+            detail[y - 1] + detail[y] + change
+        })
+        .collect()
+}
+
+/// How far a pixel's brightness must be from the background to count as ink.
+const INK_CONTRAST: u8 = 48;
+
+/// Moves the cuts between cells off lines of text and into the whitespace nearby,
+/// so splitting a screenshot or document never slices through a line. Text is found by counting
+/// the pixels of each row and column that stand out from the background, the most common brightness,
+/// so light text on a dark background is found as well as dark text on a light one.
+/// Each cut is looked for within a quarter of the bands on either side of it, so bands keep at least half their size,
+/// and the image's outer edges never move.
+///
+/// # Arguments
+///
+/// * `img` - Image the cells are cut from.
+/// * `cells` - Cells to move the cuts of.
+///
+/// # Returns
+///
+/// The cells with their cuts moved.
+pub fn avoid_text(img: &DynamicImage, cells: &[Cell]) -> Vec<Cell> {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    let background = background(&luma);
+
+    let mut row_ink = vec![0u32; height as usize];
+    let mut col_ink = vec![0u32; width as usize];
+    for (x, y, pixel) in luma.enumerate_pixels() {
+        if pixel.0[0].abs_diff(background) > INK_CONTRAST {
+            row_ink[y as usize] += 1;
+            col_ink[x as usize] += 1;
+        }
+    }
+
+    let ys = moved_cuts(
+        cells.iter().flat_map(|cell| [cell.y, cell.y + cell.height]),
+        &row_ink,
+    );
+    let xs = moved_cuts(
+        cells.iter().flat_map(|cell| [cell.x, cell.x + cell.width]),
+        &col_ink,
+    );
+    let moved = |cuts: &[(u32, u32)], cut: u32| match cuts.binary_search_by_key(&cut, |c| c.0) {
+        Ok(i) => cuts[i].1,
+        Err(_) => cut,
+    };
+
+    cells
+        .iter()
+        .map(|cell| {
+            let (x, y) = (moved(&xs, cell.x), moved(&ys, cell.y));
+            Cell {
+                x,
+                y,
+                width: moved(&xs, cell.x + cell.width) - x,
+                height: moved(&ys, cell.y + cell.height) - y,
+                ..*cell
+            }
+        })
+        .collect()
+}
+
+/// The most common brightness of an image, taken to be its background.
+fn background(luma: &GrayImage) -> u8 {
+    let mut counts = [0u64; 256];
+    for &value in luma.as_raw() {
+        counts[value as usize] += 1;
+    }
+
+    (0..=u8::MAX)
+        .max_by_key(|&value| counts[value as usize])
+        .unwrap_or(u8::MAX)
+}
+
+/// Moves the cuts along one side of an image to where they slice through the least ink.
+/// A cut slices through text when the rows or columns on both sides of it have ink.
+///
+/// # Arguments
+///
+/// * `cuts` - Where cells start and end along this side.
+/// * `ink` - Number of ink pixels in each row or column along this side.
+///
+/// # Returns
+///
+/// Each cut and where it moves to, sorted by the cut.
+fn moved_cuts(cuts: impl Iterator<Item = u32>, ink: &[u32]) -> Vec<(u32, u32)> {
+    let length = ink.len() as u32;
+    let mut cuts: Vec<u32> = cuts.chain([0, length]).collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let cost = |cut: u32| ink[cut as usize - 1].min(ink[cut as usize]);
+    let mut moved: Vec<(u32, u32)> = cuts.iter().map(|&cut| (cut, cut)).collect();
+    for i in 1..cuts.len() - 1 {
+        let cut = cuts[i];
+        let earliest = cut - (cut - cuts[i - 1]) / 4;
+        let latest = cut + (cuts[i + 1] - cut) / 4;
+
+        // Of the cuts through the least ink, the one closest to where the cut was wins.
+        moved[i].1 = (earliest.max(1)..=latest.min(length - 1))
+            .min_by_key(|&at| (cost(at), at.abs_diff(cut)))
+            .unwrap_or(cut);
+    }
+
+    moved
+}
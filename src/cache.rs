@@ -0,0 +1,134 @@
+use crate::manifest;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use splix::grid::Cell;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the file in the output directory recording what each tile was made from, for `--update`.
+pub const CACHE_FILE: &str = ".splix-cache.json";
+
+/// What a tile in the output was made from.
+#[derive(Clone, Deserialize, Serialize)]
+struct CachedTile {
+    /// Hash of the tile's source region and format.
+    region: String,
+    /// SHA-256 of the tile's file, if it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+/// The source regions of the tiles already in an output directory, so tiles whose region hasn't changed
+/// since they were written aren't encoded and written again.
+pub struct TileCache {
+    /// The output directory.
+    dir: PathBuf,
+    /// Tiles by their path relative to the output, as of the last run and updated by this one.
+    tiles: Mutex<BTreeMap<PathBuf, CachedTile>>,
+}
+
+impl TileCache {
+    /// Reads the cache of an output directory, or starts an empty one if there isn't any.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The output directory.
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        let tiles = match File::open(dir.join(CACHE_FILE)) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(TileCache {
+            dir: dir.to_path_buf(),
+            tiles: Mutex::new(tiles),
+        })
+    }
+
+    /// Checks whether a tile is already in the output, made from the same region, and records the region otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Path of the tile relative to the output.
+    /// * `region` - Hash of the tile's source region, from [`region_hash`].
+    ///
+    /// # Returns
+    ///
+    /// The SHA-256 recorded for the tile's file if it's unchanged, as `Some(None)` if none was recorded,
+    /// or `None` if the tile needs to be written.
+    pub fn unchanged(&self, name: &Path, region: String) -> Option<Option<String>> {
+        let mut tiles = self.tiles.lock().unwrap();
+        if let Some(tile) = tiles.get(name) {
+            if tile.region == region && self.dir.join(name).is_file() {
+                return Some(tile.sha256.clone());
+            }
+        }
+
+        tiles.insert(
+            name.to_path_buf(),
+            CachedTile {
+                region,
+                sha256: None,
+            },
+        );
+        None
+    }
+
+    /// Records the SHA-256 of a tile's file once it's encoded.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Path of the tile relative to the output.
+    /// * `sha256` - SHA-256 of the file.
+    pub fn set_sha256(&self, name: &Path, sha256: &str) {
+        if let Some(tile) = self.tiles.lock().unwrap().get_mut(name) {
+            tile.sha256 = Some(sha256.to_string());
+        }
+    }
+
+    /// Drops tiles that failed to be written, so they're written again by the next run.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - Where the tiles would have been written.
+    pub fn forget_files(&self, files: &HashSet<PathBuf>) {
+        self.tiles
+            .lock()
+            .unwrap()
+            .retain(|name, _| !files.contains(&self.dir.join(name)));
+    }
+
+    /// Writes the cache to the output directory as JSON.
+    pub fn write(self) -> io::Result<()> {
+        let tiles = self.tiles.into_inner().unwrap();
+        let mut writer = BufWriter::new(File::create(self.dir.join(CACHE_FILE))?);
+        serde_json::to_writer_pretty(&mut writer, &tiles)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+}
+
+/// Hashes the pixels of a tile's source region, along with its format, so a tile is written again if either changes.
+///
+/// # Arguments
+///
+/// * `img` - Image the tile is cut from.
+/// * `cell` - Region of the tile.
+/// * `ext` - Extension of the tile's format.
+pub fn region_hash(img: &DynamicImage, cell: &Cell, ext: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(ext.as_bytes());
+    hasher.update(format!("{:?}", img.color()).as_bytes());
+    hasher.update(cell.width.to_le_bytes());
+    hasher.update(cell.height.to_le_bytes());
+    for row in cell.rows(img) {
+        hasher.update(row);
+    }
+
+    manifest::hex(&hasher.finalize())
+}
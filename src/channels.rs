@@ -0,0 +1,146 @@
+use clap::ValueEnum;
+use image::{ColorType, DynamicImage, GrayImage, ImageBuffer, Luma};
+
+/// Which planes to split an image into.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Channels {
+    /// Red, green, and blue.
+    Rgb,
+    /// Red, green, blue, and alpha.
+    Rgba,
+    /// Cyan, magenta, yellow, and black ink, each shown dark where it's printed, as a separation for a printing plate.
+    Cmyk,
+    /// Luma and the blue and red chroma differences, as JPEG stores them.
+    Ycbcr,
+}
+
+/// Every mode of `--channels`, to find the one a plane belongs to.
+const MODES: [Channels; 4] = [
+    Channels::Rgb,
+    Channels::Rgba,
+    Channels::Cmyk,
+    Channels::Ycbcr,
+];
+
+impl Channels {
+    /// Names of the planes, in the order they're split, which `{channel}` is replaced with.
+    pub fn names(self) -> &'static [&'static str] {
+        match self {
+            Channels::Rgb => &["red", "green", "blue"],
+            Channels::Rgba => &["red", "green", "blue", "alpha"],
+            Channels::Cmyk => &["cyan", "magenta", "yellow", "black"],
+            Channels::Ycbcr => &["luma", "cb", "cr"],
+        }
+    }
+
+    /// Value of each plane for a pixel.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel` - Red, green, blue, and alpha of the pixel, from 0 to 1.
+    ///
+    /// # Returns
+    ///
+    /// The value of each plane from 0 to 1, in the order of [`Channels::names`], followed by zeros.
+    fn separate(self, [red, green, blue, alpha]: [f32; 4]) -> [f32; 4] {
+        match self {
+            Channels::Rgb | Channels::Rgba => [red, green, blue, alpha],
+            Channels::Cmyk => {
+                let black = 1.0 - red.max(green).max(blue);
+                let ink = |value: f32| {
+                    if black >= 1.0 {
+                        0.0
+                    } else {
+                        (1.0 - value - black) / (1.0 - black)
+                    }
+                };
+                [
+                    1.0 - ink(red),
+                    1.0 - ink(green),
+                    1.0 - ink(blue),
+                    1.0 - black,
+                ]
+            }
+            // Full-range BT.601, the same as JFIF.
+            Channels::Ycbcr => [
+                0.299 * red + 0.587 * green + 0.114 * blue,
+                0.5 - 0.168_736 * red - 0.331_264 * green + 0.5 * blue,
+                0.5 + 0.5 * red - 0.418_688 * green - 0.081_312 * blue,
+                0.0,
+            ],
+        }
+    }
+}
+
+/// Splits an image into grayscale planes, for `--channels`.
+/// Images with more than 8 bits per channel give 16-bit planes, so no precision is lost.
+///
+/// # Arguments
+///
+/// * `img` - Image to split.
+/// * `channels` - Which planes to split it into.
+///
+/// # Returns
+///
+/// The name and pixels of each plane.
+pub fn planes(img: &DynamicImage, channels: Channels) -> Vec<(&'static str, DynamicImage)> {
+    let rgba = img.to_rgba16();
+    let (width, height) = rgba.dimensions();
+    let names = channels.names();
+    let deep = !matches!(
+        img.color(),
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8
+    );
+
+    let mut values: Vec<Vec<f32>> = vec![Vec::with_capacity(rgba.len() / 4); names.len()];
+    for pixel in rgba.pixels() {
+        let separated = channels.separate(pixel.0.map(|value| value as f32 / u16::MAX as f32));
+        for (plane, value) in values.iter_mut().zip(separated) {
+            plane.push(value.clamp(0.0, 1.0));
+        }
+    }
+
+    names
+        .iter()
+        .zip(values)
+        .map(|(&name, plane)| {
+            let plane = if deep {
+                let samples = plane
+                    .iter()
+                    .map(|value| (value * u16::MAX as f32).round() as u16)
+                    .collect();
+                DynamicImage::ImageLuma16(
+                    ImageBuffer::<Luma<u16>, _>::from_raw(width, height, samples).unwrap(),
+                )
+            } else {
+                let samples = plane
+                    .iter()
+                    .map(|value| (value * u8::MAX as f32).round() as u8)
+                    .collect();
+                DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, samples).unwrap())
+            };
+            (name, plane)
+        })
+        .collect()
+}
+
+/// Takes one plane of an image by its name, such as to compare or replay tiles split with `--channels`.
+///
+/// # Arguments
+///
+/// * `img` - Image to take the plane from.
+/// * `name` - Name of the plane, such as `red` or `luma`.
+///
+/// # Returns
+///
+/// The plane, or `None` if no mode of `--channels` has a plane of that name.
+pub fn plane(img: &DynamicImage, name: &str) -> Option<DynamicImage> {
+    let channels = MODES
+        .into_iter()
+        .find(|channels| channels.names().contains(&name))?;
+
+    planes(img, channels)
+        .into_iter()
+        .find(|&(plane, _)| plane == name)
+        .map(|(_, plane)| plane)
+}
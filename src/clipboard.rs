@@ -0,0 +1,144 @@
+use crate::grid::Cell;
+use image::{DynamicImage, ImageError, ImageResult};
+use std::io;
+use std::sync::Mutex;
+
+/// Path reported for an image read from the clipboard.
+pub const CLIPBOARD_PATH: &str = "clipboard.png";
+
+/// Parses the tile to copy to the clipboard, as `ROW,COL` counting from 0.
+///
+/// # Arguments
+///
+/// * `tile` - Tile to parse.
+///
+/// # Returns
+///
+/// The row and column of the tile, or an error message if it isn't a valid tile.
+pub fn parse_tile(tile: &str) -> Result<(usize, usize), String> {
+    tile.split_once(',')
+        .and_then(|(row, col)| Some((row.trim().parse().ok()?, col.trim().parse().ok()?)))
+        .ok_or_else(|| format!("'{}' is not a tile, such as 0,2 for row 0, column 2", tile))
+}
+
+/// Holds a tile of the first source image until it's copied to the clipboard.
+pub struct ClipboardTile {
+    row: usize,
+    col: usize,
+    image: Mutex<Option<DynamicImage>>,
+}
+
+impl ClipboardTile {
+    /// # Arguments
+    ///
+    /// * `(row, col)` - Row and column of the tile to copy.
+    pub fn new((row, col): (usize, usize)) -> Self {
+        ClipboardTile {
+            row,
+            col,
+            image: Mutex::new(None),
+        }
+    }
+
+    /// Keeps a copy of a cell if it's the chosen tile and no earlier frame's tile was kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `img` - Image being split.
+    /// * `cell` - Cell of the image.
+    pub fn offer(&self, img: &DynamicImage, cell: &Cell) {
+        if (cell.row, cell.col) != (self.row, self.col) {
+            return;
+        }
+
+        let mut image = self.image.lock().unwrap();
+        if image.is_none() {
+            *image = Some(img.crop_imm(cell.x, cell.y, cell.width, cell.height));
+        }
+    }
+
+    /// Copies the kept tile to the clipboard.
+    ///
+    /// # Returns
+    ///
+    /// An error message if the grid had no such tile or the clipboard couldn't be written.
+    pub fn copy(self) -> Result<(), String> {
+        let Some(image) = self.image.into_inner().unwrap() else {
+            return Err(format!(
+                "splix: to-clipboard: The grid has no tile at row {}, column {}",
+                self.row, self.col
+            ));
+        };
+
+        write_image(image)
+    }
+}
+
+/// Reads an image from the clipboard, such as a screenshot.
+///
+/// # Returns
+///
+/// The image, or an error if the clipboard doesn't hold one.
+#[cfg(feature = "clipboard")]
+pub fn read_image() -> ImageResult<DynamicImage> {
+    let image = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_image())
+        .map_err(|err| {
+            ImageError::IoError(io::Error::other(format!(
+                "Failed to read an image from the clipboard: {}",
+                err
+            )))
+        })?;
+
+    image::RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .map(DynamicImage::ImageRgba8)
+    .ok_or_else(|| ImageError::IoError(io::Error::other("The image on the clipboard is malformed")))
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn read_image() -> ImageResult<DynamicImage> {
+    Err(ImageError::IoError(io::Error::other(unsupported())))
+}
+
+/// Puts an image on the clipboard.
+/// On Linux, the clipboard is emptied when its owner exits, so this waits until another program replaces it.
+#[cfg(feature = "clipboard")]
+fn write_image(image: DynamicImage) -> Result<(), String> {
+    let image = image.into_rgba8();
+    let image = arboard::ImageData {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        bytes: image.into_raw().into(),
+    };
+
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|err| format!("splix: to-clipboard: Failed to open the clipboard: {}", err))?;
+
+    #[cfg(target_os = "linux")]
+    let result = {
+        use arboard::SetExtLinux;
+        eprintln!("splix: Keeping the tile on the clipboard until it's replaced");
+        clipboard.set().wait().image(image)
+    };
+    #[cfg(not(target_os = "linux"))]
+    let result = clipboard.set_image(image);
+
+    result.map_err(|err| format!("splix: to-clipboard: Failed to copy the tile: {}", err))
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn write_image(_image: DynamicImage) -> Result<(), String> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn unsupported() -> String {
+    crate::diagnostic::with_help(
+        "splix: The clipboard requires splix to be built with the `clipboard` feature",
+        crate::diagnostic::rebuild_with("clipboard"),
+    )
+}
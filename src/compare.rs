@@ -0,0 +1,97 @@
+use image::{DynamicImage, GrayImage, ImageBuffer, Rgba};
+use rayon::prelude::*;
+
+/// Side of the square windows SSIM is averaged over.
+const SSIM_WINDOW: u32 = 8;
+
+/// Computes the peak signal-to-noise ratio between two images of the same size, in decibels.
+/// Pixels are compared at 16 bits per channel, so higher bit depths are compared exactly.
+///
+/// # Arguments
+///
+/// * `a` - First image.
+/// * `b` - Second image.
+///
+/// # Returns
+///
+/// The PSNR, or infinity if every pixel matches.
+pub fn psnr(a: &ImageBuffer<Rgba<u16>, Vec<u16>>, b: &ImageBuffer<Rgba<u16>, Vec<u16>>) -> f64 {
+    let squared_error: f64 = a
+        .as_raw()
+        .par_iter()
+        .zip(b.as_raw().par_iter())
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+
+    if squared_error == 0.0 {
+        return f64::INFINITY;
+    }
+
+    let mean = squared_error / a.as_raw().len() as f64;
+    10.0 * (u16::MAX as f64).powi(2).log10() - 10.0 * mean.log10()
+}
+
+/// Computes the structural similarity of two images of the same size,
+/// averaged over square windows of their brightness.
+///
+/// # Arguments
+///
+/// * `a` - First image.
+/// * `b` - Second image.
+///
+/// # Returns
+///
+/// The SSIM, from 1 for identical images down towards 0 and below for unrelated ones.
+pub fn ssim(a: &DynamicImage, b: &DynamicImage) -> f64 {
+    let (a, b) = (a.to_luma8(), b.to_luma8());
+    let (width, height) = a.dimensions();
+
+    let windows: Vec<(u32, u32)> = (0..height)
+        .step_by(SSIM_WINDOW as usize)
+        .flat_map(|y| {
+            (0..width)
+                .step_by(SSIM_WINDOW as usize)
+                .map(move |x| (x, y))
+        })
+        .collect();
+    if windows.is_empty() {
+        return 1.0;
+    }
+
+    let total: f64 = windows
+        .par_iter()
+        .map(|&(x, y)| window_ssim(&a, &b, x, y))
+        .sum();
+    total / windows.len() as f64
+}
+
+/// Computes the SSIM of one window, clipped to the images.
+fn window_ssim(a: &GrayImage, b: &GrayImage, x: u32, y: u32) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let right = (x + SSIM_WINDOW).min(a.width());
+    let bottom = (y + SSIM_WINDOW).min(a.height());
+    let pixels = ((right - x) * (bottom - y)) as f64;
+
+    let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for py in y..bottom {
+        for px in x..right {
+            let pa = a.get_pixel(px, py)[0] as f64;
+            let pb = b.get_pixel(px, py)[0] as f64;
+            sum_a += pa;
+            sum_b += pb;
+            sum_aa += pa * pa;
+            sum_bb += pb * pb;
+            sum_ab += pa * pb;
+        }
+    }
+
+    let (mean_a, mean_b) = (sum_a / pixels, sum_b / pixels);
+    let var_a = sum_aa / pixels - mean_a * mean_a;
+    let var_b = sum_bb / pixels - mean_b * mean_b;
+    let covariance = sum_ab / pixels - mean_a * mean_b;
+
+    ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+        / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2))
+}
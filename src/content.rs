@@ -0,0 +1,75 @@
+use crate::manifest::TileStats;
+use image::{GenericImageView, Rgba};
+use std::cmp::Reverse;
+
+/// Bits kept of each channel when finding the most common color, so near-identical colors count together.
+const COLOR_BITS: u32 = 4;
+
+/// Measures what a tile contains, for `--tile-stats`.
+/// Brightness is measured as BT.601 luma, from 0 to 255.
+///
+/// # Arguments
+///
+/// * `tile` - The tile, as 8-bit RGBA.
+///
+/// # Returns
+///
+/// The tile's statistics, rounded for the manifest.
+pub fn measure(tile: &impl GenericImageView<Pixel = Rgba<u8>>) -> TileStats {
+    let mut histogram = [0u64; 256];
+    let mut colors = vec![0u32; 1 << (COLOR_BITS * 3)];
+    // The sum of each channel of the pixels in each bin of `colors`, to report their average.
+    let mut sums = vec![[0u64; 3]; colors.len()];
+    let (mut sum, mut sum_squares) = (0f64, 0f64);
+
+    for (_, _, Rgba([r, g, b, _])) in tile.pixels() {
+        let luma = 0.299 * f64::from(r) + 0.587 * f64::from(g) + 0.114 * f64::from(b);
+        sum += luma;
+        sum_squares += luma * luma;
+        histogram[luma.round() as usize] += 1;
+
+        let shift = 8 - COLOR_BITS;
+        let bin = (usize::from(r >> shift) << (2 * COLOR_BITS))
+            | (usize::from(g >> shift) << COLOR_BITS)
+            | usize::from(b >> shift);
+        colors[bin] += 1;
+        for (sum, channel) in sums[bin].iter_mut().zip([r, g, b]) {
+            *sum += u64::from(channel);
+        }
+    }
+
+    let count = u64::from(tile.width()) * u64::from(tile.height());
+    if count == 0 {
+        return TileStats::default();
+    }
+    let total = count as f64;
+    let mean = sum / total;
+    let stddev = (sum_squares / total - mean * mean).max(0.0).sqrt();
+    let entropy: f64 = histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            p * (1.0 / p).log2()
+        })
+        .sum();
+
+    let (bin, &dominant) = colors
+        .iter()
+        .enumerate()
+        .max_by_key(|&(bin, &count)| (count, Reverse(bin)))
+        .unwrap();
+    let [r, g, b] = sums[bin].map(|sum| (sum / u64::from(dominant.max(1))) as u8);
+
+    let round = |value: f64, places: i32| {
+        let scale = 10f64.powi(places);
+        ((value * scale).round() / scale) as f32
+    };
+    TileStats {
+        mean: round(mean, 2),
+        stddev: round(stddev, 2),
+        entropy: round(entropy, 3),
+        dominant_color: format!("#{:02x}{:02x}{:02x}", r, g, b),
+        blankness: round(f64::from(dominant) / total, 3),
+    }
+}
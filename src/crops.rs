@@ -0,0 +1,96 @@
+use crate::rng::Rng;
+use splix::grid::Cell;
+
+/// Fixed-size crops taken from random positions in each image, instead of splitting it into a grid.
+#[derive(Clone, Copy)]
+pub struct RandomCrops {
+    pub count: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RandomCrops {
+    /// The crops taken from an image, which are cut down to the image where they're larger than it.
+    /// If a crop fits in only one place, such as in an image smaller than it, only one is taken,
+    /// since the others would all be the same.
+    ///
+    /// # Arguments
+    ///
+    /// * `(width, height)` - Size of the image.
+    pub fn fit(&self, (width, height): (u32, u32)) -> RandomCrops {
+        let (crop_width, crop_height) = (self.width.min(width), self.height.min(height));
+        RandomCrops {
+            count: if (crop_width, crop_height) == (width, height) {
+                1
+            } else {
+                self.count
+            },
+            width: crop_width,
+            height: crop_height,
+        }
+    }
+
+    /// Picks where to take the crops from in an image, as [`RandomCrops::fit`] fits them to it.
+    /// Crops may overlap each other, and are numbered as columns of a single row.
+    ///
+    /// # Arguments
+    ///
+    /// * `(width, height)` - Size of the image.
+    /// * `rng` - Random number generator to pick the positions with.
+    ///
+    /// # Returns
+    ///
+    /// The cell of each crop.
+    pub fn cells(&self, (width, height): (u32, u32), rng: &mut Rng) -> Vec<Cell> {
+        let RandomCrops {
+            count,
+            width: crop_width,
+            height: crop_height,
+        } = self.fit((width, height));
+
+        (0..count)
+            .map(|col| Cell {
+                row: 0,
+                col,
+                x: rng.below((width - crop_width) as u64 + 1) as u32,
+                y: rng.below((height - crop_height) as u64 + 1) as u32,
+                width: crop_width,
+                height: crop_height,
+            })
+            .collect()
+    }
+}
+
+/// Parses the crops for `--random-crops`, written as `COUNTxWIDTHxHEIGHT`.
+///
+/// # Arguments
+///
+/// * `crops` - Crops to parse.
+///
+/// # Returns
+///
+/// The crops, or an error message if they aren't valid.
+pub fn parse_random_crops(crops: &str) -> Result<RandomCrops, String> {
+    let invalid = || {
+        format!(
+            "'{}' is not a number of crops and their size, such as 10x256x256",
+            crops
+        )
+    };
+
+    let parts: Vec<&str> = crops.split(['x', 'X']).map(str::trim).collect();
+    let [count, width, height] = parts[..] else {
+        return Err(invalid());
+    };
+
+    match (count.parse(), width.parse(), height.parse()) {
+        (Ok(count), Ok(width), Ok(height)) if count > 0 && width > 0 && height > 0 => {
+            Ok(RandomCrops {
+                count,
+                width,
+                height,
+            })
+        }
+        _ => Err(invalid()),
+    }
+}
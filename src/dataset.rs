@@ -0,0 +1,435 @@
+use crate::jobfile;
+use crate::output;
+use crate::rng::Rng;
+use clap::ValueEnum;
+use splix::grid::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Tiles in each WebDataset shard, unless `--shard-size` says otherwise.
+pub const DEFAULT_SHARD_SIZE: usize = 1000;
+
+/// Name of the file describing how images were assigned to splits and classes.
+pub const DATASET_JSON: &str = "dataset.json";
+
+/// How `--dataset-layout` organizes tiles.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum DatasetLayout {
+    /// Folders of tiles for each split and class, as torchvision's `ImageFolder` reads them.
+    #[value(name = "imagefolder")]
+    ImageFolder,
+    /// Tar shards of tiles for each split, as the `webdataset` library reads them.
+    #[value(name = "webdataset")]
+    WebDataset,
+}
+
+/// Which split of a dataset an image's tiles belong to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Split {
+    Train,
+    Val,
+}
+
+impl Split {
+    fn name(self) -> &'static str {
+        match self {
+            Split::Train => "train",
+            Split::Val => "val",
+        }
+    }
+}
+
+/// The split and class of a source image.
+struct Assignment {
+    split: Split,
+    class: Option<String>,
+}
+
+/// A WebDataset shard being filled.
+struct Shard {
+    number: usize,
+    samples: usize,
+    builder: tar::Builder<Vec<u8>>,
+}
+
+/// Organizes the tiles of a run into a dataset for training models.
+/// Every tile of an image goes to the same split, so no image is seen in both training and validation.
+pub struct Dataset {
+    layout: DatasetLayout,
+    /// Fraction of the images to hold out for validation.
+    val_split: f64,
+    shard_size: usize,
+    /// Class of each image listed in `--classes`, by the name it was listed under.
+    classes: Option<HashMap<String, String>>,
+    /// Every class, in alphabetical order, as `ImageFolder` numbers them.
+    class_names: Vec<String>,
+    seed: OnceLock<u64>,
+    /// The path, split, and class of each source, by its position in the batch.
+    assignments: OnceLock<Vec<(PathBuf, Assignment)>>,
+    shards: Mutex<HashMap<Split, Shard>>,
+}
+
+impl Dataset {
+    /// # Arguments
+    ///
+    /// * `layout` - How to organize the tiles.
+    /// * `val_split` - Fraction of the images to hold out for validation.
+    /// * `shard_size` - Tiles in each WebDataset shard.
+    /// * `classes` - CSV mapping images to classes, if the tiles should be sorted into them.
+    ///
+    /// # Returns
+    ///
+    /// The dataset, or an error message if the CSV couldn't be read.
+    pub fn new(
+        layout: DatasetLayout,
+        val_split: f64,
+        shard_size: usize,
+        classes: Option<&Path>,
+    ) -> Result<Self, String> {
+        let classes = classes
+            .map(|path| {
+                fs::read_to_string(path)
+                    .map_err(|err| {
+                        format!("splix: classes: Failed to read {}: {}", path.display(), err)
+                    })
+                    .and_then(|csv| {
+                        parse_classes(&csv)
+                            .map_err(|err| format!("splix: classes: {}: {}", path.display(), err))
+                    })
+            })
+            .transpose()?;
+        let mut class_names: Vec<String> = classes
+            .iter()
+            .flat_map(|classes| classes.values().cloned())
+            .collect();
+        class_names.sort_unstable();
+        class_names.dedup();
+
+        Ok(Dataset {
+            layout,
+            val_split,
+            shard_size,
+            classes,
+            class_names,
+            seed: OnceLock::new(),
+            assignments: OnceLock::new(),
+            shards: Mutex::default(),
+        })
+    }
+
+    /// Whether images need to be shuffled to hold some out for validation, which takes a seed.
+    pub fn shuffles(&self) -> bool {
+        self.val_split > 0.0
+    }
+
+    /// Assigns each image to a split, and to its class if classes were given.
+    /// Each class is shuffled and split separately, so every class is held out in the same proportion.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Every image in the run, in order.
+    /// * `seed` - Seed to shuffle the images with, so the same images are held out again.
+    ///
+    /// # Returns
+    ///
+    /// An error message naming an image missing from the classes.
+    pub fn assign(&self, paths: &[PathBuf], seed: u64) -> Result<(), String> {
+        let mut classes = Vec::with_capacity(paths.len());
+        for path in paths {
+            let class = match &self.classes {
+                Some(map) => Some(lookup(map, path).ok_or_else(|| {
+                    format!(
+                        "splix: classes: {} isn't listed, so it has no class",
+                        path.display()
+                    )
+                })?),
+                None => None,
+            };
+            classes.push(class);
+        }
+
+        let mut groups: BTreeMap<Option<String>, Vec<usize>> = BTreeMap::new();
+        for (index, class) in classes.iter().enumerate() {
+            groups.entry(class.clone()).or_default().push(index);
+        }
+        let mut splits = vec![Split::Train; paths.len()];
+        let mut rng = Rng::new(seed);
+        for indices in groups.into_values() {
+            let held_out = (indices.len() as f64 * self.val_split).round() as usize;
+            for index in rng.sample(indices, held_out) {
+                splits[index] = Split::Val;
+            }
+        }
+
+        let _ = self.seed.set(seed);
+        let _ = self.assignments.set(
+            paths
+                .iter()
+                .cloned()
+                .zip(splits.into_iter().zip(classes))
+                .map(|(path, (split, class))| (path, Assignment { split, class }))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn assignment(&self, index: usize) -> Option<&Assignment> {
+        self.assignments
+            .get()
+            .and_then(|assignments| assignments.get(index))
+            .map(|(_, assignment)| assignment)
+    }
+
+    /// Whether tiles are collected into shards instead of saved as files.
+    pub fn sharded(&self) -> bool {
+        self.layout == DatasetLayout::WebDataset
+    }
+
+    /// Places a tile in the folders of its image's split and class.
+    /// Tiles collected into shards keep their names, which become their keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Position of the tile's image in the batch.
+    /// * `name` - Name of the tile.
+    pub fn place(&self, index: usize, name: &Path) -> PathBuf {
+        match (self.layout, self.assignment(index)) {
+            (DatasetLayout::ImageFolder, Some(assignment)) => {
+                let mut path = PathBuf::from(assignment.split.name());
+                if let Some(class) = &assignment.class {
+                    path.push(class);
+                }
+                path.join(name)
+            }
+            _ => name.to_path_buf(),
+        }
+    }
+
+    /// Adds a tile to the shard of its image's split, as a sample of the tile, its class, and where it was cut from.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Position of the tile's image in the batch.
+    /// * `name` - Name of the tile, whose stem becomes the sample's key.
+    /// * `cell` - Region of the image the tile was cut from.
+    /// * `bytes` - The encoded tile.
+    ///
+    /// # Returns
+    ///
+    /// The name of the shard the tile was added to, and the shard's contents if it's now full.
+    pub fn add_sample(
+        &self,
+        index: usize,
+        name: &Path,
+        cell: &Cell,
+        bytes: &[u8],
+    ) -> io::Result<(PathBuf, Option<Vec<u8>>)> {
+        let Some(assignment) = self.assignment(index) else {
+            return Err(io::Error::other("The tile's image has no split"));
+        };
+        let source = self
+            .assignments
+            .get()
+            .map(|assignments| &assignments[index].0);
+
+        // WebDataset splits a sample's files at the first dot of their names, so keys can't contain one.
+        let key = name
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/")
+            .replace('.', "_");
+        let ext = name.extension().unwrap_or_default().to_string_lossy();
+        let meta = serde_json::json!({
+            "source": source.map(|source| source.to_string_lossy()),
+            "class": assignment.class,
+            "row": cell.row,
+            "col": cell.col,
+            "x": cell.x,
+            "y": cell.y,
+            "width": cell.width,
+            "height": cell.height,
+        });
+
+        let mut files = vec![
+            (format!("{}.{}", key, ext), bytes.to_vec()),
+            (format!("{}.json", key), meta.to_string().into_bytes()),
+        ];
+        if let Some(class) = &assignment.class {
+            files.push((
+                format!("{}.cls", key),
+                self.class_names
+                    .iter()
+                    .position(|name| name == class)
+                    .unwrap_or(0)
+                    .to_string()
+                    .into_bytes(),
+            ));
+        }
+
+        let mut shards = self.shards.lock().unwrap();
+        let shard = shards.entry(assignment.split).or_insert_with(|| Shard {
+            number: 0,
+            samples: 0,
+            builder: tar::Builder::new(Vec::new()),
+        });
+        let shard_name = shard_name(assignment.split, shard.number);
+        for (name, contents) in files {
+            append(&mut shard.builder, &name, &contents)?;
+        }
+        shard.samples += 1;
+
+        if shard.samples < self.shard_size {
+            return Ok((shard_name, None));
+        }
+        let full = std::mem::replace(
+            shard,
+            Shard {
+                number: shard.number + 1,
+                samples: 0,
+                builder: tar::Builder::new(Vec::new()),
+            },
+        );
+        Ok((shard_name, Some(full.builder.into_inner()?)))
+    }
+
+    /// Finishes the dataset, once every tile has been added.
+    ///
+    /// # Returns
+    ///
+    /// Each file left to write: the shards that weren't full, and a JSON description of the dataset,
+    /// listing its classes and the split and class of each image.
+    pub fn finish(self) -> io::Result<Vec<(PathBuf, Vec<u8>)>> {
+        let mut files = Vec::new();
+        let mut shards: Vec<_> = self.shards.into_inner().unwrap().into_iter().collect();
+        shards.sort_by_key(|(split, _)| *split);
+        for (split, shard) in shards {
+            if shard.samples > 0 {
+                files.push((shard_name(split, shard.number), shard.builder.into_inner()?));
+            }
+        }
+
+        let images: Vec<serde_json::Value> = self
+            .assignments
+            .get()
+            .into_iter()
+            .flatten()
+            .map(|(path, assignment)| {
+                serde_json::json!({
+                    "path": path.to_string_lossy(),
+                    "split": assignment.split.name(),
+                    "class": assignment.class,
+                })
+            })
+            .collect();
+        let layout = match self.layout {
+            DatasetLayout::ImageFolder => "imagefolder",
+            DatasetLayout::WebDataset => "webdataset",
+        };
+        let description = serde_json::json!({
+            "layout": layout,
+            "val_split": self.val_split,
+            "seed": self.seed.get(),
+            "classes": (!self.class_names.is_empty()).then_some(&self.class_names),
+            "images": images,
+        });
+        files.push((
+            PathBuf::from(DATASET_JSON),
+            serde_json::to_vec_pretty(&description).unwrap(),
+        ));
+        Ok(files)
+    }
+}
+
+/// Parses a fraction of images to hold out for `--val-split`.
+///
+/// # Arguments
+///
+/// * `fraction` - A fraction from 0 up to but not including 1, such as `0.2`, or a percentage such as `20%`.
+///
+/// # Returns
+///
+/// The fraction, or an error message if it isn't valid.
+pub fn parse_val_split(fraction: &str) -> Result<f64, String> {
+    let fraction = fraction.trim();
+    let parsed = match fraction.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|percent| percent / 100.0),
+        None => fraction.parse::<f64>(),
+    };
+    parsed
+        .ok()
+        .filter(|fraction| (0.0..1.0).contains(fraction))
+        .ok_or_else(|| {
+            format!(
+                "'{}' is not a fraction from 0 up to 1, such as 0.2 or 20%",
+                fraction
+            )
+        })
+}
+
+/// Parses a CSV of images and their classes, one per row, with an optional header row of `image,class`.
+/// Values may be quoted, as in a job file, so a quoted image or class may hold commas.
+fn parse_classes(csv: &str) -> Result<HashMap<String, String>, String> {
+    let mut classes = HashMap::new();
+    for (number, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields =
+            jobfile::split_fields(line).map_err(|err| format!("Line {}: {}", number + 1, err))?;
+        let [image, class] = <[String; 2]>::try_from(fields).map_err(|_| {
+            format!(
+                "Line {} should be an image and its class, separated by a comma",
+                number + 1
+            )
+        })?;
+        if classes.is_empty()
+            && matches!(
+                class.to_lowercase().as_str(),
+                "class" | "label" | "category"
+            )
+        {
+            continue;
+        }
+        // Classes name folders, so they can't climb out of their split's folder.
+        if class.is_empty() || class == "." || class == ".." || class.contains(['/', '\\']) {
+            return Err(format!(
+                "Line {} has a class of '{}', which can't be a folder name",
+                number + 1,
+                class
+            ));
+        }
+        classes.insert(image.replace('\\', "/"), class);
+    }
+    Ok(classes)
+}
+
+/// Finds the class of an image, listed under its path, its path's last components, its file name, or its stem.
+fn lookup(classes: &HashMap<String, String>, path: &Path) -> Option<String> {
+    let components: Vec<String> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    (0..components.len())
+        .map(|start| components[start..].join("/"))
+        .chain(
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned()),
+        )
+        .find_map(|key| classes.get(&key).cloned())
+}
+
+fn shard_name(split: Split, number: usize) -> PathBuf {
+    PathBuf::from(format!("{}-{:06}.tar", split.name(), number))
+}
+
+fn append(builder: &mut tar::Builder<Vec<u8>>, name: &str, contents: &[u8]) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(output::archive_time());
+    builder.append_data(&mut header, name, contents)
+}
@@ -0,0 +1,149 @@
+use crate::grid::Cell;
+use crate::output::long_path;
+use clap::ValueEnum;
+use image::DynamicImage;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// What to do with a tile whose pixels match a tile that was already saved.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DedupeMode {
+    /// Don't write the duplicate tile.
+    Skip,
+    /// Hard-link the duplicate tile to the first occurrence.
+    Link,
+    /// Copy the first occurrence to the duplicate tile.
+    Copy,
+}
+
+/// Tracks the pixel data of saved tiles across every image in a run.
+pub struct Dedupe {
+    mode: DedupeMode,
+    /// The first tile saved with each SHA-256 of size, color type, and pixels.
+    /// A 64-bit hash would let two different tiles collide in runs with millions of them.
+    seen: Mutex<HashMap<[u8; 32], PathBuf>>,
+    links: Mutex<Vec<(PathBuf, PathBuf)>>,
+}
+
+impl Dedupe {
+    pub fn new(mode: DedupeMode) -> Self {
+        Dedupe {
+            mode,
+            seen: Mutex::new(HashMap::new()),
+            links: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registers a tile that is about to be saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `img` - Image the tile is split from.
+    /// * `cell` - Region of the image that makes up the tile.
+    /// * `file_path` - Path the tile would be saved to.
+    ///
+    /// # Returns
+    ///
+    /// `None` if this is the first occurrence of these pixels and the tile should be saved,
+    /// otherwise the path of the first occurrence.
+    pub fn check(&self, img: &DynamicImage, cell: &Cell, file_path: &Path) -> Option<PathBuf> {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{:?}", img.color()).as_bytes());
+        hasher.update(cell.width.to_le_bytes());
+        hasher.update(cell.height.to_le_bytes());
+        for row in cell.rows(img) {
+            hasher.update(row);
+        }
+
+        let original = match self.seen.lock().unwrap().entry(hasher.finalize().into()) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                entry.insert(file_path.to_path_buf());
+                return None;
+            }
+        };
+
+        if self.links() {
+            self.links
+                .lock()
+                .unwrap()
+                .push((original.clone(), file_path.to_path_buf()));
+        }
+
+        Some(original)
+    }
+
+    /// Whether duplicates are written as links or copies, and so still have a file of their own.
+    pub fn links(&self) -> bool {
+        self.mode.writes_duplicates()
+    }
+
+    /// Creates the links or copies for every duplicate found.
+    /// This must run after all originals have been saved.
+    pub fn finish(self) {
+        for (original, duplicate) in self.links.into_inner().unwrap() {
+            link(&original, &duplicate, self.mode);
+        }
+    }
+}
+
+impl DedupeMode {
+    /// Whether duplicates still get a file of their own.
+    pub fn writes_duplicates(self) -> bool {
+        matches!(self, DedupeMode::Link | DedupeMode::Copy)
+    }
+}
+
+/// Gives a duplicate the contents of the first occurrence, replacing any file already there.
+/// Hard links fall back to copying if the file system doesn't support them. Failures are reported, not returned.
+///
+/// # Arguments
+///
+/// * `original` - The first occurrence.
+/// * `duplicate` - Path of the duplicate.
+/// * `mode` - Whether to link or copy.
+///
+/// # Returns
+///
+/// Whether the duplicate was written.
+pub fn link(original: &Path, duplicate: &Path, mode: DedupeMode) -> bool {
+    let (original_file, duplicate_file) = (long_path(original), long_path(duplicate));
+    if duplicate_file.exists() {
+        if let Err(err) = fs::remove_file(&duplicate_file) {
+            eprintln!(
+                "splix: Failed to remove existing image {}: {}",
+                duplicate.display(),
+                err
+            );
+            return false;
+        }
+    }
+
+    if let Err(err) = fs::create_dir_all(duplicate_file.parent().unwrap()) {
+        eprintln!(
+            "splix: Failed to create directory {}: {}",
+            duplicate.parent().unwrap().display(),
+            err
+        );
+        return false;
+    }
+
+    let linked =
+        matches!(mode, DedupeMode::Link) && fs::hard_link(&original_file, &duplicate_file).is_ok();
+    if !linked {
+        if let Err(err) = fs::copy(&original_file, &duplicate_file) {
+            eprintln!(
+                "splix: Failed to link {} to {}: {}",
+                duplicate.display(),
+                original.display(),
+                err
+            );
+            return false;
+        }
+    }
+    true
+}
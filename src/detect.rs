@@ -0,0 +1,178 @@
+use image::{DynamicImage, GrayImage};
+use splix::grid::Cell;
+
+/// Smallest size in pixels of a cell that can be detected.
+const MIN_PERIOD: usize = 8;
+
+/// How strongly a side must repeat to be divided, as a fraction of how much its detail varies overall.
+const MIN_CORRELATION: f64 = 0.3;
+
+/// Fraction of the strongest repeat a shorter one must reach to be picked instead,
+/// so a grid isn't mistaken for one with cells two or three times as large.
+const SHORTER_PERIOD: f64 = 0.9;
+
+/// Fraction of a cell a band at the edge of the image must span to be kept as a cell, rather than a margin.
+const MIN_EDGE_CELL: f64 = 0.9;
+
+/// Finds the grid of a regularly repeating image, such as a contact sheet, a sticker sheet, or a photographed board,
+/// and cuts it into its cells. Along each side, the detail of each row or column is compared with itself
+/// shifted by every distance, and the shortest distance at which it repeats best is the size of a cell.
+/// Cuts are then placed where the repeating feature stands out most, such as on the lines of a board or in the gutters
+/// between photos, and margins narrower than a cell are left out.
+///
+/// # Arguments
+///
+/// * `img` - Image to split.
+///
+/// # Returns
+///
+/// The cells of the grid, from left to right, top to bottom.
+/// A side that doesn't repeat is kept whole.
+pub fn detect_grid(img: &DynamicImage) -> Vec<Cell> {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    let (row_detail, col_detail) = detail(&luma);
+
+    let row_bands = repeating_bands(&row_detail);
+    let col_bands = repeating_bands(&col_detail);
+
+    let mut cells = Vec::with_capacity(row_bands.len() * col_bands.len());
+    for (row, &(y, cell_height)) in row_bands.iter().enumerate() {
+        for (col, &(x, cell_width)) in col_bands.iter().enumerate() {
+            cells.push(Cell {
+                row,
+                col,
+                x,
+                y,
+                width: cell_width.min(width - x),
+                height: cell_height.min(height - y),
+            });
+        }
+    }
+
+    cells
+}
+
+/// Measures how much the brightness changes across each row and down each column,
+/// which is high on lines and edges and low on blank gutters.
+///
+/// # Arguments
+///
+/// * `luma` - Brightness of the image.
+///
+/// # Returns
+///
+/// The change from the row above summed along each row, and the change from the column to the left summed down each column.
+fn detail(luma: &GrayImage) -> (Vec<f64>, Vec<f64>) {
+    let (width, height) = luma.dimensions();
+    let mut rows = vec![0.0; height as usize];
+    let mut cols = vec![0.0; width as usize];
+
+    for (x, y, pixel) in luma.enumerate_pixels() {
+        let value = pixel.0[0];
+        if y > 0 {
+            rows[y as usize] += value.abs_diff(luma.get_pixel(x, y - 1).0[0]) as f64;
+        }
+        if x > 0 {
+            cols[x as usize] += value.abs_diff(luma.get_pixel(x - 1, y).0[0]) as f64;
+        }
+    }
+
+    (rows, cols)
+}
+
+/// Divides one side of an image into the cells its detail repeats in.
+///
+/// # Arguments
+///
+/// * `profile` - Detail of each row or column along the side.
+///
+/// # Returns
+///
+/// The offset and length of each cell, or the whole side if it doesn't repeat.
+fn repeating_bands(profile: &[f64]) -> Vec<(u32, u32)> {
+    let length = profile.len();
+    let whole = vec![(0, length as u32)];
+    let Some(period) = period(profile) else {
+        return whole;
+    };
+
+    // The cuts go where the rows or columns a period apart stand out most from the rest.
+    let mean = profile.iter().sum::<f64>() / length as f64;
+    let phase = (0..period)
+        .max_by(|&a, &b| {
+            let strength = |phase: usize| {
+                let values: Vec<f64> = profile[phase..].iter().step_by(period).copied().collect();
+                (values.iter().sum::<f64>() / values.len() as f64 - mean).abs()
+            };
+            strength(a).total_cmp(&strength(b))
+        })
+        .unwrap_or(0);
+
+    let mut cuts: Vec<usize> = (phase..=length).step_by(period).collect();
+    if cuts[0] as f64 >= period as f64 * MIN_EDGE_CELL {
+        cuts.insert(0, 0);
+    }
+    if (length - cuts[cuts.len() - 1]) as f64 >= period as f64 * MIN_EDGE_CELL {
+        cuts.push(length);
+    }
+
+    let bands: Vec<(u32, u32)> = cuts
+        .windows(2)
+        .map(|cut| (cut[0] as u32, (cut[1] - cut[0]) as u32))
+        .collect();
+    if bands.len() < 2 {
+        return whole;
+    }
+    bands
+}
+
+/// Finds the size of the cells a side repeats in, by correlating its detail with itself shifted by each distance.
+/// Each correlation is divided by the whole length rather than the overlap, so long shifts that only overlap
+/// for a cell or two don't outscore the size of a single cell.
+///
+/// # Arguments
+///
+/// * `profile` - Detail of each row or column along the side.
+///
+/// # Returns
+///
+/// The size of a cell, or `None` if the side doesn't repeat at least twice.
+fn period(profile: &[f64]) -> Option<usize> {
+    let length = profile.len();
+    let mean = profile.iter().sum::<f64>() / length as f64;
+    let centred: Vec<f64> = profile.iter().map(|value| value - mean).collect();
+    let variance = centred.iter().map(|value| value * value).sum::<f64>() / length as f64;
+    if variance == 0.0 {
+        return None;
+    }
+
+    let correlations: Vec<(usize, f64)> = (MIN_PERIOD..=length / 2)
+        .map(|shift| {
+            let sum: f64 = centred
+                .iter()
+                .zip(&centred[shift..])
+                .map(|(a, b)| a * b)
+                .sum();
+            (shift, sum / length as f64 / variance)
+        })
+        .collect();
+    let strongest = correlations
+        .iter()
+        .map(|&(_, correlation)| correlation)
+        .fold(f64::MIN, f64::max);
+    if strongest < MIN_CORRELATION {
+        return None;
+    }
+
+    // Multiples of the cell size repeat about as well as the cell size itself, so the shortest strong repeat wins,
+    // at the top of its peak.
+    let first = correlations
+        .iter()
+        .position(|&(_, correlation)| correlation >= strongest * SHORTER_PERIOD)?;
+    correlations[first..]
+        .iter()
+        .take_while(|&&(_, correlation)| correlation >= strongest * SHORTER_PERIOD)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|&(shift, _)| shift)
+}
@@ -0,0 +1,106 @@
+use crate::i18n;
+use std::fmt::Display;
+use std::fs;
+use std::mem;
+use std::path::Path;
+
+/// Adds a suggestion of how to fix an error to its message, on a line of its own below it.
+///
+/// # Arguments
+///
+/// * `message` - The error message.
+/// * `help` - What to do about it.
+///
+/// # Returns
+///
+/// The message followed by the suggestion.
+pub fn with_help(message: impl Display, help: impl Display) -> String {
+    format!("{}\n  {}: {}", message, i18n::message("help", &[]), help)
+}
+
+/// A suggestion to rebuild splix with a feature it was built without.
+pub fn rebuild_with(feature: &str) -> String {
+    format!(
+        "Install splix with it: `cargo install splix --features {}`",
+        feature
+    )
+}
+
+/// Suggests the closest of a list of names to one that isn't on it, for typos such as `carousle`.
+///
+/// # Arguments
+///
+/// * `name` - The name that was given.
+/// * `candidates` - The names that would have been accepted.
+///
+/// # Returns
+///
+/// A suggestion, or `None` if no name is close enough to be what was meant.
+pub fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    closest(name, candidates).map(|candidate| format!("Did you mean '{}'?", candidate))
+}
+
+/// Suggests a file next to a path that doesn't exist whose name is close to it, for typos in paths.
+///
+/// # Arguments
+///
+/// * `path` - The path that doesn't exist.
+///
+/// # Returns
+///
+/// A suggestion, or `None` if the directory can't be read or has nothing close.
+pub fn did_you_mean_path(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty());
+    let names: Vec<String> = fs::read_dir(parent.unwrap_or(Path::new(".")))
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    let closest = closest(name, names.iter().map(String::as_str))?;
+    let closest = parent.map_or_else(|| closest.into(), |parent| parent.join(closest));
+    Some(format!("Did you mean '{}'?", closest.display()))
+}
+
+/// Finds the name closest to one that was given, if it's close enough to be a typo of it.
+/// A third of the name may be mistyped, so short names must be nearly right.
+///
+/// # Arguments
+///
+/// * `name` - The name that was given.
+/// * `candidates` - The names that would have been accepted.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Counts the characters that must be inserted, removed, or replaced, or the neighbouring pairs
+/// that must be swapped, to turn one text into another.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // The row of distances two characters of `a` back, to count swapped pairs.
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 0..a.len() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for j in 0..b.len() {
+            let replace = previous[j] + usize::from(a[i] != b[j]);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                current[j + 1] = current[j + 1].min(before[j - 1] + 1);
+            }
+        }
+        before = mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
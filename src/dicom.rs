@@ -0,0 +1,646 @@
+use crate::reduce;
+use clap::ValueEnum;
+use flate2::read::DeflateDecoder;
+use image::error::{DecodingError, ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+use image::{DynamicImage, GrayImage, ImageError, ImageResult, RgbImage};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Window chosen with `--window`, which every DICOM image in the run is mapped to 8 bits with.
+static WINDOW: OnceLock<Window> = OnceLock::new();
+
+/// Transfer syntaxes of uncompressed pixel data.
+const IMPLICIT_LITTLE: &str = "1.2.840.10008.1.2";
+const EXPLICIT_LITTLE: &str = "1.2.840.10008.1.2.1";
+const DEFLATED_LITTLE: &str = "1.2.840.10008.1.2.1.99";
+const EXPLICIT_BIG: &str = "1.2.840.10008.1.2.2";
+
+/// Tags, as group and element packed into 32 bits.
+const TRANSFER_SYNTAX: u32 = 0x0002_0010;
+const SAMPLES_PER_PIXEL: u32 = 0x0028_0002;
+const PHOTOMETRIC: u32 = 0x0028_0004;
+const PLANAR_CONFIGURATION: u32 = 0x0028_0006;
+const NUMBER_OF_FRAMES: u32 = 0x0028_0008;
+const ROWS: u32 = 0x0028_0010;
+const COLUMNS: u32 = 0x0028_0011;
+const BITS_ALLOCATED: u32 = 0x0028_0100;
+const BITS_STORED: u32 = 0x0028_0101;
+const PIXEL_REPRESENTATION: u32 = 0x0028_0103;
+const WINDOW_CENTER: u32 = 0x0028_1050;
+const WINDOW_WIDTH: u32 = 0x0028_1051;
+const RESCALE_INTERCEPT: u32 = 0x0028_1052;
+const RESCALE_SLOPE: u32 = 0x0028_1053;
+const PIXEL_DATA: u32 = 0x7FE0_0010;
+const ITEM: u32 = 0xFFFE_E000;
+const ITEM_END: u32 = 0xFFFE_E00D;
+const SEQUENCE_END: u32 = 0xFFFE_E0DD;
+const UNDEFINED_LENGTH: u32 = u32::MAX;
+
+/// Deepest that sequences may be nested in one another, which keeps crafted files from exhausting the stack.
+const MAX_NESTING: usize = 64;
+
+/// Attributes that describe how an image was acquired and stored without identifying the patient,
+/// which `--dicom-tags` records unless asked for every attribute.
+const SAFE_TAGS: [u32; 26] = [
+    0x0008_0008, // Image Type
+    0x0008_0016, // SOP Class UID
+    0x0008_0060, // Modality
+    0x0008_0070, // Manufacturer
+    0x0008_1090, // Manufacturer's Model Name
+    0x0018_0015, // Body Part Examined
+    0x0018_0050, // Slice Thickness
+    0x0018_0060, // KVP
+    0x0018_5101, // View Position
+    0x0020_0013, // Instance Number
+    0x0020_0020, // Patient Orientation, the directions of the image's rows and columns
+    0x0020_0032, // Image Position (Patient)
+    0x0020_0037, // Image Orientation (Patient)
+    SAMPLES_PER_PIXEL,
+    PHOTOMETRIC,
+    NUMBER_OF_FRAMES,
+    ROWS,
+    COLUMNS,
+    0x0028_0030, // Pixel Spacing
+    BITS_ALLOCATED,
+    BITS_STORED,
+    PIXEL_REPRESENTATION,
+    WINDOW_CENTER,
+    WINDOW_WIDTH,
+    RESCALE_INTERCEPT,
+    RESCALE_SLOPE,
+];
+
+/// Attributes stored as binary unsigned shorts, which are written as numbers.
+const SHORT_TAGS: [u32; 7] = [
+    SAMPLES_PER_PIXEL,
+    PLANAR_CONFIGURATION,
+    ROWS,
+    COLUMNS,
+    BITS_ALLOCATED,
+    BITS_STORED,
+    PIXEL_REPRESENTATION,
+];
+
+/// How the values of grayscale DICOM images are mapped to 8 bits, for `--window`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Window {
+    /// The window stored in the image, or the full range of its values if it has none.
+    #[default]
+    File,
+    /// From the lowest value in the image to the highest.
+    Full,
+    /// A window centred on a level, such as -600,1500 for lungs in a CT scan.
+    Manual { center: f64, width: f64 },
+}
+
+/// Which attributes of DICOM images `--dicom-tags` saves.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum DicomTags {
+    /// Only attributes describing how the image was acquired and stored.
+    Safe,
+    /// Every attribute with a text value, including those that identify the patient.
+    All,
+}
+
+/// Parses a window for `--window`.
+///
+/// # Arguments
+///
+/// * `window` - `file`, `full`, or the centre and width of the window as `CENTER,WIDTH`.
+///
+/// # Returns
+///
+/// The window, or an error message if it isn't valid.
+pub fn parse_window(window: &str) -> Result<Window, String> {
+    match window.trim().to_ascii_lowercase().as_str() {
+        "file" => Ok(Window::File),
+        "full" => Ok(Window::Full),
+        window => window
+            .split_once(',')
+            .and_then(|(center, width)| {
+                Some((center.trim().parse().ok()?, width.trim().parse().ok()?))
+            })
+            .filter(|&(center, width): &(f64, f64)| center.is_finite() && width >= 1.0)
+            .map(|(center, width)| Window::Manual { center, width })
+            .ok_or_else(|| {
+                format!(
+                    "'{}' is not a window: file, full, or CENTER,WIDTH with a width of at least 1, such as 40,400",
+                    window
+                )
+            }),
+    }
+}
+
+/// Chooses the window every DICOM image in the run is decoded with.
+pub fn set_window(window: Window) {
+    let _ = WINDOW.set(window);
+}
+
+/// Checks whether a file is a DICOM image by its extension, if splix was built with the `dicom` feature.
+pub fn is_dicom(path: &Path) -> bool {
+    cfg!(feature = "dicom")
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("dcm") || ext.eq_ignore_ascii_case("dicom"))
+}
+
+/// An element's value representation, if the transfer syntax records them, and value.
+type Element = (Option<[u8; 2]>, Vec<u8>);
+
+/// The top-level elements of a DICOM file, and how its numbers are stored.
+struct Dataset {
+    elements: BTreeMap<u32, Element>,
+    big_endian: bool,
+}
+
+impl Dataset {
+    /// Reads a DICOM file, up to and including its pixel data. Sequences are skipped.
+    fn read(path: &Path) -> ImageResult<Self> {
+        let bytes = fs::read(path)?;
+
+        // Files usually start with a 128-byte preamble and `DICM`, then the file meta information,
+        // which is always explicit little endian. Old files have neither, and are implicit little endian.
+        let (meta, mut offset) = if bytes.get(128..132) == Some(b"DICM") {
+            let mut reader = Reader::new(&bytes, 132, true, false);
+            let mut meta = BTreeMap::new();
+            while reader.peek_group() == Some(0x0002) {
+                let (tag, element) = reader.element()?;
+                meta.insert(tag, element);
+            }
+            (meta, reader.offset)
+        } else {
+            (BTreeMap::new(), 0)
+        };
+
+        let syntax = meta
+            .get(&TRANSFER_SYNTAX)
+            .map_or(IMPLICIT_LITTLE.to_string(), |(_, value)| text(value));
+        let inflated;
+        let (data, explicit, big_endian) = match syntax.as_str() {
+            IMPLICIT_LITTLE => (&bytes[..], false, false),
+            EXPLICIT_LITTLE => (&bytes[..], true, false),
+            EXPLICIT_BIG => (&bytes[..], true, true),
+            DEFLATED_LITTLE => {
+                // Inflate no more than the memory budget, so a small file can't expand without bound.
+                let budget = reduce::default_budget();
+                let mut data = Vec::new();
+                DeflateDecoder::new(&bytes[offset..])
+                    .take(budget + 1)
+                    .read_to_end(&mut data)?;
+                if data.len() as u64 > budget {
+                    return Err(invalid("The data set inflates to more than fits in memory"));
+                }
+                inflated = data;
+                offset = 0;
+                (&inflated[..], true, false)
+            }
+            syntax => {
+                return Err(unsupported(format!(
+                    "The pixel data is compressed with transfer syntax {}, which isn't supported",
+                    syntax
+                )))
+            }
+        };
+
+        let mut reader = Reader::new(data, offset, explicit, big_endian);
+        let mut elements = meta;
+        while reader.offset < data.len() {
+            let (tag, element) = reader.element()?;
+            let last = tag == PIXEL_DATA;
+            elements.insert(tag, element);
+            if last {
+                break;
+            }
+        }
+
+        Ok(Dataset {
+            elements,
+            big_endian,
+        })
+    }
+
+    fn value(&self, tag: u32) -> Option<&[u8]> {
+        self.elements.get(&tag).map(|(_, value)| value.as_slice())
+    }
+
+    /// An unsigned short, such as the number of rows.
+    fn short(&self, tag: u32) -> Option<u32> {
+        let value = self.value(tag)?.get(..2)?;
+        Some(u32::from(if self.big_endian {
+            u16::from_be_bytes([value[0], value[1]])
+        } else {
+            u16::from_le_bytes([value[0], value[1]])
+        }))
+    }
+
+    /// The values of a decimal or integer string, separated by backslashes.
+    fn numbers(&self, tag: u32) -> Vec<f64> {
+        self.value(tag)
+            .map(text)
+            .unwrap_or_default()
+            .split('\\')
+            .filter_map(|value| value.trim().parse().ok())
+            .collect()
+    }
+
+    fn text(&self, tag: u32) -> String {
+        self.value(tag).map(text).unwrap_or_default()
+    }
+}
+
+/// Reads elements from a DICOM data set.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    /// Whether each element records its value representation.
+    explicit: bool,
+    big_endian: bool,
+    /// Number of sequences being skipped that hold the current element.
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], offset: usize, explicit: bool, big_endian: bool) -> Self {
+        Reader {
+            bytes,
+            offset,
+            explicit,
+            big_endian,
+            depth: 0,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> ImageResult<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset.saturating_add(len))
+            .ok_or_else(|| invalid("The file ends in the middle of an element"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> ImageResult<u16> {
+        let bytes = self.take(2)?;
+        Ok(if self.big_endian {
+            u16::from_be_bytes([bytes[0], bytes[1]])
+        } else {
+            u16::from_le_bytes([bytes[0], bytes[1]])
+        })
+    }
+
+    fn u32(&mut self) -> ImageResult<u32> {
+        let bytes: [u8; 4] = self.take(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn tag(&mut self) -> ImageResult<u32> {
+        let group = self.u16()?;
+        let element = self.u16()?;
+        Ok(u32::from(group) << 16 | u32::from(element))
+    }
+
+    fn peek_group(&self) -> Option<u16> {
+        let bytes = self.bytes.get(self.offset..self.offset + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads an element, skipping the items of a sequence.
+    fn element(&mut self) -> ImageResult<(u32, Element)> {
+        let tag = self.tag()?;
+        let (vr, len) = if self.explicit && tag >> 16 != 0xFFFE {
+            let vr: [u8; 2] = self.take(2)?.try_into().unwrap();
+            let len = match &vr {
+                b"OB" | b"OD" | b"OF" | b"OL" | b"OV" | b"OW" | b"SQ" | b"SV" | b"UC" | b"UN"
+                | b"UR" | b"UT" | b"UV" => {
+                    self.take(2)?;
+                    self.u32()?
+                }
+                _ => u32::from(self.u16()?),
+            };
+            (Some(vr), len)
+        } else {
+            (None, self.u32()?)
+        };
+
+        if len != UNDEFINED_LENGTH {
+            return Ok((tag, (vr, self.take(len as usize)?.to_vec())));
+        }
+        if tag == PIXEL_DATA {
+            return Err(unsupported(
+                "The pixel data is compressed, which isn't supported".to_string(),
+            ));
+        }
+        self.skip_sequence()?;
+        Ok((tag, (vr, Vec::new())))
+    }
+
+    /// Skips the items of a sequence of undefined length, up to and including its end.
+    fn skip_sequence(&mut self) -> ImageResult<()> {
+        if self.depth == MAX_NESTING {
+            return Err(invalid("Sequences are nested too deeply"));
+        }
+        self.depth += 1;
+        let skipped = self.skip_items();
+        self.depth -= 1;
+        skipped
+    }
+
+    /// Skips items up to and including the end of the sequence that holds them.
+    fn skip_items(&mut self) -> ImageResult<()> {
+        loop {
+            let tag = self.tag()?;
+            let len = self.u32()?;
+            match tag {
+                SEQUENCE_END => return Ok(()),
+                ITEM if len == UNDEFINED_LENGTH => loop {
+                    if self.peek_tag()? == ITEM_END {
+                        self.take(8)?;
+                        break;
+                    }
+                    self.element()?;
+                },
+                ITEM => {
+                    self.take(len as usize)?;
+                }
+                _ => return Err(invalid("A sequence holds something other than items")),
+            }
+        }
+    }
+
+    fn peek_tag(&mut self) -> ImageResult<u32> {
+        let offset = self.offset;
+        let tag = self.tag();
+        self.offset = offset;
+        tag
+    }
+}
+
+/// Reads the width and height of a DICOM image without reading its pixels' values.
+pub fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    let dataset = Dataset::read(path)?;
+    Ok((
+        dataset.short(COLUMNS).unwrap_or(0),
+        dataset.short(ROWS).unwrap_or(0),
+    ))
+}
+
+/// Decodes every frame of a DICOM image, which must be stored uncompressed.
+/// Grayscale images are rescaled to their modality's values, such as Hounsfield units,
+/// then mapped to 8 bits through the window chosen with [`set_window`], white for high values.
+/// Color images are read as they are.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+///
+/// # Returns
+///
+/// The frames, or the error that kept the image from being decoded.
+pub fn decode(path: &Path) -> ImageResult<Vec<DynamicImage>> {
+    let dataset = Dataset::read(path)?;
+    let width = dataset.short(COLUMNS).unwrap_or(0);
+    let height = dataset.short(ROWS).unwrap_or(0);
+    let samples = dataset.short(SAMPLES_PER_PIXEL).unwrap_or(1);
+    let bits = dataset.short(BITS_ALLOCATED).unwrap_or(8);
+    let stored = dataset.short(BITS_STORED).unwrap_or(bits).clamp(1, bits);
+    let signed = dataset.short(PIXEL_REPRESENTATION) == Some(1);
+    let photometric = dataset.text(PHOTOMETRIC);
+    let frames = dataset
+        .numbers(NUMBER_OF_FRAMES)
+        .first()
+        .map_or(1, |&frames| frames.max(1.0) as usize);
+    let pixels = dataset
+        .value(PIXEL_DATA)
+        .ok_or_else(|| invalid("The file has no pixel data"))?;
+
+    if width == 0 || height == 0 {
+        return Err(invalid("The image has no rows or columns"));
+    }
+    let pixel_count = width as usize * height as usize;
+
+    match (samples, photometric.as_str()) {
+        (1, "MONOCHROME1" | "MONOCHROME2") => {}
+        (3, "RGB" | "YBR_FULL") if bits == 8 => {
+            let frame_len = pixel_count * 3;
+            check_length(pixels, frame_len, frames)?;
+            let planar = dataset.short(PLANAR_CONFIGURATION) == Some(1);
+            return Ok(pixels
+                .chunks_exact(frame_len)
+                .take(frames)
+                .map(|frame| {
+                    let mut rgb = vec![0; frame_len];
+                    for i in 0..pixel_count {
+                        let sample = |channel: usize| {
+                            if planar {
+                                frame[channel * pixel_count + i]
+                            } else {
+                                frame[i * 3 + channel]
+                            }
+                        };
+                        let pixel = [sample(0), sample(1), sample(2)];
+                        let pixel = if photometric == "YBR_FULL" {
+                            ybr_to_rgb(pixel)
+                        } else {
+                            pixel
+                        };
+                        rgb[i * 3..i * 3 + 3].copy_from_slice(&pixel);
+                    }
+                    DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, rgb).unwrap())
+                })
+                .collect());
+        }
+        _ => {
+            return Err(unsupported(format!(
+                "{} images with {} bits and {} samples per pixel aren't supported",
+                photometric, bits, samples
+            )))
+        }
+    }
+
+    if !matches!(bits, 8 | 16 | 32) {
+        return Err(unsupported(format!(
+            "Grayscale images with {} bits allocated per pixel aren't supported",
+            bits
+        )));
+    }
+    let sample_len = bits as usize / 8;
+    let frame_len = pixel_count * sample_len;
+    check_length(pixels, frame_len, frames)?;
+
+    let slope = dataset
+        .numbers(RESCALE_SLOPE)
+        .first()
+        .copied()
+        .unwrap_or(1.0);
+    let intercept = dataset
+        .numbers(RESCALE_INTERCEPT)
+        .first()
+        .copied()
+        .unwrap_or(0.0);
+    let stored_window = dataset
+        .numbers(WINDOW_CENTER)
+        .first()
+        .zip(dataset.numbers(WINDOW_WIDTH).first())
+        .filter(|(_, &width)| width >= 1.0)
+        .map(|(&center, &width)| (center, width));
+
+    Ok(pixels
+        .chunks_exact(frame_len)
+        .take(frames)
+        .map(|frame| {
+            let values: Vec<f64> = frame
+                .chunks_exact(sample_len)
+                .map(|sample| {
+                    let raw = match (sample_len, dataset.big_endian) {
+                        (1, _) => u32::from(sample[0]),
+                        (2, false) => u32::from(u16::from_le_bytes([sample[0], sample[1]])),
+                        (2, true) => u32::from(u16::from_be_bytes([sample[0], sample[1]])),
+                        (_, false) => u32::from_le_bytes(sample.try_into().unwrap()),
+                        (_, true) => u32::from_be_bytes(sample.try_into().unwrap()),
+                    };
+                    stored_value(raw, stored, signed) * slope + intercept
+                })
+                .collect();
+
+            let window = match WINDOW.get().copied().unwrap_or_default() {
+                Window::Manual { center, width } => (center, width),
+                Window::File if stored_window.is_some() => stored_window.unwrap(),
+                Window::File | Window::Full => full_window(&values),
+            };
+            let invert = photometric == "MONOCHROME1";
+            let gray = values
+                .iter()
+                .map(|&value| {
+                    let level = apply_window(value, window);
+                    if invert {
+                        u8::MAX - level
+                    } else {
+                        level
+                    }
+                })
+                .collect();
+
+            DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, gray).unwrap())
+        })
+        .collect())
+}
+
+/// Takes the stored bits of a sample, sign-extending them if the image's values are signed.
+fn stored_value(raw: u32, stored: u32, signed: bool) -> f64 {
+    let value = if stored < 32 {
+        raw & ((1 << stored) - 1)
+    } else {
+        raw
+    };
+    if signed && value >> (stored - 1) & 1 == 1 {
+        (i64::from(value) - (1i64 << stored)) as f64
+    } else {
+        f64::from(value)
+    }
+}
+
+/// A window from the lowest to the highest of some values.
+fn full_window(values: &[f64]) -> (f64, f64) {
+    let (min, max) = values
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+    if min > max {
+        return (0.0, 1.0);
+    }
+    // The linear function of PS3.3 C.11.2.1.2 maps `center - 0.5 ± (width - 1) / 2` to black and white.
+    ((min + max) / 2.0 + 0.5, (max - min + 1.0).max(1.0))
+}
+
+/// Maps a value to 8 bits through a window, with the linear function of PS3.3 C.11.2.1.2.
+fn apply_window(value: f64, (center, width): (f64, f64)) -> u8 {
+    let position = (value - (center - 0.5)) / (width - 1.0).max(f64::MIN_POSITIVE) + 0.5;
+    (position.clamp(0.0, 1.0) * u8::MAX as f64).round() as u8
+}
+
+/// Converts a full-range YCbCr pixel to RGB.
+fn ybr_to_rgb([y, cb, cr]: [u8; 3]) -> [u8; 3] {
+    let (y, cb, cr) = (f64::from(y), f64::from(cb) - 128.0, f64::from(cr) - 128.0);
+    [
+        y + 1.402 * cr,
+        y - 0.344_136 * cb - 0.714_136 * cr,
+        y + 1.772 * cb,
+    ]
+    .map(|value| value.round().clamp(0.0, 255.0) as u8)
+}
+
+/// Describes the attributes of a DICOM image as JSON, for `--dicom-tags`,
+/// keyed by group and element as in the DICOM JSON model, each with a list of its values.
+/// Only attributes describing how the image was acquired and stored are included,
+/// leaving out those that could identify the patient, such as their name, ID, and birth date,
+/// and the dates, institution, and physicians of the study, unless `all` is given.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+/// * `all` - Whether to include every attribute with a text value, including identifying ones.
+///
+/// # Returns
+///
+/// The JSON, or the error that kept the image from being read.
+pub fn tags(path: &Path, all: bool) -> ImageResult<String> {
+    let dataset = Dataset::read(path)?;
+    let mut tags = serde_json::Map::new();
+    for (&tag, (vr, value)) in &dataset.elements {
+        if tag == PIXEL_DATA || tag >> 16 == 0x0002 || !(all || SAFE_TAGS.contains(&tag)) {
+            continue;
+        }
+
+        let values: Vec<serde_json::Value> = if SHORT_TAGS.contains(&tag) {
+            dataset.short(tag).into_iter().map(Into::into).collect()
+        } else {
+            // Binary values, which implicit transfer syntaxes don't mark, are left out.
+            let binary = vr.is_some_and(|vr| vr[0] == b'O' || matches!(&vr, b"SQ" | b"UN"));
+            let value = text(value);
+            if binary || value.chars().any(|c| c.is_control()) {
+                continue;
+            }
+            value.split('\\').map(|value| value.trim().into()).collect()
+        };
+        tags.insert(format!("{:08X}", tag), values.into());
+    }
+
+    Ok(serde_json::to_string_pretty(&tags).unwrap())
+}
+
+/// Checks that pixel data holds every frame, refusing frame counts whose total length overflows.
+fn check_length(pixels: &[u8], frame_len: usize, frames: usize) -> ImageResult<()> {
+    match frame_len.checked_mul(frames) {
+        Some(len) if len <= pixels.len() => Ok(()),
+        _ => Err(invalid("The pixel data is shorter than the image")),
+    }
+}
+
+/// A text value, without the padding that makes its length even.
+fn text(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+fn invalid(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("DICOM".to_string()),
+        message,
+    ))
+}
+
+fn unsupported(message: String) -> ImageError {
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        ImageFormatHint::Name("DICOM".to_string()),
+        UnsupportedErrorKind::GenericFeature(message),
+    ))
+}
@@ -0,0 +1,75 @@
+use crate::compare;
+use image::{DynamicImage, ImageReader};
+use splix::grid::Cell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How closely a tile matches the region of the source image it was split from.
+pub struct Similarity {
+    /// Peak signal-to-noise ratio in decibels, infinite if every pixel matches.
+    pub psnr: f64,
+    /// Structural similarity, 1 if the tile looks identical to the region.
+    pub ssim: f64,
+}
+
+/// Compares a saved tile with the region of the source image it was split from.
+///
+/// # Arguments
+///
+/// * `source` - Decoded source image.
+/// * `cell` - Region of the source image the tile covers.
+/// * `file` - Path of the tile.
+///
+/// # Returns
+///
+/// The similarity, or an error message if the tile can't be read or isn't the size of its region.
+pub fn compare_tile(source: &DynamicImage, cell: &Cell, file: &Path) -> Result<Similarity, String> {
+    let tile = ImageReader::open(file)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|err| err.to_string())?
+        .decode()
+        .map_err(|err| err.to_string())?;
+
+    if (tile.width(), tile.height()) != (cell.width, cell.height) {
+        return Err(format!(
+            "The tile is {}x{}, but its region of the image is {}x{}",
+            tile.width(),
+            tile.height(),
+            cell.width,
+            cell.height
+        ));
+    }
+
+    let region = source.crop_imm(cell.x, cell.y, cell.width, cell.height);
+    Ok(Similarity {
+        psnr: compare::psnr(&region.to_rgba16(), &tile.to_rgba16()),
+        ssim: compare::ssim(&region, &tile),
+    })
+}
+
+/// Finds the tiles of an image in a directory by their default names, `{stem}-r{row}c{col}.{ext}`.
+///
+/// # Arguments
+///
+/// * `dir` - Directory holding the tiles.
+/// * `stem` - File name of the source image, without its extension.
+///
+/// # Returns
+///
+/// The row, column, and path of each tile, ordered by row and column.
+pub fn find_tiles(dir: &Path, stem: &str) -> io::Result<Vec<(usize, usize, PathBuf)>> {
+    let prefix = format!("{}-r", stem);
+
+    let mut tiles: Vec<(usize, usize, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_stem()?.to_str()?;
+            let (row, col) = name.strip_prefix(&prefix)?.split_once('c')?;
+            Some((row.parse().ok()?, col.parse().ok()?, path))
+        })
+        .collect();
+
+    tiles.sort();
+    Ok(tiles)
+}
@@ -0,0 +1,101 @@
+use crate::encode::EncodeOptions;
+use crate::selftest::{self, RoundTrip};
+use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb};
+use splix::grid;
+
+/// Optional features of splix, in the order they're listed in `Cargo.toml`.
+const FEATURES: [(&str, bool); 8] = [
+    ("async", cfg!(feature = "async")),
+    ("clipboard", cfg!(feature = "clipboard")),
+    ("dicom", cfg!(feature = "dicom")),
+    ("hdr", cfg!(feature = "hdr")),
+    ("openslide", cfg!(feature = "openslide")),
+    ("python", cfg!(feature = "python")),
+    ("s3", cfg!(feature = "s3")),
+    ("wasm", cfg!(feature = "wasm")),
+];
+
+/// Formats split in the self-test, one lossless and one lossy.
+const SELF_TEST_FORMATS: [ImageFormat; 2] = [ImageFormat::Png, ImageFormat::Jpeg];
+
+/// Whether splix was built with each of its optional features.
+pub fn features() -> &'static [(&'static str, bool)] {
+    &FEATURES
+}
+
+/// Whether splix can read and write each format of the image crate it was built with.
+///
+/// # Returns
+///
+/// Each format's extensions, and whether it can be read and written.
+pub fn formats() -> Vec<(&'static [&'static str], bool, bool)> {
+    ImageFormat::all()
+        .map(|format| {
+            (
+                format.extensions_str(),
+                format.reading_enabled(),
+                format.writing_enabled(),
+            )
+        })
+        .collect()
+}
+
+/// The SIMD instruction sets the CPU has, which some decoders and resizing use when they're present.
+///
+/// # Returns
+///
+/// Each instruction set splix's dependencies look for on this architecture, and whether the CPU has it.
+pub fn simd() -> Vec<(&'static str, bool)> {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        vec![
+            ("sse2", is_x86_feature_detected!("sse2")),
+            ("sse4.1", is_x86_feature_detected!("sse4.1")),
+            ("avx", is_x86_feature_detected!("avx")),
+            ("avx2", is_x86_feature_detected!("avx2")),
+            ("avx512f", is_x86_feature_detected!("avx512f")),
+        ]
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        vec![("neon", std::arch::is_aarch64_feature_detected!("neon"))]
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        vec![("simd128", cfg!(target_feature = "simd128"))]
+    }
+    #[cfg(not(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "wasm32"
+    )))]
+    {
+        Vec::new()
+    }
+}
+
+/// Splits a small generated image into a grid of tiles in memory, in a lossless and a lossy format,
+/// and joins the tiles back together, to check the installation splits images correctly.
+///
+/// # Returns
+///
+/// Each format tested, how closely its rejoined image matched, and whether that passed.
+pub fn self_test() -> Vec<(ImageFormat, RoundTrip, bool)> {
+    // Uneven sides, so the grid's last row and column are a different size.
+    let img = DynamicImage::ImageRgb8(ImageBuffer::from_fn(67, 45, |x, y| {
+        Rgb([(x * 3) as u8, (y * 5) as u8, ((x + y) * 2) as u8])
+    }));
+    let rows = grid::parse_spec(&[1.0, 2.0], "rows").unwrap();
+    let cols = grid::parse_spec(&[3.0], "cols").unwrap();
+    let cells = grid::grid_cells(img.width(), img.height(), &rows, &cols);
+
+    SELF_TEST_FORMATS
+        .into_iter()
+        .map(|format| {
+            let result = selftest::round_trip(&img, &cells, format, &EncodeOptions::default());
+            let passed = result.passed(format, 30.0);
+            (format, result, passed)
+        })
+        .collect()
+}
@@ -0,0 +1,281 @@
+use crate::png_writer;
+use splix::grid::Cell;
+
+/// Namespace of the XMP properties describing where a tile came from.
+const XMP_NAMESPACE: &str = "https://github.com/rymdtian/splix/ns/tile/1.0/";
+
+/// Header of a JPEG APP1 segment holding XMP.
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Header of a JPEG APP1 segment holding EXIF.
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+/// EXIF tag holding a description of the image.
+const IMAGE_DESCRIPTION: u16 = 0x010E;
+
+/// WebP VP8X flags for EXIF, XMP, and alpha.
+const VP8X_EXIF: u8 = 0x08;
+const VP8X_XMP: u8 = 0x04;
+const VP8X_ALPHA: u8 = 0x10;
+
+/// Where a tile came from, for `--tile-metadata`.
+pub struct TileOrigin<'a> {
+    /// File name of the image the tile was cut from.
+    pub source: &'a str,
+    /// Width and height of the image, as it was split.
+    pub source_size: (u32, u32),
+    /// Region of the image the tile was cut from.
+    pub cell: &'a Cell,
+    /// Frame of the image, or 0 for a still image.
+    pub frame: usize,
+    /// Zoom level of map tiles, or 0 for other layouts.
+    pub zoom: u32,
+    /// Grid of `--grid`, or empty without it.
+    pub grid: &'a str,
+    /// Resolution of `--levels`, or 0 without it.
+    pub level: u32,
+    /// Plane of `--channels`, or empty without it.
+    pub channel: &'a str,
+}
+
+impl TileOrigin<'_> {
+    /// Each property recorded, as its XMP name and value. Frames, zoom levels, grids, levels,
+    /// and planes are left out when they're the default.
+    fn properties(&self) -> Vec<(&'static str, String)> {
+        let cell = self.cell;
+        let mut properties = vec![
+            ("Source", self.source.to_string()),
+            ("SourceWidth", self.source_size.0.to_string()),
+            ("SourceHeight", self.source_size.1.to_string()),
+            ("Row", cell.row.to_string()),
+            ("Col", cell.col.to_string()),
+            ("X", cell.x.to_string()),
+            ("Y", cell.y.to_string()),
+            ("Width", cell.width.to_string()),
+            ("Height", cell.height.to_string()),
+        ];
+        if self.frame > 0 {
+            properties.push(("Frame", self.frame.to_string()));
+        }
+        if self.zoom > 0 {
+            properties.push(("Zoom", self.zoom.to_string()));
+        }
+        if !self.grid.is_empty() {
+            properties.push(("Grid", self.grid.to_string()));
+        }
+        if self.level > 0 {
+            properties.push(("Level", self.level.to_string()));
+        }
+        if !self.channel.is_empty() {
+            properties.push(("Channel", self.channel.to_string()));
+        }
+        properties
+    }
+
+    /// A one-line description of the tile, for viewers that show EXIF but not XMP.
+    fn description(&self) -> String {
+        let cell = self.cell;
+        format!(
+            "Row {} column {} of {}, {}x{} at {},{} of {}x{}",
+            cell.row,
+            cell.col,
+            self.source,
+            cell.width,
+            cell.height,
+            cell.x,
+            cell.y,
+            self.source_size.0,
+            self.source_size.1
+        )
+    }
+
+    /// An XMP packet holding the properties.
+    fn xmp(&self) -> Vec<u8> {
+        let properties: String = self
+            .properties()
+            .into_iter()
+            .map(|(name, value)| format!("\n   splix:{}=\"{}\"", name, escape(&value)))
+            .collect();
+        format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+             <rdf:Description rdf:about=\"\"\n   xmlns:splix=\"{}\"{}/>\n \
+             </rdf:RDF>\n\
+             </x:xmpmeta>\n\
+             <?xpacket end=\"w\"?>",
+            XMP_NAMESPACE, properties
+        )
+        .into_bytes()
+    }
+
+    /// A little-endian EXIF block, as stored after a TIFF header, holding the description.
+    fn exif(&self) -> Vec<u8> {
+        let mut description = self.description().into_bytes();
+        description.push(0);
+
+        // The header, then one directory entry pointing past the directory to the description.
+        let mut exif = b"II*\0".to_vec();
+        exif.extend(8u32.to_le_bytes());
+        exif.extend(1u16.to_le_bytes());
+        exif.extend(IMAGE_DESCRIPTION.to_le_bytes());
+        // ASCII.
+        exif.extend(2u16.to_le_bytes());
+        exif.extend((description.len() as u32).to_le_bytes());
+        exif.extend(26u32.to_le_bytes());
+        // No further directories.
+        exif.extend(0u32.to_le_bytes());
+        exif.extend(description);
+        exif
+    }
+}
+
+/// Writes where a tile came from into its metadata, as XMP and an EXIF description.
+/// PNG, JPEG, and WebP tiles are recognized by their contents, and other formats are left as they are.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded tile.
+/// * `origin` - Where the tile came from.
+///
+/// # Returns
+///
+/// The tile with its metadata.
+pub fn embed(bytes: Vec<u8>, origin: &TileOrigin) -> Vec<u8> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        embed_png(bytes, origin)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        embed_jpeg(bytes, origin)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        embed_webp(bytes, origin)
+    } else {
+        bytes
+    }
+}
+
+/// Adds an `eXIf` chunk and an `iTXt` chunk with the XMP after the PNG's header.
+fn embed_png(bytes: Vec<u8>, origin: &TileOrigin) -> Vec<u8> {
+    // The signature, then the IHDR chunk: its length, type, 13 bytes of data, and checksum.
+    let header_end = 8 + 4 + 4 + 13 + 4;
+    if bytes.len() < header_end || &bytes[12..16] != b"IHDR" {
+        return bytes;
+    }
+
+    // The keyword, then no compression, no language, and no translated keyword.
+    let mut itxt = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+    itxt.extend(origin.xmp());
+
+    let mut embedded = bytes[..header_end].to_vec();
+    png_writer::write_chunk(&mut embedded, b"eXIf", &origin.exif());
+    png_writer::write_chunk(&mut embedded, b"iTXt", &itxt);
+    embedded.extend(&bytes[header_end..]);
+    embedded
+}
+
+/// Adds APP1 segments with EXIF and XMP after the JPEG's start, or after its JFIF segment if it has one.
+fn embed_jpeg(bytes: Vec<u8>, origin: &TileOrigin) -> Vec<u8> {
+    let mut insert_at = 2;
+    if bytes.len() >= 6 && bytes[2..4] == [0xFF, 0xE0] {
+        insert_at = 4 + usize::from(u16::from_be_bytes([bytes[4], bytes[5]]));
+    }
+    if insert_at > bytes.len() {
+        return bytes;
+    }
+
+    let mut embedded = bytes[..insert_at].to_vec();
+    for (header, data) in [
+        (JPEG_EXIF_HEADER, origin.exif()),
+        (JPEG_XMP_HEADER, origin.xmp()),
+    ] {
+        // The length counts itself, but not the marker.
+        let len = 2 + header.len() + data.len();
+        let Ok(len) = u16::try_from(len) else {
+            continue;
+        };
+        embedded.extend([0xFF, 0xE1]);
+        embedded.extend(len.to_be_bytes());
+        embedded.extend(header);
+        embedded.extend(data);
+    }
+    embedded.extend(&bytes[insert_at..]);
+    embedded
+}
+
+/// Adds `EXIF` and `XMP ` chunks to the end of a WebP, which needs an extended `VP8X` header to list them.
+/// A simple WebP is given one, sized from its `VP8 ` or `VP8L` chunk.
+fn embed_webp(bytes: Vec<u8>, origin: &TileOrigin) -> Vec<u8> {
+    let Some(chunk) = bytes.get(12..20) else {
+        return bytes;
+    };
+    let kind: [u8; 4] = chunk[..4].try_into().unwrap();
+    let data = &bytes[20..];
+
+    let mut embedded = b"RIFF\0\0\0\0WEBP".to_vec();
+    let flags = VP8X_EXIF | VP8X_XMP;
+    match &kind {
+        b"VP8X" => {
+            embedded.extend(&bytes[12..]);
+            embedded[20] |= flags;
+        }
+        b"VP8 " | b"VP8L" => {
+            let Some((width, height, alpha)) = webp_size(&kind, data) else {
+                return bytes;
+            };
+            let mut vp8x = vec![flags | if alpha { VP8X_ALPHA } else { 0 }, 0, 0, 0];
+            vp8x.extend(&(width - 1).to_le_bytes()[..3]);
+            vp8x.extend(&(height - 1).to_le_bytes()[..3]);
+            write_webp_chunk(&mut embedded, b"VP8X", &vp8x);
+            embedded.extend(&bytes[12..]);
+        }
+        _ => return bytes,
+    }
+    write_webp_chunk(&mut embedded, b"EXIF", &origin.exif());
+    write_webp_chunk(&mut embedded, b"XMP ", &origin.xmp());
+
+    let riff_len = (embedded.len() - 8) as u32;
+    embedded[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    embedded
+}
+
+/// Reads the width, height, and whether there's alpha from the data of a WebP's `VP8 ` or `VP8L` chunk.
+fn webp_size(kind: &[u8; 4], data: &[u8]) -> Option<(u32, u32, bool)> {
+    if kind == b"VP8L" {
+        // A signature byte, then 14 bits each of the width and height less one, and a bit for alpha.
+        let bits = u32::from_le_bytes(data.get(1..5)?.try_into().ok()?);
+        if data[0] != 0x2F {
+            return None;
+        }
+        Some((
+            (bits & 0x3FFF) + 1,
+            ((bits >> 14) & 0x3FFF) + 1,
+            bits >> 28 & 1 == 1,
+        ))
+    } else {
+        // A frame tag, a start code, then 14 bits each of the width and height.
+        let frame = data.get(..10)?;
+        if frame[3..6] != [0x9D, 0x01, 0x2A] {
+            return None;
+        }
+        let width = u32::from(u16::from_le_bytes([frame[6], frame[7]]) & 0x3FFF);
+        let height = u32::from(u16::from_le_bytes([frame[8], frame[9]]) & 0x3FFF);
+        Some((width, height, false))
+    }
+}
+
+/// Appends a RIFF chunk, padded to an even length.
+fn write_webp_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend(kind);
+    bytes.extend((data.len() as u32).to_le_bytes());
+    bytes.extend(data);
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+}
+
+/// Escapes text for an XML attribute.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
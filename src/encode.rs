@@ -0,0 +1,360 @@
+use crate::png_writer;
+use crate::quantize::{self, Dither};
+use crate::tiff_writer::{self, TiffCompression};
+use clap::ValueEnum;
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::{
+    ColorType, DynamicImage, GenericImageView, ImageBuffer, ImageError, ImageFormat, ImageResult,
+    Rgb, Rgba,
+};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::{self, Cursor};
+
+/// Speed used for AVIF tiles when a quality is given, the same as the encoder's default.
+const AVIF_SPEED: u8 = 4;
+
+/// Quality used for progressive JPEG tiles when none is given, the same as the baseline encoder's default.
+const JPEG_QUALITY: u8 = 75;
+
+/// Average difference between neighbouring pixels, out of 255, at which a tile counts as fully detailed.
+const FULL_DETAIL_GRADIENT: f64 = 16.0;
+
+/// Extension given to `--ext` to choose the format of each tile by its contents.
+pub const AUTO_EXT: &str = "auto";
+
+/// Most colors a tile can have and still count as flat graphics, such as a diagram or a screenshot of text.
+const FLAT_COLORS: usize = 256;
+
+/// Fraction of pixels matching the one to their left above which a tile counts as flat graphics,
+/// even with more colors than [`FLAT_COLORS`], such as antialiased text on a plain background.
+const FLAT_REPEATS: f64 = 0.5;
+
+/// Color transparent tiles are composited onto when `--background` isn't given.
+const DEFAULT_BACKGROUND: [u8; 3] = [255, 255, 255];
+
+/// What to do with a tile that has transparency when its format can't store it.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum AlphaFallback {
+    /// Composite the tile onto `--background`, keeping the requested format.
+    #[default]
+    Flatten,
+    /// Save the tile as a PNG instead.
+    Png,
+    /// Save the tile as a lossless WebP instead.
+    Webp,
+}
+
+impl AlphaFallback {
+    /// The format to save a tile in instead, if any.
+    pub fn format(self) -> Option<ImageFormat> {
+        match self {
+            AlphaFallback::Flatten => None,
+            AlphaFallback::Png => Some(ImageFormat::Png),
+            AlphaFallback::Webp => Some(ImageFormat::WebP),
+        }
+    }
+}
+
+/// Settings for encoding tiles.
+#[derive(Clone, Copy, Default)]
+pub struct EncodeOptions {
+    /// Quality from 1 to 100 for lossy formats, or the encoder's default if not given.
+    pub quality: Option<u8>,
+    /// Lowest and highest quality to choose from by each tile's detail, instead of a fixed quality.
+    pub adaptive: Option<(u8, u8)>,
+    /// Whether JPEG tiles are saved as progressive JPEGs instead of baseline.
+    pub progressive: bool,
+    /// Whether PNG tiles are saved with Adam7 interlacing.
+    pub interlace: bool,
+    /// Number of colors to reduce PNG tiles to, saving them with a palette.
+    pub quantize: Option<u16>,
+    /// How colors missing from the palette are approximated when quantizing.
+    pub dither: Dither,
+    /// How TIFF tiles are compressed.
+    pub tiff_compression: TiffCompression,
+    /// Color to composite tiles with transparency onto when their format can't store it, or white if not given.
+    pub background: Option<[u8; 3]>,
+}
+
+impl EncodeOptions {
+    /// The quality to encode a tile at, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `img` - Tile to encode.
+    pub fn quality_for(&self, img: &DynamicImage) -> Option<u8> {
+        match self.adaptive {
+            Some((min, max)) => Some(min + ((max - min) as f64 * detail(img)).round() as u8),
+            None => self.quality,
+        }
+    }
+}
+
+/// Parses a range of qualities for `--adaptive-quality`, written as `MIN-MAX`.
+///
+/// # Arguments
+///
+/// * `range` - Range to parse.
+///
+/// # Returns
+///
+/// The lowest and highest quality, or an error message if it isn't a valid range.
+pub fn parse_quality_range(range: &str) -> Result<(u8, u8), String> {
+    range
+        .split_once('-')
+        .and_then(|(min, max)| Some((min.trim().parse().ok()?, max.trim().parse().ok()?)))
+        .filter(|&(min, max): &(u8, u8)| 1 <= min && min <= max && max <= 100)
+        .ok_or_else(|| {
+            format!(
+                "'{}' is not a range of qualities from 1 to 100, such as 40-90",
+                range
+            )
+        })
+}
+
+/// Chooses the format of a tile by its contents, for `--ext auto`.
+/// Photographic tiles are saved as JPEG, which is far smaller for them, and tiles of flat graphics or with
+/// transparency as PNG, which keeps their hard edges and alpha that JPEG would blur or lose.
+///
+/// # Arguments
+///
+/// * `tile` - Pixels of the tile.
+pub fn auto_format(tile: &impl GenericImageView<Pixel = Rgba<u8>>) -> ImageFormat {
+    let mut colors = HashSet::new();
+    let (mut repeats, mut pixels) = (0u64, 0u64);
+    let mut left = None;
+
+    for (x, _, pixel) in tile.pixels() {
+        if pixel[3] != u8::MAX {
+            return ImageFormat::Png;
+        }
+        if colors.len() <= FLAT_COLORS {
+            colors.insert(pixel.0);
+        }
+        if x > 0 && left == Some(pixel) {
+            repeats += 1;
+        }
+        left = Some(pixel);
+        pixels += 1;
+    }
+
+    if colors.len() <= FLAT_COLORS || repeats as f64 > pixels as f64 * FLAT_REPEATS {
+        ImageFormat::Png
+    } else {
+        ImageFormat::Jpeg
+    }
+}
+
+/// Whether a format can't store transparency, so tiles with it are handled by `--alpha-fallback`.
+pub fn drops_alpha(format: ImageFormat) -> bool {
+    matches!(format, ImageFormat::Jpeg | ImageFormat::Hdr)
+}
+
+/// Whether any pixel of a tile is less than fully opaque.
+///
+/// # Arguments
+///
+/// * `tile` - Pixels of the tile.
+pub fn has_transparency(tile: &impl GenericImageView<Pixel = Rgba<u8>>) -> bool {
+    tile.pixels().any(|(_, _, pixel)| pixel[3] != u8::MAX)
+}
+
+/// Composites a tile onto a solid color, keeping its bit depth.
+///
+/// # Arguments
+///
+/// * `img` - Tile with an alpha channel.
+/// * `background` - Color to composite it onto.
+fn flatten(img: &DynamicImage, background: [u8; 3]) -> DynamicImage {
+    let rgba = img.to_rgba32f();
+    let background = background.map(|value| value as f32 / u8::MAX as f32);
+    let flat = ImageBuffer::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let pixel = rgba.get_pixel(x, y).0;
+        let alpha = pixel[3].clamp(0.0, 1.0);
+        Rgb([0, 1, 2].map(|i| pixel[i] * alpha + background[i] * (1.0 - alpha)))
+    });
+    let flat = DynamicImage::ImageRgb32F(flat);
+
+    match img.color() {
+        ColorType::La8 | ColorType::Rgba8 => DynamicImage::ImageRgb8(flat.to_rgb8()),
+        ColorType::La16 | ColorType::Rgba16 => DynamicImage::ImageRgb16(flat.to_rgb16()),
+        _ => flat,
+    }
+}
+
+/// Encodes a tile as a progressive JPEG, which is shown at low detail while it loads and sharpens as the rest arrives.
+/// The `image` crate only writes baseline JPEGs, so this uses a separate encoder.
+fn encode_progressive_jpeg(
+    img: &DynamicImage,
+    quality: u8,
+    bytes: &mut Vec<u8>,
+) -> ImageResult<()> {
+    let (Ok(width), Ok(height)) = (u16::try_from(img.width()), u16::try_from(img.height())) else {
+        return Err(ImageError::IoError(io::Error::other(
+            "JPEG images can't be wider or taller than 65535 pixels",
+        )));
+    };
+
+    let mut encoder = jpeg_encoder::Encoder::new(bytes, quality);
+    encoder.set_progressive(true);
+
+    let result = if img.color().has_color() {
+        encoder.encode(&img.to_rgb8(), width, height, jpeg_encoder::ColorType::Rgb)
+    } else {
+        encoder.encode(
+            &img.to_luma8(),
+            width,
+            height,
+            jpeg_encoder::ColorType::Luma,
+        )
+    };
+    result.map_err(|err| ImageError::IoError(io::Error::other(err.to_string())))
+}
+
+/// Measures how much detail a tile has, from 0 for a flat color to 1 for busy texture.
+/// Combines the entropy of its brightness with the average difference between neighbouring pixels,
+/// so both smooth gradients and noisy flat areas score lower than real detail.
+fn detail(img: &DynamicImage) -> f64 {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+
+    let mut histogram = [0u64; 256];
+    let (mut gradient, mut pairs) = (0u64, 0u64);
+    for (x, y, pixel) in luma.enumerate_pixels() {
+        let value = pixel[0];
+        histogram[value as usize] += 1;
+
+        if x + 1 < width {
+            gradient += value.abs_diff(luma.get_pixel(x + 1, y)[0]) as u64;
+            pairs += 1;
+        }
+        if y + 1 < height {
+            gradient += value.abs_diff(luma.get_pixel(x, y + 1)[0]) as u64;
+            pairs += 1;
+        }
+    }
+
+    let pixels = (width * height) as f64;
+    let entropy: f64 = histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / pixels;
+            -p * p.log2()
+        })
+        .sum();
+    let gradient = if pairs == 0 {
+        0.0
+    } else {
+        gradient as f64 / pairs as f64
+    };
+
+    (entropy / 8.0 + (gradient / FULL_DETAIL_GRADIENT).min(1.0)) / 2.0
+}
+
+/// Converts a tile to a color type a format can store, keeping as much of its precision as the format allows.
+/// OpenEXR and Radiance HDR only store float samples, TIFF can store them too,
+/// and other formats round them to 16 or 8 bits.
+///
+/// # Arguments
+///
+/// * `img` - Tile to convert.
+/// * `format` - Format the tile will be encoded in.
+///
+/// # Returns
+///
+/// The tile, converted only if the format can't store it as it is.
+fn for_format(img: &DynamicImage, format: ImageFormat) -> Cow<'_, DynamicImage> {
+    let color = img.color();
+    let float = matches!(color, ColorType::Rgb32F | ColorType::Rgba32F);
+
+    let converted = match format {
+        ImageFormat::OpenExr if float => return Cow::Borrowed(img),
+        ImageFormat::OpenExr if color.has_alpha() => DynamicImage::ImageRgba32F(img.to_rgba32f()),
+        ImageFormat::OpenExr => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+        ImageFormat::Hdr if color == ColorType::Rgb32F => return Cow::Borrowed(img),
+        ImageFormat::Hdr => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+        ImageFormat::Qoi | ImageFormat::Gif
+            if matches!(color, ColorType::Rgb8 | ColorType::Rgba8) =>
+        {
+            return Cow::Borrowed(img)
+        }
+        ImageFormat::Qoi | ImageFormat::Gif if color.has_alpha() => {
+            DynamicImage::ImageRgba8(img.to_rgba8())
+        }
+        ImageFormat::Qoi | ImageFormat::Gif => DynamicImage::ImageRgb8(img.to_rgb8()),
+        ImageFormat::Tiff if float => return Cow::Borrowed(img),
+        // Few TIFF readers, `image` among them, open gray images with alpha.
+        ImageFormat::Tiff if color == ColorType::La8 => DynamicImage::ImageRgba8(img.to_rgba8()),
+        ImageFormat::Tiff if color == ColorType::La16 => DynamicImage::ImageRgba16(img.to_rgba16()),
+        ImageFormat::Png if float && color.has_alpha() => {
+            DynamicImage::ImageRgba16(img.to_rgba16())
+        }
+        ImageFormat::Png if float => DynamicImage::ImageRgb16(img.to_rgb16()),
+        _ if float && color.has_alpha() => DynamicImage::ImageRgba8(img.to_rgba8()),
+        _ if float => DynamicImage::ImageRgb8(img.to_rgb8()),
+        _ => return Cow::Borrowed(img),
+    };
+
+    Cow::Owned(converted)
+}
+
+/// Encodes a tile in a format, applying the options that format supports.
+///
+/// # Arguments
+///
+/// * `img` - Tile to encode.
+/// * `format` - Format to encode the tile in.
+/// * `options` - Encoder settings.
+///
+/// # Returns
+///
+/// The encoded tile, or the error that kept it from being encoded.
+pub fn encode(
+    img: &DynamicImage,
+    format: ImageFormat,
+    options: &EncodeOptions,
+) -> ImageResult<Vec<u8>> {
+    let flat;
+    let img = if drops_alpha(format) && img.color().has_alpha() {
+        flat = flatten(img, options.background.unwrap_or(DEFAULT_BACKGROUND));
+        &flat
+    } else {
+        img
+    };
+    let img = &for_format(img, format);
+    let mut bytes = Vec::new();
+    let quality = match format {
+        ImageFormat::Jpeg | ImageFormat::Avif => options.quality_for(img),
+        _ => None,
+    };
+
+    if let (ImageFormat::Png, Some(colors)) = (format, options.quantize) {
+        let indexed = quantize::quantize(img, colors as usize, options.dither);
+        return png_writer::encode_indexed(&indexed, options.interlace);
+    }
+
+    match (format, quality) {
+        (ImageFormat::Png, _) if options.interlace => bytes = png_writer::encode(img, true)?,
+        (ImageFormat::Tiff, _) if options.tiff_compression != TiffCompression::None => {
+            bytes = tiff_writer::encode(img, options.tiff_compression)?
+        }
+        (ImageFormat::Jpeg, quality) if options.progressive => {
+            encode_progressive_jpeg(img, quality.unwrap_or(JPEG_QUALITY), &mut bytes)?
+        }
+        (ImageFormat::Jpeg, Some(quality)) => {
+            img.write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality))?
+        }
+        (ImageFormat::Avif, Some(quality)) => img.write_with_encoder(
+            AvifEncoder::new_with_speed_quality(&mut bytes, AVIF_SPEED, quality),
+        )?,
+        _ => img.write_to(&mut Cursor::new(&mut bytes), format)?,
+    }
+
+    Ok(bytes)
+}
@@ -0,0 +1,106 @@
+use crate::semaphore::Semaphore;
+use std::path::Path;
+use std::process::Command;
+
+/// An external command run for every tile that is written.
+pub struct Exec {
+    args: Vec<String>,
+    jobs: Semaphore,
+}
+
+impl Exec {
+    /// Parses a command line such as `oxipng -o 4 {}`.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - Command line to run. `{}` is replaced with the tile's path,
+    ///   or the path is appended as the last argument if `{}` doesn't appear.
+    /// * `jobs` - Maximum number of commands that may run at once.
+    ///
+    /// # Returns
+    ///
+    /// The parsed command, or an error message if the command line is empty or has unbalanced quotes.
+    pub fn new(command: &str, jobs: usize) -> Result<Self, String> {
+        let mut args = split_command(command).map_err(|err| format!("splix: exec: {}", err))?;
+
+        if args.is_empty() {
+            return Err("splix: exec: The command must not be empty".to_string());
+        }
+
+        if !args.iter().any(|arg| arg.contains("{}")) {
+            args.push("{}".to_string());
+        }
+
+        Ok(Exec {
+            args,
+            jobs: Semaphore::new(jobs),
+        })
+    }
+
+    /// Runs the command for a written tile, waiting for a free job slot first.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_path` - Path of the tile that was written.
+    pub fn run(&self, tile_path: &Path) {
+        let tile_path = tile_path.to_string_lossy();
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| arg.replace("{}", &tile_path))
+            .collect();
+
+        let _job = self.jobs.acquire();
+
+        match Command::new(&args[0]).args(&args[1..]).status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!(
+                "splix: exec: '{}' failed for {}: {}",
+                self.args[0], tile_path, status
+            ),
+            Err(err) => eprintln!(
+                "splix: exec: Failed to run '{}' for {}: {}",
+                self.args[0], tile_path, err
+            ),
+        }
+    }
+}
+
+/// Splits a command line into arguments on whitespace, honoring single quotes,
+/// double quotes, and backslash escapes.
+///
+/// # Returns
+///
+/// The arguments, or an error message if a quote is left open.
+pub fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => current.get_or_insert_with(String::new).push(c),
+            (_, '\\') => {
+                if let Some(escaped) = chars.next() {
+                    current.get_or_insert_with(String::new).push(escaped);
+                }
+            }
+            (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => args.extend(current.take()),
+            (None, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err("The command has an unterminated quote".to_string());
+    }
+
+    args.extend(current);
+    Ok(args)
+}
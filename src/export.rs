@@ -0,0 +1,593 @@
+use crate::manifest::{SourceEntry, TileEntry};
+use crate::preflight;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Name of the demo page written with `--sprite-css`, showing every sprite.
+pub const SPRITES_DEMO: &str = "sprites.html";
+
+/// Which stylesheet language `--sprite-css` writes.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StyleFormat {
+    /// Plain CSS, in `sprites.css`.
+    Css,
+    /// SCSS, in `sprites.scss`, sharing each sheet's rules through a placeholder selector.
+    Scss,
+}
+
+impl StyleFormat {
+    /// Name of the stylesheet.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            StyleFormat::Css => "sprites.css",
+            StyleFormat::Scss => "sprites.scss",
+        }
+    }
+}
+
+/// A source image split in full, whose tiles map straight onto regions of the file, such as a sprite sheet.
+pub struct Sheet<'a> {
+    /// The source and its tiles.
+    pub source: &'a SourceEntry,
+    /// Where the source image is, relative to the output directory where possible.
+    pub image: PathBuf,
+    /// Each written tile, and its path relative to the output.
+    pub tiles: Vec<(&'a TileEntry, PathBuf)>,
+}
+
+/// Picks the sources whose tiles are regions of the image file as it is, leaving out map tiles, scaled levels,
+/// planes, and frames, and images that were cropped, padded, or scaled before they were split.
+///
+/// # Arguments
+///
+/// * `sources` - Every source of the run.
+/// * `output_dir` - Directory the tiles were written to, if local.
+/// * `auto_orient` - Whether images were rotated upright before splitting.
+///
+/// # Returns
+///
+/// The sheets, in order of their paths.
+pub fn sheets<'a>(
+    sources: &'a [SourceEntry],
+    output_dir: Option<&Path>,
+    auto_orient: bool,
+) -> Vec<Sheet<'a>> {
+    let mut sheets: Vec<Sheet> = sources
+        .iter()
+        .filter(|source| {
+            source.zoom.is_none()
+                && source.level.unwrap_or(0) == 0
+                && source.channel.is_none()
+                && source.frame.is_none()
+        })
+        .filter(|source| {
+            let whole = preflight::header_size(&source.path, auto_orient)
+                == Some((source.width, source.height));
+            if !whole {
+                eprintln!(
+                    "splix: Leaving {} out of the sprite files, since it was resized before it was split",
+                    source.path.display()
+                );
+            }
+            whole
+        })
+        .map(|source| Sheet {
+            source,
+            image: match output_dir {
+                Some(dir) => relative_path(dir, &source.path),
+                None => PathBuf::from(source.path.file_name().unwrap_or_default()),
+            },
+            tiles: source
+                .tiles
+                .iter()
+                .filter_map(|tile| {
+                    let file = tile.file.as_deref()?;
+                    let relative = output_dir
+                        .and_then(|dir| file.strip_prefix(dir).ok())
+                        .unwrap_or(file);
+                    Some((tile, relative.to_path_buf()))
+                })
+                .collect(),
+        })
+        .filter(|sheet| !sheet.tiles.is_empty())
+        .collect();
+
+    sheets.sort_by(|a, b| a.source.path.cmp(&b.source.path));
+    sheets
+}
+
+/// Writes a stylesheet with a class for each tile, showing its region of the source image as a CSS sprite.
+///
+/// # Arguments
+///
+/// * `sheets` - Sources and their tiles.
+/// * `format` - Stylesheet language to write.
+///
+/// # Returns
+///
+/// The contents of the stylesheet.
+pub fn sprite_css(sheets: &[Sheet], format: StyleFormat) -> String {
+    let mut css = String::from("/* Sprites generated by splix. */\n");
+
+    for (i, sheet) in sheets.iter().enumerate() {
+        let classes: Vec<String> = sheet
+            .tiles
+            .iter()
+            .map(|(_, path)| class_name(path))
+            .collect();
+        let shared = format!(
+            "  background-image: url(\"{}\");\n  background-repeat: no-repeat;\n  background-size: {}px {}px;\n  display: inline-block;\n",
+            url(&sheet.image),
+            sheet.source.width, sheet.source.height
+        );
+
+        let _ = writeln!(css, "\n/* {} */", sheet.source.path.display());
+        match format {
+            StyleFormat::Css => {
+                let selectors: Vec<String> =
+                    classes.iter().map(|class| format!(".{}", class)).collect();
+                let _ = write!(css, "{} {{\n{}}}\n", selectors.join(",\n"), shared);
+            }
+            StyleFormat::Scss => {
+                let _ = write!(css, "%sheet-{} {{\n{}}}\n", i, shared);
+            }
+        }
+
+        for ((tile, _), class) in sheet.tiles.iter().zip(&classes) {
+            let _ = writeln!(css, ".{} {{", class);
+            if let StyleFormat::Scss = format {
+                let _ = writeln!(css, "  @extend %sheet-{};", i);
+            }
+            let _ = write!(
+                css,
+                "  width: {}px;\n  height: {}px;\n  background-position: {} {};\n}}\n",
+                tile.width,
+                tile.height,
+                offset(tile.x),
+                offset(tile.y)
+            );
+        }
+    }
+
+    css
+}
+
+/// Writes a page showing every sprite with its class name, so they can be checked and copied from.
+///
+/// # Arguments
+///
+/// * `sheets` - Sources and their tiles.
+///
+/// # Returns
+///
+/// The contents of the page.
+pub fn sprite_demo(sheets: &[Sheet]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Sprites</title>\n<style>\nbody {{ font-family: sans-serif; }}\nfigure {{ display: inline-block; margin: 8px; vertical-align: top; }}\nfigcaption {{ font-size: 12px; }}\n{}</style>\n</head>\n<body>\n",
+        sprite_css(sheets, StyleFormat::Css)
+    );
+
+    for sheet in sheets {
+        let _ = writeln!(
+            html,
+            "<h2>{}</h2>",
+            escape_html(&sheet.source.path.display().to_string())
+        );
+        for (_, path) in &sheet.tiles {
+            let class = class_name(path);
+            let _ = writeln!(
+                html,
+                "<figure><div class=\"{}\"></div><figcaption>.{}</figcaption></figure>",
+                class, class
+            );
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Names the class of a tile after its path, such as `icons-r0c1` for `icons-r0c1.png`,
+/// or `r0c0-frame_01` for `r0c0/frame_01.png`, replacing characters CSS doesn't allow in names.
+fn class_name(path: &Path) -> String {
+    let mut class: String = path
+        .with_extension("")
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    if class.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        class.insert(0, '_');
+    }
+    class
+}
+
+/// Formats the offset of a sprite within its sheet for `background-position`.
+fn offset(position: u32) -> String {
+    match position {
+        0 => "0".to_string(),
+        position => format!("-{}px", position),
+    }
+}
+
+/// Finds the path of a file relative to a directory, going up with `..` where needed.
+/// Both are resolved first, so the path works wherever the directory was given from.
+///
+/// # Arguments
+///
+/// * `dir` - Directory the path is relative to.
+/// * `path` - Path of the file.
+///
+/// # Returns
+///
+/// The relative path, or the path as it is if the two share no root, such as on different Windows drives.
+pub fn relative_path(dir: &Path, path: &Path) -> PathBuf {
+    let (Ok(dir), Ok(path)) = (fs::canonicalize(dir), fs::canonicalize(path)) else {
+        return path.to_path_buf();
+    };
+    let dir: Vec<Component> = dir.components().collect();
+    let target: Vec<Component> = path.components().collect();
+    if dir.first() != target.first() {
+        return path;
+    }
+
+    let common = dir.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..dir.len() {
+        relative.push("..");
+    }
+    for component in &target[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+/// Writes a path as a URL, with `/` between its parts and characters that have a meaning in URLs escaped.
+pub fn url(path: &Path) -> String {
+    let mut url = String::new();
+
+    for (i, component) in path.components().enumerate() {
+        if i > 0 && !url.ends_with('/') {
+            url.push('/');
+        }
+        match component {
+            Component::RootDir => url.push('/'),
+            Component::Prefix(prefix) => {
+                url.push_str(&prefix.as_os_str().to_string_lossy().replace('\\', "/"))
+            }
+            component => {
+                for byte in component.as_os_str().to_string_lossy().bytes() {
+                    if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                        url.push(byte as char);
+                    } else {
+                        let _ = write!(url, "%{:02X}", byte);
+                    }
+                }
+            }
+        }
+    }
+
+    url
+}
+
+/// Escapes text for HTML.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Name of the snippet written with `--html`, reassembling the tiles.
+pub const TILES_HTML: &str = "tiles.html";
+
+/// How `--html` lays out the tiles.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum HtmlLayout {
+    /// A borderless table, which email clients lay out reliably.
+    Table,
+    /// Rows of a wrapping flex box.
+    Flex,
+}
+
+/// Writes an HTML snippet that shows each image reassembled from its tiles, such as to paste into an email.
+/// Tiles that weren't written are left as empty space of their size, and duplicates show the tile they duplicate.
+///
+/// # Arguments
+///
+/// * `sources` - Every source of the run.
+/// * `output_dir` - Directory the tiles were written to and the snippet is written to, if local.
+/// * `layout` - How to lay out the tiles.
+///
+/// # Returns
+///
+/// The contents of the snippet.
+pub fn reassembly_html(
+    sources: &[SourceEntry],
+    output_dir: Option<&Path>,
+    layout: HtmlLayout,
+) -> String {
+    let mut sources: Vec<&SourceEntry> = sources.iter().collect();
+    sources.sort_by(|a, b| {
+        (&a.path, a.frame, &a.grid, a.level, &a.channel)
+            .cmp(&(&b.path, b.frame, &b.grid, b.level, &b.channel))
+    });
+
+    let mut html = String::from("<!-- Tiles reassembled by splix. -->\n");
+    for source in sources {
+        let mut tiles: Vec<&TileEntry> = source.tiles.iter().collect();
+        tiles.sort_by_key(|tile| (tile.row, tile.col));
+
+        let _ = writeln!(
+            html,
+            "<!-- {} -->",
+            escape_html(&describe(source)).replace("--", "- -")
+        );
+        match layout {
+            HtmlLayout::Table => {
+                let _ = writeln!(
+                    html,
+                    "<table width=\"{}\" border=\"0\" cellpadding=\"0\" cellspacing=\"0\" style=\"border-collapse: collapse;\">",
+                    source.width
+                );
+            }
+            HtmlLayout::Flex => {
+                let _ = writeln!(
+                    html,
+                    "<div style=\"display: flex; flex-direction: column; width: {}px;\">",
+                    source.width
+                );
+            }
+        }
+
+        for row in tiles.chunk_by(|a, b| a.row == b.row) {
+            html.push_str(match layout {
+                HtmlLayout::Table => "<tr>\n",
+                HtmlLayout::Flex => "<div style=\"display: flex;\">\n",
+            });
+            for tile in row {
+                let img = match tile.file.as_ref().or(tile.duplicate_of.as_ref()) {
+                    Some(file) => format!(
+                        "<img src=\"{}\" width=\"{}\" height=\"{}\" alt=\"\" style=\"display: block; border: 0;\">",
+                        escape_html(&href(file, output_dir)),
+                        tile.width,
+                        tile.height
+                    ),
+                    None => format!(
+                        "<div style=\"width: {}px; height: {}px;\"></div>",
+                        tile.width, tile.height
+                    ),
+                };
+                let _ = match layout {
+                    HtmlLayout::Table => writeln!(
+                        html,
+                        "<td width=\"{}\" height=\"{}\">{}</td>",
+                        tile.width, tile.height, img
+                    ),
+                    HtmlLayout::Flex => writeln!(html, "{}", img),
+                };
+            }
+            html.push_str(match layout {
+                HtmlLayout::Table => "</tr>\n",
+                HtmlLayout::Flex => "</div>\n",
+            });
+        }
+
+        html.push_str(match layout {
+            HtmlLayout::Table => "</table>\n",
+            HtmlLayout::Flex => "</div>\n",
+        });
+    }
+
+    html
+}
+
+/// Describes which image, frame, grid, level, and plane a source's tiles came from.
+fn describe(source: &SourceEntry) -> String {
+    let mut description = source.path.display().to_string();
+    if let Some(frame) = source.frame {
+        let _ = write!(description, ", frame {}", frame);
+    }
+    if let Some(grid) = &source.grid {
+        let _ = write!(description, ", grid {}", grid);
+    }
+    if let Some(level) = source.level {
+        let _ = write!(description, ", level {}", level);
+    }
+    if let Some(channel) = &source.channel {
+        let _ = write!(description, ", {}", channel);
+    }
+    description
+}
+
+/// Links to a tile from a file in the output directory.
+///
+/// # Arguments
+///
+/// * `file` - Where the tile was written, as recorded in the manifest.
+/// * `output_dir` - Directory the tiles were written to, if local.
+///
+/// # Returns
+///
+/// The path of the tile relative to the output directory as a URL, or the location as it is if it's already a URL.
+fn href(file: &Path, output_dir: Option<&Path>) -> String {
+    match output_dir.and_then(|dir| file.strip_prefix(dir).ok()) {
+        Some(relative) => url(relative),
+        None if output_dir.is_some() => url(file),
+        None => file.to_string_lossy().into_owned(),
+    }
+}
+
+/// Which game engine `--engine-meta` writes sprite metadata for.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum EngineFormat {
+    /// An `AtlasTexture` resource next to each tile, as `.tres`, showing its region of the source image in Godot 4.
+    Godot,
+    /// A sprite sheet description of each source image, as `.json`, in the array layout Unity's sprite sheet importers read.
+    Unity,
+}
+
+/// A sprite sheet, in the layout of `--engine-meta unity`.
+#[derive(Serialize)]
+struct UnitySheet {
+    frames: Vec<UnityFrame>,
+    meta: UnityMeta,
+}
+
+/// A sprite in a sprite sheet. Coordinates start at the top left.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UnityFrame {
+    filename: String,
+    frame: UnityRect,
+    rotated: bool,
+    trimmed: bool,
+    sprite_source_size: UnityRect,
+    source_size: UnitySize,
+    pivot: UnityPoint,
+}
+
+/// The image a sprite sheet describes.
+#[derive(Serialize)]
+struct UnityMeta {
+    app: &'static str,
+    image: String,
+    size: UnitySize,
+    scale: &'static str,
+}
+
+#[derive(Serialize)]
+struct UnityRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Serialize)]
+struct UnitySize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Serialize)]
+struct UnityPoint {
+    x: f32,
+    y: f32,
+}
+
+/// Writes game engine metadata for the tiles of each sheet, so they import as sprites of the source image.
+///
+/// # Arguments
+///
+/// * `sheets` - Sources and their tiles.
+/// * `output_dir` - Directory the tiles were written to, if local, to link the source images from.
+/// * `format` - Engine to write metadata for.
+///
+/// # Returns
+///
+/// The path of each file relative to the output and its contents, or an error if JSON failed to be written.
+pub fn engine_meta(
+    sheets: &[Sheet],
+    output_dir: Option<&Path>,
+    format: EngineFormat,
+) -> serde_json::Result<Vec<(PathBuf, String)>> {
+    match format {
+        EngineFormat::Godot => Ok(sheets
+            .iter()
+            .flat_map(|sheet| {
+                sheet.tiles.iter().map(move |(tile, path)| {
+                    // Godot resolves relative paths from the directory of the resource.
+                    let texture = match output_dir {
+                        Some(dir) => relative_path(
+                            &dir.join(path.parent().unwrap_or(Path::new(""))),
+                            &sheet.source.path,
+                        ),
+                        None => PathBuf::from(sheet.source.path.file_name().unwrap_or_default()),
+                    };
+                    let tres = format!(
+                        "[gd_resource type=\"AtlasTexture\" load_steps=2 format=3]\n\n[ext_resource type=\"Texture2D\" path={} id=\"1\"]\n\n[resource]\natlas = ExtResource(\"1\")\nregion = Rect2({}, {}, {}, {})\n",
+                        serde_json::to_string(&texture.to_string_lossy().replace('\\', "/")).unwrap(),
+                        tile.x,
+                        tile.y,
+                        tile.width,
+                        tile.height
+                    );
+                    (path.with_extension("tres"), tres)
+                })
+            })
+            .collect()),
+        EngineFormat::Unity => {
+            let mut names = HashSet::new();
+            sheets
+                .iter()
+                .map(|sheet| {
+                    let stem = sheet
+                        .source
+                        .path
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy();
+                    // Images of the same name in different directories each get their own file.
+                    let name = (1..)
+                        .map(|i| match i {
+                            1 => format!("{}.sprites.json", stem),
+                            i => format!("{}-{}.sprites.json", stem, i),
+                        })
+                        .find(|name| names.insert(name.clone()))
+                        .unwrap();
+
+                    let unity = UnitySheet {
+                        frames: sheet
+                            .tiles
+                            .iter()
+                            .map(|(tile, path)| UnityFrame {
+                                filename: path.with_extension("").to_string_lossy().replace('\\', "/"),
+                                frame: UnityRect {
+                                    x: tile.x,
+                                    y: tile.y,
+                                    w: tile.width,
+                                    h: tile.height,
+                                },
+                                rotated: false,
+                                trimmed: false,
+                                sprite_source_size: UnityRect {
+                                    x: 0,
+                                    y: 0,
+                                    w: tile.width,
+                                    h: tile.height,
+                                },
+                                source_size: UnitySize {
+                                    w: tile.width,
+                                    h: tile.height,
+                                },
+                                pivot: UnityPoint { x: 0.5, y: 0.5 },
+                            })
+                            .collect(),
+                        meta: UnityMeta {
+                            app: "splix",
+                            image: sheet.image.to_string_lossy().replace('\\', "/"),
+                            size: UnitySize {
+                                w: sheet.source.width,
+                                h: sheet.source.height,
+                            },
+                            scale: "1",
+                        },
+                    };
+                    Ok((PathBuf::from(name), serde_json::to_string_pretty(&unity)?))
+                })
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::encode::{self, EncodeOptions};
+use crate::exec;
+use crate::rng::Rng;
+use image::{DynamicImage, ImageFormat};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Placeholder for the path the command writes the encoded tile to.
+const OUT: &str = "{out}";
+
+/// Placeholder for the path of the tile to encode, for commands that can't read standard input.
+const IN: &str = "{in}";
+
+/// Number of temporary files created so far, to give each one a unique name.
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// How many names to try for a temporary file before giving up.
+const TEMP_FILE_ATTEMPTS: usize = 16;
+
+/// An external command that encodes tiles, for formats or settings the built-in encoders don't support.
+/// Each tile is given to the command as a PNG, which keeps every pixel and bit depth intact.
+pub struct ExternalEncoder {
+    args: Vec<String>,
+    /// Extension of the files the command writes.
+    ext: String,
+}
+
+impl ExternalEncoder {
+    /// Parses a command line such as `cwebp -q 80 - -o {out}`.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - Command line to run. The tile is written to its standard input, or to a file at `{in}` if given.
+    ///   The encoded tile is read from a file at `{out}` if given, or from its standard output.
+    /// * `ext` - Extension of the files the command writes.
+    ///
+    /// # Returns
+    ///
+    /// The parsed command, or an error message if the command line is empty or has unbalanced quotes.
+    pub fn new(command: &str, ext: &str) -> Result<Self, String> {
+        let args =
+            exec::split_command(command).map_err(|err| format!("splix: encoder-cmd: {}", err))?;
+
+        if args.is_empty() {
+            return Err("splix: encoder-cmd: The command must not be empty".to_string());
+        }
+
+        Ok(ExternalEncoder {
+            args,
+            ext: ext.trim_start_matches('.').to_string(),
+        })
+    }
+
+    /// MIME type of the files the command writes, if their extension is one `image` knows.
+    pub fn content_type(&self) -> &'static str {
+        ImageFormat::from_extension(&self.ext)
+            .map_or("application/octet-stream", |format| format.to_mime_type())
+    }
+
+    /// Encodes a tile by running the command on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `img` - Tile to encode.
+    ///
+    /// # Returns
+    ///
+    /// The encoded tile, or the error that kept it from being encoded.
+    pub fn encode(&self, img: &DynamicImage) -> io::Result<Vec<u8>> {
+        let png = encode::encode(img, ImageFormat::Png, &EncodeOptions::default())
+            .map_err(io::Error::other)?;
+
+        let input = self
+            .args
+            .iter()
+            .any(|arg| arg.contains(IN))
+            .then(|| temp_file("png"))
+            .transpose()?;
+        let output = match self
+            .args
+            .iter()
+            .any(|arg| arg.contains(OUT))
+            .then(|| temp_file(&self.ext))
+            .transpose()
+        {
+            Ok(output) => output,
+            Err(err) => {
+                if let Some(input) = &input {
+                    let _ = fs::remove_file(input);
+                }
+                return Err(err);
+            }
+        };
+        let result = self.run(&png, input.as_ref(), output.as_ref());
+
+        for path in input.iter().chain(&output) {
+            let _ = fs::remove_file(path);
+        }
+        result
+    }
+
+    /// Runs the command on an encoded PNG.
+    ///
+    /// # Arguments
+    ///
+    /// * `png` - The tile as a PNG.
+    /// * `input` - File to give the tile to the command through, or `None` to write it to its standard input.
+    /// * `output` - File the command writes the encoded tile to, or `None` to read it from its standard output.
+    fn run(
+        &self,
+        png: &[u8],
+        input: Option<&PathBuf>,
+        output: Option<&PathBuf>,
+    ) -> io::Result<Vec<u8>> {
+        if let Some(input) = input {
+            fs::write(input, png)?;
+        }
+
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| {
+                let mut arg = arg.clone();
+                if let Some(input) = input {
+                    arg = arg.replace(IN, &input.to_string_lossy());
+                }
+                if let Some(output) = output {
+                    arg = arg.replace(OUT, &output.to_string_lossy());
+                }
+                arg
+            })
+            .collect();
+
+        let mut child = Command::new(&args[0])
+            .args(&args[1..])
+            .stdin(if input.is_some() {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stdout(if output.is_some() {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .spawn()
+            .map_err(|err| {
+                io::Error::new(err.kind(), format!("Failed to run '{}': {}", args[0], err))
+            })?;
+
+        // The tile is written from another thread, so a command that writes as it reads doesn't block on a full pipe.
+        // Standard input is closed once the tile is written, so the command knows it has all of it.
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+        let (fed, mut bytes) = thread::scope(|scope| {
+            let feeder = scope.spawn(move || match stdin {
+                // Commands may exit without reading their input, which isn't an error by itself.
+                Some(mut stdin) => match stdin.write_all(png) {
+                    Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                    result => result,
+                },
+                None => Ok(()),
+            });
+            let mut bytes = Vec::new();
+            let read = match stdout {
+                Some(mut stdout) => stdout.read_to_end(&mut bytes).map(|_| ()),
+                None => Ok(()),
+            };
+            (feeder.join().unwrap().and(read), bytes)
+        });
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "'{}' failed: {}",
+                args[0], status
+            )));
+        }
+        fed?;
+
+        if let Some(output) = output {
+            bytes = fs::read(output).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("'{}' didn't write {}: {}", args[0], OUT, err),
+                )
+            })?;
+        }
+        if bytes.is_empty() {
+            return Err(io::Error::other(format!("'{}' wrote nothing", args[0])));
+        }
+        Ok(bytes)
+    }
+}
+
+/// Creates a new, empty temporary file with a random name.
+/// A file that already exists is never reused, since another run or user may own it.
+///
+/// # Arguments
+///
+/// * `ext` - Extension of the file, which some commands choose their format by.
+///
+/// # Returns
+///
+/// The path of the file, or the error that kept it from being created.
+fn temp_file(ext: &str) -> io::Result<PathBuf> {
+    let count = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    let mut rng = Rng::new(Rng::random_seed() ^ count as u64);
+    for _ in 0..TEMP_FILE_ATTEMPTS {
+        let path = env::temp_dir().join(format!(
+            "splix-{}-{}-{:016x}.{}",
+            process::id(),
+            count,
+            rng.next_u64(),
+            ext
+        ));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|_| path),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "Failed to find an unused temporary file",
+    ))
+}
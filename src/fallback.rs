@@ -0,0 +1,121 @@
+use crate::exec;
+use image::{DynamicImage, ImageResult};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// External commands to decode images with when the built-in decoders can't,
+/// such as CMYK or 12-bit JPEGs, unusual TIFF compressions, or camera raw files.
+pub struct DecoderChain {
+    commands: Vec<Decoder>,
+}
+
+/// A decoder command, and whether it's been reported as impossible to run.
+struct Decoder {
+    args: Vec<String>,
+    /// Set once the command couldn't be started, so it's only reported for the first image.
+    reported: AtomicBool,
+}
+
+impl DecoderChain {
+    /// Parses command lines such as `magick {} png:-` or `dcraw -c -w {}`.
+    ///
+    /// # Arguments
+    ///
+    /// * `commands` - Command lines to try in order. `{}` is replaced with the image's path,
+    ///   or the path is appended as the last argument if `{}` doesn't appear.
+    ///   Each command writes the decoded image to its standard output, in any format splix can read.
+    ///
+    /// # Returns
+    ///
+    /// The parsed commands, or an error message if a command line is empty or has unbalanced quotes.
+    pub fn new(commands: &[String]) -> Result<Self, String> {
+        let commands = commands
+            .iter()
+            .map(|command| {
+                let mut args = exec::split_command(command)
+                    .map_err(|err| format!("splix: decode-fallback: {}", err))?;
+                if args.is_empty() {
+                    return Err("splix: decode-fallback: The command must not be empty".to_string());
+                }
+                if !args.iter().any(|arg| arg.contains("{}")) {
+                    args.push("{}".to_string());
+                }
+                Ok(Decoder {
+                    args,
+                    reported: AtomicBool::new(false),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(DecoderChain { commands })
+    }
+
+    /// Decodes an image, falling back to each command in turn if the built-in decoders fail.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the image.
+    /// * `decode` - Decodes the image with the built-in decoders.
+    ///
+    /// # Returns
+    ///
+    /// The image, or the built-in decoders' error if every command failed too.
+    pub fn decode(
+        &self,
+        path: &Path,
+        decode: impl FnOnce(&Path) -> ImageResult<DynamicImage>,
+    ) -> ImageResult<DynamicImage> {
+        let err = match decode(path) {
+            Ok(img) => return Ok(img),
+            Err(err) => err,
+        };
+
+        self.commands
+            .iter()
+            .find_map(|decoder| decoder.run(path))
+            .ok_or(err)
+    }
+}
+
+impl Decoder {
+    /// Runs the command on an image. A command that can't be started at all, such as one that isn't installed,
+    /// is reported the first time, since it would fail the same way for every image.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the image.
+    ///
+    /// # Returns
+    ///
+    /// The decoded image, or `None` if the command failed or wrote something that can't be read.
+    fn run(&self, path: &Path) -> Option<DynamicImage> {
+        let path = path.to_string_lossy();
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| arg.replace("{}", &path))
+            .collect();
+
+        // Failures are expected while working down the chain, so the commands' own messages aren't shown.
+        let output = Command::new(&args[0])
+            .args(&args[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .inspect_err(|err| {
+                if !self.reported.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "splix: decode-fallback: Failed to run '{}': {}",
+                        args[0], err
+                    );
+                }
+            })
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        image::load_from_memory(&output.stdout).ok()
+    }
+}
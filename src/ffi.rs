@@ -0,0 +1,108 @@
+use crate::split::{split_encoded, SplitError};
+use std::ffi::{c_int, c_void};
+use std::ops::ControlFlow;
+use std::slice;
+
+/// The image was split and every tile was passed to the callback.
+pub const SPLIX_OK: c_int = 0;
+/// A pointer was null or the grid had no rows or columns.
+pub const SPLIX_ERROR_ARGUMENT: c_int = 1;
+/// The buffer isn't an image in a supported format.
+pub const SPLIX_ERROR_DECODE: c_int = 2;
+/// A tile couldn't be encoded.
+pub const SPLIX_ERROR_ENCODE: c_int = 3;
+/// The callback asked to stop before every tile was passed to it.
+pub const SPLIX_STOPPED: c_int = 4;
+
+/// Options for [`splix_split`].
+#[repr(C)]
+pub struct SplixOptions {
+    /// Number of equal rows to split the image into.
+    pub rows: u32,
+    /// Number of equal columns to split the image into.
+    pub cols: u32,
+}
+
+/// A tile passed to the callback of [`splix_split`].
+#[repr(C)]
+pub struct SplixTile {
+    pub row: u32,
+    pub col: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// The tile, encoded in the same format as the source image.
+    /// Only valid until the callback returns.
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// Called with each tile, left to right, top to bottom.
+/// Returning anything other than 0 stops splitting.
+pub type SplixTileCallback = extern "C" fn(user_data: *mut c_void, tile: *const SplixTile) -> c_int;
+
+/// Splits an encoded image into a grid of tiles, passing each one to a callback.
+///
+/// # Arguments
+///
+/// * `buffer` - The encoded image, in any format splix can read.
+/// * `len` - Length of the buffer in bytes.
+/// * `options` - Grid to split the image into.
+/// * `callback` - Function called with each tile.
+/// * `user_data` - Pointer passed through to the callback unchanged.
+///
+/// # Returns
+///
+/// `SPLIX_OK`, or one of the `SPLIX_ERROR_*` codes or `SPLIX_STOPPED`.
+///
+/// # Safety
+///
+/// `buffer` must point to `len` readable bytes and `options` to a valid `SplixOptions`, both for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn splix_split(
+    buffer: *const u8,
+    len: usize,
+    options: *const SplixOptions,
+    callback: Option<SplixTileCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    let (Some(options), Some(callback)) = (options.as_ref(), callback) else {
+        return SPLIX_ERROR_ARGUMENT;
+    };
+    if buffer.is_null() || options.rows == 0 || options.cols == 0 {
+        return SPLIX_ERROR_ARGUMENT;
+    }
+
+    let result = split_encoded(
+        slice::from_raw_parts(buffer, len),
+        &[options.rows],
+        &[options.cols],
+        |cell, data| {
+            let tile = SplixTile {
+                row: cell.row as u32,
+                col: cell.col as u32,
+                x: cell.x,
+                y: cell.y,
+                width: cell.width,
+                height: cell.height,
+                data: data.as_ptr(),
+                len: data.len(),
+            };
+
+            if callback(user_data, &tile) == 0 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        },
+    );
+
+    match result {
+        Ok(ControlFlow::Continue(())) => SPLIX_OK,
+        Ok(ControlFlow::Break(())) => SPLIX_STOPPED,
+        Err(SplitError::Decode(_)) => SPLIX_ERROR_DECODE,
+        // Tiles are handed to the callback rather than written anywhere, so there are no write errors.
+        Err(SplitError::Encode(_) | SplitError::Write(_)) => SPLIX_ERROR_ENCODE,
+    }
+}
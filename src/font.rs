@@ -0,0 +1,133 @@
+use image::{GenericImage, Rgba};
+
+/// Width of a glyph in font pixels, not counting the gap between glyphs.
+pub const GLYPH_WIDTH: u32 = 5;
+/// Height of a glyph in font pixels.
+pub const GLYPH_HEIGHT: u32 = 7;
+
+/// A 5x7 bitmap font covering digits, capital letters, and a little punctuation.
+/// Each glyph is 7 rows from top to bottom, with the leftmost pixel in bit 4.
+const GLYPHS: [(char, [u8; 7]); 43] = [
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('A', [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('/', [0x01, 0x01, 0x02, 0x04, 0x08, 0x10, 0x10]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+];
+
+/// Width of a line of text in pixels.
+///
+/// # Arguments
+///
+/// * `text` - Text to measure.
+/// * `scale` - Size of each font pixel in image pixels.
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    let glyphs = text.chars().count() as u32;
+    (glyphs * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale
+}
+
+/// Draws a line of text, clipped to the image.
+/// Lowercase letters are drawn as capitals, and characters the font doesn't have are left blank.
+///
+/// # Arguments
+///
+/// * `img` - Image to draw on.
+/// * `x` - Left edge of the text.
+/// * `y` - Top edge of the text.
+/// * `scale` - Size of each font pixel in image pixels.
+/// * `text` - Text to draw.
+/// * `color` - Color of the text.
+pub fn draw_text(
+    img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+    x: u32,
+    y: u32,
+    scale: u32,
+    text: &str,
+    color: Rgba<u8>,
+) {
+    for (i, c) in text.chars().enumerate() {
+        let c = c.to_ascii_uppercase();
+        let Some((_, rows)) = GLYPHS.iter().find(|(glyph, _)| *glyph == c) else {
+            continue;
+        };
+
+        let glyph_x = x + i as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> col) == 0 {
+                    continue;
+                }
+
+                let left = glyph_x + col * scale;
+                let top = y + row as u32 * scale;
+                fill_rect(img, left, top, scale, scale, color);
+            }
+        }
+    }
+}
+
+/// Fills a rectangle, clipped to the image.
+///
+/// # Arguments
+///
+/// * `img` - Image to draw on.
+/// * `x` - Left edge of the rectangle.
+/// * `y` - Top edge of the rectangle.
+/// * `width` - Width of the rectangle.
+/// * `height` - Height of the rectangle.
+/// * `color` - Color to fill with.
+pub fn fill_rect(
+    img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    color: Rgba<u8>,
+) {
+    let right = x.saturating_add(width).min(img.width());
+    let bottom = y.saturating_add(height).min(img.height());
+
+    for py in y..bottom {
+        for px in x..right {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::aseprite;
+use crate::dicom;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{
+    AnimationDecoder, DynamicImage, Frame, Frames, ImageError, ImageFormat, ImageReader,
+    ImageResult,
+};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::iter;
+use std::path::Path;
+
+/// Which frames of an animated image to split.
+#[derive(Clone, Copy)]
+pub enum FrameSelection {
+    /// A single frame, counting from 0.
+    Index(usize),
+    /// Every frame, each split as a separate still.
+    All,
+}
+
+/// Parses a frame selection, either a frame number or `all`.
+///
+/// # Arguments
+///
+/// * `frame` - Selection to parse.
+///
+/// # Returns
+///
+/// The frame selection, or an error message if it isn't a valid selection.
+pub fn parse_frame(frame: &str) -> Result<FrameSelection, String> {
+    if frame.eq_ignore_ascii_case("all") {
+        return Ok(FrameSelection::All);
+    }
+
+    frame
+        .trim()
+        .parse()
+        .map(FrameSelection::Index)
+        .map_err(|_| format!("'{}' is not a frame number or 'all'", frame))
+}
+
+/// Decodes the selected frames of an image, decoding it only once whether or not it's animated.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+/// * `selection` - Which frames to pick.
+/// * `open` - Decodes the image as a still, if it isn't animated or its frames can't be read.
+///
+/// # Returns
+///
+/// Each selected frame with its number, or an error if the selected frame doesn't exist.
+pub fn decode(
+    path: &Path,
+    selection: FrameSelection,
+    open: impl FnOnce(&Path) -> ImageResult<DynamicImage>,
+) -> SelectedFrames {
+    // Sprites are decoded whole, so even a sprite of one frame is picked from its frames rather than decoded again.
+    if aseprite::is_aseprite(path) {
+        if let Ok(frames) = aseprite::decode(path) {
+            return select(
+                Frames::new(Box::new(
+                    frames.into_iter().map(|frame| Ok(Frame::new(frame))),
+                )),
+                selection,
+            );
+        }
+    }
+
+    // Multi-frame DICOM images, such as ultrasound clips, are decoded whole too.
+    if dicom::is_dicom(path) {
+        return match dicom::decode(path) {
+            Ok(frames) if frames.len() > 1 => select(
+                Frames::new(Box::new(
+                    frames
+                        .into_iter()
+                        .map(|frame| Ok(Frame::new(frame.into_rgba8()))),
+                )),
+                selection,
+            ),
+            _ => still(open(path), selection),
+        };
+    }
+
+    match decode_frames(path) {
+        Ok(Some(frames)) => select(frames, selection),
+        Ok(None) => still(open(path), selection),
+        Err(err) => Box::new(iter::once(Err(err))),
+    }
+}
+
+/// Starts decoding the frames of an animated GIF, PNG, or WebP image, one at a time.
+/// Each frame is composited onto the frames before it, as it would be shown.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+///
+/// # Returns
+///
+/// The image's frames, `None` if the image isn't animated, or the error that kept it from being read.
+fn decode_frames(path: &Path) -> ImageResult<Option<Frames<'static>>> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    let format = reader.format();
+    let file = BufReader::new(File::open(path)?);
+
+    Ok(match format {
+        Some(ImageFormat::Gif) => Some(GifDecoder::new(file)?.into_frames()),
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(file)?;
+            if decoder.is_apng()? {
+                Some(decoder.apng()?.into_frames())
+            } else {
+                None
+            }
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(file)?;
+            if decoder.has_animation() {
+                Some(decoder.into_frames())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    })
+}
+
+/// An iterator over the selected frames of an image, each with its frame number.
+pub type SelectedFrames = Box<dyn Iterator<Item = ImageResult<(usize, DynamicImage)>>>;
+
+/// Picks the selected frames out of an animated image's frames.
+///
+/// # Arguments
+///
+/// * `frames` - Frames of the image.
+/// * `selection` - Which frames to pick.
+///
+/// # Returns
+///
+/// Each selected frame with its number, or an error if the selected frame doesn't exist.
+pub fn select(frames: Frames<'static>, selection: FrameSelection) -> SelectedFrames {
+    let mut frames = frames.enumerate().map(|(number, frame)| {
+        frame.map(|frame| (number, DynamicImage::ImageRgba8(frame.into_buffer())))
+    });
+
+    match selection {
+        FrameSelection::All => Box::new(frames),
+        FrameSelection::Index(index) => {
+            for count in 0..index {
+                match frames.next() {
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Box::new(iter::once(Err(err))),
+                    None => return Box::new(iter::once(Err(missing_frame(index, count)))),
+                }
+            }
+
+            Box::new(iter::once(
+                frames
+                    .next()
+                    .unwrap_or_else(|| Err(missing_frame(index, index))),
+            ))
+        }
+    }
+}
+
+/// Picks the selected frames of a still image, which only has frame 0.
+///
+/// # Arguments
+///
+/// * `img` - The decoded image.
+/// * `selection` - Which frames to pick.
+///
+/// # Returns
+///
+/// The image as frame 0, or an error if another frame was selected.
+pub fn still(img: ImageResult<DynamicImage>, selection: FrameSelection) -> SelectedFrames {
+    Box::new(iter::once(match selection {
+        FrameSelection::Index(index) if index > 0 => Err(missing_frame(index, 1)),
+        _ => img.map(|img| (0, img)),
+    }))
+}
+
+/// Builds the error for a frame number past the end of an image.
+fn missing_frame(index: usize, count: usize) -> ImageError {
+    ImageError::IoError(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "Frame {} doesn't exist, the image has {} frame{}",
+            index,
+            count,
+            if count == 1 { "" } else { "s" }
+        ),
+    ))
+}
@@ -0,0 +1,743 @@
+use image::DynamicImage;
+use std::cmp;
+use std::str::FromStr;
+
+/// Prefix of a row or column size whose band is cut out of the image and discarded instead of becoming tiles.
+pub const SKIP_PREFIX: &str = "skip:";
+
+/// Suffix of a row or column size given in pixels.
+pub const PIXELS_SUFFIX: &str = "px";
+
+/// A row or column size that takes whatever length the pixel sizes leave.
+pub const AUTO: &str = "auto";
+
+/// A rectangular region of an image that becomes one tile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub row: usize,
+    pub col: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Cell {
+    /// Borrows the raw bytes of each row of the cell from an image, without copying them.
+    pub fn rows<'a>(&self, img: &'a DynamicImage) -> impl Iterator<Item = &'a [u8]> {
+        let bytes_per_pixel = img.color().bytes_per_pixel() as usize;
+        let stride = img.width() as usize * bytes_per_pixel;
+        let start = self.x as usize * bytes_per_pixel;
+        let len = self.width as usize * bytes_per_pixel;
+
+        img.as_bytes()
+            .chunks_exact(stride)
+            .skip(self.y as usize)
+            .take(self.height as usize)
+            .map(move |row| &row[start..start + len])
+    }
+}
+
+/// Computes the cells of a grid over an image, without touching its pixels.
+///
+/// # Arguments
+///
+/// * `width` - Width of the image.
+/// * `height` - Height of the image.
+/// * `rows` - Number of rows to split the image into. Provide a single integer for equal division, or a list of integers for custom division.
+/// * `cols` - Number of columns to split the image into. Provide a single integer for equal division, or a list of integers for custom division.
+///
+/// # Returns
+///
+/// The cells of the grid, from left to right, top to bottom.
+pub fn grid_cells(width: u32, height: u32, rows: &[u32], cols: &[u32]) -> Vec<Cell> {
+    cells(&bands(height, rows), &bands(width, cols))
+}
+
+/// Numbers the columns of some cells from right to left, for images read that way, such as the spreads of manga.
+///
+/// # Arguments
+///
+/// * `cells` - Cells numbered from left to right.
+///
+/// # Returns
+///
+/// The cells with their columns numbered from the right, from right to left, top to bottom.
+pub fn right_to_left(mut cells: Vec<Cell>) -> Vec<Cell> {
+    let last = cells.iter().map(|cell| cell.col).max().unwrap_or(0);
+    for cell in &mut cells {
+        cell.col = last - cell.col;
+    }
+    cells.sort_by_key(|cell| (cell.row, cell.col));
+    cells
+}
+
+/// A way of dividing images into tiles, for library users with layouts of their own, such as hexagonal grids
+/// or regions found by a detector. [`split::split_with`](crate::split::split_with) and
+/// [`sink::split_to_sink_with`](crate::sink::split_to_sink_with) cut and encode the tiles it chooses,
+/// and closures taking the width, height, and image can be used as strategies.
+pub trait SplitStrategy {
+    /// Chooses the regions of an image to cut into tiles.
+    /// Regions reaching past the edges of the image are cut short, and those outside it are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the image.
+    /// * `height` - Height of the image.
+    /// * `img` - The image, for strategies that look at its pixels.
+    ///
+    /// # Returns
+    ///
+    /// The cells, in the order their tiles are written.
+    fn cells(&self, width: u32, height: u32, img: &DynamicImage) -> Vec<Cell>;
+}
+
+impl<F: Fn(u32, u32, &DynamicImage) -> Vec<Cell>> SplitStrategy for F {
+    fn cells(&self, width: u32, height: u32, img: &DynamicImage) -> Vec<Cell> {
+        self(width, height, img)
+    }
+}
+
+impl SplitStrategy for Layout {
+    fn cells(&self, width: u32, height: u32, _: &DynamicImage) -> Vec<Cell> {
+        Layout::cells(self, width, height)
+    }
+}
+
+/// How images are divided into cells.
+#[derive(PartialEq)]
+pub enum Layout {
+    /// A grid of rows and columns, each a single number of equal bands or the size of each band.
+    /// Bands whose positions are listed in `skip_rows` or `skip_cols` are discarded,
+    /// and the remaining ones numbered as if they weren't there.
+    Grid {
+        rows: Vec<Band>,
+        cols: Vec<Band>,
+        skip_rows: Vec<usize>,
+        skip_cols: Vec<usize>,
+    },
+    /// Pages of a fixed size in pixels, overlapping their neighbours by some pixels.
+    Pages {
+        width: u32,
+        height: u32,
+        overlap: u32,
+    },
+    /// Fixed regions of a canvas, such as monitors on a virtual desktop.
+    /// Images are scaled and cropped to fill the canvas before they're divided.
+    Regions {
+        width: u32,
+        height: u32,
+        regions: Vec<Cell>,
+    },
+    /// A grid of equal rows and columns cut the way ImageMagick's `-crop COLSxROWS@` cuts,
+    /// with each cut rounded to the nearest pixel instead of up.
+    MagickGrid { rows: u32, cols: u32 },
+    /// The fewest equal rows that are each no taller than a height, such as for long screenshots.
+    MaxHeight { height: u32 },
+    /// Bands along whichever side of each image is longer: rows for portrait images, columns for landscape ones.
+    LongestAxis { sizes: Vec<u32> },
+    /// A total number of tiles, arranged in whichever grid suits each image's shape.
+    Tiles { count: u32 },
+    /// Crops of a fixed size, at positions picked for each image by the caller.
+    /// On its own, the layout gives the single crop in the top left corner.
+    Crops { width: u32, height: u32 },
+}
+
+impl Layout {
+    /// Computes the cells of an image, without touching its pixels.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the image.
+    /// * `height` - Height of the image.
+    ///
+    /// # Returns
+    ///
+    /// The cells, from left to right, top to bottom.
+    pub fn cells(&self, width: u32, height: u32) -> Vec<Cell> {
+        match self {
+            Layout::Grid {
+                rows,
+                cols,
+                skip_rows,
+                skip_cols,
+            } => cells(
+                &spec_bands(height, rows, skip_rows),
+                &spec_bands(width, cols, skip_cols),
+            ),
+            Layout::MagickGrid { rows, cols } => {
+                cells(&rounded_bands(height, *rows), &rounded_bands(width, *cols))
+            }
+            Layout::MaxHeight { height: max_height } => {
+                grid_cells(width, height, &[height.div_ceil(*max_height).max(1)], &[1])
+            }
+            Layout::LongestAxis { sizes } if height > width => {
+                grid_cells(width, height, sizes, &[1])
+            }
+            Layout::LongestAxis { sizes } => grid_cells(width, height, &[1], sizes),
+            Layout::Tiles { count } => {
+                let (rows, cols) = grid_for_count(*count, width, height);
+                grid_cells(width, height, &[rows], &[cols])
+            }
+            Layout::Pages {
+                width: page_width,
+                height: page_height,
+                overlap,
+            } => cells(
+                &pages(height, *page_height, *overlap),
+                &pages(width, *page_width, *overlap),
+            ),
+            Layout::Regions { regions, .. } => regions
+                .iter()
+                .filter(|region| region.x < width && region.y < height)
+                .map(|region| Cell {
+                    width: region.width.min(width - region.x),
+                    height: region.height.min(height - region.y),
+                    ..*region
+                })
+                .collect(),
+            Layout::Crops {
+                width: crop_width,
+                height: crop_height,
+            } => vec![Cell {
+                row: 0,
+                col: 0,
+                x: 0,
+                y: 0,
+                width: (*crop_width).min(width),
+                height: (*crop_height).min(height),
+            }],
+        }
+    }
+
+    /// The size images are scaled and cropped to fill before they're divided, if any.
+    pub fn canvas(&self) -> Option<(u32, u32)> {
+        match self {
+            Layout::Regions { width, height, .. } => Some((*width, *height)),
+            Layout::Grid { .. }
+            | Layout::MagickGrid { .. }
+            | Layout::MaxHeight { .. }
+            | Layout::LongestAxis { .. }
+            | Layout::Tiles { .. }
+            | Layout::Pages { .. }
+            | Layout::Crops { .. } => None,
+        }
+    }
+
+    /// The smallest width and height that can be divided without dropping any rows or columns.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the image, for layouts that depend on its shape.
+    /// * `height` - Height of the image, for layouts that depend on its shape.
+    pub fn min_size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Layout::Grid { rows, cols, .. } => (min_spec_length(cols), min_spec_length(rows)),
+            Layout::MagickGrid { rows, cols } => (*cols, *rows),
+            Layout::LongestAxis { sizes } if height > width => (1, min_length(sizes)),
+            Layout::LongestAxis { sizes } => (min_length(sizes), 1),
+            Layout::Tiles { count } => {
+                let (rows, cols) = grid_for_count(*count, width, height);
+                (cols, rows)
+            }
+            Layout::Crops { width, height } => (*width, *height),
+            Layout::MaxHeight { .. } | Layout::Pages { .. } | Layout::Regions { .. } => (1, 1),
+        }
+    }
+
+    /// The pixels of fixed-size bands across and down, and the number of sections the rest of the width
+    /// and height is shared by, so that the tiles come out even if the rest divides by the sections.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the image, for layouts that depend on its shape.
+    /// * `height` - Height of the image, for layouts that depend on its shape.
+    ///
+    /// # Returns
+    ///
+    /// The fixed pixels and sections across, then the fixed pixels and sections down.
+    pub fn sections(&self, width: u32, height: u32) -> ((u32, u32), (u32, u32)) {
+        match self {
+            Layout::Grid { rows, cols, .. } => (spec_sections(cols), spec_sections(rows)),
+            _ => {
+                let (min_width, min_height) = self.min_size(width, height);
+                ((0, min_width), (0, min_height))
+            }
+        }
+    }
+}
+
+/// Picks the rows and columns for a number of tiles whose shape is closest to the image's,
+/// so the tiles come out as close to square as the count allows.
+/// For example, 12 tiles on a 3:2 photo are arranged in 3 rows of 4.
+///
+/// # Arguments
+///
+/// * `count` - Total number of tiles.
+/// * `width` - Width of the image.
+/// * `height` - Height of the image.
+///
+/// # Returns
+///
+/// The number of rows and columns, which multiply to `count`.
+pub fn grid_for_count(count: u32, width: u32, height: u32) -> (u32, u32) {
+    let aspect = width as f64 / height.max(1) as f64;
+
+    (1..=count)
+        .filter(|&rows| count.is_multiple_of(rows))
+        .map(|rows| (rows, count / rows))
+        .min_by(|&(rows_a, cols_a), &(rows_b, cols_b)| {
+            let distance = |rows: u32, cols: u32| (cols as f64 / rows as f64 / aspect).ln().abs();
+            distance(rows_a, cols_a).total_cmp(&distance(rows_b, cols_b))
+        })
+        .unwrap_or((1, 1))
+}
+
+/// Combines row and column bands into cells.
+fn cells(row_bands: &[(u32, u32)], col_bands: &[(u32, u32)]) -> Vec<Cell> {
+    let mut cells = Vec::with_capacity(row_bands.len() * col_bands.len());
+    for (row, &(y, cell_height)) in row_bands.iter().enumerate() {
+        for (col, &(x, cell_width)) in col_bands.iter().enumerate() {
+            cells.push(Cell {
+                row,
+                col,
+                x,
+                y,
+                width: cell_width,
+                height: cell_height,
+            });
+        }
+    }
+
+    cells
+}
+
+/// Converts a row or column spec into whole numbers of sections.
+/// A single value is a number of equal bands and must be a whole number.
+/// A list of values is the relative size of each band, and may use fractions,
+/// which are scaled up to the smallest whole numbers with the same ratios.
+///
+/// # Arguments
+///
+/// * `spec` - Values given on the command line.
+/// * `name` - Name of the argument, for error messages.
+///
+/// # Returns
+///
+/// The number of bands or the size of each band in sections, or an error message.
+pub fn parse_spec(spec: &[f64], name: &str) -> Result<Vec<u32>, String> {
+    if let [count] = spec {
+        if count.fract() != 0.0 || *count < 1.0 || *count > u32::MAX as f64 {
+            return Err(format!(
+                "splix: {}: A single value is the number of {} and must be a whole number",
+                name,
+                if name == "cols" { "columns" } else { name }
+            ));
+        }
+        return Ok(vec![*count as u32]);
+    }
+
+    sections(spec, name)
+}
+
+/// Scales relative sizes up to the smallest whole numbers of sections with the same ratios.
+///
+/// # Arguments
+///
+/// * `spec` - Relative size of each band.
+/// * `name` - Name of the argument, for error messages.
+fn sections(spec: &[f64], name: &str) -> Result<Vec<u32>, String> {
+    let mut scale = 1.0;
+    while scale < 1e6
+        && spec
+            .iter()
+            .any(|size| ((size * scale).round() - size * scale).abs() > 1e-9)
+    {
+        scale *= 10.0;
+    }
+
+    let sizes: Vec<u64> = spec
+        .iter()
+        .map(|size| (size * scale).round() as u64)
+        .collect();
+    let divisor = sizes.iter().fold(0, |a, &b| gcd(a, b));
+
+    sizes
+        .iter()
+        .map(|&size| u32::try_from(size / divisor).ok().filter(|&size| size > 0))
+        .collect::<Option<Vec<u32>>>()
+        .ok_or_else(|| {
+            format!(
+                "splix: {}: The sizes are too far apart to split the image with",
+                name
+            )
+        })
+}
+
+/// One value of a row or column spec given on the command line,
+/// optionally prefixed with `skip:` to discard the band.
+#[derive(Clone, Copy, Debug)]
+pub struct BandSize {
+    pub length: BandLength,
+    pub skip: bool,
+}
+
+/// The length of a band as given on the command line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BandLength {
+    /// The relative size of a band, or a number of equal bands if it's the only value.
+    Relative(f64),
+    /// A fixed number of pixels.
+    Pixels(u32),
+    /// Whatever length the pixel sizes leave, shared with any relative sizes as if it were a size of 1.
+    Auto,
+}
+
+impl FromStr for BandSize {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (size, skip) = match value.strip_prefix(SKIP_PREFIX) {
+            Some(size) => (size.trim(), true),
+            None => (value.trim(), false),
+        };
+
+        let length = if size == AUTO {
+            BandLength::Auto
+        } else if let Some(pixels) = size.strip_suffix(PIXELS_SUFFIX) {
+            match pixels.trim().parse() {
+                Ok(pixels) if pixels > 0 => BandLength::Pixels(pixels),
+                _ => return Err(format!("'{}' isn't a positive number of pixels", value)),
+            }
+        } else {
+            BandLength::Relative(size.parse().map_err(|_| {
+                format!(
+                    "'{}' isn't a number, a number of pixels such as `200px`, or `auto`",
+                    value
+                )
+            })?)
+        };
+
+        Ok(BandSize { length, skip })
+    }
+}
+
+/// The length of a band of a grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Band {
+    /// A share of the length left after the pixel bands, in sections,
+    /// or a number of equal bands if it's the only band.
+    Sections(u32),
+    /// A fixed number of pixels.
+    Pixels(u32),
+}
+
+/// The relative sizes of a spec, with `auto` counted as a size of 1 and pixel sizes left out.
+///
+/// # Arguments
+///
+/// * `spec` - Values given on the command line.
+pub fn relative_sizes(spec: &[BandSize]) -> Vec<f64> {
+    spec.iter()
+        .filter_map(|band| match band.length {
+            BandLength::Relative(size) => Some(size),
+            BandLength::Auto => Some(1.0),
+            BandLength::Pixels(_) => None,
+        })
+        .collect()
+}
+
+/// Converts a row or column spec into the bands of a grid.
+/// A single relative value is a number of equal bands, as with [`parse_spec`].
+/// Otherwise pixel sizes are cut at their exact length, and the rest of the image
+/// is shared by the relative sizes and `auto`, so `auto,200px` always ends with the last 200 pixels.
+///
+/// # Arguments
+///
+/// * `spec` - Values given on the command line.
+/// * `name` - Name of the argument, for error messages.
+///
+/// # Returns
+///
+/// The bands and the positions of the discarded ones, or an error message.
+pub fn parse_bands(spec: &[BandSize], name: &str) -> Result<(Vec<Band>, Vec<usize>), String> {
+    let skipped = spec
+        .iter()
+        .enumerate()
+        .filter(|(_, band)| band.skip)
+        .map(|(i, _)| i)
+        .collect();
+
+    if let [BandSize {
+        length: BandLength::Relative(count),
+        ..
+    }] = spec
+    {
+        let count = parse_spec(&[*count], name)?[0];
+        return Ok((vec![Band::Sections(count)], skipped));
+    }
+
+    let relative = relative_sizes(spec);
+    let mut sections = if relative.is_empty() {
+        Vec::new()
+    } else {
+        sections(&relative, name)?
+    }
+    .into_iter();
+    let bands = spec
+        .iter()
+        .map(|band| match band.length {
+            BandLength::Pixels(pixels) => Band::Pixels(pixels),
+            BandLength::Relative(_) | BandLength::Auto => Band::Sections(sections.next().unwrap()),
+        })
+        .collect();
+
+    Ok((bands, skipped))
+}
+
+/// Divides a length into the bands of a spec and drops the discarded ones.
+/// If the length is too small for every band, it's divided into equal bands instead, none discarded.
+/// Without a relative size or `auto`, the pixels past the last band are left out.
+///
+/// # Arguments
+///
+/// * `length` - Length to divide.
+/// * `spec` - Bands to divide the length into.
+/// * `skip` - Positions of the bands to discard.
+///
+/// # Returns
+///
+/// The offset and length of each kept band.
+fn spec_bands(length: u32, spec: &[Band], skip: &[usize]) -> Vec<(u32, u32)> {
+    let sections: Vec<u32> = spec
+        .iter()
+        .filter_map(|band| match band {
+            Band::Sections(sections) => Some(*sections),
+            Band::Pixels(_) => None,
+        })
+        .collect();
+    if sections.len() == spec.len() {
+        return kept_bands(bands(length, &sections), &sections, skip);
+    }
+    if length < min_spec_length(spec) {
+        return bands(length, &[spec.len() as u32]);
+    }
+
+    let fixed: u32 = spec
+        .iter()
+        .map(|band| match band {
+            Band::Pixels(pixels) => *pixels,
+            Band::Sections(_) => 0,
+        })
+        .fold(0, u32::saturating_add);
+    let mut shares = if sections.is_empty() {
+        Vec::new()
+    } else {
+        divide(length - fixed, &sections)
+    }
+    .into_iter();
+
+    let mut kept = Vec::with_capacity(spec.len());
+    let mut offset = 0;
+    for (i, band) in spec.iter().enumerate() {
+        let band_length = match band {
+            Band::Pixels(pixels) => *pixels,
+            Band::Sections(_) => shares.next().unwrap().1,
+        };
+        if !skip.contains(&i) {
+            kept.push((offset, band_length));
+        }
+        offset += band_length;
+    }
+
+    kept
+}
+
+/// The smallest length that can be divided into the bands of a spec without dropping any.
+///
+/// # Arguments
+///
+/// * `spec` - Bands of the spec.
+pub fn min_spec_length(spec: &[Band]) -> u32 {
+    match spec {
+        [Band::Sections(count)] => *count,
+        _ => spec
+            .iter()
+            .map(|band| match band {
+                Band::Sections(size) | Band::Pixels(size) => *size,
+            })
+            .fold(0, u32::saturating_add),
+    }
+}
+
+/// The pixels of a spec's fixed-size bands, and the number of sections the rest of the length is shared by.
+/// A spec of only pixel bands has the rest left out, as a single section.
+///
+/// # Arguments
+///
+/// * `spec` - Bands of the spec.
+pub fn spec_sections(spec: &[Band]) -> (u32, u32) {
+    let fixed = spec
+        .iter()
+        .map(|band| match band {
+            Band::Pixels(pixels) => *pixels,
+            Band::Sections(_) => 0,
+        })
+        .fold(0, u32::saturating_add);
+    let sections = match spec {
+        [Band::Sections(count)] => *count,
+        _ => spec
+            .iter()
+            .map(|band| match band {
+                Band::Sections(sections) => *sections,
+                Band::Pixels(_) => 0,
+            })
+            .fold(0, u32::saturating_add),
+    };
+    (fixed, sections.max(1))
+}
+
+/// Drops discarded bands. They're kept if the image was too small for every band,
+/// since its bands then no longer match the sizes.
+///
+/// # Arguments
+///
+/// * `bands` - Offset and length of each band.
+/// * `sizes` - Sizes the bands were divided by.
+/// * `skip` - Positions of the bands to discard.
+fn kept_bands(bands: Vec<(u32, u32)>, sizes: &[u32], skip: &[usize]) -> Vec<(u32, u32)> {
+    if skip.is_empty() || bands.len() != sizes.len() {
+        return bands;
+    }
+
+    bands
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !skip.contains(i))
+        .map(|(_, band)| band)
+        .collect()
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The smallest length that can be divided into bands without dropping any.
+///
+/// # Arguments
+///
+/// * `sizes` - A single number of equal bands, or the relative size of each band.
+pub fn min_length(sizes: &[u32]) -> u32 {
+    if sizes.len() > 1 {
+        sizes.iter().copied().fold(0, u32::saturating_add)
+    } else {
+        sizes[0]
+    }
+}
+
+/// Divides a length into pages that overlap by a fixed amount.
+/// The last page is cut short where the length ends.
+///
+/// # Arguments
+///
+/// * `length` - Length to divide.
+/// * `page` - Length of each page.
+/// * `overlap` - Length each page shares with the next, which must be less than `page`.
+///
+/// # Returns
+///
+/// The offset and length of each page.
+fn pages(length: u32, page: u32, overlap: u32) -> Vec<(u32, u32)> {
+    let step = page - overlap;
+
+    let mut pages = Vec::new();
+    let mut offset = 0;
+    loop {
+        pages.push((offset, cmp::min(page, length - offset)));
+        if offset + page >= length {
+            break;
+        }
+        offset += step;
+    }
+
+    pages
+}
+
+/// Divides a length into equal bands the way ImageMagick does,
+/// placing each cut at its exact position rounded to the nearest pixel, with halves rounded up.
+///
+/// # Arguments
+///
+/// * `length` - Length to divide.
+/// * `count` - Number of bands, which is reduced to `length` if there are fewer pixels than bands.
+///
+/// # Returns
+///
+/// The offset and length of each band.
+fn rounded_bands(length: u32, count: u32) -> Vec<(u32, u32)> {
+    let count = count.min(length).max(1) as u64;
+
+    let mut bands = Vec::with_capacity(count as usize);
+    let mut offset = 0;
+    for band in 1..=count {
+        let cut = ((length as u64 * band * 2 + count) / (count * 2)) as u32;
+        bands.push((offset, cut - offset));
+        offset = cut;
+    }
+
+    bands
+}
+
+/// Divides a length into bands.
+/// Each cut is placed at its exact proportional position, rounded up,
+/// so leftover pixels are spread one at a time over the first bands instead of piling up in the last.
+///
+/// # Arguments
+///
+/// * `length` - Length to divide.
+/// * `sizes` - A single number of equal bands, or the relative size of each band.
+///
+/// # Returns
+///
+/// The offset and length of each band.
+fn bands(length: u32, sizes: &[u32]) -> Vec<(u32, u32)> {
+    let single_sizes: Vec<u32>;
+    let sizes = if sizes.len() > 1 && length >= sizes.iter().copied().fold(0, u32::saturating_add) {
+        sizes
+    } else {
+        single_sizes = vec![1; cmp::min(length as usize, sizes[0] as usize)];
+        &single_sizes
+    };
+
+    divide(length, sizes)
+}
+
+/// Divides a length into bands of relative sizes, with each cut rounded up.
+///
+/// # Arguments
+///
+/// * `length` - Length to divide.
+/// * `sizes` - The relative size of each band.
+///
+/// # Returns
+///
+/// The offset and length of each band.
+fn divide(length: u32, sizes: &[u32]) -> Vec<(u32, u32)> {
+    let total: u64 = sizes.iter().map(|&size| size as u64).sum();
+
+    let mut bands = Vec::with_capacity(sizes.len());
+    let mut offset = 0;
+    let mut sections = 0;
+    for &size in sizes {
+        sections += size as u64;
+        let cut = (length as u64 * sections).div_ceil(total) as u32;
+        bands.push((offset, cut - offset));
+        offset = cut;
+    }
+
+    bands
+}
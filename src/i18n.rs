@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Messages of each locale, as Fluent files built into splix. English comes first and has every message.
+const LOCALES: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+/// Text of each message of a locale, by its identifier.
+type Messages = HashMap<&'static str, String>;
+
+/// Messages of the chosen locale, and of English for those it leaves out.
+static MESSAGES: OnceLock<(Messages, Messages)> = OnceLock::new();
+
+/// Parses a language for `--lang`: a locale splix has messages for, such as `de`, or one of its regions, such as `de-AT`.
+///
+/// # Arguments
+///
+/// * `lang` - The language, as a BCP 47 or POSIX locale name.
+///
+/// # Returns
+///
+/// The code of the locale, or an error message if splix has no messages in the language.
+pub fn parse_lang(lang: &str) -> Result<String, String> {
+    locale_of(lang).map(str::to_string).ok_or_else(|| {
+        format!(
+            "'{}' isn't a language splix has messages in: {}",
+            lang,
+            LOCALES.map(|(code, _)| code).join(", ")
+        )
+    })
+}
+
+/// Chooses the language messages are printed in, from `--lang` or else from the `LC_ALL`, `LC_MESSAGES`,
+/// and `LANG` environment variables, in that order. Languages splix has no messages in fall back to English.
+///
+/// # Arguments
+///
+/// * `lang` - The language of `--lang`, if given.
+pub fn init(lang: Option<&str>) {
+    let locale = lang.and_then(locale_of).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| locale_of(&value))
+    });
+    let messages = |code: &str| {
+        LOCALES
+            .iter()
+            .find(|(locale, _)| *locale == code)
+            .map_or_else(HashMap::new, |(_, source)| parse(source))
+    };
+    let _ = MESSAGES.set((messages(locale.unwrap_or("en")), messages("en")));
+}
+
+/// Looks up a message in the chosen language and fills in its variables, each written `{ $name }`,
+/// and its selectors, which pick a variant by a number's plural category, as [`fill`] describes.
+///
+/// # Arguments
+///
+/// * `id` - Identifier of the message.
+/// * `args` - The name and value of each variable.
+///
+/// # Returns
+///
+/// The message, in English if the language has no such message, or the identifier if English has none either.
+pub fn message(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    // Messages printed before the language is chosen, such as errors in arguments, are in English.
+    let english;
+    let text = match MESSAGES.get() {
+        Some((chosen, english)) => chosen.get(id).or_else(|| english.get(id)),
+        None => {
+            english = parse(LOCALES[0].1);
+            english.get(id)
+        }
+    };
+    match text {
+        Some(text) => fill(text, args),
+        None => id.to_string(),
+    }
+}
+
+/// Fills in the placeables of a message: variables, written `{ $name }`, and selectors, written
+/// `{ $name -> [one] ... *[other] ... }` with a variant on each line, the one marked `*` being the default.
+/// A variant is picked by the variable's exact value, such as `[0]`, or else by its plural category.
+///
+/// # Arguments
+///
+/// * `text` - Text of the message.
+/// * `args` - The name and value of each variable.
+///
+/// # Returns
+///
+/// The text, with any placeable whose variable isn't given left as it is.
+fn fill(text: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut message = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        // Variants hold placeables of their own, so the placeable ends at the brace that balances its first.
+        let mut depth = 0;
+        let Some(len) = rest[start..].find(|c| {
+            depth += match c {
+                '{' => 1,
+                '}' => -1,
+                _ => 0,
+            };
+            depth == 0
+        }) else {
+            break;
+        };
+        let placeable = &rest[start + 1..start + len];
+        let (name, variants) = placeable.split_once("->").unwrap_or((placeable, ""));
+        let name = name.trim().trim_start_matches('$');
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) if placeable.contains("->") => {
+                let value = value.to_string();
+                if let Some(variant) = select(variants, &value) {
+                    message.push_str(&fill(variant, args));
+                }
+            }
+            Some((_, value)) => message.push_str(&value.to_string()),
+            None => message.push_str(&rest[start..start + len + 1]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    message.push_str(rest);
+    message
+}
+
+/// Picks the variant of a selector for a value: the one keyed by the value itself, then the one keyed by its
+/// plural category, then the default.
+fn select<'a>(variants: &'a str, value: &str) -> Option<&'a str> {
+    let variants: Vec<(bool, &str, &str)> = variants
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (default, line) = match line.strip_prefix('*') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (key, text) = line.strip_prefix('[')?.split_once(']')?;
+            Some((default, key.trim(), text.trim()))
+        })
+        .collect();
+    let category = plural_category(value);
+    variants
+        .iter()
+        .find(|(_, key, _)| *key == value)
+        .or_else(|| variants.iter().find(|(_, key, _)| *key == category))
+        .or_else(|| variants.iter().find(|(default, _, _)| *default))
+        .map(|(_, _, text)| *text)
+}
+
+/// The plural category of a number in the languages splix has messages in, English and German:
+/// `one` for exactly 1, and `other` for anything else.
+fn plural_category(value: &str) -> &'static str {
+    if value == "1" {
+        "one"
+    } else {
+        "other"
+    }
+}
+
+/// Finds the locale splix has messages for that a language refers to, such as `de` for `de_DE.UTF-8`.
+fn locale_of(lang: &str) -> Option<&'static str> {
+    let language = lang
+        .split(['-', '_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    LOCALES
+        .iter()
+        .map(|(code, _)| *code)
+        .find(|code| *code == language)
+}
+
+/// Parses the messages of a Fluent file: `id = text` lines, with `#` comments,
+/// where indented lines continue the text of the message above them.
+/// Only text, variables, and selectors are read, not Fluent's terms or attributes.
+fn parse(source: &'static str) -> Messages {
+    let mut messages = Messages::new();
+    let mut last: Option<&str> = None;
+    for line in source.lines() {
+        if line.starts_with([' ', '\t']) && !line.trim().is_empty() {
+            if let Some(text) = last.and_then(|id| messages.get_mut(id)) {
+                text.push('\n');
+                text.push_str(line.trim());
+            }
+            continue;
+        }
+        last = None;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((id, text)) = line.split_once('=') {
+            let id = id.trim();
+            messages.insert(id, text.trim().to_string());
+            last = Some(id);
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selectors_pick_plural_variants() {
+        let text = "{ $count } { $count ->\n[0] nothing\n[one] file\n*[other] files\n} left";
+        assert_eq!(fill(text, &[("count", &1)]), "1 file left");
+        assert_eq!(fill(text, &[("count", &3)]), "3 files left");
+        assert_eq!(fill(text, &[("count", &0)]), "0 nothing left");
+        assert_eq!(fill(text, &[]), text);
+    }
+
+    #[test]
+    fn every_locale_has_a_default_variant() {
+        for (_, source) in LOCALES {
+            for text in parse(source).values() {
+                assert!(!text.contains("->") || text.contains("*["), "{}", text);
+            }
+        }
+    }
+}
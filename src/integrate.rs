@@ -0,0 +1,419 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Label of the context-menu entry.
+const LABEL: &str = "Split with splix…";
+
+/// Options the entry splits images with when none are given to `splix integrate install`.
+pub const DEFAULT_ARGS: [&str; 5] = ["-r", "2", "-c", "2", "--open"];
+
+/// Key the Windows entry is registered under, offered for every image type.
+const WINDOWS_KEY: &str = r"HKCU\Software\Classes\SystemFileAssociations\image\shell\splix";
+
+/// Image types the Linux entries are offered for.
+const MIME_TYPES: &str = "image/png;image/jpeg;image/gif;image/webp;image/bmp;image/tiff;image/x-tga;image/x-portable-anymap;image/vnd.radiance;image/x-exr;image/avif;image/x-icon;image/x-qoi;image/x-aseprite;";
+
+/// Name of the macOS Services workflow.
+const WORKFLOW: &str = "Split with splix.workflow";
+
+/// Registers the context-menu entry for the current user, replacing any registered before.
+/// Windows gets a verb on every image type in the registry, Linux a desktop entry offered in "Open With"
+/// and a service menu for KDE's Dolphin, and macOS a Quick Action in Finder's Services menu.
+///
+/// # Arguments
+///
+/// * `exe` - Path of the splix executable the entry runs.
+/// * `args` - Options to split images with.
+///
+/// # Returns
+///
+/// Where the entry was registered, or an error message if it couldn't be.
+pub fn install(exe: &Path, args: &[String]) -> Result<Vec<String>, String> {
+    let mut command = vec![
+        exe.display().to_string(),
+        "integrate".into(),
+        "split".into(),
+    ];
+    command.extend(args.iter().cloned());
+
+    if cfg!(windows) {
+        let mut line: Vec<String> = command.iter().map(|arg| windows_quote(arg)).collect();
+        line.push("\"%1\"".to_string());
+        reg(&["add", WINDOWS_KEY, "/ve", "/d", LABEL, "/f"])?;
+        reg(&["add", WINDOWS_KEY, "/v", "Icon", "/d", &command[0], "/f"])?;
+        reg(&[
+            "add",
+            &format!(r"{}\command", WINDOWS_KEY),
+            "/ve",
+            "/d",
+            &line.join(" "),
+            "/f",
+        ])?;
+        Ok(vec![WINDOWS_KEY.to_string()])
+    } else if cfg!(target_os = "macos") {
+        let dir = macos_workflow()?;
+        let script = format!(
+            "for f in \"$@\"; do\n  {} \"$f\"\ndone",
+            command
+                .iter()
+                .map(|arg| sh_quote(arg))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        write(&dir.join("Contents/Info.plist"), &macos_info_plist())?;
+        write(&dir.join("Contents/document.wflow"), &macos_wflow(&script))?;
+        // Refreshes the Services menu, which otherwise only notices new workflows after logging in again.
+        run("/System/Library/CoreServices/pbs", &["-update"]);
+        Ok(vec![dir.display().to_string()])
+    } else {
+        let mut exec: Vec<String> = command.iter().map(|arg| exec_quote(arg)).collect();
+        exec.push("%f".to_string());
+        let exec = exec.join(" ").replace('\\', "\\\\");
+
+        let (application, service_menu) = linux_entries()?;
+        write(
+            &application,
+            &format!(
+                "[Desktop Entry]\nType=Application\nName={}\nComment=Split the image into tiles\nExec={}\nIcon=image-x-generic\nMimeType={}\nNoDisplay=true\nTerminal=false\n",
+                LABEL, exec, MIME_TYPES
+            ),
+        )?;
+        write(
+            &service_menu,
+            &format!(
+                "[Desktop Entry]\nType=Service\nMimeType={}\nActions=split;\nX-KDE-ServiceTypes=KonqPopupMenu/Plugin\n\n[Desktop Action split]\nName={}\nIcon=image-x-generic\nExec={}\n",
+                MIME_TYPES, LABEL, exec
+            ),
+        )?;
+        // Newer versions of Dolphin only show service menus that are executable.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(&service_menu, fs::Permissions::from_mode(0o755));
+        }
+        if let Some(dir) = application.parent() {
+            run("update-desktop-database", &[&dir.display().to_string()]);
+        }
+        Ok(vec![
+            application.display().to_string(),
+            service_menu.display().to_string(),
+        ])
+    }
+}
+
+/// Removes the context-menu entry registered by [`install`].
+///
+/// # Returns
+///
+/// Where the entry was removed from, or an error message if it couldn't be removed.
+pub fn uninstall() -> Result<Vec<String>, String> {
+    if cfg!(windows) {
+        reg(&["delete", WINDOWS_KEY, "/f"])?;
+        Ok(vec![WINDOWS_KEY.to_string()])
+    } else if cfg!(target_os = "macos") {
+        let dir = macos_workflow()?;
+        remove(&dir, fs::remove_dir_all(&dir))?;
+        run("/System/Library/CoreServices/pbs", &["-update"]);
+        Ok(vec![dir.display().to_string()])
+    } else {
+        let (application, service_menu) = linux_entries()?;
+        remove(&application, fs::remove_file(&application))?;
+        remove(&service_menu, fs::remove_file(&service_menu))?;
+        if let Some(dir) = application.parent() {
+            run("update-desktop-database", &[&dir.display().to_string()]);
+        }
+        Ok(vec![
+            application.display().to_string(),
+            service_menu.display().to_string(),
+        ])
+    }
+}
+
+/// Finds the directory the tiles of an image split from the context menu are saved in:
+/// `splixed-images` next to the image, since the entry isn't run from any particular directory.
+///
+/// # Arguments
+///
+/// * `image` - Path of the image.
+///
+/// # Returns
+///
+/// The directory.
+pub fn output_dir(image: &Path) -> PathBuf {
+    let image = fs::canonicalize(image).unwrap_or(image.to_path_buf());
+    image
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join("splixed-images")
+}
+
+/// Finds a directory under the user's home directory.
+fn home(relative: &str) -> Result<PathBuf, String> {
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(relative))
+        .ok_or(
+            "splix: integrate: $HOME isn't set, so there's nowhere to register the entry"
+                .to_string(),
+        )
+}
+
+/// Finds the Linux desktop entry and the KDE service menu, in `$XDG_DATA_HOME` or `~/.local/share`.
+fn linux_entries() -> Result<(PathBuf, PathBuf), String> {
+    let data_dir = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home(".local/share")?,
+    };
+    Ok((
+        data_dir.join("applications/splix.desktop"),
+        data_dir.join("kio/servicemenus/splix.desktop"),
+    ))
+}
+
+/// Finds the macOS Services workflow, in `~/Library/Services`.
+fn macos_workflow() -> Result<PathBuf, String> {
+    Ok(home("Library/Services")?.join(WORKFLOW))
+}
+
+/// Writes a file of the entry, creating its directory.
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, contents))
+        .map_err(|err| {
+            format!(
+                "splix: integrate: Failed to write {}: {}",
+                path.display(),
+                err
+            )
+        })
+}
+
+/// Checks the result of removing a file of the entry, which is fine if it was already gone.
+fn remove(path: &Path, result: io::Result<()>) -> Result<(), String> {
+    match result {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(format!(
+            "splix: integrate: Failed to remove {}: {}",
+            path.display(),
+            err
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Runs `reg.exe` to edit the Windows registry.
+fn reg(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("reg")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("splix: integrate: Failed to run reg: {}", err))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "splix: integrate: Failed to edit the registry: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Runs a helper that refreshes the system's menus, ignoring whether it's installed or succeeds.
+fn run(program: &str, args: &[&str]) {
+    let _ = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Quotes an argument for a Windows command line, as programs split it with `CommandLineToArgvW`.
+fn windows_quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes an argument for the `Exec` key of a desktop entry, before the key's own escaping of backslashes.
+fn exec_quote(arg: &str) -> String {
+    let reserved = |c: char| " \t\n\"'\\><~|&;$*?#()`".contains(c);
+    let arg = if arg.is_empty() || arg.contains(reserved) {
+        let mut quoted = String::from('"');
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    } else {
+        arg.to_string()
+    };
+    arg.replace('%', "%%")
+}
+
+/// Quotes an argument for a POSIX shell.
+fn sh_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Escapes text for an XML property list.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The `Info.plist` of the macOS workflow, offering it in Finder for images.
+fn macos_info_plist() -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSMenuItem</key>
+			<dict>
+				<key>default</key>
+				<string>{}</string>
+			</dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict>
+				<key>NSApplicationIdentifier</key>
+				<string>com.apple.finder</string>
+			</dict>
+			<key>NSSendFileTypes</key>
+			<array>
+				<string>public.image</string>
+			</array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#,
+        xml_escape(LABEL)
+    )
+}
+
+/// The `document.wflow` of the macOS workflow, a single "Run Shell Script" action given the images as arguments.
+fn macos_wflow(script: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>523</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Optional</key>
+					<true/>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.path</string>
+					</array>
+				</dict>
+				<key>AMActionVersion</key>
+				<string>2.0.3</string>
+				<key>AMApplication</key>
+				<array>
+					<string>Automator</string>
+				</array>
+				<key>AMProvides</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.string</string>
+					</array>
+				</dict>
+				<key>ActionBundlePath</key>
+				<string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key>
+				<string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<string>{}</string>
+					<key>CheckedForUserDefaultShell</key>
+					<true/>
+					<key>inputMethod</key>
+					<integer>1</integer>
+					<key>shell</key>
+					<string>/bin/sh</string>
+					<key>source</key>
+					<string></string>
+				</dict>
+				<key>BundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+				<key>CFBundleVersion</key>
+				<string>2.0.3</string>
+				<key>Class Name</key>
+				<string>RunShellScriptAction</string>
+			</dict>
+		</dict>
+	</array>
+	<key>connectors</key>
+	<dict/>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceInputTypeIdentifier</key>
+		<string>com.apple.Automator.fileSystemObject.image</string>
+		<key>serviceOutputTypeIdentifier</key>
+		<string>com.apple.Automator.nothing</string>
+		<key>serviceProcessesInput</key>
+		<integer>0</integer>
+		<key>workflowTypeIdentifier</key>
+		<string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#,
+        xml_escape(script)
+    )
+}
@@ -0,0 +1,48 @@
+use crate::i18n;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Exit code of a run stopped by Ctrl-C or SIGTERM, the code shells report for a process killed by Ctrl-C.
+pub const EXIT_CODE: u8 = 130;
+
+/// Whether Ctrl-C or SIGTERM has been received.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Message printed at the first Ctrl-C, in the chosen language, since it can't be looked up inside the handler.
+static MESSAGE: OnceLock<String> = OnceLock::new();
+
+/// Catches Ctrl-C and SIGTERM, so the run stops starting new images and tiles
+/// but finishes writing the tiles already being written instead of leaving partial files behind.
+/// A second Ctrl-C quits at once.
+#[cfg(unix)]
+pub fn install() {
+    extern "C" fn handle(_: libc::c_int) {
+        // SAFETY: write and _exit are async-signal-safe, and the message is never changed or freed once set.
+        unsafe {
+            if INTERRUPTED.swap(true, Ordering::Relaxed) {
+                libc::_exit(EXIT_CODE as libc::c_int);
+            }
+            if let Some(message) = MESSAGE.get() {
+                libc::write(libc::STDERR_FILENO, message.as_ptr().cast(), message.len());
+            }
+        }
+    }
+
+    let _ = MESSAGE.set(format!("{}\n", i18n::message("interrupting", &[])));
+
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only touches an atomic and calls async-signal-safe functions.
+        unsafe {
+            libc::signal(signal, handle as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+/// Signals are only caught on Unix. Elsewhere, Ctrl-C stops the run at once.
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// Whether the run has been interrupted.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,67 @@
+use crate::rng::Rng;
+use splix::grid::Cell;
+
+/// Moves each cut line between a grid's rows and columns by a random amount,
+/// producing varied crops of the same image while keeping roughly the same grid.
+/// Every band keeps at least one pixel, and the image's outer edges never move.
+///
+/// # Arguments
+///
+/// * `cells` - Cells of the grid, from left to right, top to bottom.
+/// * `(width, height)` - Size of the image.
+/// * `amount` - Largest distance in pixels to move each cut line either way.
+/// * `rng` - Random number generator to pick the distances with.
+///
+/// # Returns
+///
+/// The cells with their cut lines moved.
+pub fn jitter(
+    cells: &[Cell],
+    (width, height): (u32, u32),
+    amount: u32,
+    rng: &mut Rng,
+) -> Vec<Cell> {
+    let cuts_x = edges(cells.iter().map(|cell| cell.x), width, amount, rng);
+    let cuts_y = edges(cells.iter().map(|cell| cell.y), height, amount, rng);
+
+    cells
+        .iter()
+        .map(|cell| Cell {
+            x: cuts_x[cell.col],
+            y: cuts_y[cell.row],
+            width: cuts_x[cell.col + 1] - cuts_x[cell.col],
+            height: cuts_y[cell.row + 1] - cuts_y[cell.row],
+            ..*cell
+        })
+        .collect()
+}
+
+/// Moves the cut lines along one side of the image.
+///
+/// # Arguments
+///
+/// * `starts` - Where each cell starts along this side.
+/// * `length` - Length of this side.
+/// * `amount` - Largest distance to move each cut line either way.
+/// * `rng` - Random number generator to pick the distances with.
+///
+/// # Returns
+///
+/// The edges of every band in order, starting at 0 and ending at `length`.
+fn edges(starts: impl Iterator<Item = u32>, length: u32, amount: u32, rng: &mut Rng) -> Vec<u32> {
+    let mut cuts: Vec<u32> = starts.collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    cuts.push(length);
+
+    // Each cut may move up to just past the one before it, which has already moved,
+    // and up to just before the next one, which hasn't yet.
+    for i in 1..cuts.len() - 1 {
+        let offset = rng.below(amount as u64 * 2 + 1) as i64 - amount as i64;
+        let low = cuts[i - 1] as i64 + 1;
+        let high = cuts[i + 1] as i64 - 1;
+        cuts[i] = (cuts[i] as i64 + offset).clamp(low, high) as u32;
+    }
+
+    cuts
+}
@@ -0,0 +1,222 @@
+use crate::diagnostic;
+use serde_json::Value;
+use splix::grid::BandSize;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+/// Columns of a jobfile, in the order they're read from a CSV without a header row.
+const COLUMNS: [&str; 5] = ["input", "rows", "cols", "output", "format"];
+
+/// One job of a jobfile: an image or directory, and the options it's split with.
+/// Options left out use those given on the command line.
+pub struct Job {
+    /// Line of the CSV, or position in the JSON list, counting from 1, for messages.
+    pub line: usize,
+    /// Path of the image or directory of images to split.
+    pub input: PathBuf,
+    /// Rows to split the images into, as `--rows` takes them.
+    pub rows: Option<Vec<BandSize>>,
+    /// Columns to split the images into, as `--cols` takes them.
+    pub cols: Option<Vec<BandSize>>,
+    /// Directory or archive to save the tiles in, as `--output-dir` takes it.
+    pub output: Option<PathBuf>,
+    /// Extension, and so format, of the tiles, as `--ext` takes it.
+    pub format: Option<String>,
+}
+
+/// Reads a jobfile, as CSV or, if its name ends in `.json`, as a JSON list of objects.
+/// A CSV has a row for each job with its `input`, `rows`, `cols`, `output`, and `format`, in that order,
+/// unless its first row is a header naming the columns it has. Values may be quoted,
+/// and a row may leave any but the input empty to use the command line's.
+///
+/// # Arguments
+///
+/// * `path` - Path of the jobfile.
+///
+/// # Returns
+///
+/// The jobs, in the order they're listed, or an error message if the jobfile can't be read.
+pub fn read(path: &Path) -> Result<Vec<Job>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("splix: jobfile: Failed to read {}: {}", path.display(), err))?;
+    let invalid =
+        |line: usize, err: String| format!("splix: jobfile: {}:{}: {}", path.display(), line, err);
+
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let jobs = if is_json {
+        read_json(&text).map_err(|(line, err)| invalid(line, err))?
+    } else {
+        read_csv(&text).map_err(|(line, err)| invalid(line, err))?
+    };
+    if jobs.is_empty() {
+        return Err(format!("splix: jobfile: {} has no jobs", path.display()));
+    }
+    Ok(jobs)
+}
+
+/// Reads the jobs of a CSV.
+fn read_csv(text: &str) -> Result<Vec<Job>, (usize, String)> {
+    let mut columns: Option<Vec<&'static str>> = None;
+    let mut jobs = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_fields(line).map_err(|err| (number + 1, err))?;
+
+        let Some(columns) = &columns else {
+            let is_header = fields
+                .first()
+                .is_some_and(|field| field.eq_ignore_ascii_case("input"));
+            if is_header {
+                columns = Some(
+                    fields
+                        .iter()
+                        .map(|field| column(field))
+                        .collect::<Result<_, _>>()
+                        .map_err(|err| (number + 1, err))?,
+                );
+                continue;
+            }
+            columns = Some(COLUMNS.to_vec());
+            jobs.push(job(number + 1, &COLUMNS, fields)?);
+            continue;
+        };
+        jobs.push(job(number + 1, columns, fields)?);
+    }
+    Ok(jobs)
+}
+
+/// Reads the jobs of a JSON list of objects, whose values may be strings, numbers, or lists of them.
+fn read_json(text: &str) -> Result<Vec<Job>, (usize, String)> {
+    let value: Value = serde_json::from_str(text).map_err(|err| (err.line(), err.to_string()))?;
+    let Value::Array(items) = value else {
+        return Err((1, "Expected a list of jobs".to_string()));
+    };
+
+    let mut jobs = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let invalid = |err: String| (index + 1, err);
+        let Value::Object(object) = item else {
+            return Err(invalid(format!("Expected a job, but found {}", item)));
+        };
+        let mut columns = Vec::new();
+        let mut fields = Vec::new();
+        for (key, value) in object {
+            columns.push(column(key).map_err(invalid)?);
+            fields.push(text_of(value).map_err(invalid)?);
+        }
+        jobs.push(job(index + 1, &columns, fields)?);
+    }
+    Ok(jobs)
+}
+
+/// Gathers the fields of a job, leaving out those that are empty.
+fn job(line: usize, columns: &[&'static str], fields: Vec<String>) -> Result<Job, (usize, String)> {
+    if fields.len() > columns.len() {
+        return Err((
+            line,
+            format!(
+                "Found {} values, but there are only {} columns",
+                fields.len(),
+                columns.len()
+            ),
+        ));
+    }
+    let mut job = Job {
+        line,
+        input: PathBuf::new(),
+        rows: None,
+        cols: None,
+        output: None,
+        format: None,
+    };
+    let bands = |value: &str| -> Result<Vec<BandSize>, (usize, String)> {
+        value
+            .split(',')
+            .map(|size| size.trim().parse())
+            .collect::<Result<_, String>>()
+            .map_err(|err| (line, err))
+    };
+    for (&column, value) in columns.iter().zip(fields) {
+        if value.is_empty() {
+            continue;
+        }
+        match column {
+            "input" => job.input = PathBuf::from(value),
+            "rows" => job.rows = Some(bands(&value)?),
+            "cols" => job.cols = Some(bands(&value)?),
+            "output" => job.output = Some(PathBuf::from(value)),
+            _ => job.format = Some(value),
+        }
+    }
+    if job.input.as_os_str().is_empty() {
+        return Err((line, "The job has no input".to_string()));
+    }
+    Ok(job)
+}
+
+/// Finds the column a name refers to.
+fn column(name: &str) -> Result<&'static str, String> {
+    let name = name.trim().to_lowercase();
+    COLUMNS
+        .into_iter()
+        .find(|&column| column == name)
+        .ok_or_else(|| {
+            let err = format!(
+                "'{}' isn't a column of jobfiles: {}",
+                name,
+                COLUMNS.join(", ")
+            );
+            match diagnostic::did_you_mean(&name, COLUMNS) {
+                Some(help) => diagnostic::with_help(err, help),
+                None => err,
+            }
+        })
+}
+
+/// The text of a JSON value, with the items of a list separated by commas, as `--rows` takes them.
+fn text_of(value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(text) => Ok(text.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Null => Ok(String::new()),
+        Value::Array(items) => Ok(items
+            .iter()
+            .map(text_of)
+            .collect::<Result<Vec<_>, _>>()?
+            .join(",")),
+        _ => Err(format!(
+            "Expected text, a number, or a list, but found {}",
+            value
+        )),
+    }
+}
+
+/// Splits a CSV row into its fields, where a quoted field may hold commas and `""` stands for a quote.
+pub fn split_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("A quoted value is missing its closing quote".to_string());
+    }
+    fields.push(field.trim().to_string());
+    Ok(fields)
+}
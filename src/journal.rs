@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the file in the output directory listing the tiles written so far, for `--resume`.
+pub const JOURNAL_FILE: &str = ".splix-journal";
+
+/// A list of the tiles written by a run, appended to as each one is written,
+/// so a run that's interrupted can be started again and carry on where it stopped.
+pub struct Journal {
+    /// Path of the journal file.
+    path: PathBuf,
+    /// Tiles written by earlier attempts at the run.
+    done: HashSet<PathBuf>,
+    /// The journal file, open for appending.
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Reads the journal of an interrupted run from an output directory, or starts a new one if there isn't any.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The output directory.
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(JOURNAL_FILE);
+        let done = match File::open(&path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .collect::<io::Result<Vec<String>>>()?
+                .into_iter()
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(err) => return Err(err),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(Journal {
+            path,
+            done,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Number of tiles written by earlier attempts at the run.
+    pub fn resumed(&self) -> usize {
+        self.done.len()
+    }
+
+    /// Checks whether a tile was written by an earlier attempt at the run and is still there.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - Where the tile is written.
+    pub fn done(&self, file: &Path) -> bool {
+        self.done.contains(file) && file.is_file()
+    }
+
+    /// Records a tile as written. Each tile is written to the journal at once,
+    /// so it's kept even if the run is killed right after.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - Where the tile was written.
+    pub fn record(&self, file: &Path) -> io::Result<()> {
+        let mut line = file.to_string_lossy().into_owned();
+        line.push('\n');
+        self.file.lock().unwrap().write_all(line.as_bytes())
+    }
+
+    /// Removes the journal once the run has finished, so the next run starts over.
+    pub fn finish(self) -> io::Result<()> {
+        drop(self.file);
+        fs::remove_file(&self.path)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::font::{self, GLYPH_HEIGHT};
+use crate::naming::{NameTemplate, TileName};
+use image::{DynamicImage, Rgba};
+
+/// The label drawn on each tile when `--label-tiles` is given without a template.
+pub const DEFAULT_LABEL: &str = "r{row}c{col}";
+
+/// Color of the label text.
+const TEXT: Rgba<u8> = Rgba([255, 255, 255, 255]);
+/// Color of the box behind the label, so it can be read on any tile.
+const BACKGROUND: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// Text burned into the top left corner of each tile.
+pub struct TileLabel {
+    template: NameTemplate,
+}
+
+impl TileLabel {
+    /// Parses the template for `--label-tiles`.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Template such as `{row},{col}`, taking the same placeholders as `--name`.
+    ///
+    /// # Returns
+    ///
+    /// The label, or an error message if the template has unknown placeholders.
+    pub fn new(template: &str) -> Result<Self, String> {
+        Ok(TileLabel {
+            template: NameTemplate::text(template, "label-tiles")?,
+        })
+    }
+
+    /// Draws the label on a tile, sized to the tile.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` - Tile to draw on.
+    /// * `name` - Values for the template's placeholders.
+    pub fn draw(&self, tile: &mut DynamicImage, name: &TileName) {
+        let text = self.template.render_text(name);
+        let scale = (tile.width().min(tile.height()) / (GLYPH_HEIGHT * 16)).max(1);
+        let padding = scale * 2;
+
+        font::fill_rect(
+            tile,
+            0,
+            0,
+            font::text_width(&text, scale) + padding * 2,
+            GLYPH_HEIGHT * scale + padding * 2,
+            BACKGROUND,
+        );
+        font::draw_text(tile, padding, padding, scale, &text, TEXT);
+    }
+}
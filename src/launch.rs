@@ -0,0 +1,40 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Opens a file or directory the way double-clicking it would, in the system's file manager or default app.
+///
+/// # Arguments
+///
+/// * `path` - File or directory to open.
+///
+/// # Returns
+///
+/// An error message if the opener couldn't be run or reported a failure.
+pub fn open(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    // Explorer is given the path directly, since `cmd /C start` would expand `%` and `^` in it.
+    #[cfg(windows)]
+    let mut command = Command::new("explorer.exe");
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut command = Command::new("xdg-open");
+
+    let status = command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|err| format!("splix: open: Failed to open {}: {}", path.display(), err))?;
+
+    // Explorer exits with 1 even once it has opened the path, so only whether it ran can be told.
+    if status.success() || cfg!(windows) {
+        Ok(())
+    } else {
+        Err(format!(
+            "splix: open: Failed to open {}: the opener exited with {}",
+            path.display(),
+            status
+        ))
+    }
+}
@@ -0,0 +1,17 @@
+//! The splitting engine behind the `splix` command.
+//!
+//! Besides the command, splix builds as a C library exposing [`ffi::splix_split`],
+//! declared in `include/splix.h`, so other languages can split images in-process.
+//! With the `python` feature, it builds as a Python extension module, `splix`, with `maturin`.
+//! With the `wasm` feature, it builds for `wasm32-unknown-unknown` with a `wasm-bindgen` API for browsers.
+//! Rust users can send tiles to a directory, a zip or tar archive, memory, or anywhere else with [`sink::TileSink`],
+//! and cut tiles of their own choosing with [`grid::SplitStrategy`].
+
+pub mod ffi;
+pub mod grid;
+#[cfg(feature = "python")]
+mod python;
+pub mod sink;
+pub mod split;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,164 @@
+use clap::ValueEnum;
+use image::{DynamicImage, GrayImage};
+use splix::grid::Cell;
+use std::ops::Range;
+
+/// Fraction of a side a run of ink must span to count as a ruled line, so lines of text and
+/// short underlines are ignored while the rules of a table are found.
+const MIN_LINE_FRACTION: f64 = 0.5;
+
+/// Gap in pixels a ruled line may have, such as from a faint scan, and still count as one line.
+const MAX_GAP: usize = 2;
+
+/// Narrowest band kept between ruled lines. Thinner ones are the space inside double rules.
+const MIN_BAND: u32 = 4;
+
+/// Which ruled lines to split along.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum RuledLines {
+    /// Horizontal and vertical lines, giving one tile per cell of a table.
+    Cells,
+    /// Horizontal lines only, giving one tile per row.
+    Rows,
+    /// Vertical lines only, giving one tile per column.
+    Cols,
+}
+
+/// Finds the long straight lines of a scanned table or ruled document and cuts between them,
+/// leaving the lines themselves out of the tiles. Lines are found by how far ink runs along each row
+/// and column, the horizontal and vertical case of a Hough transform, counting ink on either neighbour
+/// so lines of a slightly skewed scan aren't broken up. Vertical lines are looked for between
+/// the first and last horizontal lines, so a table in part of a page is still found.
+///
+/// # Arguments
+///
+/// * `img` - Image to split.
+/// * `split` - Which lines to split along.
+///
+/// # Returns
+///
+/// The cells between the lines, from left to right, top to bottom.
+/// The whole image if no lines are found.
+pub fn ruled_cells(img: &DynamicImage, split: RuledLines) -> Vec<Cell> {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    let ink = ink_threshold(&luma);
+    let dark = |x: u32, y: u32| luma.get_pixel(x, y).0[0] < ink;
+
+    let rows = match split {
+        RuledLines::Cells | RuledLines::Rows => find_lines(height, 0..width, |y, x| dark(x, y)),
+        RuledLines::Cols => Vec::new(),
+    };
+    let cols = match split {
+        RuledLines::Cells | RuledLines::Cols => {
+            let table = match (rows.first(), rows.last()) {
+                (Some(first), Some(last)) if rows.len() > 1 => first.0..last.1,
+                _ => 0..height,
+            };
+            find_lines(width, table, dark)
+        }
+        RuledLines::Rows => Vec::new(),
+    };
+
+    let row_bands = bands_between(height, &rows);
+    let col_bands = bands_between(width, &cols);
+    let mut cells = Vec::with_capacity(row_bands.len() * col_bands.len());
+    for (row, &(y, cell_height)) in row_bands.iter().enumerate() {
+        for (col, &(x, cell_width)) in col_bands.iter().enumerate() {
+            cells.push(Cell {
+                row,
+                col,
+                x,
+                y,
+                width: cell_width,
+                height: cell_height,
+            });
+        }
+    }
+
+    cells
+}
+
+/// Picks the brightness below which a pixel counts as ink, halfway between the darkest and lightest pixels,
+/// so grey rules on a scan count as well as black ones on a clean page.
+fn ink_threshold(luma: &GrayImage) -> u8 {
+    let (darkest, lightest) = luma
+        .as_raw()
+        .iter()
+        .fold((u8::MAX, u8::MIN), |(lo, hi), &value| {
+            (lo.min(value), hi.max(value))
+        });
+
+    ((darkest as u16 + lightest as u16).div_ceil(2)) as u8
+}
+
+/// Finds the lines running along one axis of an image.
+///
+/// # Arguments
+///
+/// * `count` - Number of rows or columns to look along.
+/// * `span` - Part of each row or column to look in.
+/// * `dark` - Whether the pixel at a row or column and a position along it is ink.
+///
+/// # Returns
+///
+/// The first row or column of each line, and the one after its last.
+fn find_lines(count: u32, span: Range<u32>, dark: impl Fn(u32, u32) -> bool) -> Vec<(u32, u32)> {
+    let min_run = (span.len() as f64 * MIN_LINE_FRACTION).ceil() as usize;
+    let mut lines: Vec<(u32, u32)> = Vec::new();
+
+    for i in 0..count {
+        let neighbours = i.saturating_sub(1)..=(i + 1).min(count - 1);
+        let mut longest = 0;
+        let mut run = 0;
+        let mut gap = 0;
+        for position in span.clone() {
+            if neighbours.clone().any(|j| dark(j, position)) {
+                run += gap + 1;
+                gap = 0;
+            } else if run > 0 && gap < MAX_GAP {
+                gap += 1;
+            } else {
+                run = 0;
+                gap = 0;
+            }
+            longest = longest.max(run);
+        }
+
+        if longest >= min_run.max(1) {
+            match lines.last_mut() {
+                Some(line) if line.1 == i => line.1 = i + 1,
+                _ => lines.push((i, i + 1)),
+            }
+        }
+    }
+
+    lines
+}
+
+/// The bands between lines, including those before the first line and after the last,
+/// leaving out bands too thin to hold anything.
+///
+/// # Arguments
+///
+/// * `length` - Length of the side the lines cross.
+/// * `lines` - The first row or column of each line, and the one after its last.
+///
+/// # Returns
+///
+/// The offset and length of each band, or the whole side if there are no bands.
+fn bands_between(length: u32, lines: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut bands = Vec::with_capacity(lines.len() + 1);
+    let mut start = 0;
+    for &(line_start, line_end) in lines.iter().chain([&(length, length)]) {
+        if line_start >= start + MIN_BAND {
+            bands.push((start, line_start - start));
+        }
+        start = line_end;
+    }
+
+    if bands.is_empty() {
+        bands.push((0, length));
+    }
+    bands
+}
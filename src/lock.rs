@@ -0,0 +1,79 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use std::process;
+
+/// Name of the lock file kept in local output directories.
+pub const LOCK_FILE: &str = ".splix.lock";
+
+/// An advisory lock on an output directory, held until dropped,
+/// so two runs writing to the same directory can't overwrite each other's tiles.
+pub struct OutputLock {
+    _file: File,
+}
+
+/// Locks an output directory, creating it if needed. The lock file is left in place afterwards,
+/// since removing it would let a run that's waiting for it lock a file no later run can see.
+///
+/// # Arguments
+///
+/// * `dir` - The output directory.
+/// * `wait` - Whether to wait for another run holding the lock to finish, rather than failing.
+///
+/// # Returns
+///
+/// The lock, or an error message if another run holds it or it couldn't be taken.
+pub fn lock_output(dir: &Path, wait: bool) -> Result<OutputLock, String> {
+    let path = dir.join(LOCK_FILE);
+    let failed = |err: &dyn std::fmt::Display| {
+        format!(
+            "splix: output-dir: Failed to lock {}: {}",
+            path.display(),
+            err
+        )
+    };
+
+    fs::create_dir_all(dir).map_err(|err| failed(&err))?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(|err| failed(&err))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let mut holder = String::new();
+            let _ = file.read_to_string(&mut holder);
+            let holder = match holder.trim() {
+                "" => "Another splix run".to_string(),
+                pid => format!("Another splix run (process {})", pid),
+            };
+
+            if !wait {
+                return Err(format!(
+                    "splix: output-dir: {} is writing to {}. Use --wait-for-lock to wait for it to finish",
+                    holder,
+                    dir.display()
+                ));
+            }
+            eprintln!(
+                "splix: {} is writing to {}, waiting for it to finish",
+                holder,
+                dir.display()
+            );
+            file.lock().map_err(|err| failed(&err))?;
+        }
+        Err(TryLockError::Error(err)) => return Err(failed(&err)),
+    }
+
+    // Records who holds the lock, for the message shown to runs that find it taken.
+    file.set_len(0)
+        .and_then(|_| file.rewind())
+        .and_then(|_| writeln!(file, "{}", process::id()))
+        .map_err(|err| failed(&err))?;
+
+    Ok(OutputLock { _file: file })
+}
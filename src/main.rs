@@ -1,264 +1,5590 @@
-use clap::Parser;
+mod align;
+mod archive;
+mod aseprite;
+#[cfg(feature = "async")]
+mod async_io;
+mod breaks;
+mod cache;
+mod channels;
+mod clipboard;
+mod compare;
+mod content;
+mod crops;
+mod dataset;
+mod dedupe;
+mod detect;
+mod diagnostic;
+mod dicom;
+mod diff;
+mod doctor;
+mod embed;
+mod encode;
+mod exec;
+mod export;
+mod external;
+mod fallback;
+mod font;
+mod frames;
+mod i18n;
+mod integrate;
+mod interrupt;
+mod jitter;
+mod jobfile;
+mod journal;
+mod label;
+mod launch;
+mod lines;
+mod lock;
+mod manifest;
+mod maptiles;
+mod marks;
+mod mask;
+mod mcu;
+mod monitors;
+mod multipage;
+mod naming;
+mod normalize;
+mod npy;
+mod output;
+mod overlay;
+mod pack;
+mod pipeline;
+mod png_writer;
+mod poster;
+mod preflight;
+mod presets;
+mod priority;
+mod progress;
+mod quantize;
+mod reduce;
+mod regions;
+mod report;
+mod resize;
+mod rng;
+#[cfg(feature = "s3")]
+mod s3;
+mod selftest;
+mod semaphore;
+mod sidecar;
+mod slide;
+mod space;
+mod stats;
+mod throttle;
+mod tiff_writer;
+mod units;
+mod user_presets;
+mod walk;
+mod watermark;
+
+#[cfg(feature = "async")]
+use async_io::AsyncWrites;
+use cache::TileCache;
+use channels::Channels;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clipboard::ClipboardTile;
+use crops::RandomCrops;
+use dataset::{Dataset, DatasetLayout};
+use dedupe::{Dedupe, DedupeMode};
+use dicom::{DicomTags, Window};
+use embed::TileOrigin;
+use encode::{AlphaFallback, EncodeOptions};
+use exec::Exec;
+use export::{EngineFormat, HtmlLayout, StyleFormat};
+use external::ExternalEncoder;
+use fallback::DecoderChain;
+use frames::FrameSelection;
 use image::*;
+use journal::Journal;
+use label::TileLabel;
+use lines::RuledLines;
+use manifest::{Manifest, ManifestBuilder, SourceEntry, TileEntry, HASH_MAP};
+use maptiles::TileScheme;
+use marks::PageMarks;
+use monitors::Monitor;
+use multipage::{MultipageTiff, PageSource};
+use naming::{NameTemplate, Sanitize, SequenceLayout, TileName};
+use normalize::Normalize;
+use npy::NpyOut;
+use output::{Output, TileAttrs, UploadOptions};
+use pipeline::{DecodedFrame, DecodedImage, Queue, WriteJob, WrittenTile};
+use poster::Poster;
+use preflight::PlannedTile;
+use presets::Preset;
+use progress::Progress;
+use quantize::Dither;
 use rayon::prelude::*;
-use std::path::PathBuf;
-use std::{cmp, fs, vec};
-use walkdir::WalkDir;
+use regions::{RegionMode, Regions};
+use report::{ErrorPolicy, SkippedFiles};
+use resize::{Resampler, ResizeFilter};
+use rng::Rng;
+use semaphore::Semaphore;
+use sidecar::Sidecar;
+use slide::SlideLevel;
+use splix::grid::{self, Band, BandLength, BandSize, Cell, Layout};
+use stats::{FileTimes, RunStats, Stage};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::iter;
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use throttle::Throttle;
+use tiff_writer::TiffCompression;
+use walkdir::{DirEntry, WalkDir};
+use watermark::Watermark;
+
+/// Lightning-fast image splitter.  
+#[derive(Parser)]
+#[clap(
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    args_override_self = true,
+    after_help = "Exits with 0 once every image is split, 1 if any image or tile failed, or 130 if interrupted by Ctrl-C."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path of the image(s) to convert.
+    /// Specify the path of an image, or a directory of images.
+    /// Aseprite sprites (`.aseprite`, `.ase`) are read with their visible layers flattened, and split into PNGs.
+    /// DICOM images (`.dcm`, `.dicom`) stored uncompressed are read with splix built with the `dicom` feature,
+    /// and split into PNGs, mapped to 8 bits through `--window`.
+    /// Whole-slide images (`.svs`, `.ndpi`, `.scn`, `.mrxs`, `.vms`, `.vmu`, `.bif`, `.svslide`) are read with splix
+    /// built with the `openslide` feature and OpenSlide installed, at the level chosen with `--slide-level`, and split into PNGs.
+    /// Zip and tar archives (`.zip`, `.cbz`, `.tar`, `.cbt`, `.tar.gz`, `.tgz`) are searched for images at any depth,
+    /// which are split as if they were extracted to a directory.
+    #[arg(required_unless_present_any = ["from_clipboard", "jobfile"], verbatim_doc_comment)]
+    images: Option<PathBuf>,
+
+    /// An optional CSV or JSON file of jobs to run, each splitting its own input with its own options.
+    /// A CSV has a row for each job with its `input`, `rows`, `cols`, `output`, and `format`, in that order,
+    /// unless its first row is a header naming the columns it has. Values may be quoted, such as `"2,3,1"`.
+    /// A file ending in `.json` is a list of objects with the same keys instead.
+    /// A job leaving a column empty uses the option given on the command line, which applies to every job.
+    /// Jobs with different outputs run at the same time, and jobs sharing an output directory,
+    /// `--multipage-tiff`, or `--npy-out` run in the order they're listed.
+    /// Files that describe a whole run, such as `--manifest` and `--stats`, can't be written from a jobfile.
+    /// Ex:
+    /// --jobfile jobs.csv --quality 90  Run each job of a CSV such as:
+    ///                                      input,rows,cols,output,format
+    ///                                      scans/page1.png,4,4,tiles/page1,webp
+    ///                                      photos,"1,2",3,tiles/photos,
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["images", "from_clipboard", "encode_jobs", "manifest", "skipped_report", "stats", "profile"], verbatim_doc_comment)]
+    jobfile: Option<PathBuf>,
+
+    /// An optional flag to split the image on the clipboard, such as a screenshot, instead of files.
+    /// Its tiles are named after `clipboard`.
+    #[arg(long, conflicts_with = "images", verbatim_doc_comment)]
+    from_clipboard: bool,
+
+    /// An optional tile of the first image to copy to the clipboard, as ROW,COL counting from 0.
+    /// Without `--output-dir`, no tiles are written to files.
+    /// On Linux, splix keeps running until another program replaces the clipboard.
+    /// Ex:
+    /// --from-clipboard -r 2 -c 2 --to-clipboard 0,1  Copy the top right quarter of the clipboard image back.
+    #[arg(long, value_name = "ROW,COL", value_parser = clipboard::parse_tile, verbatim_doc_comment)]
+    to_clipboard: Option<(usize, usize)>,
+
+    /// The number of rows to split the image into.
+    /// Specify an integer, or a list of numbers.
+    /// Ex:
+    /// -r 4          Split the image into 4 equal rows.
+    /// -r 2,3,1,5    Split the image into four rows of different heights.
+    ///               The image will be divided vertically into 2+3+1+5=11 equal sections.
+    ///               The first row will take up 2 sections, second row 3 sections, etc.
+    /// -r 1.5,1,0.5  Split the image into three rows taking up 1.5/3, 1/3, and 0.5/3 of its height.
+    /// -r skip:1,8,skip:1  Cut off a header and a footer a tenth of the height each, and keep the rest as one row.
+    ///                     A size prefixed with `skip:` is discarded instead of becoming tiles.
+    /// -r 100px,auto,50px  Split the image into a 100 pixel header, a 50 pixel footer, and the rest between them.
+    ///                     Sizes ending in `px` are cut at that many pixels, and `auto` takes what they leave,
+    ///                     shared with any relative sizes as if it were a size of 1.
+    #[arg(short, long, value_delimiter = ',', verbatim_doc_comment)]
+    rows: Option<Vec<BandSize>>,
+
+    /// The number of columns to split the image into.
+    /// Specity an integer, or a list of numbers.
+    /// Ex:
+    /// -c 4          Split the image into 4 equal columns.
+    /// -c 2,3,1,5    Split the image into four columns of different widths.
+    ///               The image will be divided horizontally into 2+3+1+5=11 equal sections.
+    ///               The first column will take up 2 sections, second column 3 sections, etc.
+    /// -c 1.5,1,0.5  Split the image into three columns taking up 1.5/3, 1/3, and 0.5/3 of its width.
+    /// -c 4,skip:1,4  Split the image into two columns, discarding the gutter of 1/9 of its width between them.
+    /// -c 200px,auto,200px  Split off 200 pixel sidebars on both sides, whatever the width of the image.
+    /// -c skip:auto,200px   Keep only the 200 pixels at the right edge.
+    #[arg(short, long, value_delimiter = ',', verbatim_doc_comment)]
+    cols: Option<Vec<BandSize>>,
+
+    /// An optional paper size and resolution to split images into printable pages, instead of rows and columns.
+    /// Each page covers the paper at the given pixels per inch, so the image prints at that resolution.
+    /// Specify a paper name (A0 to A6, Letter, Legal, Tabloid) or a size in millimetres, then `@` and the resolution.
+    /// Ex:
+    /// --poster A4@300dpi         Split images into portrait A4 pages at 300 pixels per inch.
+    /// --poster 297x210mm@150dpi  Split images into landscape A4 pages at 150 pixels per inch.
+    #[arg(long, value_name = "PAPER@DPI", value_parser = poster::parse_poster, conflicts_with_all = ["rows", "cols"], verbatim_doc_comment)]
+    poster: Option<Poster>,
+
+    /// The width of paper each page shares with its neighbours with `--poster`, for gluing. Default: `0mm`.
+    /// Ex:
+    /// --poster-overlap 10mm  Repeat 10 millimetres of the image along the edges of neighbouring pages.
+    #[arg(long, value_name = "MM", value_parser = poster::parse_length, default_value = "0", hide_default_value = true, requires = "poster", verbatim_doc_comment)]
+    poster_overlap: f64,
+
+    /// An optional flag to draw crop marks, overlap guides, and a row and column label around each page with `--poster`.
+    /// The image is printed inside a 10mm margin on every side of the page, so each page covers less of it.
+    #[arg(long, requires = "poster", verbatim_doc_comment)]
+    poster_marks: bool,
+
+    /// An optional list of monitors to split images into wallpaper for, instead of rows and columns.
+    /// Each monitor is written as its resolution and its offset on the virtual desktop, like `WIDTHxHEIGHT+X+Y`.
+    /// Images are scaled and cropped to fill the whole desktop, then each monitor gets the slice it shows.
+    /// Slices are numbered by column in the order the monitors are given.
+    /// Ex:
+    /// --monitors 2560x1440+0+0,1920x1080+2560+200  A 1440p monitor with a 1080p monitor to its right, 200 pixels lower.
+    /// --monitors 1920x1080-1920+0,1920x1080+0+0    Two 1080p monitors, the first to the left of the primary one.
+    #[arg(long, value_name = "MONITORS", value_delimiter = ',', value_parser = monitors::parse_monitor, conflicts_with_all = ["rows", "cols", "poster"], verbatim_doc_comment)]
+    monitors: Option<Vec<Monitor>>,
+
+    /// The width in pixels hidden behind the bezels between neighbouring monitors with `--monitors`. Default: `0`.
+    /// That part of the image is skipped, so lines that cross from one monitor to the next stay straight.
+    /// Ex:
+    /// --bezel 60  Skip 60 pixels of the image between each pair of monitors.
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        default_value_t = 0,
+        hide_default_value = true,
+        requires = "monitors",
+        verbatim_doc_comment
+    )]
+    bezel: u32,
+
+    /// An optional preset tuned for posting split images to a platform.
+    /// Each tile is scaled to the size the platform shows, in a format and quality suited to it,
+    /// unless `--ext`, `--quality`, or `--name` is given.
+    /// twitter-2up   Two 700x800 posts side by side.
+    /// twitter-4up   Four 1200x675 posts in a 2x2 block.
+    /// story         A panorama across three 1080x1920 stories.
+    /// carousel      A panorama across three 1080x1080 carousel slides.
+    /// carousel-W:H  A panorama across three 1080 pixel wide carousel slides with the given aspect ratio.
+    /// emoji[:SIZE]  Square emoji for Slack or Discord, 128 pixels unless a size is given, from `--rows` and `--cols`.
+    ///               Tiles are padded to a square with transparency and named `{stem}_{row}_{col}.png` for bulk upload.
+    /// ereader[:WxH] Comic or manga pages for an e-reader's 1404x1872 screen, unless a size is given.
+    ///               Landscape images are taken as double-page spreads and split into their two pages, in reverse with `--rtl`.
+    ///               Pages are scaled to fit the screen, with white margins or `--background`, and named `{stem}_{index}.jpg`.
+    /// stereo-sbs    A stereo pair side by side, split into `{stem}_left` and `{stem}_right` in the image's own format.
+    /// stereo-tb     A stereo pair over and under, with the left eye on top, split the same way.
+    /// stereo-half-sbs, stereo-half-tb
+    ///               A stereo pair squeezed into the size of one image, with each eye stretched back to full width or height.
+    /// The twitter, story, carousel, ereader, and stereo presets choose the grid themselves, so they can't be combined with
+    /// `--rows` or `--cols`, and the twitter, story, and carousel presets crop images around their centre to fit it.
+    /// Ex:
+    /// --preset carousel-4:5       Split images into three 1080x1350 slides for an Instagram carousel.
+    /// --preset emoji -r 2 -c 2    Split images into four 128x128 emoji.
+    /// --preset stereo-half-sbs    Split a 1920x1080 half-width 3D frame into two 1920x1080 images, one for each eye.
+    /// --preset ereader --rtl      Split the spreads of a folder of manga pages, right page first, for a 1404x1872 e-reader.
+    #[arg(long, value_name = "PRESET", value_parser = presets::parse_preset, conflicts_with_all = ["poster", "monitors"], verbatim_doc_comment)]
+    preset: Option<Preset>,
+
+    /// An optional flag to ignore sidecar files next to the images.
+    /// Without it, an image with a sidecar named after it plus `.splix.toml`, such as `photo.jpg.splix.toml`,
+    /// is split with the `rows`, `cols`, and `preset` it sets in place of the run's. A sidecar holds TOML lines like:
+    ///     rows = 3
+    ///     cols = ["100px", "auto"]
+    ///     preset = "carousel"
+    /// A sidecar's preset only changes how the image is cut and how its tiles are sized. Their format, quality,
+    /// and names are the run's. Sidecars can only be used when images are split into a plain grid.
+    /// Ex: --no-sidecars  Split every image the same way, even those with a sidecar.
+    #[arg(long, verbatim_doc_comment)]
+    no_sidecars: bool,
+
+    /// An optional directory to save the splixed images in. Default: `./splixed-images`.
+    /// Specify `-` to stream the images to standard output as a tar archive instead.
+    /// Specify a path ending in `.zip`, `.cbz`, `.tar`, or `.cbt` to write the images into a new archive instead.
+    /// Specify an `s3://bucket/prefix` URL to upload the images to S3-compatible object storage instead.
+    /// Credentials are read from the standard AWS environment variables,
+    /// and `AWS_ENDPOINT_URL` selects a service other than AWS.
+    #[arg(
+        short = 'd',
+        long = "output-dir",
+        visible_alias = "output",
+        verbatim_doc_comment
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// The maximum number of tiles to upload at once when saving to object storage, or to write at once with `--async-io`. Default: 16.
+    #[arg(
+        long,
+        value_name = "JOBS",
+        default_value_t = 16,
+        hide_default_value = true
+    )]
+    upload_jobs: usize,
+
+    /// The number of times to retry a failed upload when saving to object storage. Default: 3.
+    #[arg(
+        long,
+        value_name = "RETRIES",
+        default_value_t = 3,
+        hide_default_value = true
+    )]
+    upload_retries: u32,
+
+    /// An optional template for the path of each tile inside the output directory.
+    /// Directories in the template are created as needed.
+    /// Placeholders:
+    /// {stem}       File name of the source image, without its extension.
+    /// {ext}        Extension of the source image's format.
+    /// {row} {col}  Row and column of the tile, starting from 0. Also available as {y} and {x}.
+    /// {index}      Position of the tile, counting left to right, top to bottom from 0.
+    /// {n}          Position of the source image in the batch, following `--sort`, from 0.
+    /// {frame}      Frame of an animated image, from 0. See `--frame`.
+    /// {z}          Zoom level of the tile with `--map-tiles`, otherwise 0.
+    /// {grid}       Grid of `--grid` the tile belongs to, such as `4x4`.
+    /// {level}      Resolution of `--levels` the tile was split at, from 0 for full size.
+    /// {channel}    Plane of `--channels` the tile was cut from, such as `red`.
+    /// {eye}        `left` for the first tile and `right` for the second, for stereo pairs.
+    /// Ex:
+    /// -n '{stem}/{row}/{col}.{ext}'
+    /// -n '{z}/{x}/{y}.png'
+    /// Default: `{stem}-r{row}c{col}.{ext}`
+    #[arg(short, long, value_name = "TEMPLATE", verbatim_doc_comment)]
+    name: Option<String>,
+
+    /// An optional flag to name each tile after the first characters of its SHA-256, keeping any directories from `--name`,
+    /// so identical tiles of any number of images are stored once, such as on a CDN.
+    /// Specify how many hex characters to use, from 8 to 64. Default: 16.
+    /// `hashes.json` in the output directory relates each hash to the image, row, and column of every tile it came from.
+    /// Ex:
+    /// --name-by-hash     Save tiles as `5891b5b522d5df08.png`.
+    /// --name-by-hash=64  Name tiles after their whole hash.
+    #[arg(long, value_name = "LEN", num_args = 0..=1, require_equals = true, default_missing_value = "16", value_parser = clap::value_parser!(u8).range(8..=64), conflicts_with_all = ["dedupe", "map_tiles"], verbatim_doc_comment)]
+    name_by_hash: Option<u8>,
+
+    /// Which system tile names are made safe to save on. Default: `host`.
+    /// Control characters and characters that reorder text are replaced with `_`, and names longer than
+    /// 255 bytes are shortened, keeping their extension. For Windows and exFAT, so are the characters
+    /// `< > : " | ? *` and trailing dots and spaces, and `_` is added to device names such as `CON` and `nul.png`.
+    /// Ex:
+    /// --sanitize host      Follow the rules of Windows on Windows, and the rules above elsewhere.
+    /// --sanitize portable  Follow the rules of Windows everywhere, so tiles split on Linux or macOS
+    ///                      can be copied to Windows and to USB drives formatted as exFAT.
+    /// --sanitize off       Keep names as they're generated.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value_t = Sanitize::Host,
+        hide_default_value = true,
+        verbatim_doc_comment
+    )]
+    sanitize: Sanitize,
+
+    /// An optional flag to give each tile the modification time of its source image, instead of the time it was split.
+    #[arg(long, verbatim_doc_comment)]
+    preserve_times: bool,
+
+    /// An optional flag to give each tile the permissions of its source image, instead of the default for new files.
+    #[arg(long, verbatim_doc_comment)]
+    preserve_perms: bool,
+
+    /// An optional flag to save the tiles of each image in their own directory, named after the image.
+    /// Same as starting `--name` with `{stem}/`.
+    #[arg(long, verbatim_doc_comment)]
+    per_image_dir: bool,
+
+    /// An optional extension for the tiles, which also chooses their format. Default: the extension of each image's format.
+    /// Ex:
+    /// --ext webp  Save the tiles as WebP images.
+    /// --ext JPG   Save the tiles as JPEG images, with an uppercase extension.
+    /// --ext exr   Save the tiles as OpenEXR images, keeping float data intact. Needs the default `hdr` feature, as does `hdr`.
+    /// --ext auto  Save photographic tiles as JPEG and tiles of flat graphics or with transparency as PNG,
+    ///             such as for pages mixing photos and text. The format of each tile is recorded in the manifest.
+    #[arg(
+        long,
+        visible_alias = "format",
+        value_name = "EXT",
+        conflicts_with = "preserve_ext_case",
+        verbatim_doc_comment
+    )]
+    ext: Option<String>,
+
+    /// An optional flag to name tiles with the extension of their image exactly as written,
+    /// such as `.JPG` or `.jpeg`, instead of the usual extension of its format.
+    #[arg(long, verbatim_doc_comment)]
+    preserve_ext_case: bool,
+
+    /// An optional quality from 1 to 100 for JPEG and AVIF tiles. Higher is sharper but larger.
+    /// Default: the encoder's own default, 75 for JPEG and 80 for AVIF.
+    #[arg(long, value_name = "QUALITY", value_parser = clap::value_parser!(u8).range(1..=100), verbatim_doc_comment)]
+    quality: Option<u8>,
+
+    /// An optional range of qualities to pick from for each JPEG and AVIF tile by how much detail it has,
+    /// instead of one quality for every tile. Flat tiles such as plain backgrounds get the lowest quality,
+    /// and busy tiles the highest, so backgrounds shrink without blurring detail. Default range: 50-90.
+    /// Ex:
+    /// --adaptive-quality        Pick qualities from 50 to 90.
+    /// --adaptive-quality 30-85  Pick qualities from 30 to 85.
+    #[arg(long, value_name = "MIN-MAX", value_parser = encode::parse_quality_range, num_args = 0..=1, default_missing_value = "50-90", conflicts_with = "quality", verbatim_doc_comment)]
+    adaptive_quality: Option<(u8, u8)>,
+
+    /// An optional flag to save JPEG tiles as progressive JPEGs instead of baseline.
+    /// Web browsers show a progressive JPEG at low detail straight away and sharpen it as it loads,
+    /// and they're often a little smaller.
+    #[arg(long, verbatim_doc_comment)]
+    progressive: bool,
+
+    /// An optional flag to save PNG tiles with Adam7 interlacing.
+    /// Web browsers show an interlaced PNG coarsely straight away and fill it in as it loads,
+    /// at the cost of a somewhat larger file.
+    #[arg(long, verbatim_doc_comment)]
+    interlace: bool,
+
+    /// An optional number of colors from 2 to 256 to reduce each PNG tile to, saving it with a palette.
+    /// Tiles with few colors, such as UI slices and map tiles, often shrink to a fraction of their size.
+    /// Tiles that already have no more colors than this keep them exactly.
+    /// Ex:
+    /// --quantize 256  Reduce each PNG tile to at most 256 colors.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(2..=256), verbatim_doc_comment)]
+    quantize: Option<u16>,
+
+    /// How to approximate colors missing from the palette with `--quantize`. Default: `none`.
+    /// Dithering trades the bands in smooth gradients for fine noise or a regular pattern.
+    #[arg(long, value_enum, value_name = "DITHER", default_value_t = Dither::None, hide_default_value = true, requires = "quantize", verbatim_doc_comment)]
+    dither: Dither,
+
+    /// How to compress TIFF tiles. Default: `none`.
+    /// Tiles of large scans often shrink to a fraction of their size, without losing any detail.
+    /// Ex:
+    /// --tiff-compression lzw   Compress TIFF tiles in a way nearly every TIFF reader can open.
+    /// --tiff-compression zstd  Compress TIFF tiles smaller and faster, for readers built on recent libtiff.
+    #[arg(long, value_enum, value_name = "COMPRESSION", default_value_t = TiffCompression::None, hide_default_value = true, verbatim_doc_comment)]
+    tiff_compression: TiffCompression,
+
+    /// An optional path of a TIFF to save every tile of an image in, one tile per page, compressed with `--tiff-compression`.
+    /// Include `{stem}` to save a TIFF for each image when splitting more than one.
+    /// Each page records the tile's name in `PageName`, its position in `XPosition` and `YPosition`, counted in pixels,
+    /// and its row, column, and region as JSON in `ImageDescription`.
+    /// Without `--output-dir`, no tiles are written to separate files.
+    /// Ex:
+    /// --multipage-tiff scan.tif -r 4            Save the four strips of an image as the pages of scan.tif.
+    /// --multipage-tiff 'pages/{stem}.tif' -r 2  Save a two-page TIFF for each image in ./pages.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["dedupe", "dedupe_sources", "update", "resume", "name_by_hash", "map_tiles"], verbatim_doc_comment)]
+    multipage_tiff: Option<String>,
+
+    /// An optional path to save every tile of an image as NumPy arrays, such as for loading straight into machine learning.
+    /// A path ending in `.npy` stacks the tiles into one array of tiles, rows, columns, and channels, so they must share a size.
+    /// A path ending in `.npz` saves each tile as an array named after it, as `numpy.savez_compressed` would.
+    /// Any other path is a directory to save each tile in as its own `.npy`.
+    /// Tiles with 16 bits per channel are saved as `uint16`, HDR tiles as `float32`, and others as `uint8`.
+    /// A JSON index of the tiles, with each one's name, row, column, and region, is saved beside a `.npy` or `.npz`
+    /// with a `.json` extension, or in the directory as `index.json`.
+    /// Include `{stem}` to save arrays for each image when splitting more than one.
+    /// Without `--output-dir`, no tiles are written as images.
+    /// Ex:
+    /// --npy-out tiles.npy --tile-size 224 -r 4 -c 4  Save a 16x224x224x3 stack of tiles.
+    /// --npy-out 'arrays/{stem}.npz' -r 2 -c 2       Save a .npz of four tiles for each image in ./arrays.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["dedupe", "dedupe_sources", "update", "resume", "name_by_hash", "map_tiles"], verbatim_doc_comment)]
+    npy_out: Option<String>,
+
+    /// An optional layout to organize tiles in as a dataset for training models, with some images held out for validation.
+    /// Every tile of an image goes to the same split, so no image is seen in both training and validation.
+    /// imagefolder  Folders for each split, such as `train/` and `val/`, holding a folder for each class with `--classes`,
+    ///              as torchvision's `ImageFolder` reads them.
+    /// webdataset   Tar shards for each split, such as `train-000000.tar`, holding each tile with its metadata as JSON,
+    ///              and its class's index as `.cls` with `--classes`, as the `webdataset` library reads them.
+    /// The split and class of each image are saved in `dataset.json`.
+    /// Ex:
+    /// --dataset-layout imagefolder --classes labels.csv --tile-size 224 -r 4 -c 4
+    /// --dataset-layout webdataset --val-split 0.1 --shard-size 5000
+    #[arg(
+        long,
+        value_enum,
+        value_name = "LAYOUT",
+        conflicts_with = "map_tiles",
+        verbatim_doc_comment
+    )]
+    dataset_layout: Option<DatasetLayout>,
+
+    /// An optional fraction of the images to hold out for validation with `--dataset-layout`,
+    /// such as `0.2` or `20%`, picked at random with `--seed`. Default: 0.2.
+    #[arg(long, value_name = "FRACTION", value_parser = dataset::parse_val_split, requires = "dataset_layout", verbatim_doc_comment)]
+    val_split: Option<f64>,
+
+    /// An optional CSV of images and their classes for `--dataset-layout`, one image per row,
+    /// with an optional header row of `image,class`. Images are matched by their path, file name, or stem.
+    /// Ex:
+    /// --classes labels.csv  Sort tiles by a CSV with rows such as `scans/0001.png,benign`.
+    #[arg(
+        long,
+        value_name = "CSV",
+        requires = "dataset_layout",
+        verbatim_doc_comment
+    )]
+    classes: Option<PathBuf>,
+
+    /// An optional number of tiles in each shard of `--dataset-layout webdataset`. Default: 1000.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), requires = "dataset_layout", verbatim_doc_comment)]
+    shard_size: Option<u64>,
+
+    /// What to do with a tile that has transparency when its format, such as JPEG, can't store it. Default: `flatten`.
+    /// Tiles saved in another format are recorded in the manifest.
+    /// Ex:
+    /// --ext jpg --alpha-fallback png  Save tiles as JPEG, except those with transparency, which are saved as PNG.
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = AlphaFallback::Flatten, hide_default_value = true, verbatim_doc_comment)]
+    alpha_fallback: AlphaFallback,
+
+    /// An optional color to composite tiles with transparency onto when their format can't store it. Default: white.
+    /// Ex:
+    /// --ext jpg --background '#202020'  Save tiles as JPEG, filling transparent areas with dark gray.
+    #[arg(long, value_name = "COLOR", value_parser = units::parse_color, verbatim_doc_comment)]
+    background: Option<[u8; 3]>,
+
+    /// An optional command to encode each tile with instead of the built-in encoders,
+    /// for formats or quality levels splix doesn't support itself. Needs `--ext` for the extension of the files it writes.
+    /// Each tile is written to the command's standard input as a PNG, or to a file at `{in}` if given.
+    /// The encoded tile is read from a file at `{out}` if given, or from the command's standard output.
+    /// Ex:
+    /// --ext webp --encoder-cmd 'cwebp -q 80 - -o {out}'
+    /// --ext jxl --encoder-cmd 'cjxl -d 1 {in} {out}'
+    #[arg(long, value_name = "COMMAND", requires = "ext", conflicts_with_all = ["quality", "adaptive_quality", "progressive", "interlace", "quantize"], verbatim_doc_comment)]
+    encoder_cmd: Option<String>,
+
+    /// An optional command to decode an image with when the built-in decoders can't, such as a CMYK JPEG,
+    /// an unusual TIFF compression, or a damaged file. May be repeated to try several commands in order.
+    /// `{}` is replaced with the image's path. If `{}` is omitted, the path is appended to the command.
+    /// The command writes the decoded image to its standard output, in any format splix can read.
+    /// Ex:
+    /// --decode-fallback 'magick {} png:-'
+    /// --decode-fallback 'magick {} png:-' --decode-fallback 'dcraw -c -w {}'
+    #[arg(long, value_name = "COMMAND", verbatim_doc_comment)]
+    decode_fallback: Vec<String>,
+
+    /// An optional way to even out the exposure of each tile before it's saved,
+    /// such as for microscopy and scanned documents whose lighting varies across the image.
+    /// Color tiles are adjusted in brightness alone, so their hues don't shift.
+    /// histogram  Spread each tile's brightness evenly across the full range.
+    /// clahe      Equalize each region of a tile separately, limiting contrast so noise isn't amplified.
+    /// minmax     Stretch each tile's brightness so its darkest pixel is black and its brightest white.
+    /// Ex:
+    /// --normalize clahe   Bring out detail in the dim and bright parts of each tile.
+    /// --normalize minmax  Use the full range in each tile without changing its contrast curve.
+    #[arg(long, value_enum, value_name = "MODE", verbatim_doc_comment)]
+    normalize: Option<Normalize>,
+
+    /// An optional image, such as a logo, to composite onto every tile.
+    /// It may be followed by where to place it, `bottom-right` by default, and its opacity, 50% by default.
+    /// Positions: top-left, top, top-right, left, center, right, bottom-left, bottom, bottom-right.
+    /// Watermarks larger than a tile are shrunk to fit it.
+    /// Ex:
+    /// --watermark logo.png               Place the logo in the bottom right corner at 50% opacity.
+    /// --watermark logo.png:center:0.3    Place the logo in the center at 30% opacity.
+    /// --watermark logo.png:top-left:80%  Place the logo in the top left corner at 80% opacity.
+    #[arg(long, value_name = "PATH[:POSITION[:OPACITY]]", verbatim_doc_comment)]
+    watermark: Option<String>,
+
+    /// An optional flag to draw each tile's row and column into its top left corner,
+    /// for proofing prints and checking grid parameters.
+    /// Specify a template to draw other text, with the same placeholders as `--name`.
+    /// Ex:
+    /// --label-tiles                   Draw `R0C0`, `R0C1`, and so on.
+    /// --label-tiles "{stem} {index}"  Draw the source's name and the tile's position.
+    #[arg(long, value_name = "TEMPLATE", num_args = 0..=1, default_missing_value = label::DEFAULT_LABEL, verbatim_doc_comment)]
+    label_tiles: Option<String>,
+
+    /// An optional flag to enable recursive search for images in specified directory.
+    #[arg(short = 'R', long)]
+    recursive: bool,
+
+    /// An optional flag to number columns from right to left, for images read that way, such as the spreads of manga.
+    /// The rightmost column is `{col}` 0 and comes first in `{index}`.
+    #[arg(long, conflicts_with = "map_tiles", verbatim_doc_comment)]
+    rtl: bool,
+
+    /// The order to process images in. Default: `name`.
+    /// The position of each image in this order is available to `--name` as `{n}`.
+    #[arg(long, value_enum, default_value_t = SortOrder::Name, hide_default_value = true, verbatim_doc_comment)]
+    sort: SortOrder,
+
+    /// An optional layout for splitting a numbered image sequence, such as `frame_0001.png` onwards,
+    /// so the tiles of each position form their own sequence for video tools. Images are ordered by their numbers,
+    /// so `frame_9` comes before `frame_10`, and renumbered so each sequence counts up without gaps. Default: `cells`.
+    /// Ex:
+    /// --sequence        Name tiles such as `r0c0/frame_0001.png`, a folder for each position.
+    /// --sequence flat   Name tiles such as `frame_0001_r0c0.png`.
+    #[arg(long, value_enum, value_name = "LAYOUT", num_args = 0..=1, default_missing_value = "cells", conflicts_with = "sort", verbatim_doc_comment)]
+    sequence: Option<SequenceLayout>,
+
+    /// An optional frame of animated GIF, PNG, and WebP images and Aseprite sprites to split, counting from 0. Default: `0`.
+    /// Specify `all` to split every frame into numbered stills, named with `{frame}`.
+    /// Ex:
+    /// --frame 3    Split the fourth frame of each animation.
+    /// --frame all  Split every frame, named `{stem}-f{frame}-r{row}c{col}.{ext}` unless `--name` is given.
+    #[arg(long, value_name = "N|all", value_parser = frames::parse_frame, verbatim_doc_comment)]
+    frame: Option<FrameSelection>,
+
+    /// An optional flag to split images as they're stored, ignoring the rotation in their EXIF Orientation tag.
+    /// By default, images such as phone photos are rotated upright before they're split.
+    #[arg(long, verbatim_doc_comment)]
+    no_auto_orient: bool,
+
+    /// An optional window to map the values of grayscale DICOM images to 8 bits through. Default: `file`.
+    /// file          The window stored in each image, or the full range of its values if it has none.
+    /// full          From the lowest value in each image to the highest.
+    /// CENTER,WIDTH  A window around a level, in the image's rescaled values, such as Hounsfield units for CT.
+    /// Ex:
+    /// --window 40,400     Show the soft tissue of CT scans.
+    /// --window -600,1500  Show the lungs of CT scans.
+    #[arg(long, value_name = "WINDOW", value_parser = dicom::parse_window, allow_hyphen_values = true, verbatim_doc_comment)]
+    window: Option<Window>,
+
+    /// An optional flag to save the attributes of each DICOM image next to its tiles, as `{stem}.dicom.json`.
+    /// Only attributes describing how the image was acquired and stored are saved, such as its modality and pixel spacing,
+    /// leaving out any that could identify the patient, such as their name, ID, and birth date,
+    /// or the dates, institution, and physicians of the study, unless `--dicom-tags=all` is given.
+    #[arg(long, value_enum, value_name = "SCOPE", num_args = 0..=1, default_missing_value = "safe", verbatim_doc_comment)]
+    dicom_tags: Option<DicomTags>,
+
+    /// An optional level to read whole-slide images at, such as pathology slides, which are too large to read at full resolution.
+    /// Give a level stored in the slide, from 0 for full resolution, or a magnification ending in `x`,
+    /// which is read from the smallest level that reaches it, and scaled down if no level matches. Default: 0.
+    /// Pair with `--skip-background` to leave out the bare glass around the tissue.
+    /// Ex:
+    /// --slide-level 2    Read the slide's third level, often 1/16 of full resolution.
+    /// --slide-level 10x  Read the slide at 10x magnification.
+    #[arg(long, value_name = "N|MAGx", value_parser = slide::parse_level, verbatim_doc_comment)]
+    slide_level: Option<SlideLevel>,
+
+    /// An optional minimum size for images to be split. Smaller images, such as thumbnails and icons, are skipped.
+    /// Ex:
+    /// --min-size 640x480  Skip images narrower than 640 pixels or shorter than 480 pixels.
+    #[arg(long, value_name = "WxH", value_parser = units::parse_dimensions, verbatim_doc_comment)]
+    min_size: Option<(u32, u32)>,
+
+    /// What to do with images that have fewer pixels than the requested number of rows or columns. Default: `as-is`.
+    /// The action taken is reported for each such image.
+    /// Ex:
+    /// --oversplit error  Fail on images too small for the grid.
+    /// --oversplit pad    Pad images too small for the grid, so they're always split into the requested grid.
+    #[arg(
+        long,
+        visible_alias = "oversplit",
+        value_enum,
+        value_name = "POLICY",
+        default_value_t = SmallImagePolicy::AsIs,
+        hide_default_value = true,
+        verbatim_doc_comment
+    )]
+    small_image: SmallImagePolicy,
+
+    /// An optional flag to scale up images that are smaller than the grid, so every image is split into the requested grid.
+    /// Images are scaled so that every tile has the same size. Same as `--small-image upscale`.
+    #[arg(long, conflicts_with = "small_image", verbatim_doc_comment)]
+    upscale_to_fit: bool,
+
+    /// An optional flag to split images too large to decode in memory at a smaller size, instead of skipping them.
+    /// An image is too large if there isn't enough memory for it once decoded, or if its decoder refuses it as too large.
+    /// It's then split at whatever fraction fits in 512 MiB. `--memory-limit` doesn't make images too large,
+    /// since one larger than it is still split on its own. JPEGs are decoded at a half, a quarter, or an eighth of their size,
+    /// and PNGs that aren't interlaced at whatever fraction fits, without ever being held at full size.
+    /// Other images are decoded in full as usual. Each image split smaller is reported,
+    /// and the manifest records how many times smaller it was split.
+    /// Ex: --downscale-oversized  Split a 40000x40000 scan at half size, rather than leaving it out of the batch.
+    #[arg(long, verbatim_doc_comment)]
+    downscale_oversized: bool,
+
+    /// An optional flag to report an error for images whose size isn't an exact multiple of the grid,
+    /// instead of making some tiles larger than others.
+    /// With a list of sizes, the image must divide evenly into the total number of sections.
+    #[arg(long, verbatim_doc_comment)]
+    strict_divisible: bool,
+
+    /// An optional number of pixels every cut must be a multiple of, such as the 8 or 16 pixel blocks of
+    /// video and JPEG encoders or GPU textures. Each cut moves to the nearest multiple, so every tile is a whole number
+    /// of blocks except those at the right and bottom edges, which take whatever the image's size leaves over.
+    /// With `--strict-divisible`, images whose sides aren't a multiple are reported as errors instead.
+    /// Ex:
+    /// -r 3 -c 3 --align 16  Split each image into 3x3 tiles whose cuts fall on the 16 pixel grid.
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["magick_compat", "map_tiles"], verbatim_doc_comment)]
+    align: Option<u32>,
+
+    /// An optional flag to move each cut of a JPEG to the nearest edge of its minimum coded units,
+    /// the 8x8 or 16x16 pixel blocks it's compressed in, so re-encoded tiles don't start partway through a block
+    /// and their edges keep the quality of the source. Other images, and JPEGs that are scaled or cropped
+    /// before they're split, are cut as usual.
+    #[arg(long, conflicts_with_all = ["align", "magick_compat", "map_tiles"], verbatim_doc_comment)]
+    snap_to_mcu: bool,
+
+    /// The filter used wherever images are resized: scaling up small images, scaling images and tiles
+    /// to fit `--monitors` or `--preset`, scaling `--levels` and `--map-tiles`, and shrinking `--watermark`.
+    /// Use `nearest` to keep the hard edges of pixel art, and `lanczos3` or `catmullrom` for photos. Default: `lanczos3`.
+    #[arg(long, visible_alias = "upscale-filter", value_enum, value_name = "FILTER", default_value_t = ResizeFilter::Lanczos3, hide_default_value = true, verbatim_doc_comment)]
+    filter: ResizeFilter,
+
+    /// An optional flag to resize images directly on their sRGB values instead of in linear light.
+    /// It's faster and matches older versions of splix, but darkens fine detail such as text and thin lines.
+    #[arg(long, verbatim_doc_comment)]
+    no_linear_light: bool,
+
+    /// An optional limit on the number of images to process, taking the first ones in `--sort` order.
+    #[arg(long, value_name = "COUNT", conflicts_with = "sample")]
+    limit: Option<usize>,
+
+    /// An optional number of images to pick at random to process, such as to try out options on part of a large directory.
+    /// Use `--seed` to pick the same images again.
+    #[arg(long, value_name = "COUNT", verbatim_doc_comment)]
+    sample: Option<usize>,
+
+    /// An optional seed for random choices, so that a run can be repeated exactly, on any machine.
+    /// It's used by `--sample`, `--jitter`, `--random-crops`, and the images held out by `--val-split`,
+    /// and is recorded in the `--manifest` to make the same dataset again. Default: picked at random.
+    /// Ex:
+    /// --seed 42 --random-crops 10x256x256     Cut the same crops on every run.
+    #[arg(long, verbatim_doc_comment)]
+    seed: Option<u64>,
+
+    /// An optional flag to make two runs on the same images write byte-identical output, for caching and verification.
+    /// Random choices use `--seed`, or 0 if it isn't given, and files added to archives and shards are dated
+    /// `SOURCE_DATE_EPOCH` if it's set, or otherwise 1970-01-01. Archives and shards are written one tile at a time,
+    /// so their tiles are always in the same order. Commands given with `--encoder-cmd` must be reproducible themselves.
+    /// Ex:
+    /// --reproducible -d tiles.tar                  Write the same archive on every run.
+    /// --reproducible --random-crops 10x256x256     Cut the same crops on every run, with seed 0.
+    #[arg(long, verbatim_doc_comment)]
+    reproducible: bool,
+
+    /// An optional distance in pixels to randomly move each cut line by, either way, such as to vary crops for
+    /// training data. Each image gets its own cut lines, and every tile keeps at least one pixel.
+    /// Use `--seed` to cut the same way again.
+    #[arg(long, value_name = "PIXELS", conflicts_with_all = ["poster", "monitors"], verbatim_doc_comment)]
+    jitter: Option<u32>,
+
+    /// An optional flag to cut and name tiles the same as ImageMagick's `convert IMAGE -crop COLSxROWS@ +repage`,
+    /// so splix can replace it in existing scripts. `--rows` and `--cols` must be single numbers,
+    /// and tiles are numbered from 0 in `--name` with `%d`, or `%03d` for numbers padded with zeros.
+    /// Default name: `{stem}-%d.{ext}`.
+    /// Ex:
+    /// --rows 2 --cols 3 --magick-compat                  Same as `-crop 3x2@ +repage IMAGE-%d.EXT`.
+    /// --rows 2 --cols 3 --magick-compat --name tile_%02d.png  Same as `-crop 3x2@ +repage tile_%02d.png`.
+    #[arg(long, conflicts_with_all = ["poster", "monitors", "preset"], verbatim_doc_comment)]
+    magick_compat: bool,
+
+    /// An optional largest height in pixels for tiles, splitting each image into the fewest equal rows that fit,
+    /// such as for webtoons and long screenshots.
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["rows", "cols", "poster", "monitors", "preset", "jitter", "magick_compat"], verbatim_doc_comment)]
+    max_height: Option<u32>,
+
+    /// An optional flag to move each cut of `--max-height` up to the quietest row nearby,
+    /// such as a blank gap between lines of text or comic panels, so they aren't sliced through the middle.
+    /// Rows are then no longer equal, but still no taller than the limit.
+    #[arg(long, requires = "max_height", verbatim_doc_comment)]
+    smart_break: bool,
+
+    /// An optional flag to move each cut between rows and columns off any line of text it would slice through,
+    /// into the whitespace nearby, such as for screenshots and scanned documents.
+    /// Each cut moves by at most a quarter of the rows or columns on either side of it, so tiles are no longer equal.
+    /// Ex:
+    /// -r 3 --avoid-text  Split each page into 3 rows, cutting between lines of text.
+    #[arg(long, conflicts_with_all = ["poster", "monitors", "magick_compat", "smart_break", "random_crops", "map_tiles", "ruled_lines"], verbatim_doc_comment)]
+    avoid_text: bool,
+
+    /// An optional flag to apply `--rows` or `--cols` to whichever side of each image is longer,
+    /// splitting portrait images into rows and landscape images into columns, such as for batches of mixed photos.
+    /// Ex:
+    /// --rows 3 --auto-axis  Split portrait images into 3 rows and landscape images into 3 columns.
+    #[arg(long, conflicts_with_all = ["poster", "monitors", "preset", "max_height", "magick_compat"], verbatim_doc_comment)]
+    auto_axis: bool,
+
+    /// An optional total number of tiles to split each image into, arranged in whichever grid of rows and columns
+    /// gives the most square tiles for that image. For example, 12 tiles on a 3:2 landscape photo are 3 rows of 4,
+    /// and on a 2:3 portrait photo 4 rows of 3.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["rows", "cols", "poster", "monitors", "preset", "auto_axis", "max_height", "magick_compat"], verbatim_doc_comment)]
+    tiles: Option<u32>,
+
+    /// An optional number and size of crops to take from random positions in each image, instead of splitting it into a grid,
+    /// such as to generate training data. Crops may overlap, and are named as columns of a single row.
+    /// Crops larger than an image are cut down to it, and an image no larger than a crop gives a single crop,
+    /// unless `--small-image` says otherwise. Use `--seed` to take the same crops again.
+    /// Ex:
+    /// --random-crops 10x256x256  Take 10 crops of 256x256 pixels from each image.
+    #[arg(long, value_name = "COUNTxWxH", value_parser = crops::parse_random_crops, conflicts_with_all = ["rows", "cols", "poster", "monitors", "preset", "jitter", "tiles", "auto_axis", "max_height", "magick_compat"], verbatim_doc_comment)]
+    random_crops: Option<RandomCrops>,
+
+    /// An optional grid of columns and rows to split each image into, which may be repeated to split each image
+    /// into several grids while decoding it only once. Each grid's tiles are saved in a folder named after it,
+    /// such as `4x4/`, unless `--name` places them with `{grid}`.
+    /// Ex:
+    /// --grid 2x2 --grid 4x4  Split each image into 2x2 tiles in `2x2/` and 4x4 tiles in `4x4/`.
+    #[arg(long, value_name = "COLSxROWS", value_parser = units::parse_grid, conflicts_with_all = ["rows", "cols", "poster", "monitors", "preset", "tiles", "auto_axis", "max_height", "magick_compat", "random_crops", "map_tiles"], verbatim_doc_comment)]
+    grid: Vec<(u32, u32)>,
+
+    /// An optional flag to split each image along the ruled lines found in it, such as the rules of a scanned table
+    /// or a ruled form, to feed each cell or row to OCR on its own. The lines themselves are left out of the tiles.
+    /// Lines must run across at least half the image, or half the table for vertical lines,
+    /// and an image without any is kept whole.
+    /// Default: cells.
+    /// Ex:
+    /// --ruled-lines       Split each table into one tile per cell.
+    /// --ruled-lines rows  Split along horizontal lines only, giving one tile per row.
+    #[arg(long, value_name = "SPLIT", num_args = 0..=1, default_missing_value = "cells", conflicts_with_all = ["rows", "cols", "poster", "monitors", "preset", "jitter", "tiles", "auto_axis", "max_height", "magick_compat", "random_crops", "map_tiles", "grid"], verbatim_doc_comment)]
+    ruled_lines: Option<RuledLines>,
+
+    /// An optional flag to find the grid of each image from how it repeats, and split it into its cells,
+    /// such as for contact sheets, sticker sheets, and photographed board games.
+    /// The size and position of the cells are found for each image, on the lines or in the gutters between them,
+    /// and margins narrower than a cell are left out. A side that doesn't repeat is kept whole.
+    /// Use `--emit-grid-overlay` to check the grid that was found.
+    /// Ex:
+    /// --detect-grid  Split each contact sheet into its photos.
+    #[arg(long, conflicts_with_all = ["rows", "cols", "poster", "monitors", "preset", "jitter", "tiles", "auto_axis", "max_height", "magick_compat", "random_crops", "map_tiles", "grid", "ruled_lines", "avoid_text"], verbatim_doc_comment)]
+    detect_grid: bool,
+
+    /// An optional number of resolutions to split each image at, each half the size of the one before,
+    /// sharing a single decode, such as for image pyramids in viewers and machine learning.
+    /// Level 0 is the image at full size, level 1 half size, level 2 quarter size, and so on.
+    /// Each level's tiles are saved in a folder named after it, such as `1/`, unless `--name` places them with `{level}`.
+    /// Levels too small for the grid are left out.
+    /// Ex:
+    /// -r 4 -c 4 --levels 3  Split each image into 4x4 tiles at full, half, and quarter size.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=32), conflicts_with = "map_tiles", verbatim_doc_comment)]
+    levels: Option<u32>,
+
+    /// An optional set of planes to split each image into, saving each as a grayscale image,
+    /// such as for scientific imaging and print preparation. Each plane is then split into the grid like a whole image.
+    /// Each plane's tiles are saved in a folder named after it, such as `red/`, unless `--name` places them with `{channel}`.
+    /// Images with more than 8 bits per channel give 16-bit planes.
+    /// Ex:
+    /// --channels rgba        Save the red, green, blue, and alpha of each image.
+    /// --channels cmyk        Save cyan, magenta, yellow, and black separations, dark where ink is printed.
+    /// --channels ycbcr -r 2  Save the luma and chroma of each image, each split into 2 rows.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "CHANNELS",
+        conflicts_with = "map_tiles",
+        verbatim_doc_comment
+    )]
+    channels: Option<Channels>,
+
+    /// An optional flag to split each image into a pyramid of 256x256 map tiles laid out like the raster profile of gdal2tiles,
+    /// for map viewers such as Leaflet and OpenLayers. Level 0 fits the whole image in one tile,
+    /// and each level above doubles its size, up to the image at full size.
+    /// Rows are numbered from the bottom as in the Tile Map Service spec (tms, the default), or from the top (xyz).
+    /// A tilemapresource.xml file describing the bounds and zoom levels is written beside the levels.
+    /// Default name: `{z}/{x}/{y}.{ext}`, saved as PNG unless `--ext` is given.
+    /// Ex:
+    /// --map-tiles      Same as `gdal2tiles -p raster IMAGE OUTPUT_DIR`.
+    /// --map-tiles xyz  Same as `gdal2tiles -p raster --xyz IMAGE OUTPUT_DIR`.
+    #[arg(long, value_name = "SCHEME", num_args = 0..=1, default_missing_value = "tms", conflicts_with_all = ["rows", "cols", "poster", "monitors", "preset", "jitter", "tiles", "auto_axis", "max_height", "magick_compat", "random_crops"], verbatim_doc_comment)]
+    map_tiles: Option<TileScheme>,
+
+    /// An optional flag to also write a viewer.html page beside the map tiles, which opens them in a zoomable map
+    /// without any other setup. The page loads Leaflet from unpkg.com.
+    /// Ex:
+    /// --map-tiles --viewer  Split each image into map tiles, then open viewer.html in a browser to explore it.
+    #[arg(long, requires = "map_tiles", verbatim_doc_comment)]
+    viewer: bool,
+
+    /// An optional flag to open the results once every image is split without errors, like `cargo doc --open`.
+    /// Opens the first image's viewer.html with `--viewer`, otherwise tiles.html with `--html`,
+    /// sprites.html with `--sprite-css`, or else the output directory in the file manager.
+    #[arg(long, verbatim_doc_comment)]
+    open: bool,
+
+    /// An optional limit on how many levels of subdirectories to search for images. Implies `--recursive`.
+    /// Ex:
+    /// --max-depth 0  Only search the specified directory, same as not using `--recursive`.
+    /// --max-depth 1  Also search the directories directly inside the specified directory.
+    #[arg(long, value_name = "DEPTH", verbatim_doc_comment)]
+    max_depth: Option<usize>,
+
+    /// An optional flag to search every file, ignoring the patterns of `.gitignore` and `.splixignore` files.
+    /// Without it, files and directories matched by the ignore files of the directories searched,
+    /// or of the directories above them in the same Git repository, are skipped, such as `node_modules` or raw folders.
+    #[arg(long, verbatim_doc_comment)]
+    no_ignore: bool,
+
+    /// An optional flag to skip hidden files and directories, such as dotfiles.
+    #[arg(long)]
+    skip_hidden: bool,
+
+    /// An optional flag to skip files and directories left by operating systems,
+    /// such as `Thumbs.db`, `.DS_Store`, `__MACOSX`, and `._` resource forks.
+    #[arg(long, verbatim_doc_comment)]
+    skip_junk: bool,
+
+    /// An optional flag to follow symbolic links to directories when searching for images.
+    /// Links that loop back to a parent directory are reported and skipped.
+    #[arg(long, verbatim_doc_comment)]
+    follow_symlinks: bool,
+
+    /// An optional flag to skip images that are symbolic links.
+    #[arg(long, conflicts_with = "follow_symlinks")]
+    skip_symlinks: bool,
+
+    /// An optional limit on the memory used by images being split at the same time.
+    /// Images wait to be decoded until the memory is available. An image larger than the limit is split on its own.
+    /// Ex:
+    /// --memory-limit 4G    Allow up to 4 GiB of decoded images at once.
+    /// --memory-limit 512M  Allow up to 512 MiB of decoded images at once.
+    #[arg(long, value_name = "SIZE", value_parser = units::parse_size, verbatim_doc_comment)]
+    memory_limit: Option<u64>,
+
+    /// The number of images to decode at once, ahead of the threads that split and encode them. Default: the number of CPUs.
+    /// Decoded images wait for the encoders in a queue of the same length, so memory stays bounded when encoding falls behind.
+    #[arg(long, value_name = "JOBS", verbatim_doc_comment)]
+    decode_jobs: Option<usize>,
+
+    /// The number of threads that split, scale, and encode tiles. Default: the number of CPUs.
+    /// Tune it together with `--io-threads`, since the best number of writes at once rarely matches the CPUs.
+    #[arg(
+        long,
+        visible_alias = "cpu-threads",
+        value_name = "JOBS",
+        verbatim_doc_comment
+    )]
+    encode_jobs: Option<usize>,
+
+    /// The number of tiles to write at once, so slow disks don't hold up decoding and encoding. Default: the number of CPUs.
+    /// Encoded tiles wait for the writers in a queue of a few per job, so memory stays bounded when writing falls behind.
+    /// Ex:
+    /// --io-threads 2                   Write to NFS, which slows down with many writes at once.
+    /// --cpu-threads 16 --io-threads 4  Encode on every core of a 16-core machine, writing 4 tiles at once.
+    #[arg(
+        long,
+        visible_alias = "io-threads",
+        value_name = "JOBS",
+        conflicts_with = "async_io",
+        verbatim_doc_comment
+    )]
+    write_jobs: Option<usize>,
+
+    /// An optional flag to start splitting without first checking that the output directory has room for the tiles.
+    /// By default, the first image is split in memory to estimate the size of every tile, and the run stops early if they won't fit.
+    #[arg(long, verbatim_doc_comment)]
+    no_space_check: bool,
+
+    /// An optional flag to refuse to start if any tile would overwrite a file that already exists in the output directory.
+    /// Tiles of the batch that would overwrite each other are always reported before splitting, with or without it.
+    #[arg(long, verbatim_doc_comment)]
+    no_clobber: bool,
+
+    /// An optional flag to wait for another splix run writing to the same output directory to finish, instead of failing.
+    /// Each run locks its output directory with a `.splix.lock` file, so runs can't overwrite each other's tiles.
+    #[arg(long, verbatim_doc_comment)]
+    wait_for_lock: bool,
+
+    /// An optional flag to run at the lowest CPU priority and, on Linux, the idle I/O priority,
+    /// so a large background job doesn't slow down other programs. Combine with `--io-limit` to cap disk use too.
+    #[arg(long, verbatim_doc_comment)]
+    nice: bool,
+
+    /// An optional limit on how many bytes per second are read from images and written as tiles.
+    /// Ex:
+    /// --io-limit 20M  Read and write at most 20 MiB per second on average.
+    #[arg(long, value_name = "SIZE", value_parser = units::parse_size, verbatim_doc_comment)]
+    io_limit: Option<u64>,
+
+    /// An optional flag to skip tiles that are entirely a single color.
+    /// Optionally specify a tolerance (0-255) for how far any channel may deviate from the tile's first pixel.
+    /// Ex:
+    /// --skip-blank     Skip tiles made up of exactly one color.
+    /// --skip-blank=8   Skip tiles whose channels all stay within 8 of the first pixel.
+    #[arg(
+        long,
+        value_name = "TOLERANCE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "0",
+        verbatim_doc_comment
+    )]
+    skip_blank: Option<u8>,
+
+    /// An optional flag to skip tiles that are fully transparent.
+    #[arg(long)]
+    skip_transparent: bool,
+
+    /// An optional flag to skip tiles that are mostly background, such as the bare glass around the tissue on a slide,
+    /// or the margins of a scanned page. Pixels that are mostly transparent, or bright in every channel, are background.
+    /// Optionally specify the percentage (1-100) of a tile that must be content for it to be kept. Default: 10.
+    /// Ex:
+    /// --skip-background     Skip tiles that are less than 10% tissue.
+    /// --skip-background=50  Skip tiles that are less than half tissue.
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "10",
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        verbatim_doc_comment
+    )]
+    skip_background: Option<u8>,
+
+    /// An optional mask image marking the foreground of each image, such as tissue or a region of interest,
+    /// to skip tiles it doesn't cover. Bright, opaque pixels of the mask are foreground.
+    /// The mask is stretched over the image, so it may be drawn at a lower resolution, as long as it has the same shape.
+    /// `{stem}` is replaced with the stem of each image, to give each its own mask.
+    /// The percentage of each tile the mask covers is recorded in `--manifest`.
+    /// Ex:
+    /// --mask tissue.png               Skip tiles that are less than 50% covered by tissue.png.
+    /// --mask 'masks/{stem}.png'       Read the mask of photo.jpg from masks/photo.png.
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    mask: Option<String>,
+
+    /// An optional percentage (0-100) of a tile `--mask` must cover for the tile to be kept. Default: 50.
+    /// Use 0 to keep every tile and only record coverage in the manifest.
+    /// Ex:
+    /// --mask tissue.png --mask-coverage 30  Keep tiles that are at least 30% tissue.
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value_t = 50,
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        requires = "mask",
+        verbatim_doc_comment
+    )]
+    mask_coverage: u8,
+
+    /// An optional labeled mask or JSON file of polygons marking regions of each image, such as the annotated areas
+    /// of a slide, to only cut tiles from those regions. In a labeled mask, each value other than 0 marks a region,
+    /// and the mask is stretched over the image. Polygons are in pixels of the image, as GeoJSON features,
+    /// such as those exported by QuPath, or as a list of polygons, each a list of [x, y] points.
+    /// `{stem}` is replaced with the stem of each image, to give each its own regions.
+    /// Ex:
+    /// --regions 'labels/{stem}.png'                     Keep the tiles that overlap a labeled region.
+    /// --regions annotations.geojson --region-mode bbox  Cut out the smallest rectangle holding each annotation.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with = "map_tiles",
+        verbatim_doc_comment
+    )]
+    regions: Option<String>,
+
+    /// How to cut tiles from `--regions`. Default: `grid`.
+    /// grid  Keep the tiles of the grid that overlap a region.
+    /// bbox  Cut one tile for each region, the smallest rectangle holding it.
+    #[arg(long, value_enum, default_value_t = RegionMode::Grid, hide_default_value = true, requires = "regions", verbatim_doc_comment)]
+    region_mode: RegionMode,
+
+    /// An optional flag to avoid writing tiles identical to a tile that was already saved.
+    /// Duplicates are detected across all images in the run, by their source pixels, so they can only be linked or copied
+    /// without `--label-tiles`, `--poster-marks`, and `--tile-metadata`, which give each tile something of its own.
+    /// Ex:
+    /// --dedupe       Don't write duplicate tiles.
+    /// --dedupe=link  Hard-link duplicate tiles to the first occurrence.
+    /// --dedupe=copy  Copy the first occurrence to duplicate tiles.
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "skip",
+        verbatim_doc_comment
+    )]
+    dedupe: Option<DedupeMode>,
+
+    /// An optional flag to split each image only once when the same file appears several times, such as in photo archives.
+    /// Files are compared by their SHA-256, and the tiles of each copy are made from the tiles of the first one found,
+    /// named as if the copy had been split. The manifest records which file each copy duplicates.
+    /// Copies can be left out with `skip`, but not linked or copied with `--tile-metadata`, since they'd name another file.
+    /// Ex:
+    /// --dedupe-sources       Hard-link the tiles of copies to the tiles of the first file.
+    /// --dedupe-sources=copy  Copy the tiles of the first file.
+    /// --dedupe-sources=skip  Don't write tiles for copies at all.
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "link",
+        conflicts_with_all = ["from_clipboard", "name_by_hash", "map_tiles"],
+        verbatim_doc_comment
+    )]
+    dedupe_sources: Option<DedupeMode>,
+
+    /// An optional path to write a JSON manifest of the source images and their tiles to.
+    /// It records the SHA-256 of each source file and tile, so they can be checked for corruption later.
+    #[arg(long, verbatim_doc_comment)]
+    manifest: Option<PathBuf>,
+
+    /// An optional flag to write where each tile came from into the tile itself, so it stays self-describing
+    /// even when separated from the manifest: the source image's file name and size, and the tile's row, column,
+    /// offset, and size. They're saved as XMP properties and an EXIF description in PNG, JPEG, and WebP tiles.
+    /// Duplicates can be left out with `--dedupe` or `--dedupe-sources=skip`, but not linked or copied,
+    /// since they'd carry another tile's metadata.
+    /// Ex:
+    /// --tile-metadata  Then `exiftool -XMP-splix:all tile.png` shows where the tile came from.
+    #[arg(long, verbatim_doc_comment)]
+    tile_metadata: bool,
+
+    /// An optional flag to record what each tile contains in `--manifest`, so tiles can be filtered or ordered
+    /// without reading them again: the mean and standard deviation of its brightness, the entropy of its brightness,
+    /// its most common color, and how blank it is, as the fraction of pixels near that color.
+    /// Tiles skipped by filters such as `--skip-blank` are measured too.
+    /// Ex:
+    /// --manifest tiles.json --tile-stats  Then `jq '.sources[].tiles[] | select(.stats.entropy > 4)' tiles.json` lists busy tiles.
+    #[arg(long, requires = "manifest", verbatim_doc_comment)]
+    tile_stats: bool,
+
+    /// An optional flag to write a stylesheet with a class for each tile to the output directory,
+    /// showing the tile's region of its source image as a CSS sprite, along with `sprites.html` to preview them.
+    /// Classes are named after the tiles' paths, such as `.icons-r0c1`. Images that are resized,
+    /// cropped, or split into levels, planes, or frames are left out, since their tiles aren't regions of the file.
+    /// Ex:
+    /// --sprite-css       Write `sprites.css`.
+    /// --sprite-css=scss  Write `sprites.scss`, sharing each sheet's rules with `@extend`.
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "css", conflicts_with = "map_tiles", verbatim_doc_comment)]
+    sprite_css: Option<StyleFormat>,
+
+    /// An optional flag to write `tiles.html` to the output directory, an HTML snippet that shows each image
+    /// reassembled from its tiles, such as to paste a sliced image into an email. Tiles are linked by their paths
+    /// relative to the output directory, so keep the snippet next to them or rewrite the paths once they're uploaded.
+    /// Ex:
+    /// --html       Lay the tiles out in a borderless table.
+    /// --html=flex  Lay the tiles out in rows of flex boxes.
+    #[arg(long, value_name = "LAYOUT", num_args = 0..=1, require_equals = true, default_missing_value = "table", conflicts_with = "map_tiles", verbatim_doc_comment)]
+    html: Option<HtmlLayout>,
+
+    /// An optional game engine to write sprite metadata for, so the tiles of a sprite sheet import as frames of it
+    /// without setting each one up by hand. Like `--sprite-css`, images that are resized, cropped,
+    /// or split into levels, planes, or frames are left out.
+    /// Ex:
+    /// --engine-meta godot  Write an `AtlasTexture` next to each tile, such as `hero-r0c1.tres`.
+    /// --engine-meta unity  Write a sprite sheet description of each image, such as `hero.sprites.json`.
+    #[arg(
+        long,
+        value_name = "ENGINE",
+        conflicts_with = "map_tiles",
+        verbatim_doc_comment
+    )]
+    engine_meta: Option<EngineFormat>,
+
+    /// An optional flag to only write the tiles whose source region has changed since they were last written
+    /// to the output directory, such as to update one corner of a huge map pyramid without rebuilding every tile.
+    /// The region each tile was made from is recorded in `.splix-cache.json` in the output directory.
+    /// Tiles are compared by their source pixels and format only, so run without `--update` after changing options
+    /// such as `--quality` or `--watermark` that change how tiles look.
+    /// Ex:
+    /// --map-tiles --update  Rebuild only the map tiles covering the part of the image that was edited.
+    #[arg(long, conflicts_with_all = ["dedupe", "name_by_hash"], verbatim_doc_comment)]
+    update: bool,
+
+    /// An optional flag to keep a journal of the tiles written so far in `.splix-journal` in the output directory,
+    /// so a long run that's interrupted or fails can be run again with the same command and carry on
+    /// from the last tile written instead of starting over. The journal is removed once a run finishes.
+    /// Ex:
+    /// --map-tiles --resume  Build a large map pyramid that can be stopped with Ctrl-C and resumed later.
+    #[arg(long, conflicts_with = "name_by_hash", verbatim_doc_comment)]
+    resume: bool,
+
+    /// An optional flag to also save a copy of each image with its cut lines and tile positions drawn on it,
+    /// named `{stem}-grid.png`, to check exactly where the image is cut.
+    /// With `--frame all` and `--levels`, each frame and level gets its own, such as `{stem}-f2-l1-grid.png`.
+    #[arg(long, verbatim_doc_comment)]
+    emit_grid_overlay: bool,
+
+    /// An optional path to write a JSON list of the files that couldn't be split, and why.
+    /// The list is always printed when any files are skipped.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    skipped_report: Option<PathBuf>,
+
+    /// An optional path to write a JSON summary of the run to: files scanned, split, and skipped,
+    /// tiles written, bytes read and written, wall time, and the time spent decoding, encoding, and writing.
+    /// The summary is always printed at the end of the run.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    stats: Option<PathBuf>,
+
+    /// An optional path to write the time spent decoding, splitting, and encoding each image to, as CSV.
+    /// Images are listed slowest first, to find the ones that dominate a run's time.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    profile: Option<PathBuf>,
+
+    /// An optional flag to stop at the first image that can't be decoded or tile that can't be saved.
+    #[arg(long, conflicts_with = "continue_on_error")]
+    fail_fast: bool,
+
+    /// An optional flag to keep going after errors and report them at the end. This is the default.
+    /// The run still stops if the output runs out of disk space.
+    #[arg(long, verbatim_doc_comment)]
+    continue_on_error: bool,
+
+    /// An optional language to print splix's own messages in, instead of the one chosen by the
+    /// `LC_ALL`, `LC_MESSAGES`, or `LANG` environment variables. Messages splix has no translation of are printed in English.
+    /// Languages: en, de.
+    /// Ex: --lang de  Print the summary and progress messages in German.
+    #[arg(long, value_name = "LANG", value_parser = i18n::parse_lang, verbatim_doc_comment)]
+    lang: Option<String>,
+
+    /// An optional command to run for each saved tile, such as an optimizer.
+    /// `{}` is replaced with the tile's path. If `{}` is omitted, the path is appended to the command.
+    /// Ex:
+    /// --exec 'oxipng -o 4 {}'
+    /// --exec 'cwebp -q 80 {} -o {}.webp'
+    #[arg(long, value_name = "COMMAND", verbatim_doc_comment)]
+    exec: Option<String>,
+
+    /// The maximum number of `--exec` commands to run at once. Default: the number of CPUs.
+    #[arg(long, value_name = "JOBS", requires = "exec")]
+    exec_jobs: Option<usize>,
+
+    /// An optional flag to write tiles in the background on an async runtime instead of on the threads that encode them.
+    /// Speeds up runs on network file systems and object storage, where each write waits on the network.
+    /// Write errors are reported once every tile has been encoded. Can't be combined with `--exec`.
+    #[arg(long, conflicts_with = "exec", verbatim_doc_comment)]
+    async_io: bool,
+
+    /// An optional file descriptor to write progress events to, one JSON object per line, for front-ends that wrap splix.
+    /// Events are `start`, `tile` for each written tile, `file` for each finished image with `percent` and `eta_secs`, and `finish`.
+    /// Ex:
+    /// --progress-fd 3  Write events to file descriptor 3, such as a pipe opened by the calling program.
+    #[arg(long, value_name = "FD", verbatim_doc_comment)]
+    progress_fd: Option<u32>,
+}
+
+/// Commands other than splitting images.
+#[derive(Subcommand)]
+enum Command {
+    /// Splits an image in memory, joins the tiles back together, and checks they match the image.
+    ///
+    /// Run this before splitting images you plan to delete, to check the tiles keep every pixel.
+    Selftest(SelftestArgs),
+    /// Reports how splix was built and what it finds on this machine, for bug reports.
+    ///
+    /// Lists the optional features splix was built with, the formats it reads and writes,
+    /// the threads it splits with, and the CPU's SIMD instruction sets, then splits a small image in memory
+    /// to check the installation works. Paste the output into bug reports.
+    Doctor,
+    /// Compares tiles that were already saved with the regions of their source images.
+    ///
+    /// Each tile's PSNR and SSIM against its region are reported, and tiles below `--min-psnr` are flagged,
+    /// such as after the tiles were edited, or to check old tiles haven't been corrupted.
+    /// Tiles are found from a manifest written with `--manifest`, or by their default names.
+    Diff(DiffArgs),
+    /// Splits images again along the exact regions recorded in a manifest written with `--manifest`.
+    ///
+    /// Each tile is cropped from the same offset and size as before and written over its old file,
+    /// so the tiles keep the same layout even if the source images were edited since, or splix's defaults changed.
+    Replay(ReplayArgs),
+    /// Packs a directory of images, such as the tiles of a split, into power-of-two atlases.
+    ///
+    /// Each image is placed whole, in as few atlases as possible, each shrunk to the smallest power of two that holds its images.
+    /// `atlas.json` next to the atlases records which atlas each image went to, and where.
+    Pack(PackArgs),
+    /// Saves, lists, and uses named bundles of options, kept in splix's config directory.
+    ///
+    /// The presets are kept in `presets` inside `$SPLIX_CONFIG_DIR` if it's set, such as a folder shared by a team,
+    /// otherwise in `~/.config/splix`, `~/Library/Application Support/splix` on macOS, or `%APPDATA%\splix` on Windows.
+    #[command(subcommand)]
+    Preset(PresetCommand),
+    /// Adds a "Split with splix…" entry to the file manager's context menu for images, or removes it.
+    ///
+    /// The entry is a verb on every image type in the registry on Windows, a desktop action offered in "Open With"
+    /// and in KDE's Dolphin on Linux, and a Quick Action in Finder's Services menu on macOS.
+    /// The tiles of an image split from the entry are saved in `splixed-images` next to it.
+    #[command(subcommand)]
+    Integrate(IntegrateCommand),
+}
+
+/// Subcommands of `splix integrate`.
+#[derive(Subcommand)]
+enum IntegrateCommand {
+    /// Registers the context-menu entry for the current user, replacing any registered before.
+    ///
+    /// Ex: splix integrate install -r 3 -c 3 --ext png
+    Install {
+        /// Options to split images with, written as they would be for splitting. Default: `-r 2 -c 2 --open`.
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Removes the context-menu entry.
+    Uninstall,
+    /// Splits an image from the context-menu entry, saving its tiles next to it unless `--output-dir` is given.
+    #[command(hide = true)]
+    Split {
+        /// The options the entry was installed with, followed by the image.
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+/// Subcommands of `splix preset`.
+#[derive(Subcommand)]
+enum PresetCommand {
+    /// Saves options for splitting as a preset, replacing any preset of the same name.
+    ///
+    /// Ex: splix preset save ml-512 --random-crops 16x512x512 --ext png --name '{stem}_{index}.{ext}'
+    Save {
+        /// Name of the preset, made of letters, digits, '-', '_', and '.'.
+        name: String,
+
+        /// Options to save, written as they would be for splitting.
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        args: Vec<String>,
+    },
+    /// Lists the saved presets and their options.
+    List,
+    /// Splits images with a saved preset. Options given after the name are added to the preset's,
+    /// and replace any the preset also sets.
+    ///
+    /// Ex: splix preset use ml-512 ./photos --quality 90
+    Use {
+        /// Name of the preset.
+        name: String,
+
+        /// Images to split and any further options, written as they would be for splitting.
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+/// Arguments of `splix replay`.
+#[derive(Args)]
+struct ReplayArgs {
+    /// Path of the manifest written with `--manifest`.
+    manifest: PathBuf,
+
+    /// An optional path of a source image in the manifest, to replay only its tiles.
+    #[arg(long, value_name = "PATH")]
+    image: Option<PathBuf>,
+
+    /// An optional quality from 1 to 100 for JPEG and AVIF tiles.
+    #[arg(long, value_name = "QUALITY", value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+
+    /// An optional flag to crop the source images as stored, if they were split with `--no-auto-orient`.
+    #[arg(long)]
+    no_auto_orient: bool,
+}
+
+/// Arguments of `splix pack`.
+#[derive(Args)]
+struct PackArgs {
+    /// Path of the directory of images to pack, searched recursively.
+    dir: PathBuf,
+
+    /// An optional directory to save the atlases and `atlas.json` in. Default: `./splixed-atlases`.
+    #[arg(short = 'd', long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// The largest width and height of an atlas, a power of two. Default: 2048.
+    #[arg(
+        long,
+        value_name = "PX",
+        default_value_t = 2048,
+        hide_default_value = true
+    )]
+    max_size: u32,
+
+    /// Empty pixels to leave between images, so they don't bleed into each other when filtered. Default: 0.
+    #[arg(
+        long,
+        value_name = "PX",
+        default_value_t = 0,
+        hide_default_value = true
+    )]
+    padding: u32,
+
+    /// An optional extension for the atlases, which also chooses their format. Default: `png`.
+    #[arg(long)]
+    ext: Option<String>,
+}
+
+/// Arguments of `splix diff`.
+#[derive(Args)]
+struct DiffArgs {
+    /// Path of the source image. With `--manifest`, only this image's tiles are compared.
+    #[arg(required_unless_present = "manifest")]
+    image: Option<PathBuf>,
+
+    /// An optional manifest written with `--manifest` that lists the tiles and the regions they cover.
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// The directory holding the tiles, named `{stem}-r{row}c{col}.{ext}`, without `--manifest`. Default: `./splixed-images`.
+    #[arg(short = 'd', long, value_name = "DIR", conflicts_with = "manifest")]
+    tiles_dir: Option<PathBuf>,
+
+    /// Number of rows the image was split into, without `--manifest`. Default: one more than the last row found.
+    #[arg(short, long, value_delimiter = ',', conflicts_with = "manifest")]
+    rows: Option<Vec<f64>>,
+
+    /// Number of columns the image was split into, without `--manifest`. Default: one more than the last column found.
+    #[arg(short, long, value_delimiter = ',', conflicts_with = "manifest")]
+    cols: Option<Vec<f64>>,
+
+    /// The lowest PSNR, in decibels, a tile may have before it's flagged as a mismatch. Default: 40.
+    #[arg(
+        long,
+        value_name = "DB",
+        default_value_t = 40.0,
+        hide_default_value = true
+    )]
+    min_psnr: f64,
+
+    /// An optional flag to compare with the source images as stored, if they were split with `--no-auto-orient`.
+    #[arg(long)]
+    no_auto_orient: bool,
+}
+
+/// Arguments of `splix selftest`.
+#[derive(Args)]
+struct SelftestArgs {
+    /// Path of the image to check.
+    image: PathBuf,
+
+    /// Number of rows to split the image into, as for splitting. Default: 1.
+    #[arg(short, long, value_delimiter = ',')]
+    rows: Option<Vec<f64>>,
+
+    /// Number of columns to split the image into, as for splitting. Default: 1.
+    #[arg(short, long, value_delimiter = ',')]
+    cols: Option<Vec<f64>>,
+
+    /// An optional extension for the tiles, which also chooses their format. Default: the extension of the image's format.
+    #[arg(long, value_name = "EXT")]
+    ext: Option<String>,
+
+    /// An optional quality from 1 to 100 for JPEG and AVIF tiles.
+    #[arg(long, value_name = "QUALITY", value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+
+    /// The lowest peak signal-to-noise ratio, in decibels, accepted from lossy formats such as JPEG.
+    /// Lossless formats must match the image exactly. Default: 40.
+    #[arg(
+        long,
+        value_name = "DB",
+        default_value_t = 40.0,
+        hide_default_value = true,
+        verbatim_doc_comment
+    )]
+    min_psnr: f64,
+}
+
+/// What to do with an image that has fewer pixels than the grid has rows or columns.
+#[derive(Clone, Copy, ValueEnum)]
+enum SmallImagePolicy {
+    /// Skip the image.
+    Skip,
+    /// Report the image as an error.
+    Error,
+    /// Scale the image up until the grid fits, with every tile the same size.
+    Upscale,
+    /// Split the image into as many rows and columns as it has pixels.
+    #[value(alias = "clamp")]
+    AsIs,
+    /// Pad the right and bottom edges of the image with transparent pixels until the grid fits, keeping its scale.
+    Pad,
+}
+
+/// The order images are processed in.
+#[derive(Clone, Copy, ValueEnum)]
+enum SortOrder {
+    /// By path.
+    Name,
+    /// By modification time, oldest first.
+    Mtime,
+    /// By file size, smallest first.
+    Size,
+    /// In the order the file system lists them.
+    None,
+}
+
+/// A source image being split.
+struct SourceInfo<'a> {
+    /// Path of the image.
+    path: &'a Path,
+    /// Path of image excluding parent directories and extension.
+    stem: &'a str,
+    /// Format of the image.
+    format: ImageFormat,
+    /// String representation of the image format.
+    ext: &'a str,
+    /// Position of the image in the batch.
+    index: usize,
+    /// Frame of the image being split, or 0 for a still image.
+    frame: usize,
+    /// Zoom level of map tiles being split, or 0 for other layouts.
+    zoom: u32,
+    /// Grid of `--grid` being split, or empty without it.
+    grid: &'a str,
+    /// Resolution of `--levels` being split, or 0 without it.
+    level: u32,
+    /// Plane of `--channels` being split, or empty without it.
+    channel: &'a str,
+    /// Foreground of the image marked by `--mask`, if given.
+    mask: Option<&'a GrayImage>,
+    /// Preset the image is split with, from its sidecar or `--preset`.
+    preset: Option<Preset>,
+    /// Attributes of the image to copy to its tiles.
+    attrs: &'a TileAttrs,
+    /// Time spent on the image, for `--profile`.
+    times: &'a FileTimes,
+}
+
+/// Criteria for tiles that should not be saved.
+struct TileFilters {
+    /// Tolerance for skipping single-color tiles, or `None` to keep them.
+    blank: Option<u8>,
+    /// Whether to skip tiles whose alpha channel is zero everywhere.
+    transparent: bool,
+    /// Percentage of content below which tiles are skipped as background, or `None` to keep them.
+    background: Option<u8>,
+    /// Percentage of a tile `--mask` must cover for it to be kept.
+    mask_coverage: u8,
+}
+
+impl TileFilters {
+    /// Checks whether a cell matches any of the enabled filters.
+    /// The cell is inspected in place, without copying it out of the image.
+    fn skips(&self, img: &DynamicImage, cell: &Cell) -> bool {
+        let view = img.view(cell.x, cell.y, cell.width, cell.height);
+
+        self.blank
+            .is_some_and(|tolerance| is_blank(&*view, tolerance))
+            || (self.transparent && img.color().has_alpha() && is_transparent(&*view))
+            || self
+                .background
+                .is_some_and(|percent| slide::is_background(&*view, percent))
+    }
+}
+
+/// Settings shared by every image saved in a run.
+struct SaveSettings {
+    /// Where split images will be saved.
+    output: Arc<Output>,
+    /// Background writer for tiles, if `--async-io` was given.
+    #[cfg(feature = "async")]
+    writes: Option<AsyncWrites>,
+    /// Template for each tile's path inside the output directory.
+    name_template: NameTemplate,
+    /// Criteria for tiles that should be skipped instead of saved.
+    filters: TileFilters,
+    /// Tracker for duplicate tiles, if deduplication is enabled.
+    dedupe: Option<Dedupe>,
+    /// Command to run for each saved tile.
+    exec: Option<Exec>,
+    /// Command to encode tiles with instead of the built-in encoders, if `--encoder-cmd` was given.
+    encoder: Option<ExternalEncoder>,
+    /// Tile to copy to the clipboard, if any.
+    clipboard: Option<ClipboardTile>,
+    /// Pages of the TIFFs to write, if `--multipage-tiff` was given.
+    multipage: Option<MultipageTiff>,
+    /// Tiles to save as NumPy arrays, if `--npy-out` was given.
+    npy: Option<NpyOut>,
+    /// Dataset to organize tiles in, if `--dataset-layout` was given.
+    dataset: Option<Dataset>,
+    /// Limit on bytes read and written per second, if any.
+    throttle: Option<Throttle>,
+    /// Where to report progress, if anywhere.
+    progress: Option<Progress>,
+    /// Image to composite onto each tile, if any.
+    watermark: Option<Watermark>,
+    /// Label to draw on each tile, if `--label-tiles` was given.
+    label: Option<TileLabel>,
+    /// Marks to draw around each page, if `--poster-marks` was given.
+    marks: Option<PageMarks>,
+    /// How to even out the exposure of each tile, if at all.
+    normalize: Option<Normalize>,
+    /// How tiles are resampled when scaling them.
+    resampler: Resampler,
+    /// Settings for encoding tiles.
+    encode: EncodeOptions,
+    /// Whether to record the SHA-256 of each tile in the manifest.
+    checksums: bool,
+    /// Whether to write where each tile came from into its metadata.
+    tile_metadata: bool,
+    /// Whether to record what each tile contains in the manifest.
+    tile_stats: bool,
+    /// Whether to choose the format of each tile by its contents, for `--ext auto`.
+    auto_format: bool,
+    /// Format to save tiles with transparency in when their format can't store it, if `--alpha-fallback` chose one.
+    alpha_fallback: Option<ImageFormat>,
+    /// Source regions of the tiles already in the output, if `--update` was given.
+    cache: Option<TileCache>,
+    /// Tiles written so far, if `--resume` was given.
+    journal: Option<Journal>,
+    /// Number of hex characters of its SHA-256 to name each tile after, if `--name-by-hash` was given.
+    name_by_hash: Option<usize>,
+    /// Names of the tiles named by their hash so far, with each one's whole hash, so each is only written once.
+    hash_names: Mutex<HashMap<PathBuf, String>>,
+    /// Files waiting for the write stage.
+    pending: Queue<WriteJob>,
+    /// Tiles the write stage failed to write, left out of the manifest.
+    unwritten: Mutex<HashSet<PathBuf>>,
+    /// Counts and timings for the end-of-run summary.
+    stats: RunStats,
+    /// Whether to keep going after errors.
+    policy: ErrorPolicy,
+}
+
+impl SaveSettings {
+    /// Queues a file for the write stage, or starts writing it in the background with `--async-io`.
+    /// Waits while the write stage is full.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Path of the file relative to the output.
+    /// * `bytes` - Contents of the file.
+    /// * `content_type` - MIME type of the contents.
+    /// * `attrs` - Attributes to give the file.
+    /// * `tile` - The tile the file holds, to report once it's written.
+    fn write(
+        &self,
+        name: PathBuf,
+        bytes: Vec<u8>,
+        content_type: &'static str,
+        attrs: &TileAttrs,
+        tile: Option<WrittenTile>,
+    ) -> io::Result<()> {
+        #[cfg(feature = "async")]
+        if let Some(writes) = &self.writes {
+            self.stats.wrote(bytes.len() as u64, tile.is_some());
+            writes.write(&self.output, name, bytes, content_type, attrs);
+            if let Some(tile) = tile {
+                self.written(&tile);
+            }
+            return Ok(());
+        }
+
+        let job = WriteJob {
+            name,
+            bytes,
+            content_type,
+            attrs: attrs.clone(),
+            tile,
+        };
+        self.pending.send(job).map_err(|job| {
+            io::Error::other(format!(
+                "splix: Failed to save {}: The writers have stopped",
+                job.name.display()
+            ))
+        })
+    }
+
+    /// Writes queued files until the write stage is closed. Runs on each thread of the write stage.
+    fn run_writes(&self) {
+        while let Some(job) = self.pending.recv() {
+            let result = if self.policy.stopped() {
+                Err(None)
+            } else {
+                self.stats
+                    .time(Stage::Write, None, || {
+                        self.output
+                            .write(&job.name, &job.bytes, job.content_type, &job.attrs)
+                    })
+                    .map_err(Some)
+            };
+            if result.is_ok() {
+                self.stats.wrote(job.bytes.len() as u64, job.tile.is_some());
+            }
+
+            match (result, job.tile) {
+                (Ok(()), Some(tile)) => self.written(&tile),
+                (Ok(()), None) => {}
+                (Err(err), tile) => {
+                    if let Some(err) = err {
+                        eprintln!("{}", err);
+                        self.policy.record(Some(err.kind()));
+                    }
+                    if let Some(tile) = tile {
+                        self.unwritten.lock().unwrap().insert(tile.file);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs `--exec` and reports progress for a tile that was written.
+    fn written(&self, tile: &WrittenTile) {
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.record(&tile.file) {
+                eprintln!(
+                    "splix: resume: Failed to write {}: {}",
+                    journal::JOURNAL_FILE,
+                    err
+                );
+                self.policy.record(Some(err.kind()));
+            }
+        }
+
+        if let Some(exec) = &self.exec {
+            exec.run(&tile.file);
+        }
+
+        if let Some(progress) = &self.progress {
+            progress.tile(&tile.source, &tile.file, tile.row, tile.col);
+        }
+    }
+}
+
+/// Validates the provided command-line arguments.
+///
+/// # Arguments
+///
+/// * `cli` - A reference to the command-line arguments parsed by Clap.
+///
+/// # Returns
+///
+/// * `Ok(())` if the arguments are valid, otherwise returns an error message.
+fn validate_args(cli: &Cli) -> Result<(), String> {
+    let row_sizes = grid::relative_sizes(cli.rows.as_deref().unwrap_or_default());
+    let col_sizes = grid::relative_sizes(cli.cols.as_deref().unwrap_or_default());
+    let rows = cli.rows.as_ref().map(|_| &row_sizes);
+    let cols = cli.cols.as_ref().map(|_| &col_sizes);
+
+    if let Some(img_dir) = &cli.images {
+        match img_dir.try_exists() {
+            Ok(true) => {}
+            Ok(false) | Err(_) => {
+                let err = format!(
+                    "splix: image: The provided path '{}' does not exist",
+                    img_dir.display()
+                );
+                return Err(match diagnostic::did_you_mean_path(img_dir) {
+                    Some(help) => diagnostic::with_help(err, help),
+                    None => err,
+                });
+            }
+        }
+    }
+
+    if cli.async_io && !cfg!(feature = "async") {
+        return Err(diagnostic::with_help(
+            "splix: async-io: Async I/O requires splix to be built with the `async` feature",
+            diagnostic::rebuild_with("async"),
+        ));
+    }
+
+    // Shards hold many tiles, so options that follow each tile's own file can't be used with them.
+    if cli.dataset_layout == Some(DatasetLayout::WebDataset) {
+        let per_file = [
+            ("--dedupe", cli.dedupe.is_some()),
+            ("--update", cli.update),
+            ("--resume", cli.resume),
+            ("--name-by-hash", cli.name_by_hash.is_some()),
+            ("--exec", cli.exec.is_some()),
+        ];
+        if let Some((flag, _)) = per_file.iter().find(|(_, used)| *used) {
+            return Err(format!(
+                "splix: dataset-layout: '{}' can't be used with webdataset, which saves tiles in shards",
+                flag
+            ));
+        }
+    }
+
+    // Duplicates are found by their source pixels, so a linked or copied one would carry what was added to the first tile.
+    if cli.dedupe.is_some_and(DedupeMode::writes_duplicates) {
+        let per_tile = [
+            ("--label-tiles", "label", cli.label_tiles.is_some()),
+            ("--poster-marks", "marks", cli.poster_marks),
+            ("--tile-metadata", "metadata", cli.tile_metadata),
+        ];
+        if let Some((flag, drawn, _)) = per_tile.iter().find(|(_, _, used)| *used) {
+            return Err(diagnostic::with_help(
+                format!(
+                    "splix: dedupe: Duplicates are found by their source pixels, so with '{}' a linked or copied duplicate would carry the {} of the tile it duplicates",
+                    flag, drawn
+                ),
+                format!("Use '--dedupe' to leave duplicates out instead, or leave out '{}'", flag),
+            ));
+        }
+    }
+
+    // Tiles of a copy are made from the first file's, whose metadata names that file instead.
+    if cli
+        .dedupe_sources
+        .is_some_and(DedupeMode::writes_duplicates)
+        && cli.tile_metadata
+    {
+        return Err(diagnostic::with_help(
+            "splix: dedupe-sources: With '--tile-metadata', the tiles of a linked or copied file would name the file it duplicates as their source",
+            "Use '--dedupe-sources=skip' to leave copies out instead, or leave out '--tile-metadata'",
+        ));
+    }
+
+    if cli.slide_level.is_some() && !cfg!(feature = "openslide") {
+        return Err(diagnostic::with_help(
+            "splix: Slides require splix to be built with the `openslide` feature",
+            diagnostic::rebuild_with("openslide"),
+        ));
+    }
+
+    if (cli.window.is_some() || cli.dicom_tags.is_some()) && !cfg!(feature = "dicom") {
+        return Err(diagnostic::with_help(
+            "splix: DICOM images require splix to be built with the `dicom` feature",
+            diagnostic::rebuild_with("dicom"),
+        ));
+    }
+
+    if (cli.from_clipboard || cli.to_clipboard.is_some()) && !cfg!(feature = "clipboard") {
+        return Err(diagnostic::with_help(
+            "splix: The clipboard requires splix to be built with the `clipboard` feature",
+            diagnostic::rebuild_with("clipboard"),
+        ));
+    }
+
+    if rows.is_none()
+        && cols.is_none()
+        && cli.poster.is_none()
+        && cli.monitors.is_none()
+        && cli.max_height.is_none()
+        && cli.tiles.is_none()
+        && cli.random_crops.is_none()
+        && cli.map_tiles.is_none()
+        && cli.grid.is_empty()
+        && cli.ruled_lines.is_none()
+        && !cli.detect_grid
+        && cli.channels.is_none()
+        && !(cli.regions.is_some() && cli.region_mode == RegionMode::Bbox)
+    {
+        return Err(diagnostic::with_help(
+            "splix: At least one of '--rows', '--cols', '--poster', '--monitors', '--preset', '--max-height', '--tiles', '--random-crops', '--map-tiles', '--grid', '--ruled-lines', '--detect-grid', '--channels', '--region-mode bbox' needs to be specified",
+            "To split each image into 2 rows of 3 tiles, add '--rows 2 --cols 3'",
+        ));
+    }
+
+    if cli.reproducible
+        && writes_in_order(cli)
+        && (cli.decode_jobs.is_some()
+            || cli.encode_jobs.is_some()
+            || cli.write_jobs.is_some()
+            || cli.async_io)
+    {
+        return Err(
+            "splix: reproducible: Archives and shards are written one tile at a time to keep their order, so '--decode-jobs', '--encode-jobs', '--write-jobs', and '--async-io' can't be given"
+                .to_string(),
+        );
+    }
+
+    if cli.auto_axis && rows.is_some() == cols.is_some() {
+        return Err(
+            "splix: auto-axis: Give the number of sections with either '--rows' or '--cols', not both"
+                .to_string(),
+        );
+    }
+
+    if cli.magick_compat
+        && (cli.rows.as_ref().is_some_and(|rows| rows.len() > 1)
+            || cli.cols.as_ref().is_some_and(|cols| cols.len() > 1))
+    {
+        return Err(
+            "splix: magick-compat: ImageMagick only cuts equal tiles, so '--rows' and '--cols' must be single numbers"
+                .to_string(),
+        );
+    }
+
+    validate_spec(rows, cols)?;
+
+    for (spec, name) in [(&cli.rows, "rows"), (&cli.cols, "cols")] {
+        let spec = spec.as_deref().unwrap_or_default();
+        if spec
+            .iter()
+            .any(|band| !matches!(band.length, BandLength::Relative(_)))
+        {
+            if cli.auto_axis {
+                return Err(format!(
+                    "splix: {}: Pixel sizes and `auto` can't be used with '--auto-axis'",
+                    name
+                ));
+            }
+            if cli.magick_compat {
+                return Err(format!(
+                    "splix: {}: ImageMagick only cuts equal tiles, so pixel sizes and `auto` can't be used with '--magick-compat'",
+                    name
+                ));
+            }
+        }
+
+        let skipped = spec.iter().filter(|band| band.skip).count();
+        if skipped == 0 {
+            continue;
+        }
+        if spec.len() < 2 {
+            return Err(format!(
+                "splix: {}: Only a band in a list of sizes can be skipped",
+                name
+            ));
+        }
+        if skipped == spec.len() {
+            return Err(format!("splix: {}: At least one band must be kept", name));
+        }
+        if cli.auto_axis {
+            return Err(format!(
+                "splix: {}: Bands can't be skipped with '--auto-axis'",
+                name
+            ));
+        }
+    }
+
+    if let Some(ext) = &cli.ext {
+        if cli.encoder_cmd.is_none() {
+            validate_ext(ext)?;
+        } else if ext == encode::AUTO_EXT {
+            return Err(
+                "splix: ext: The format of each tile can't be chosen with '--encoder-cmd'"
+                    .to_string(),
+            );
+        }
+        if cli.alpha_fallback != AlphaFallback::Flatten && cli.map_tiles.is_some() {
+            return Err(
+                "splix: alpha-fallback: Map tiles share one format, so only `flatten` can be used with '--map-tiles'"
+                    .to_string(),
+            );
+        }
+        if ext == encode::AUTO_EXT && cli.map_tiles.is_some() {
+            return Err(
+                "splix: ext: Map tiles share one format, so `auto` can't be used with '--map-tiles'"
+                    .to_string(),
+            );
+        }
+    }
+
+    if cli.io_limit == Some(0) {
+        return Err("splix: io-limit: The limit must be greater than zero".to_string());
+    }
+
+    if cli.upload_jobs == 0 {
+        return Err("splix: upload-jobs: The number of jobs must be greater than zero".to_string());
+    }
+
+    if cli.exec_jobs == Some(0) {
+        return Err("splix: exec-jobs: The number of jobs must be greater than zero".to_string());
+    }
+
+    for (jobs, arg) in [
+        (cli.decode_jobs, "decode-jobs"),
+        (cli.encode_jobs, "encode-jobs"),
+        (cli.write_jobs, "write-jobs"),
+    ] {
+        if jobs == Some(0) {
+            return Err(format!(
+                "splix: {}: The number of jobs must be greater than zero",
+                arg
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that every row and column size is a positive number.
+///
+/// # Arguments
+///
+/// * `rows` - Row sizes, if given.
+/// * `cols` - Column sizes, if given.
+///
+/// # Returns
+///
+/// * `Ok(())` if the sizes are valid, otherwise returns an error message.
+fn validate_spec(rows: Option<&Vec<f64>>, cols: Option<&Vec<f64>>) -> Result<(), String> {
+    if let Some(rows) = rows {
+        if rows
+            .iter()
+            .any(|&row_val| !(row_val > 0.0 && row_val.is_finite()))
+        {
+            return Err("splix: rows: All row sizes must be greater than zero".to_string());
+        }
+    }
+
+    if let Some(cols) = cols {
+        if cols
+            .iter()
+            .any(|&col_val| !(col_val > 0.0 && col_val.is_finite()))
+        {
+            return Err("splix: cols: All column sizes must be greater than zero".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether tiles are written into archives or shards, which hold them in the order they're written,
+/// so `--reproducible` must write them one at a time.
+fn writes_in_order(cli: &Cli) -> bool {
+    cli.dataset_layout == Some(DatasetLayout::WebDataset)
+        || cli.output_dir.as_deref().is_some_and(output::is_archive)
+}
+
+/// Uses the seed given with `--seed`, or picks one and reports it so the run can be repeated.
+/// Later random choices in the run reuse the same seed.
+///
+/// # Arguments
+///
+/// * `seed` - Seed given with `--seed`, which is set to the picked seed if there isn't one.
+/// * `purpose` - What the seed is used for, to report with a picked seed.
+fn pick_seed(seed: &mut Option<u64>, purpose: &str) -> u64 {
+    *seed.get_or_insert_with(|| {
+        let seed = Rng::random_seed();
+        eprintln!("splix: {} with seed {}", purpose, seed);
+        seed
+    })
+}
+
+/// Checks that an extension belongs to a format splix can save.
+///
+/// # Arguments
+///
+/// * `ext` - Extension given with `--ext`.
+///
+/// # Returns
+///
+/// * `Ok(())` if the extension is valid, otherwise returns an error message.
+fn validate_ext(ext: &str) -> Result<(), String> {
+    if ext == encode::AUTO_EXT {
+        return Ok(());
+    }
+
+    match ImageFormat::from_extension(ext.trim_start_matches('.')) {
+        Some(format) if format.writing_enabled() => Ok(()),
+        Some(ImageFormat::OpenExr | ImageFormat::Hdr) => Err(diagnostic::with_help(
+            format!(
+                "splix: ext: Saving '{}' images requires splix to be built with the `hdr` feature",
+                ext
+            ),
+            diagnostic::rebuild_with("hdr"),
+        )),
+        _ => {
+            let err = format!(
+                "splix: ext: '{}' isn't the extension of a format splix can save",
+                ext
+            );
+            let saved = ImageFormat::all()
+                .filter(|format| format.writing_enabled())
+                .flat_map(|format| format.extensions_str());
+            Err(
+                match diagnostic::did_you_mean(ext.trim_start_matches('.'), saved.copied()) {
+                    Some(help) => diagnostic::with_help(err, help),
+                    None => err,
+                },
+            )
+        }
+    }
+}
+
+/// Runs `splix diff`, printing how closely each tile matches its source region.
+///
+/// # Arguments
+///
+/// * `args` - Arguments of the command.
+///
+/// # Returns
+///
+/// Whether every tile matched, or an error message if the tiles couldn't be found.
+fn run_diff(args: &DiffArgs) -> Result<bool, String> {
+    let sources = match &args.manifest {
+        Some(manifest_path) => {
+            let manifest = Manifest::read(manifest_path).map_err(|err| {
+                format!(
+                    "splix: manifest: Failed to read manifest {}: {}",
+                    manifest_path.display(),
+                    err
+                )
+            })?;
+
+            manifest
+                .sources
+                .into_iter()
+                // Map tiles and levels after the first are cut from scaled copies of the image, not the image itself.
+                .filter(|source| {
+                    source.zoom.is_none()
+                        && source.level.unwrap_or(0) == 0
+                        && args
+                            .image
+                            .as_ref()
+                            .is_none_or(|image| *image == source.path)
+                })
+                .map(|source| {
+                    let tiles: Vec<(Cell, PathBuf)> = source
+                        .tiles
+                        .into_iter()
+                        .filter_map(|tile| {
+                            let file = tile.file.or(tile.duplicate_of)?;
+                            Some((
+                                Cell {
+                                    row: tile.row,
+                                    col: tile.col,
+                                    x: tile.x,
+                                    y: tile.y,
+                                    width: tile.width,
+                                    height: tile.height,
+                                },
+                                file,
+                            ))
+                        })
+                        .collect();
+                    (source.path, source.frame, source.channel, tiles)
+                })
+                .collect()
+        }
+        None => {
+            validate_spec(args.rows.as_ref(), args.cols.as_ref())?;
+
+            let image = args.image.clone().unwrap();
+            let tiles_dir = args
+                .tiles_dir
+                .clone()
+                .unwrap_or(PathBuf::from("splixed-images"));
+            let stem = image.file_stem().unwrap_or_default().to_string_lossy();
+            let found = diff::find_tiles(&tiles_dir, &stem).map_err(|err| {
+                format!(
+                    "splix: tiles-dir: Failed to read directory {}: {}",
+                    tiles_dir.display(),
+                    err
+                )
+            })?;
+
+            let img = open_image(&image, !args.no_auto_orient).map_err(|err| {
+                format!("splix: Failed to open image {}: {}", image.display(), err)
+            })?;
+            let last_row = found.iter().map(|(row, _, _)| row + 1).max().unwrap_or(1);
+            let last_col = found.iter().map(|(_, col, _)| col + 1).max().unwrap_or(1);
+            let rows =
+                grid::parse_spec(args.rows.as_deref().unwrap_or(&[last_row as f64]), "rows")?;
+            let cols =
+                grid::parse_spec(args.cols.as_deref().unwrap_or(&[last_col as f64]), "cols")?;
+            let cells = grid::grid_cells(img.width(), img.height(), &rows, &cols);
+
+            let tiles = found
+                .into_iter()
+                .map(|(row, col, file)| {
+                    let cell = cells
+                        .iter()
+                        .find(|cell| (cell.row, cell.col) == (row, col))
+                        .copied()
+                        .unwrap_or(Cell {
+                            row,
+                            col,
+                            x: 0,
+                            y: 0,
+                            width: 0,
+                            height: 0,
+                        });
+                    (cell, file)
+                })
+                .collect();
+            vec![(image, None, None, tiles)]
+        }
+    };
+
+    if sources.is_empty() {
+        return Err("splix: No tiles to compare were found".to_string());
+    }
+
+    let (mut compared, mut mismatched) = (0, 0);
+    for (path, frame, channel, tiles) in sources {
+        let mut img = open_frame(&path, frame, !args.no_auto_orient);
+        match (frame, &channel) {
+            (Some(frame), Some(channel)) => {
+                println!("{} (frame {}, {})", path.display(), frame, channel)
+            }
+            (Some(frame), None) => println!("{} (frame {})", path.display(), frame),
+            (None, Some(channel)) => println!("{} ({})", path.display(), channel),
+            (None, None) => println!("{}", path.display()),
+        }
+        if let Some(channel) = &channel {
+            img = img.and_then(|img| {
+                channels::plane(&img, channel).ok_or_else(|| {
+                    ImageError::IoError(io::Error::other(format!(
+                        "'{}' isn't a plane of `--channels`",
+                        channel
+                    )))
+                })
+            });
+        }
+
+        let img = match img {
+            Ok(img) => img,
+            Err(err) => {
+                println!("  Failed to open the image: {}", err);
+                mismatched += tiles.len();
+                compared += tiles.len();
+                continue;
+            }
+        };
+
+        let results: Vec<_> = tiles
+            .par_iter()
+            .map(|(cell, file)| diff::compare_tile(&img, cell, file))
+            .collect();
+
+        for ((cell, file), result) in tiles.iter().zip(results) {
+            compared += 1;
+            match result {
+                Ok(similarity) => {
+                    let psnr = if similarity.psnr.is_infinite() {
+                        "identical".to_string()
+                    } else {
+                        format!("PSNR {:.2} dB", similarity.psnr)
+                    };
+                    let flag = if similarity.psnr < args.min_psnr {
+                        mismatched += 1;
+                        "  MISMATCH"
+                    } else {
+                        ""
+                    };
+                    println!(
+                        "  r{}c{} {}: {}, SSIM {:.4}{}",
+                        cell.row,
+                        cell.col,
+                        file.display(),
+                        psnr,
+                        similarity.ssim,
+                        flag
+                    );
+                }
+                Err(err) => {
+                    mismatched += 1;
+                    println!(
+                        "  r{}c{} {}: {}  MISMATCH",
+                        cell.row,
+                        cell.col,
+                        file.display(),
+                        err
+                    );
+                }
+            }
+        }
+    }
+
+    println!("{} tiles compared, {} mismatched", compared, mismatched);
+    Ok(mismatched == 0)
+}
+
+/// Runs `splix preset save` and `splix preset list`.
+///
+/// # Arguments
+///
+/// * `command` - Subcommand to run.
+///
+/// # Returns
+///
+/// Whether the subcommand succeeded, or an error message if the presets couldn't be read or written.
+fn run_preset(command: &PresetCommand) -> Result<bool, String> {
+    match command {
+        PresetCommand::Save { name, args } => {
+            // The options are checked now, rather than when the preset is first used.
+            check_split_args("preset", "A preset", args)?;
+
+            let path = user_presets::save(name, args)?;
+            println!("Saved preset '{}' to {}", name, path.display());
+        }
+        PresetCommand::List => {
+            for (name, args) in user_presets::list()? {
+                println!("{}  {}", name, args.join(" "));
+            }
+        }
+        // Handled in `main`, since it splits images like splix without a command.
+        PresetCommand::Use { .. } => unreachable!(),
+    }
+
+    Ok(true)
+}
+
+/// Checks options saved for splitting later parse, without needing the images they'll be given.
+///
+/// # Arguments
+///
+/// * `context` - Name of the command, to start error messages with.
+/// * `subject` - What the options are saved as, to start the message about running commands with.
+/// * `args` - Options to check.
+///
+/// # Returns
+///
+/// An error message if the options don't parse.
+fn check_split_args(context: &str, subject: &str, args: &[String]) -> Result<(), String> {
+    let parsed = Cli::try_parse_from(iter::once("splix").chain(args.iter().map(String::as_str)));
+    match parsed {
+        Ok(cli) if cli.command.is_some() => Err(format!(
+            "splix: {}: {} can't run another command",
+            context, subject
+        )),
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == clap::error::ErrorKind::MissingRequiredArgument => Ok(()),
+        Err(err) => {
+            // Only the first line, without clap's usage summary of every option.
+            let message = err.render().to_string();
+            let message = message.lines().next().unwrap_or_default();
+            Err(format!(
+                "splix: {}: {}",
+                context,
+                message.trim_start_matches("error: ")
+            ))
+        }
+    }
+}
+
+/// Runs `splix doctor`, printing how splix was built and what it finds on this machine.
+///
+/// # Returns
+///
+/// Whether the self-test passed.
+fn run_doctor() -> Result<bool, String> {
+    println!(
+        "splix {} on {} {}",
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH
+    );
+
+    let list = |items: &[(&str, bool)], on: bool| {
+        let names: Vec<&str> = items
+            .iter()
+            .filter(|(_, enabled)| *enabled == on)
+            .map(|(name, _)| *name)
+            .collect();
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(", ")
+        }
+    };
+    println!("\nFeatures");
+    println!("  Built with:    {}", list(doctor::features(), true));
+    println!("  Built without: {}", list(doctor::features(), false));
+
+    println!("\nFormats");
+    for (exts, read, write) in doctor::formats() {
+        let support = match (read, write) {
+            (true, true) => "read and write",
+            (true, false) => "read only",
+            (false, true) => "write only",
+            (false, false) => "not built in",
+        };
+        println!("  {:<24} {}", exts.join(", "), support);
+    }
+    let extra = [
+        ("aseprite", Ok(())),
+        (
+            "dicom",
+            if cfg!(feature = "dicom") {
+                Ok(())
+            } else {
+                Err("splix was built without the `dicom` feature".to_string())
+            },
+        ),
+        ("slides", slide::check_library()),
+    ];
+    for (name, available) in extra {
+        match available {
+            Ok(()) => println!("  {:<24} read only", name),
+            Err(err) => println!("  {:<24} unavailable: {}", name, err),
+        }
+    }
+
+    println!("\nThreads");
+    println!(
+        "  CPUs available: {}",
+        thread::available_parallelism().map_or(1, NonZero::get)
+    );
+    println!("  Split threads:  {}", rayon::current_num_threads());
+    if let Ok(threads) = env::var("RAYON_NUM_THREADS") {
+        println!("  RAYON_NUM_THREADS: {}", threads);
+    }
+
+    let simd = doctor::simd();
+    println!("\nAcceleration");
+    println!("  SIMD:    {}", list(&simd, true));
+    if simd.iter().any(|(_, available)| !available) {
+        println!("  Missing: {}", list(&simd, false));
+    }
+    println!("  GPU:     not used, splix splits on the CPU");
+
+    println!("\nSelf-test");
+    let mut passed = true;
+    for (format, result, format_passed) in doctor::self_test() {
+        let psnr = if result.psnr.is_infinite() {
+            "every pixel matches".to_string()
+        } else {
+            format!("PSNR {:.2} dB", result.psnr)
+        };
+        println!(
+            "  {:<24} {} tiles, {}: {}",
+            format.extensions_str()[0],
+            result.tiles,
+            psnr,
+            if format_passed { "PASS" } else { "FAIL" }
+        );
+        for err in &result.errors {
+            println!("    {}", err);
+        }
+        passed &= format_passed;
+    }
+
+    Ok(passed)
+}
+
+/// Runs `splix integrate install` or `splix integrate uninstall`.
+///
+/// # Arguments
+///
+/// * `command` - Subcommand to run.
+///
+/// # Returns
+///
+/// Whether the entry was changed, or an error message if it couldn't be.
+fn run_integrate(command: &IntegrateCommand) -> Result<bool, String> {
+    match command {
+        IntegrateCommand::Install { args } => {
+            let args = if args.is_empty() {
+                integrate::DEFAULT_ARGS.map(str::to_string).to_vec()
+            } else {
+                args.clone()
+            };
+            check_split_args("integrate", "The entry", &args)?;
+
+            let exe = env::current_exe()
+                .and_then(fs::canonicalize)
+                .map_err(|err| {
+                    format!(
+                        "splix: integrate: Failed to find splix's executable: {}",
+                        err
+                    )
+                })?;
+            for place in integrate::install(&exe, &args)? {
+                println!("Registered \"Split with splix…\" in {}", place);
+            }
+        }
+        IntegrateCommand::Uninstall => {
+            for place in integrate::uninstall()? {
+                println!("Removed \"Split with splix…\" from {}", place);
+            }
+        }
+        // Handled in `main`, since it splits images like splix without a command.
+        IntegrateCommand::Split { .. } => unreachable!(),
+    }
+
+    Ok(true)
+}
+
+/// The single cell of an image kept whole.
+fn whole_image((width, height): (u32, u32)) -> Cell {
+    Cell {
+        row: 0,
+        col: 0,
+        x: 0,
+        y: 0,
+        width,
+        height,
+    }
+}
+
+/// Runs `splix replay`, cropping every tile in a manifest from its source again.
+///
+/// # Arguments
+///
+/// * `args` - Arguments of the command.
+///
+/// # Returns
+///
+/// Whether every tile was written, or an error message if the manifest couldn't be read.
+fn run_replay(args: &ReplayArgs) -> Result<bool, String> {
+    let manifest = Manifest::read(&args.manifest).map_err(|err| {
+        format!(
+            "splix: manifest: Failed to read manifest {}: {}",
+            args.manifest.display(),
+            err
+        )
+    })?;
+    let options = EncodeOptions {
+        quality: args.quality,
+        ..EncodeOptions::default()
+    };
+
+    let (mut written, mut failed) = (0, 0);
+    for source in manifest.sources.into_iter().filter(|source| {
+        args.image
+            .as_ref()
+            .is_none_or(|image| *image == source.path)
+    }) {
+        // Map tiles are cut from scaled and padded copies of the image, not the image itself.
+        if let Some(zoom) = source.zoom {
+            eprintln!(
+                "splix: replay: Skipping zoom level {} of {}, since map tiles can't be replayed",
+                zoom,
+                source.path.display()
+            );
+            continue;
+        }
+        if let Some(level) = source.level.filter(|&level| level > 0) {
+            eprintln!(
+                "splix: replay: Skipping level {} of {}, since scaled levels can't be replayed",
+                level,
+                source.path.display()
+            );
+            continue;
+        }
+
+        let tiles: Vec<TileEntry> = source
+            .tiles
+            .into_iter()
+            .filter(|tile| tile.file.is_some() && tile.duplicate_of.is_none())
+            .collect();
+        // Images in archives were split from a copy that was removed once their run ended.
+        if let Some(archive) = archive::archive_of(&source.path) {
+            eprintln!(
+                "splix: replay: Skipping {}, since images inside archives can't be replayed. Extract {} and split its images instead",
+                source.path.display(),
+                archive.display()
+            );
+            failed += tiles.len();
+            continue;
+        }
+        let mut img = match open_frame(&source.path, source.frame, !args.no_auto_orient) {
+            Ok(img) => img,
+            Err(err) => {
+                eprintln!(
+                    "splix: Failed to open image {}: {}",
+                    source.path.display(),
+                    err
+                );
+                failed += tiles.len();
+                continue;
+            }
+        };
+        if let Some(channel) = &source.channel {
+            match channels::plane(&img, channel) {
+                Some(plane) => img = plane,
+                None => {
+                    eprintln!(
+                        "splix: replay: Skipping plane '{}' of {}, which isn't a plane of `--channels`",
+                        channel,
+                        source.path.display()
+                    );
+                    failed += tiles.len();
+                    continue;
+                }
+            }
+        }
+        if img.dimensions() != (source.width, source.height) {
+            eprintln!(
+                "splix: replay: {} is now {}x{}, but was {}x{} when it was split",
+                source.path.display(),
+                img.width(),
+                img.height(),
+                source.width,
+                source.height
+            );
+        }
+
+        let results: Vec<Result<(), String>> = tiles
+            .par_iter()
+            .map(|tile| {
+                let file = tile.file.as_ref().unwrap();
+                if tile.x + tile.width > img.width() || tile.y + tile.height > img.height() {
+                    return Err(format!(
+                        "splix: replay: Tile r{}c{} of {} lies outside the image",
+                        tile.row,
+                        tile.col,
+                        source.path.display()
+                    ));
+                }
+
+                let format = ImageFormat::from_path(file)
+                    .map_err(|err| format!("splix: {}: {}", file.display(), err))?;
+                let bytes = encode::encode(
+                    &img.crop_imm(tile.x, tile.y, tile.width, tile.height),
+                    format,
+                    &options,
+                )
+                .map_err(|err| format!("splix: Failed to encode {}: {}", file.display(), err))?;
+                if let Some(dir) = file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    fs::create_dir_all(output::long_path(dir)).map_err(|err| {
+                        format!("splix: Failed to create {}: {}", dir.display(), err)
+                    })?;
+                }
+                fs::write(output::long_path(file), bytes)
+                    .map_err(|err| format!("splix: Failed to save {}: {}", file.display(), err))
+            })
+            .collect();
+
+        for result in results {
+            match result {
+                Ok(()) => written += 1,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!("{} tiles written, {} failed", written, failed);
+    Ok(failed == 0)
+}
+
+/// Runs `splix pack`, packing a directory of images into atlases.
+///
+/// # Arguments
+///
+/// * `args` - Arguments of the command.
+///
+/// # Returns
+///
+/// Whether every image was packed, or an error message if the atlases couldn't be written.
+fn run_pack(args: &PackArgs) -> Result<bool, String> {
+    if !args.max_size.is_power_of_two() {
+        return Err(format!(
+            "splix: max-size: {} isn't a power of two",
+            args.max_size
+        ));
+    }
+    if args.padding >= args.max_size {
+        return Err(format!(
+            "splix: padding: {} leaves no room for images in an atlas of '--max-size {}'",
+            args.padding, args.max_size
+        ));
+    }
+    let ext = args.ext.as_deref().unwrap_or("png").trim_start_matches('.');
+    validate_ext(ext)?;
+    if ext == encode::AUTO_EXT {
+        return Err("splix: ext: Atlases can't be saved with 'auto'".to_string());
+    }
+    let format = ImageFormat::from_extension(ext).unwrap();
+
+    let output_dir = args
+        .output_dir
+        .clone()
+        .unwrap_or(PathBuf::from("splixed-atlases"));
+    // Atlases from an earlier run in the same directory aren't packed again.
+    let skip = fs::canonicalize(&output_dir).ok();
+
+    let mut paths: Vec<PathBuf> = WalkDir::new(&args.dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !(walk::is_hidden(entry)
+                    || skip.as_ref().is_some_and(|skip| {
+                        fs::canonicalize(entry.path()).is_ok_and(|path| path == *skip)
+                    }))
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && (ImageFormat::from_path(entry.path()).is_ok()
+                    || aseprite::is_aseprite(entry.path())
+                    || dicom::is_dicom(entry.path())
+                    || slide::is_slide(entry.path()))
+        })
+        .map(DirEntry::into_path)
+        .collect();
+    paths.sort();
+
+    let mut failed = 0;
+    let mut images = Vec::new();
+    for path in paths {
+        match open_image(&path, true) {
+            Ok(img) => images.push((path, img)),
+            Err(err) => {
+                eprintln!("splix: Failed to open image {}: {}", path.display(), err);
+                failed += 1;
+            }
+        }
+    }
+    if images.is_empty() {
+        return Err(format!(
+            "splix: pack: {} has no images to pack",
+            args.dir.display()
+        ));
+    }
+
+    let sizes: Vec<(u32, u32)> = images.iter().map(|(_, img)| img.dimensions()).collect();
+    let atlases = pack::pack(&sizes, args.max_size, args.padding).map_err(|index| {
+        format!(
+            "splix: pack: {} is {}x{}, larger than '--max-size {}'",
+            images[index].0.display(),
+            sizes[index].0,
+            sizes[index].1,
+            args.max_size
+        )
+    })?;
+    // Atlases keep the precision of images with more than 8 bits per channel.
+    let deep = images.iter().any(|(_, img)| {
+        !matches!(
+            img.color(),
+            ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8
+        )
+    });
+
+    fs::create_dir_all(output::long_path(&output_dir)).map_err(|err| {
+        format!(
+            "splix: Failed to create output directory {}: {}",
+            output_dir.display(),
+            err
+        )
+    })?;
+
+    let mut map = pack::AtlasMap {
+        atlases: Vec::new(),
+    };
+    for (number, atlas) in atlases.iter().enumerate() {
+        let mut canvas = if deep {
+            DynamicImage::new_rgba16(atlas.width, atlas.height)
+        } else {
+            DynamicImage::new_rgba8(atlas.width, atlas.height)
+        };
+        let mut sprites = Vec::new();
+        for &(index, x, y) in &atlas.placements {
+            let (path, img) = &images[index];
+            imageops::replace(&mut canvas, img, x.into(), y.into());
+            let relative = path.strip_prefix(&args.dir).unwrap_or(path);
+            sprites.push(pack::SpriteEntry {
+                name: relative
+                    .with_extension("")
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                source: path.clone(),
+                x,
+                y,
+                width: img.width(),
+                height: img.height(),
+            });
+        }
+        sprites.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let file = PathBuf::from(format!("atlas-{}.{}", number, ext));
+        let path = output_dir.join(&file);
+        let bytes = encode::encode(&canvas, format, &EncodeOptions::default())
+            .map_err(|err| format!("splix: Failed to encode {}: {}", path.display(), err))?;
+        fs::write(output::long_path(&path), bytes)
+            .map_err(|err| format!("splix: Failed to save {}: {}", path.display(), err))?;
+
+        map.atlases.push(pack::AtlasEntry {
+            file,
+            width: atlas.width,
+            height: atlas.height,
+            sprites,
+        });
+    }
+
+    let map_path = output_dir.join(pack::ATLAS_MAP);
+    let json = serde_json::to_vec_pretty(&map)
+        .map_err(|err| format!("splix: Failed to write {}: {}", map_path.display(), err))?;
+    fs::write(output::long_path(&map_path), json)
+        .map_err(|err| format!("splix: Failed to save {}: {}", map_path.display(), err))?;
+
+    println!(
+        "{} images packed into {} atlas{}, {} failed",
+        images.len(),
+        atlases.len(),
+        if atlases.len() == 1 { "" } else { "es" },
+        failed
+    );
+    Ok(failed == 0)
+}
+
+/// Decodes an image, or one frame of an animated image.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+/// * `frame` - Frame to decode, or `None` for the image as a still.
+/// * `auto_orient` - Whether to apply the image's orientation to stills.
+///
+/// # Returns
+///
+/// The decoded image, or the error that kept it from being decoded.
+fn open_frame(path: &Path, frame: Option<usize>, auto_orient: bool) -> ImageResult<DynamicImage> {
+    let Some(frame) = frame else {
+        return open_image(path, auto_orient);
+    };
+
+    let mut frames = frames::decode(path, FrameSelection::Index(frame), |path| {
+        open_image(path, auto_orient)
+    });
+    // Selecting a single frame always gives exactly one result.
+    frames.next().unwrap().map(|(_, img)| img)
+}
+
+/// Runs `splix selftest`, printing a pass or fail report.
+///
+/// # Arguments
+///
+/// * `args` - Arguments of the command.
+///
+/// # Returns
+///
+/// Whether the tiles matched the image, or an error message if the check couldn't run.
+fn run_selftest(args: &SelftestArgs) -> Result<bool, String> {
+    validate_spec(args.rows.as_ref(), args.cols.as_ref())?;
+    if let Some(ext) = &args.ext {
+        validate_ext(ext)?;
+    }
+
+    let rows = grid::parse_spec(args.rows.as_deref().unwrap_or(&[1.0]), "rows")?;
+    let cols = grid::parse_spec(args.cols.as_deref().unwrap_or(&[1.0]), "cols")?;
+
+    let img = ImageReader::open(&args.image)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(ImageError::IoError)
+        .and_then(|reader| {
+            let format = reader.format();
+            reader.decode().map(|img| (img, format))
+        });
+    let (img, format) = match img {
+        Ok((img, Some(format))) => (img, format),
+        Ok((_, None)) => {
+            return Err(format!(
+                "splix: {} isn't an image in a supported format",
+                args.image.display()
+            ))
+        }
+        Err(err) => {
+            return Err(format!(
+                "splix: Failed to open image {}: {}",
+                args.image.display(),
+                err
+            ))
+        }
+    };
+
+    let format = match &args.ext {
+        Some(ext) => ImageFormat::from_extension(ext.trim_start_matches('.')).unwrap(),
+        None if format.writing_enabled() => format,
+        None => ImageFormat::Png,
+    };
+    let cells = grid::grid_cells(img.width(), img.height(), &rows, &cols);
+    let result = selftest::round_trip(
+        &img,
+        &cells,
+        format,
+        &EncodeOptions {
+            quality: args.quality,
+            adaptive: None,
+            progressive: false,
+            interlace: false,
+            quantize: None,
+            dither: Dither::None,
+            tiff_compression: TiffCompression::None,
+            background: None,
+        },
+    );
+
+    println!(
+        "{}: {}x{} {:?} image split into {} {} tiles",
+        args.image.display(),
+        img.width(),
+        img.height(),
+        img.color(),
+        result.tiles,
+        format.extensions_str()[0]
+    );
+    for err in &result.errors {
+        println!("  {}", err);
+    }
+
+    let psnr = if result.psnr.is_infinite() {
+        "every pixel matches".to_string()
+    } else {
+        format!("PSNR {:.2} dB", result.psnr)
+    };
+    let passed = result.passed(format, args.min_psnr);
+    if selftest::is_lossy(format) {
+        println!(
+            "  Rejoined image: {} (minimum {:.2} dB)",
+            psnr, args.min_psnr
+        );
+    } else {
+        println!("  Rejoined image: {} (lossless, must match exactly)", psnr);
+    }
+    println!("{}", if passed { "PASS" } else { "FAIL" });
+
+    Ok(passed)
+}
+
+/// Checks whether every pixel of an image is within a tolerance of its first pixel.
+///
+/// # Arguments
+///
+/// * `img` - Image to check.
+/// * `tolerance` - Maximum difference allowed between any channel of a pixel and the first pixel.
+///
+/// # Returns
+///
+/// `true` if the image is (nearly) a single color.
+fn is_blank(img: &impl GenericImageView<Pixel = Rgba<u8>>, tolerance: u8) -> bool {
+    let mut pixels = img.pixels();
+    let Some((_, _, first)) = pixels.next() else {
+        return true;
+    };
+
+    pixels.all(|(_, _, pixel)| {
+        pixel
+            .0
+            .iter()
+            .zip(first.0.iter())
+            .all(|(&a, &b)| a.abs_diff(b) <= tolerance)
+    })
+}
+
+/// Checks whether every pixel of an image is fully transparent.
+///
+/// # Arguments
+///
+/// * `img` - Image to check.
+///
+/// # Returns
+///
+/// `true` if every pixel's alpha is zero.
+fn is_transparent(img: &impl GenericImageView<Pixel = Rgba<u8>>) -> bool {
+    img.pixels().all(|(_, _, pixel)| pixel[3] == 0)
+}
+
+/// Chooses the format and extension of an image's tiles.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+/// * `ext` - Extension requested with `--ext`, if any.
+/// * `preserve_case` - Whether to keep the image's own extension exactly as written.
+///
+/// # Returns
+///
+/// The format to encode the tiles with and the extension to name them with.
+fn output_format(path: &Path, ext: Option<&str>, preserve_case: bool) -> (ImageFormat, String) {
+    // Each tile's format is chosen as it's saved, so PNG stands in for it until then, such as when planning names.
+    if ext == Some(encode::AUTO_EXT) {
+        return (ImageFormat::Png, "png".to_string());
+    }
+    if let Some(ext) = ext {
+        let ext = ext.trim_start_matches('.');
+        // Extensions only `--encoder-cmd` writes have no format of their own, so PNG, which the command is given, stands in.
+        let format = ImageFormat::from_extension(ext).unwrap_or(ImageFormat::Png);
+        return (format, ext.to_string());
+    }
+
+    // Formats splix can read but not write, such as Aseprite sprites, are split into PNGs.
+    let Ok(format) = ImageFormat::from_path(path) else {
+        return (ImageFormat::Png, "png".to_string());
+    };
+    if preserve_case {
+        (
+            format,
+            path.extension().unwrap().to_string_lossy().into_owned(),
+        )
+    } else {
+        (format, format.extensions_str()[0].to_string())
+    }
+}
+
+/// Decodes an image, optionally rotating and flipping it as its EXIF Orientation tag says it should be displayed.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+/// * `auto_orient` - Whether to apply the image's orientation.
+///
+/// # Returns
+///
+/// The decoded image, or the error that kept it from being decoded.
+fn open_image(path: &Path, auto_orient: bool) -> ImageResult<DynamicImage> {
+    // Sprites have no orientation, and show their first frame unless `--frame` picks another.
+    if aseprite::is_aseprite(path) {
+        return aseprite::decode(path)?
+            .into_iter()
+            .next()
+            .map(DynamicImage::ImageRgba8)
+            .ok_or_else(|| {
+                ImageError::IoError(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "The sprite has no frames",
+                ))
+            });
+    }
+    if slide::is_slide(path) {
+        return slide::decode(path);
+    }
+    if dicom::is_dicom(path) {
+        return dicom::decode(path)?.into_iter().next().ok_or_else(|| {
+            ImageError::IoError(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The image has no frames",
+            ))
+        });
+    }
+
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+
+    if auto_orient {
+        img.apply_orientation(orientation);
+    }
+
+    Ok(img)
+}
+
+/// Estimates how many bytes the tiles of a run will take,
+/// by splitting the first image in memory and scaling its ratio of tile bytes to file bytes up to every image.
+/// Each tile is rounded up to whole blocks, since small tiles take up a block each.
+///
+/// # Arguments
+///
+/// * `paths` - Paths of the images to split.
+/// * `formats` - Format each image's tiles are saved in.
+/// * `layout` - How images are divided into tiles.
+/// * `sample` - The first image, decoded.
+/// * `options` - Settings for encoding tiles.
+/// * `block_size` - Size of the blocks files are stored in.
+///
+/// # Returns
+///
+/// The estimated size in bytes, or `None` if the first image's file is empty or can't be read.
+fn estimate_output_size(
+    paths: &[PathBuf],
+    formats: &[(ImageFormat, String)],
+    layout: &Layout,
+    sample: &DynamicImage,
+    options: &EncodeOptions,
+    block_size: u64,
+) -> Option<u64> {
+    let file_size = |path: &PathBuf| fs::metadata(path).map_or(0, |metadata| metadata.len());
+    let sample_size = file_size(paths.first()?);
+    if sample_size == 0 {
+        return None;
+    }
+
+    let resized;
+    let img = match layout.canvas() {
+        Some((width, height)) => {
+            resized = sample.resize_to_fill(width, height, imageops::FilterType::Nearest);
+            &resized
+        }
+        None => sample,
+    };
+    let cells = layout.cells(img.width(), img.height());
+    let tile_size: u64 = cells
+        .par_iter()
+        .map(|cell| {
+            encode::encode(
+                &img.crop_imm(cell.x, cell.y, cell.width, cell.height),
+                formats[0].0,
+                options,
+            )
+            .map_or(0, |bytes| {
+                (bytes.len() as u64).next_multiple_of(block_size.max(1))
+            })
+        })
+        .sum();
+
+    let min_size = cells.len() as u64 * block_size;
+    Some(
+        paths
+            .iter()
+            .map(|path| {
+                ((file_size(path) as f64 * tile_size as f64 / sample_size as f64) as u64)
+                    .max(min_size)
+            })
+            .sum(),
+    )
+}
+
+/// Reads an image's header to find how much memory it takes once decoded.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+///
+/// # Returns
+///
+/// The size of the decoded image in bytes, or `None` if the header can't be read.
+fn decoded_size(path: &Path) -> Option<u64> {
+    ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()
+        .map(|decoder| decoder.total_bytes())
+}
+
+/// Crops the cells out of an image and saves them to the specified output.
+/// Cells are cropped, encoded, and saved in parallel, sharing the source image.
+/// Each cell is only copied out of the image once it's known that it will be saved.
+///
+/// # Arguments
+///
+/// * `img` - Image to split.
+/// * `cells` - Regions of the image to save as tiles.
+/// * `settings` - Settings shared by every image in the run.
+/// * `source` - Details of the image used to name its tiles.
+///
+/// # Returns
+///
+/// A manifest entry for each cell.
+fn save_images(
+    img: &DynamicImage,
+    cells: &[Cell],
+    settings: &SaveSettings,
+    source: &SourceInfo,
+) -> Vec<TileEntry> {
+    let output = &settings.output;
+    let pages = cells
+        .last()
+        .map_or((0, 0), |cell| (cell.row + 1, cell.col + 1));
+
+    cells
+        .par_iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            let mut entry = TileEntry {
+                row: cell.row,
+                col: cell.col,
+                x: cell.x,
+                y: cell.y,
+                width: cell.width,
+                height: cell.height,
+                file: None,
+                duplicate_of: None,
+                sha256: None,
+                format: None,
+                coverage: None,
+                stats: None,
+            };
+
+            if settings.policy.stopped() {
+                return entry;
+            }
+
+            if let Some(clipboard) = settings.clipboard.as_ref().filter(|_| source.index == 0) {
+                clipboard.offer(img, cell);
+            }
+
+            if settings.tile_stats {
+                entry.stats = Some(content::measure(&*img.view(
+                    cell.x,
+                    cell.y,
+                    cell.width,
+                    cell.height,
+                )));
+            }
+            if settings.filters.skips(img, cell) {
+                return entry;
+            }
+            if let Some(mask) = source.mask {
+                let coverage = mask::coverage(mask, cell, img.dimensions());
+                entry.coverage = Some(coverage);
+                if coverage < f32::from(settings.filters.mask_coverage) {
+                    return entry;
+                }
+            }
+
+            let (format, ext) = if settings.auto_format {
+                let format =
+                    encode::auto_format(&*img.view(cell.x, cell.y, cell.width, cell.height));
+                entry.format = Some(format.extensions_str()[0].to_string());
+                (format, format.extensions_str()[0])
+            } else {
+                match settings.alpha_fallback {
+                    Some(fallback)
+                        if settings.encoder.is_none()
+                            && encode::drops_alpha(source.format)
+                            && img.color().has_alpha()
+                            && encode::has_transparency(&*img.view(
+                                cell.x,
+                                cell.y,
+                                cell.width,
+                                cell.height,
+                            )) =>
+                    {
+                        entry.format = Some(fallback.extensions_str()[0].to_string());
+                        (fallback, fallback.extensions_str()[0])
+                    }
+                    _ => (source.format, source.ext),
+                }
+            };
+            let tile_name = TileName {
+                stem: source.stem,
+                ext,
+                row: entry.row,
+                col: entry.col,
+                index: i,
+                source_index: source.index,
+                frame: source.frame,
+                zoom: source.zoom,
+                grid: source.grid,
+                level: source.level,
+                channel: source.channel,
+            };
+            let mut name = settings.name_template.render(&tile_name);
+            if let Some(dataset) = &settings.dataset {
+                name = dataset.place(source.index, &name);
+            }
+            let mut file_path = output.location(&name);
+
+            if settings
+                .journal
+                .as_ref()
+                .is_some_and(|journal| journal.done(&file_path))
+            {
+                entry.file = Some(file_path);
+                return entry;
+            }
+
+            if let Some(cache) = &settings.cache {
+                if let Some(sha256) = cache.unchanged(&name, cache::region_hash(img, cell, ext)) {
+                    entry.file = Some(file_path);
+                    entry.sha256 = sha256;
+                    return entry;
+                }
+            }
+
+            if let Some(dedupe) = &settings.dedupe {
+                entry.duplicate_of = dedupe.check(img, cell, &file_path);
+                if entry.duplicate_of.is_some() {
+                    if dedupe.links() {
+                        entry.file = Some(file_path);
+                    }
+                    return entry;
+                }
+            }
+
+            let encoded = settings.stats.time(Stage::Encode, Some(source.times), || {
+                let mut image = img.crop_imm(cell.x, cell.y, cell.width, cell.height);
+                if let Some(mode) = settings.normalize {
+                    image = normalize::normalize(&image, mode);
+                }
+                if let Some((x, y)) = source.preset.and_then(|preset| preset.stretch) {
+                    image = settings.resampler.resize_exact(
+                        &image,
+                        image.width() * x,
+                        image.height() * y,
+                    );
+                }
+                if let Some(preset) = source.preset {
+                    if let Some(size) = preset.tile_size {
+                        image = resize::fit_tile(&image, size, preset.fit, settings.resampler);
+                    }
+                }
+                if let Some(watermark) = &settings.watermark {
+                    watermark.apply(&mut image, settings.resampler);
+                }
+                if let Some(label) = &settings.label {
+                    label.draw(&mut image, &tile_name);
+                }
+                if let Some(marks) = &settings.marks {
+                    image = marks.decorate(&image, cell, pages);
+                }
+                if settings.multipage.is_some() || settings.npy.is_some() {
+                    let page = PageSource {
+                        stem: source.stem,
+                        index: source.index,
+                        frame: source.frame,
+                        grid: source.grid,
+                        level: source.level,
+                        channel: source.channel,
+                        tile: i,
+                        name: &name,
+                    };
+                    if let Some(multipage) = &settings.multipage {
+                        multipage.add(&page, cell, &image)?;
+                    }
+                    if let Some(npy) = &settings.npy {
+                        npy.add(&page, cell, &image);
+                    }
+                    // Tiles are only saved as pages or arrays without an output directory.
+                    if matches!(**output, Output::Discard) {
+                        return Ok(None);
+                    }
+                }
+                match &settings.encoder {
+                    Some(encoder) => encoder.encode(&image).map_err(ImageError::IoError),
+                    None => encode::encode(&image, format, &settings.encode),
+                }
+                .map(Some)
+            });
+            let mut bytes = match encoded {
+                Ok(Some(bytes)) => bytes,
+                Ok(None) => {
+                    settings.stats.wrote(0, true);
+                    return entry;
+                }
+                Err(err) => {
+                    eprintln!(
+                        "splix: Failed to encode image {}: {}",
+                        file_path.file_stem().unwrap().to_string_lossy(),
+                        err
+                    );
+                    settings.policy.record(None);
+                    return entry;
+                }
+            };
+
+            if settings.tile_metadata {
+                let origin = TileOrigin {
+                    source: &source
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                    source_size: img.dimensions(),
+                    cell,
+                    frame: source.frame,
+                    zoom: source.zoom,
+                    grid: source.grid,
+                    level: source.level,
+                    channel: source.channel,
+                };
+                bytes = embed::embed(bytes, &origin);
+            }
+
+            if let Some(throttle) = &settings.throttle {
+                throttle.take(bytes.len() as u64);
+            }
+            let sha256 = settings.checksums.then(|| manifest::sha256(&bytes));
+            if let (Some(cache), Some(sha256)) = (&settings.cache, &sha256) {
+                cache.set_sha256(&name, sha256);
+            }
+            if let (Some(len), Some(sha256)) = (settings.name_by_hash, &sha256) {
+                name.set_file_name(format!("{}.{}", &sha256[..len], ext));
+                file_path = output.location(&name);
+                let earlier = {
+                    let mut hash_names = settings.hash_names.lock().unwrap();
+                    let earlier = hash_names.get(&name).cloned();
+                    if earlier.is_none() {
+                        hash_names.insert(name.clone(), sha256.clone());
+                    }
+                    earlier
+                };
+                match earlier {
+                    Some(earlier) if earlier == *sha256 => {
+                        entry.file = Some(file_path);
+                        entry.sha256 = Some(sha256.clone());
+                        return entry;
+                    }
+                    // A different tile whose hash starts the same would be overwritten.
+                    Some(_) => {
+                        eprintln!(
+                            "{}",
+                            diagnostic::with_help(
+                                format!(
+                                    "splix: name-by-hash: Tile r{}c{} of {} differs from the tile already saved as {}, though their hashes start with the same {} characters",
+                                    entry.row,
+                                    entry.col,
+                                    source.path.display(),
+                                    name.display(),
+                                    len
+                                ),
+                                "Name tiles after more of their hash, such as with '--name-by-hash=64'",
+                            )
+                        );
+                        settings.policy.record(None);
+                        return entry;
+                    }
+                    None => {}
+                }
+            }
+
+            if let Some(dataset) = settings
+                .dataset
+                .as_ref()
+                .filter(|dataset| dataset.sharded())
+            {
+                let (shard, full) = match dataset.add_sample(source.index, &name, cell, &bytes) {
+                    Ok(added) => added,
+                    Err(err) => {
+                        eprintln!(
+                            "splix: Failed to add {} to a shard: {}",
+                            name.display(),
+                            err
+                        );
+                        settings.policy.record(Some(err.kind()));
+                        return entry;
+                    }
+                };
+                settings.stats.wrote(0, true);
+                if let Some(full) = full {
+                    let written = settings.write(
+                        shard.clone(),
+                        full,
+                        "application/x-tar",
+                        &TileAttrs::default(),
+                        None,
+                    );
+                    if let Err(err) = written {
+                        eprintln!("{}", err);
+                        settings.policy.record(Some(err.kind()));
+                    }
+                }
+                entry.file = Some(output.location(&shard));
+                entry.sha256 = sha256;
+                return entry;
+            }
+
+            let tile = WrittenTile {
+                source: source.path.to_path_buf(),
+                file: file_path.clone(),
+                row: entry.row,
+                col: entry.col,
+            };
+            let content_type = settings
+                .encoder
+                .as_ref()
+                .map_or(format.to_mime_type(), ExternalEncoder::content_type);
+            if let Err(err) = settings.write(name, bytes, content_type, source.attrs, Some(tile)) {
+                eprintln!("{}", err);
+                settings.policy.record(Some(err.kind()));
+                return entry;
+            }
+
+            entry.file = Some(file_path);
+            entry.sha256 = sha256;
+            entry
+        })
+        .collect()
+}
+
+fn main() -> ExitCode {
+    // Kept to give each job of `--jobfile` the same options.
+    let mut cli_args: Vec<OsString> = env::args_os().collect();
+    let mut cli = Cli::parse_from(&cli_args);
+
+    if let Some(Command::Preset(PresetCommand::Use { name, args })) = &cli.command {
+        let saved = match user_presets::load(name) {
+            Ok(saved) => saved,
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        let program = env::args().next().unwrap_or("splix".to_string());
+        cli_args = iter::once(program)
+            .chain(saved)
+            .chain(args.iter().cloned())
+            .map(OsString::from)
+            .collect();
+        cli = Cli::parse_from(&cli_args);
+    }
+
+    if let Some(Command::Integrate(IntegrateCommand::Split { args })) = &cli.command {
+        let program = env::args().next().unwrap_or("splix".to_string());
+        cli_args = iter::once(program)
+            .chain(args.iter().cloned())
+            .map(OsString::from)
+            .collect();
+        cli = Cli::parse_from(&cli_args);
+        if let (None, Some(image)) = (&cli.output_dir, &cli.images) {
+            cli.output_dir = Some(integrate::output_dir(image));
+        }
+    }
+    // Chosen once the arguments are final, since a preset or integration may give `--lang`.
+    i18n::init(cli.lang.as_deref());
+
+    if let Some(command) = &cli.command {
+        let result = match command {
+            Command::Selftest(args) => run_selftest(args),
+            Command::Doctor => run_doctor(),
+            Command::Diff(args) => run_diff(args),
+            Command::Replay(args) => run_replay(args),
+            Command::Pack(args) => run_pack(args),
+            Command::Preset(command) => run_preset(command),
+            Command::Integrate(command) => run_integrate(command),
+        };
+        return match result {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(err) => {
+                eprintln!("{}", err);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if let Some(jobfile) = &cli.jobfile {
+        return run_jobs(jobfile, &cli_args);
+    }
+    run_split(cli)
+}
+
+/// The paths a group of jobs writes to, and its jobs with the lines they're listed on, which run one at a time.
+type JobGroup = (Vec<OsString>, Vec<(usize, Cli)>);
+
+/// Runs `--jobfile`, splitting each job's input as if splix were run with the job's options
+/// added to the rest of the command line.
+///
+/// # Arguments
+///
+/// * `path` - Path of the jobfile.
+/// * `cli_args` - Arguments splix was run with.
+///
+/// # Returns
+///
+/// Success if every job succeeded, failure if any job failed, or the interrupted exit code if interrupted by Ctrl-C.
+fn run_jobs(path: &Path, cli_args: &[OsString]) -> ExitCode {
+    let jobs = match jobfile::read(path) {
+        Ok(jobs) => jobs,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Every job is checked before any is run, and jobs writing to any of the same paths are run one at a time.
+    let mut groups: Vec<JobGroup> = Vec::new();
+    let count = jobs.len();
+    for job in jobs {
+        // Parsed again for each job, since the options can't be copied.
+        let mut cli = Cli::parse_from(cli_args);
+        cli.jobfile = None;
+        cli.images = Some(job.input);
+        cli.rows = job.rows.or(cli.rows);
+        cli.cols = job.cols.or(cli.cols);
+        cli.output_dir = job.output.or(cli.output_dir);
+        cli.ext = job.format.or(cli.ext);
+        if let Err(err) = validate_args(&cli) {
+            eprintln!(
+                "splix: jobfile: {}:{}: {}",
+                path.display(),
+                job.line,
+                err.trim_start_matches("splix: ")
+            );
+            return ExitCode::FAILURE;
+        }
+        // Jobs share one pool of encoding threads, so none can be kept to the single thread this needs.
+        if cli.reproducible && writes_in_order(&cli) {
+            eprintln!(
+                "splix: jobfile: {}:{}: '--reproducible' can't write archives or shards from a jobfile",
+                path.display(),
+                job.line
+            );
+            return ExitCode::FAILURE;
+        }
+        // A job sharing paths with several groups joins them into one, keeping the jobs in the order listed.
+        let mut outputs: Vec<OsString> = [
+            cli.output_dir.clone().map(PathBuf::into_os_string),
+            cli.multipage_tiff.clone().map(OsString::from),
+            cli.npy_out.clone().map(OsString::from),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let mut group = Vec::new();
+        let mut index = 0;
+        while index < groups.len() {
+            if groups[index]
+                .0
+                .iter()
+                .any(|output| outputs.contains(output))
+            {
+                let (shared, jobs) = groups.remove(index);
+                outputs.extend(shared);
+                group.extend(jobs);
+            } else {
+                index += 1;
+            }
+        }
+        group.push((job.line, cli));
+        group.sort_by_key(|(line, _)| *line);
+        groups.push((outputs, group));
+    }
+
+    let failed = groups
+        .into_par_iter()
+        .map(|(_, group)| {
+            group
+                .into_iter()
+                .map(|(_, cli)| run_split(cli))
+                .filter(|code| *code != ExitCode::SUCCESS)
+                .count()
+        })
+        .sum::<usize>();
+    if interrupt::interrupted() {
+        ExitCode::from(interrupt::EXIT_CODE)
+    } else if failed > 0 {
+        eprintln!(
+            "{}",
+            i18n::message("jobs-failed", &[("failed", &failed), ("count", &count)])
+        );
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Splits images, as splix does without a command.
+///
+/// # Arguments
+///
+/// * `cli` - Options to split the images with.
+///
+/// # Returns
+///
+/// Success if every image was split, failure if any image or tile failed, or the interrupted exit code if interrupted by Ctrl-C.
+fn run_split(mut cli: Cli) -> ExitCode {
+    if let Some(preset) = cli.preset {
+        if let Some((rows, cols)) = preset.grid {
+            if cli.rows.is_some() || cli.cols.is_some() {
+                eprintln!("splix: preset: This preset chooses its own rows and columns, so it can't be combined with '--rows' or '--cols'");
+                return ExitCode::FAILURE;
+            }
+            let band = |size: u32| BandSize {
+                length: BandLength::Relative(size as f64),
+                skip: false,
+            };
+            cli.rows = Some(vec![band(rows)]);
+            cli.cols = Some(vec![band(cols)]);
+        }
+        if let Some(ext) = preset.ext {
+            cli.ext.get_or_insert_with(|| ext.to_string());
+        }
+        cli.quality = cli.quality.or(preset.quality);
+        cli.name = cli.name.take().or(preset.name.map(str::to_string));
+    }
+
+    if cli.map_tiles.is_some() {
+        cli.ext.get_or_insert_with(|| "png".to_string());
+    }
+
+    if let Err(err) = validate_args(&cli) {
+        eprintln!("{}", err);
+        return ExitCode::FAILURE;
+    }
+    let started = Instant::now();
+    if cli.reproducible {
+        cli.seed.get_or_insert(0);
+        output::fix_archive_time();
+        if writes_in_order(&cli) {
+            cli.encode_jobs = Some(1);
+            cli.decode_jobs = Some(1);
+            cli.write_jobs = Some(1);
+        }
+    }
+    if let Some(window) = cli.window {
+        dicom::set_window(window);
+    }
+    if let Some(level) = cli.slide_level {
+        slide::set_level(level);
+    }
+
+    if cli.nice {
+        if let Err(err) = priority::lower_priority() {
+            eprintln!("{}", err);
+        }
+    }
+
+    if let Some(jobs) = cli.encode_jobs {
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+        {
+            eprintln!(
+                "splix: encode-jobs: Failed to start the encoding threads: {}",
+                err
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+    let cpus = thread::available_parallelism().map_or(1, NonZero::get);
+    let decode_jobs = cli.decode_jobs.unwrap_or(cpus);
+    let write_jobs = cli.write_jobs.unwrap_or(cpus);
+
+    let exec = match &cli.exec {
+        Some(command) => match Exec::new(
+            command,
+            cli.exec_jobs.unwrap_or_else(rayon::current_num_threads),
+        ) {
+            Ok(exec) => Some(exec),
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    let fallback = match DecoderChain::new(&cli.decode_fallback) {
+        Ok(chain) => chain,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let encoder = match (&cli.encoder_cmd, &cli.ext) {
+        (Some(command), Some(ext)) => match ExternalEncoder::new(command, ext) {
+            Ok(encoder) => Some(encoder),
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => None,
+    };
+
+    let watermark = match cli.watermark.as_deref().map(Watermark::open).transpose() {
+        Ok(watermark) => watermark,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let label = match cli.label_tiles.as_deref().map(TileLabel::new).transpose() {
+        Ok(label) => label,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let progress = match cli.progress_fd.map(Progress::open).transpose() {
+        Ok(progress) => progress,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut template = cli.name.clone().unwrap_or(match (cli.frame, cli.sequence) {
+        (Some(FrameSelection::All), _) => naming::FRAMES_TEMPLATE.to_string(),
+        _ if cli.magick_compat => naming::MAGICK_TEMPLATE.to_string(),
+        (_, Some(sequence)) => sequence.template().to_string(),
+        _ if cli.map_tiles.is_some() => maptiles::MAP_TEMPLATE.to_string(),
+        _ => naming::DEFAULT_TEMPLATE.to_string(),
+    });
+    if cli.per_image_dir {
+        template = format!("{{stem}}/{}", template);
+    }
+    if cli.levels.is_some() && !template.contains("{level}") {
+        template = format!("{{level}}/{}", template);
+    }
+    if !cli.grid.is_empty() && !template.contains("{grid}") {
+        template = format!("{{grid}}/{}", template);
+    }
+    if cli.channels.is_some() && !template.contains("{channel}") {
+        template = format!("{{channel}}/{}", template);
+    }
+
+    let name_template = match NameTemplate::new(&template) {
+        Ok(name_template) if cli.magick_compat => name_template
+            .with_magick_numbering()
+            .with_sanitize(cli.sanitize),
+        Ok(name_template) => name_template.with_sanitize(cli.sanitize),
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if matches!(cli.frame, Some(FrameSelection::All)) && !name_template.uses_frame() {
+        eprintln!("splix: name: The template must include '{{frame}}' to split every frame, so frames don't overwrite each other");
+        return ExitCode::FAILURE;
+    }
+
+    if cli.map_tiles.is_some() && !name_template.uses_zoom() {
+        eprintln!("splix: name: The template must include '{{z}}' for map tiles, so zoom levels don't overwrite each other");
+        return ExitCode::FAILURE;
+    }
+
+    let output = match cli.output_dir {
+        None if cli.to_clipboard.is_some()
+            || cli.multipage_tiff.is_some()
+            || cli.npy_out.is_some() =>
+        {
+            Ok(Output::Discard)
+        }
+        output_dir => Output::new(
+            output_dir.unwrap_or(PathBuf::from("splixed-images")),
+            UploadOptions {
+                jobs: cli.upload_jobs,
+                retries: cli.upload_retries,
+            },
+        ),
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if output.local_dir().is_none() {
+        if cli.dedupe.is_some_and(DedupeMode::writes_duplicates) {
+            eprintln!("splix: dedupe: Linking duplicates requires a local output directory");
+            return ExitCode::FAILURE;
+        }
+
+        if cli
+            .dedupe_sources
+            .is_some_and(DedupeMode::writes_duplicates)
+        {
+            eprintln!(
+                "splix: dedupe-sources: Linking duplicates requires a local output directory"
+            );
+            return ExitCode::FAILURE;
+        }
+
+        if exec.is_some() {
+            eprintln!("splix: exec: Running commands on tiles requires a local output directory");
+            return ExitCode::FAILURE;
+        }
+
+        if cli.update {
+            eprintln!("splix: update: Updating tiles requires a local output directory");
+            return ExitCode::FAILURE;
+        }
+
+        if cli.open {
+            eprintln!("splix: open: Opening the results requires a local output directory");
+            return ExitCode::FAILURE;
+        }
+
+        if cli.resume {
+            eprintln!("splix: resume: Resuming a run requires a local output directory");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let _lock = match output
+        .local_dir()
+        .map(|dir| lock::lock_output(dir, cli.wait_for_lock))
+        .transpose()
+    {
+        Ok(lock) => lock,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Archives are extracted to a temporary directory, which is removed once the run ends.
+    let extracted = match cli
+        .images
+        .as_deref()
+        .and_then(|path| Some(path).zip(archive::ArchiveKind::from_path(path)))
+        .filter(|(path, _)| path.is_file())
+    {
+        Some((path, kind)) => match archive::extract(path, kind) {
+            Ok(extracted) => Some(extracted),
+            Err(err) => {
+                eprintln!(
+                    "splix: image: Failed to read archive {}: {}",
+                    path.display(),
+                    err
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    let img_dir = match &extracted {
+        Some(extracted) => extracted.dir().to_path_buf(),
+        None => cli.images.clone().unwrap_or_default(),
+    };
+    // Where each image came from, for messages and the manifest: its path inside the archive it was extracted from.
+    let source_path = |path: &Path| match &extracted {
+        Some(extracted) => extracted.source_path(path),
+        None => path.to_path_buf(),
+    };
+    // How many times smaller each image too large to decode in full was decoded, for the manifest.
+    let downscaled: Mutex<HashMap<PathBuf, u32>> = Mutex::new(HashMap::new());
+    // Only images that can't be decoded in full are shrunk. `--memory-limit` just makes larger ones wait their turn.
+    let budget = reduce::default_budget();
+    let shrink = |path: &Path| {
+        let (img, factor) = reduce::open(path, budget, !cli.no_auto_orient)?;
+        eprintln!(
+            "splix: {} is too large to decode in full, so it's split at 1/{} of its size, {}x{}",
+            source_path(path).display(),
+            factor,
+            img.width(),
+            img.height()
+        );
+        downscaled
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), factor);
+        Ok(img)
+    };
+    let open = |path: &Path| {
+        let oversized = cli.downscale_oversized
+            && decoded_size(path).is_some_and(|size| !reduce::fits_in_memory(size));
+        if oversized {
+            match shrink(path) {
+                // Decoded in full, as it would be without `--downscale-oversized`.
+                Err(ImageError::Unsupported(_)) => {}
+                result => return result,
+            }
+        }
+        match fallback.decode(path, |path| open_image(path, !cli.no_auto_orient)) {
+            Err(ImageError::Limits(err)) if cli.downscale_oversized => {
+                shrink(path).map_err(|_| ImageError::Limits(err))
+            }
+            result => result,
+        }
+    };
+    let marks = cli
+        .poster
+        .filter(|_| cli.poster_marks)
+        .map(|poster| PageMarks::new(&poster, poster.pixels(cli.poster_overlap)));
+    let layout = match (cli.poster, &cli.monitors, cli.random_crops) {
+        // Lines and grids are found in each image as it's split, so the whole image is the only cell until then.
+        _ if cli.ruled_lines.is_some() || cli.detect_grid => Layout::Grid {
+            rows: vec![Band::Sections(1)],
+            cols: vec![Band::Sections(1)],
+            skip_rows: Vec::new(),
+            skip_cols: Vec::new(),
+        },
+        _ if cli.map_tiles.is_some() => Layout::Pages {
+            width: maptiles::TILE_SIZE,
+            height: maptiles::TILE_SIZE,
+            overlap: 0,
+        },
+        (_, _, Some(crops)) => Layout::Crops {
+            width: crops.width,
+            height: crops.height,
+        },
+        (_, Some(monitors), None) => monitors::layout(monitors, cli.bezel),
+        (Some(poster), None, None) => {
+            let margin = marks.as_ref().map_or(0, |marks| marks.margin() * 2);
+            let (width, height) = (
+                poster.pixels(poster.width_mm).saturating_sub(margin),
+                poster.pixels(poster.height_mm).saturating_sub(margin),
+            );
+            if width == 0 || height == 0 {
+                eprintln!("splix: poster-marks: The page is too small for the margin");
+                return ExitCode::FAILURE;
+            }
+            let overlap = poster.pixels(cli.poster_overlap);
+            if overlap >= width.min(height) {
+                eprintln!("splix: poster-overlap: The overlap must be narrower than the page");
+                return ExitCode::FAILURE;
+            }
+
+            Layout::Pages {
+                width,
+                height,
+                overlap,
+            }
+        }
+        (None, None, None) if cli.auto_axis => {
+            let (spec, name) = match &cli.rows {
+                Some(rows) => (rows, "rows"),
+                None => (cli.cols.as_ref().unwrap(), "cols"),
+            };
+            match grid::parse_spec(&grid::relative_sizes(spec), name) {
+                Ok(sizes) => Layout::LongestAxis { sizes },
+                Err(err) => {
+                    eprintln!("{}", err);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        (None, None, None) => match (cli.max_height, cli.tiles) {
+            (Some(height), _) => Layout::MaxHeight { height },
+            (None, Some(count)) => Layout::Tiles { count },
+            (None, None) => {
+                let one = [BandSize {
+                    length: BandLength::Relative(1.0),
+                    skip: false,
+                }];
+                match (
+                    grid::parse_bands(cli.rows.as_deref().unwrap_or(&one), "rows"),
+                    grid::parse_bands(cli.cols.as_deref().unwrap_or(&one), "cols"),
+                ) {
+                    // Each is a single number of equal bands, as checked by `validate_args`.
+                    (Ok((rows, _)), Ok((cols, _))) if cli.magick_compat => Layout::MagickGrid {
+                        rows: grid::min_spec_length(&rows),
+                        cols: grid::min_spec_length(&cols),
+                    },
+                    (Ok((rows, skip_rows)), Ok((cols, skip_cols))) => Layout::Grid {
+                        rows,
+                        cols,
+                        skip_rows,
+                        skip_cols,
+                    },
+                    (Err(err), _) | (_, Err(err)) => {
+                        eprintln!("{}", err);
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+        },
+    };
+    // Each grid of `--grid` is split from the same decoded image, named after the grid.
+    let layouts: Vec<(String, Layout)> = if cli.grid.is_empty() {
+        vec![(String::new(), layout)]
+    } else {
+        cli.grid
+            .iter()
+            .map(|&(cols, rows)| {
+                (
+                    format!("{}x{}", cols, rows),
+                    Layout::Grid {
+                        rows: vec![Band::Sections(rows)],
+                        cols: vec![Band::Sections(cols)],
+                        skip_rows: Vec::new(),
+                        skip_cols: Vec::new(),
+                    },
+                )
+            })
+            .collect()
+    };
+    // Images need to be large enough for every grid.
+    let min_size = |layouts: &[(String, Layout)], width: u32, height: u32| {
+        layouts
+            .iter()
+            .map(|(_, layout)| layout.min_size(width, height))
+            .fold((0, 0), |(min_width, min_height), (width, height)| {
+                (min_width.max(width), min_height.max(height))
+            })
+    };
+    let canvas = layouts[0].1.canvas();
+    #[cfg(feature = "async")]
+    let writes = match cli
+        .async_io
+        .then(|| AsyncWrites::new(cli.upload_jobs))
+        .transpose()
+    {
+        Ok(writes) => writes,
+        Err(err) => {
+            eprintln!(
+                "splix: async-io: Failed to start the async runtime: {}",
+                err
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let resampler = Resampler {
+        filter: cli.filter,
+        linear: !cli.no_linear_light,
+    };
+    let cache = match output
+        .local_dir()
+        .filter(|_| cli.update)
+        .map(TileCache::open)
+        .transpose()
+    {
+        Ok(cache) => cache,
+        Err(err) => {
+            eprintln!(
+                "splix: update: Failed to read {}: {}",
+                cache::CACHE_FILE,
+                err
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let journal = match output
+        .local_dir()
+        .filter(|_| cli.resume)
+        .map(Journal::open)
+        .transpose()
+    {
+        Ok(journal) => journal,
+        Err(err) => {
+            eprintln!(
+                "splix: resume: Failed to open {}: {}",
+                journal::JOURNAL_FILE,
+                err
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(journal) = journal.as_ref().filter(|journal| journal.resumed() > 0) {
+        eprintln!(
+            "splix: resume: Carrying on from an earlier run that wrote {} tiles",
+            journal.resumed()
+        );
+    }
+    let dataset = match cli.dataset_layout.map(|layout| {
+        Dataset::new(
+            layout,
+            cli.val_split.unwrap_or(0.2),
+            cli.shard_size
+                .map_or(dataset::DEFAULT_SHARD_SIZE, |size| size as usize),
+            cli.classes.as_deref(),
+        )
+    }) {
+        Some(Ok(dataset)) => Some(dataset),
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+    let settings = SaveSettings {
+        output: Arc::new(output),
+        #[cfg(feature = "async")]
+        writes,
+        name_template,
+        filters: TileFilters {
+            blank: cli.skip_blank,
+            background: cli.skip_background,
+            mask_coverage: cli.mask_coverage,
+            transparent: cli.skip_transparent,
+        },
+        dedupe: cli.dedupe.map(Dedupe::new),
+        exec,
+        encoder,
+        clipboard: cli.to_clipboard.map(ClipboardTile::new),
+        multipage: cli
+            .multipage_tiff
+            .clone()
+            .map(|path| MultipageTiff::new(path, cli.tiff_compression)),
+        npy: cli.npy_out.clone().map(NpyOut::new),
+        dataset,
+        throttle: cli.io_limit.map(Throttle::new),
+        progress,
+        watermark,
+        label,
+        marks,
+        normalize: cli.normalize,
+        resampler,
+        encode: EncodeOptions {
+            quality: cli.quality,
+            adaptive: cli.adaptive_quality,
+            progressive: cli.progressive,
+            interlace: cli.interlace,
+            quantize: cli.quantize,
+            dither: cli.dither,
+            tiff_compression: cli.tiff_compression,
+            background: cli.background,
+        },
+        checksums: cli.manifest.is_some() || cli.name_by_hash.is_some(),
+        tile_metadata: cli.tile_metadata,
+        tile_stats: cli.tile_stats,
+        auto_format: cli.ext.as_deref() == Some(encode::AUTO_EXT),
+        alpha_fallback: cli.alpha_fallback.format(),
+        cache,
+        journal,
+        name_by_hash: cli.name_by_hash.map(usize::from),
+        hash_names: Mutex::default(),
+        pending: Queue::new(write_jobs * 4),
+        unwritten: Mutex::default(),
+        stats: RunStats::default(),
+        policy: ErrorPolicy::new(cli.fail_fast),
+    };
+    let manifest = ManifestBuilder::default();
+    let skipped = SkippedFiles::default();
+    let memory = cli
+        .memory_limit
+        .map(|limit| Semaphore::new(usize::try_from(limit).unwrap_or(usize::MAX)));
+
+    let mut ignore_rules = (!cli.no_ignore).then(|| walk::IgnoreRules::new(&img_dir));
+    let mut entries: Vec<DirEntry> = if cli.from_clipboard {
+        Vec::new()
+    } else {
+        WalkDir::new(&img_dir)
+            .max_depth(match cli.max_depth {
+                Some(depth) => depth.saturating_add(1),
+                None if cli.recursive || extracted.is_some() => usize::MAX,
+                None => 1,
+            })
+            .follow_links(cli.follow_symlinks)
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.depth() == 0
+                    || !((cli.skip_hidden && walk::is_hidden(entry))
+                        || (cli.skip_junk && walk::is_junk(entry))
+                        || ignore_rules
+                            .as_mut()
+                            .is_some_and(|rules| rules.is_ignored(entry)))
+            })
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry).filter(|entry| {
+                    entry.path().is_file() && !(cli.skip_symlinks && entry.path_is_symlink())
+                }),
+                Err(err) if err.loop_ancestor().is_some() => {
+                    eprintln!(
+                        "splix: Skipping symbolic link {}, which loops back to {}",
+                        err.path().unwrap_or(&img_dir).display(),
+                        err.loop_ancestor().unwrap().display()
+                    );
+                    None
+                }
+                Err(err) => {
+                    skipped.push(err.path().unwrap_or(&img_dir).to_path_buf(), err);
+                    settings.policy.record(None);
+                    None
+                }
+            })
+            .filter(|entry| {
+                ImageFormat::from_path(entry.path()).is_ok()
+                    || aseprite::is_aseprite(entry.path())
+                    || dicom::is_dicom(entry.path())
+                    || slide::is_slide(entry.path())
+            })
+            .collect()
+    };
+
+    match cli.sort {
+        SortOrder::Name => entries.sort_by(|a, b| a.path().cmp(b.path())),
+        SortOrder::Mtime => entries.sort_by_cached_key(|entry| {
+            entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| metadata.modified().ok())
+        }),
+        SortOrder::Size => entries
+            .sort_by_cached_key(|entry| entry.metadata().map_or(0, |metadata| metadata.len())),
+        SortOrder::None => {}
+    }
+    if cli.sequence.is_some() {
+        entries.sort_by_cached_key(|entry| naming::sequence_key(entry.path()));
+    }
+
+    if let Some(limit) = cli.limit {
+        entries.truncate(limit);
+    }
+
+    if let Some(sample) = cli.sample {
+        let seed = pick_seed(&mut cli.seed, "Sampling images");
+        entries = Rng::new(seed).sample(entries, sample);
+    }
+
+    let jitter = cli
+        .jitter
+        .map(|amount| (amount, pick_seed(&mut cli.seed, "Jittering cut lines")));
+    let random_crops = cli
+        .random_crops
+        .map(|crops| (crops, pick_seed(&mut cli.seed, "Cropping images")));
+
+    let paths: Vec<PathBuf> = if cli.from_clipboard {
+        vec![PathBuf::from(clipboard::CLIPBOARD_PATH)]
+    } else {
+        entries
+            .iter()
+            .map(|entry| entry.path().to_path_buf())
+            .collect()
+    };
+    let sources: Vec<PathBuf> = paths.iter().map(|path| source_path(path)).collect();
+
+    if paths.len() > 1
+        && settings
+            .multipage
+            .as_ref()
+            .is_some_and(|multipage| !multipage.per_source())
+    {
+        eprintln!("splix: multipage-tiff: The path must include '{{stem}}' to split more than one image, so their TIFFs don't overwrite each other");
+        return ExitCode::FAILURE;
+    }
+    if let Some(dataset) = &settings.dataset {
+        let seed = if dataset.shuffles() {
+            pick_seed(&mut cli.seed, "Holding out validation images")
+        } else {
+            cli.seed.unwrap_or(0)
+        };
+        if let Err(err) = dataset.assign(&sources, seed) {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    }
+    if paths.len() > 1 && settings.npy.as_ref().is_some_and(|npy| !npy.per_source()) {
+        eprintln!("splix: npy-out: The path must include '{{stem}}' to split more than one image, so their arrays don't overwrite each other");
+        return ExitCode::FAILURE;
+    }
+
+    // The SHA-256 of each file, if `--dedupe-sources` needs them, which the manifest then reuses.
+    let hashes: Option<Vec<Option<String>>> = cli.dedupe_sources.map(|_| {
+        paths
+            .par_iter()
+            .map(|path| manifest::sha256_file(path).ok())
+            .collect()
+    });
+    let formats: Vec<(ImageFormat, String)> = paths
+        .iter()
+        .map(|path| output_format(path, cli.ext.as_deref(), cli.preserve_ext_case))
+        .collect();
+
+    // Sidecars are read up front, so a mistake in one stops the run before any tiles are written.
+    let mut sidecars: Vec<Option<Sidecar>> = Vec::with_capacity(paths.len());
+    for path in &paths {
+        if cli.no_sidecars {
+            sidecars.push(None);
+            continue;
+        }
+        match sidecar::read(path, cli.rows.as_deref(), cli.cols.as_deref()) {
+            Ok(sidecar) => sidecars.push(sidecar),
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let plain_grid = cli.grid.is_empty()
+        && cli.ruled_lines.is_none()
+        && !cli.detect_grid
+        && matches!(layouts[0].1, Layout::Grid { .. });
+    if let Some(sidecar) = sidecars.iter().flatten().next().filter(|_| !plain_grid) {
+        eprintln!(
+            "splix: {}: Sidecars can only be used when images are split into a plain grid, or pass '--no-sidecars' to ignore them",
+            sidecar.path.display()
+        );
+        return ExitCode::FAILURE;
+    }
+    // The grids and preset each image is split with, from its sidecar if it has one.
+    let options = |index: usize| match &sidecars[index] {
+        Some(sidecar) => (&sidecar.layouts[..], sidecar.preset.or(cli.preset)),
+        None => (&layouts[..], cli.preset),
+    };
+    // The earlier file each image is a copy of, which is split in its place.
+    // Copies are only split in place of files split the same way, since sidecars can give them their own grid.
+    let duplicates: Vec<Option<usize>> = match &hashes {
+        Some(hashes) => {
+            let mut first: HashMap<&str, Vec<usize>> = HashMap::new();
+            hashes
+                .iter()
+                .enumerate()
+                .map(|(index, hash)| {
+                    let originals = first.entry(hash.as_deref()?).or_default();
+                    let original = originals
+                        .iter()
+                        .copied()
+                        .find(|&original| options(original) == options(index));
+                    if original.is_none() {
+                        originals.push(index);
+                    }
+                    original
+                })
+                .collect()
+        }
+        None => vec![None; paths.len()],
+    };
 
-/// Lightning-fast image splitter.  
-#[derive(Parser)]
-#[clap(version)]
-struct Cli {
-    /// Path of the image(s) to convert.
-    /// Specify the path of an image, or a directory of images.
-    #[arg(verbatim_doc_comment)]
-    images: PathBuf,
+    // The directory of each source's zoom levels, and the path of its tiles inside it.
+    let pyramid = |index: usize, stem: &str| {
+        let name = TileName {
+            stem,
+            ext: &formats[index].1,
+            row: 0,
+            col: 0,
+            index: 0,
+            source_index: index,
+            frame: 0,
+            zoom: 0,
+            grid: "",
+            level: 0,
+            channel: "",
+        };
+        (
+            settings.name_template.zoom_dir(&name),
+            settings.name_template.url_template(&name),
+        )
+    };
 
-    /// The number of rows to split the image into.
-    /// Specify an integer, or a list of integers.
-    /// Ex:
-    /// -r 4        Split the image into 4 equal rows.
-    /// -r 2,3,1,5  Split the image into four rows of different heights.
-    ///             The image will be divided vertically into 2+3+1+5=11 equal sections.
-    ///             The first row will take up 2 sections, second row 3 sections, etc.
-    #[arg(short, long, value_delimiter = ',', verbatim_doc_comment)]
-    rows: Option<Vec<u32>>,
+    // Every image needs its own pyramid, since tiles of each level are named only by their position.
+    if cli.map_tiles.is_some() {
+        let dirs: HashSet<PathBuf> = paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| pyramid(index, &path.file_stem().unwrap().to_string_lossy()).0)
+            .collect();
+        if dirs.len() < paths.len() {
+            eprintln!("splix: map-tiles: Each image needs its own directory of zoom levels. Use --per-image-dir or include '{{stem}}' in '--name' before '{{z}}'");
+            return ExitCode::FAILURE;
+        }
+    }
 
-    /// The number of columns to split the image into.
-    /// Specity an integer, or a list of integers.
-    /// Ex:
-    /// -c 4        Split the image into 4 equal columns.
-    /// -c 2,3,1,5  Split the image into four columns of different widths.
-    ///             The image will be divided horizontally into 2+3+1+5=11 equal sections.
-    ///             The first column will take up 2 sections, second column 3 sections, etc.
-    #[arg(short, long, value_delimiter = ',', verbatim_doc_comment)]
-    cols: Option<Vec<u32>>,
+    let mut stems = naming::unique_stems(
+        &settings.name_template,
+        cli.images.as_deref().unwrap_or(&img_dir),
+        &sources
+            .iter()
+            .zip(&formats)
+            .map(|(path, (_, ext))| (path.as_path(), ext.as_str()))
+            .collect::<Vec<_>>(),
+    );
+    if cli.sequence.is_some() {
+        stems = naming::renumber_sequence(&stems);
+    }
 
-    /// An optional directory to save the splixed images in. Default: `./splixed-images`.
-    #[arg(short = 'd', long = "output-dir")]
-    output_dir: Option<PathBuf>,
+    let pyramids: Option<Vec<(PathBuf, String)>> = cli.map_tiles.map(|_| {
+        stems
+            .iter()
+            .enumerate()
+            .map(|(index, stem)| pyramid(index, stem))
+            .collect()
+    });
 
-    /// An optional flag to enable recursive search for images in specified directory.
-    #[arg(short = 'R', long)]
-    recursive: bool,
-}
+    // Every tile's path is worked out from the image headers, so clobbered tiles are found before any are written.
+    let plan = |index: usize, path: &PathBuf| -> Vec<PlannedTile> {
+        let (layouts, preset) = options(index);
+        let Some(mut size) = preflight::header_size(path, !cli.no_auto_orient) else {
+            return Vec::new();
+        };
+        if let Some((min_width, min_height)) = cli.min_size {
+            if size.0 < min_width || size.1 < min_height {
+                return Vec::new();
+            }
+        }
+        if let Some(aspect) = preset.and_then(|preset| preset.aspect) {
+            size = resize::aspect_size(size, aspect);
+        }
+        if let Some(canvas) = canvas {
+            size = canvas;
+        }
 
-/// Validates the provided command-line arguments.
-///
-/// # Arguments
-///
-/// * `cli` - A reference to the command-line arguments parsed by Clap.
-///
-/// # Returns
-///
-/// * `Ok(())` if the arguments are valid, otherwise returns an error message.
-fn validate_args(cli: &Cli) -> Result<(), String> {
-    let img_dir = &cli.images;
-    let rows = cli.rows.as_ref();
-    let cols = cli.cols.as_ref();
+        let (min_width, min_height) = min_size(layouts, size.0, size.1);
+        if size.0 < min_width || size.1 < min_height {
+            let policy = if cli.upscale_to_fit {
+                SmallImagePolicy::Upscale
+            } else {
+                cli.small_image
+            };
+            match policy {
+                SmallImagePolicy::Upscale => {
+                    size = resize::upscaled_size(size, min_width, min_height)
+                }
+                SmallImagePolicy::Pad => size = (size.0.max(min_width), size.1.max(min_height)),
+                SmallImagePolicy::AsIs => {}
+                SmallImagePolicy::Skip | SmallImagePolicy::Error => return Vec::new(),
+            }
+        }
 
-    match img_dir.try_exists() {
-        Ok(true) => {}
-        Ok(false) | Err(_) => {
-            return Err(format!(
-                "splix: image: The provided path '{}' does not exist",
-                img_dir.display()
-            ))
+        // The cells of each zoom level, level, and grid, with the grid, zoom level, and level naming them.
+        let mut splits: Vec<(&str, u32, u32, Vec<Cell>)> = Vec::new();
+        if let Some(scheme) = cli.map_tiles {
+            let max_zoom = maptiles::max_zoom(size.0, size.1);
+            for zoom in 0..=max_zoom {
+                let (width, height) = maptiles::level_size(size, zoom, max_zoom);
+                let mut cells = layouts[0].1.cells(width, height);
+                maptiles::number_rows(&mut cells, scheme);
+                splits.push(("", zoom, 0, cells));
+            }
+        } else {
+            for level in 0..cli.levels.unwrap_or(1) {
+                if level > 0 {
+                    let halved = (size.0.div_ceil(2), size.1.div_ceil(2));
+                    let (min_width, min_height) = min_size(layouts, halved.0, halved.1);
+                    if halved.0 < min_width || halved.1 < min_height {
+                        break;
+                    }
+                    size = halved;
+                }
+                for (grid, layout) in layouts {
+                    let mut cells = match random_crops {
+                        Some((crops, seed)) => {
+                            crops.cells(size, &mut Rng::new(seed.wrapping_add(index as u64)))
+                        }
+                        None => layout.cells(size.0, size.1),
+                    };
+                    if preset.is_some_and(|preset| preset.spreads) && size.0 <= size.1 {
+                        cells = vec![whole_image(size)];
+                    }
+                    if cli.rtl {
+                        cells = grid::right_to_left(cells);
+                    }
+                    splits.push((grid, 0, level, cells));
+                }
+            }
         }
-    }
+        let frame = match cli.frame {
+            Some(FrameSelection::Index(frame)) => frame,
+            _ => 0,
+        };
 
-    if rows.is_none() && cols.is_none() {
-        return Err("splix: At least one of '--rows', '--cols' needs to be specified".to_string());
-    }
+        let channels = cli.channels.map_or(&[""][..], Channels::names);
+        let mut tiles = Vec::new();
+        for (grid, zoom, level, cells) in splits {
+            for channel in channels {
+                for (i, cell) in cells.iter().enumerate() {
+                    let name = settings.name_template.render(&TileName {
+                        stem: &stems[index],
+                        ext: &formats[index].1,
+                        row: cell.row,
+                        col: cell.col,
+                        index: i,
+                        source_index: index,
+                        frame,
+                        zoom,
+                        grid,
+                        level,
+                        channel,
+                    });
+                    tiles.push(PlannedTile {
+                        source: index,
+                        row: cell.row,
+                        col: cell.col,
+                        path: settings.output.location(&name),
+                    });
+                }
+            }
+        }
+        tiles
+    };
 
-    if let Some(rows) = rows {
-        if rows.iter().any(|&row_val| row_val == 0) {
-            return Err("splix: rows: All row sizes must be greater than zero".to_string());
+    // Tiles named by their hash can't be planned before they're encoded, and share files by design.
+    if !cli.from_clipboard && cli.name_by_hash.is_none() {
+        let planned: Vec<PlannedTile> = paths
+            .par_iter()
+            .enumerate()
+            .flat_map_iter(|(index, path)| plan(index, path))
+            .collect();
+
+        let collisions = preflight::collisions(&planned);
+        if !collisions.is_empty() {
+            for (first, tile) in collisions.iter().take(preflight::MAX_REPORTED) {
+                eprintln!(
+                    "splix: name: Tile r{}c{} of {} would overwrite tile r{}c{} of {} at {}",
+                    tile.row,
+                    tile.col,
+                    sources[tile.source].display(),
+                    first.row,
+                    first.col,
+                    sources[first.source].display(),
+                    tile.path.display()
+                );
+            }
+            eprintln!(
+                "splix: name: {} tiles would overwrite other tiles of the batch. Use a template with '{{row}}' and '{{col}}', or '{{stem}}' for batches",
+                collisions.len()
+            );
+            return ExitCode::FAILURE;
         }
-    }
 
-    if let Some(cols) = cols {
-        if cols.iter().any(|&col_val| col_val == 0) {
-            return Err("splix: cols: All column sizes must be greater than zero".to_string());
+        if cli.no_clobber && settings.output.local_dir().is_some() {
+            let existing = preflight::existing(&planned);
+            if !existing.is_empty() {
+                for tile in existing.iter().take(preflight::MAX_REPORTED) {
+                    eprintln!(
+                        "splix: no-clobber: Tile r{}c{} of {} would overwrite {}",
+                        tile.row,
+                        tile.col,
+                        sources[tile.source].display(),
+                        tile.path.display()
+                    );
+                }
+                eprintln!(
+                    "splix: no-clobber: {} tiles would overwrite existing files",
+                    existing.len()
+                );
+                return ExitCode::FAILURE;
+            }
         }
     }
 
-    Ok(())
-}
+    let times: Vec<FileTimes> = iter::repeat_with(FileTimes::default)
+        .take(paths.len())
+        .collect();
+    // The first image, if it was decoded to estimate the space its tiles take, so the split doesn't decode it again.
+    let sample: Mutex<Option<DynamicImage>> = Mutex::new(None);
 
-/// Splits the input image into the specified number of rows and columns.
-///
-/// # Arguments
-///
-/// * `img` - Image to split.
-/// * `rows` - Number of rows to split the image into. Provide a single integer for equal division, or a list of integers for custom division.
-/// * `cols` - Number of columns to split the image into. Provide a single integer for equal division, or a list of integers for custom division.
-///
-/// # Returns
-///
-/// A vector of split images.
-fn split_image(mut img: DynamicImage, rows: &Vec<u32>, cols: &Vec<u32>) -> Vec<DynamicImage> {
-    let (width, height) = img.dimensions();
-    let sum_rows: u32 = rows.iter().sum();
-    let sum_cols: u32 = cols.iter().sum();
+    if let Some(dir) = settings.output.local_dir().filter(|_| !cli.no_space_check) {
+        if let Some(space) = space::available_space(dir) {
+            // Every grid is estimated from the same decode of the first image.
+            let img = paths
+                .first()
+                .filter(|_| !cli.from_clipboard)
+                .and_then(|path| {
+                    settings
+                        .stats
+                        .time(Stage::Decode, Some(&times[0]), || match cli.frame {
+                            Some(FrameSelection::Index(frame)) => {
+                                open_frame(path, Some(frame), !cli.no_auto_orient).ok()
+                            }
+                            _ => open(path).ok(),
+                        })
+                });
+            let needed: Option<u64> = img.as_ref().and_then(|img| {
+                layouts
+                    .iter()
+                    .map(|(_, layout)| {
+                        estimate_output_size(
+                            &paths,
+                            &formats,
+                            layout,
+                            img,
+                            &settings.encode,
+                            space.block_size,
+                        )
+                    })
+                    .sum()
+            });
+            if cli.frame.is_none() && !cli.from_clipboard {
+                *sample.lock().unwrap() = img;
+            }
 
-    let single_rows: Vec<u32>;
-    let rows = if rows.len() > 1 && height >= sum_rows {
-        rows
-    } else {
-        single_rows = vec![1; cmp::min(height as usize, rows[0] as usize)];
-        &single_rows
-    };
+            if let Some(needed) = needed.filter(|&needed| needed > space.available) {
+                eprintln!(
+                    "splix: output-dir: The tiles need about {}, but only {} is free for {}. Use --no-space-check to split anyway",
+                    units::format_size(needed),
+                    units::format_size(space.available),
+                    dir.display()
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+    }
 
-    let single_cols: Vec<u32>;
-    let cols = if cols.len() > 1 && width >= sum_cols {
-        cols
-    } else {
-        single_cols = vec![1; cmp::min(width as usize, cols[0] as usize)];
-        &single_cols
+    if let Some(progress) = &settings.progress {
+        progress.start(paths.len());
+    }
+    settings.stats.scanned(paths.len());
+
+    // Once every frame of an image has been split, its pages and arrays are written and it's reported as finished.
+    let finished = |index: usize| {
+        let kept = [
+            settings
+                .multipage
+                .as_ref()
+                .and_then(|multipage| multipage.finish(index)),
+            settings.npy.as_ref().and_then(|npy| npy.finish(index)),
+        ];
+        for written in kept.into_iter().flatten() {
+            match written {
+                Ok(len) => settings.stats.wrote(len, false),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    settings.policy.record(None);
+                }
+            }
+        }
+        if let Some(progress) = &settings.progress {
+            progress.file(&sources[index]);
+        }
     };
+    let decoded = Queue::new(decode_jobs);
 
-    let row_height = cmp::max(1, height / sum_rows);
-    let col_width = cmp::max(1, width / sum_cols);
+    let decode = |index: usize| {
+        let (path, source_path) = (&paths[index], &sources[index]);
+        let done = || finished(index);
 
-    let mut split_images = Vec::new();
-    let mut x = 0;
-    let mut y = 0;
+        if settings.policy.stopped() || duplicates[index].is_some() {
+            return done();
+        }
 
-    for i in 0..rows.len() {
-        y = if i == 0 {
-            0
-        } else {
-            y + row_height * rows[i - 1]
+        if let Some((min_width, min_height)) = cli.min_size {
+            if let Some((width, height)) = preflight::header_size(path, false) {
+                if width < min_width || height < min_height {
+                    return done();
+                }
+            }
+        }
+
+        let reserved = memory.as_ref().map(|memory| {
+            memory.acquire_many(
+                decoded_size(path).map_or(0, |size| usize::try_from(size).unwrap_or(usize::MAX)),
+            )
+        });
+
+        let metadata = fs::metadata(path).ok();
+        let attrs = TileAttrs {
+            modified: metadata
+                .as_ref()
+                .filter(|_| cli.preserve_times)
+                .and_then(|metadata| metadata.modified().ok()),
+            permissions: metadata
+                .as_ref()
+                .filter(|_| cli.preserve_perms)
+                .map(|metadata| metadata.permissions()),
         };
 
-        let crop_height = if i == rows.len() - 1 {
-            height - y
-        } else {
-            row_height * rows[i]
+        if let Some(metadata) = &metadata {
+            settings.stats.read(metadata.len());
+        }
+        if let Some(throttle) = &settings.throttle {
+            throttle.take(metadata.map_or(0, |metadata| metadata.len()));
+        }
+
+        let hash = hashes.as_ref().and_then(|hashes| hashes[index].clone());
+        let sha256 = match &cli.manifest {
+            Some(_) if !cli.from_clipboard => {
+                match hash.map_or_else(|| manifest::sha256_file(path), Ok) {
+                    Ok(sha256) => Some(sha256),
+                    Err(err) => {
+                        skipped.push(source_path.clone(), ImageError::IoError(err));
+                        settings.policy.record(None);
+                        return done();
+                    }
+                }
+            }
+            _ => None,
         };
 
-        for j in 0..cols.len() {
-            x = if j == 0 {
-                0
-            } else {
-                x as u32 + col_width * cols[j - 1]
+        let mask = match &cli.mask {
+            Some(template) => {
+                let mask_path = mask::path(template, &stems[index]);
+                match mask::read(&mask_path) {
+                    Ok(mask) => Some(mask),
+                    Err(err) => {
+                        skipped.push(
+                            source_path.clone(),
+                            format!("Failed to read mask {}: {}", mask_path.display(), err),
+                        );
+                        settings.policy.record(None);
+                        return done();
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let mut frames =
+            settings
+                .stats
+                .time(Stage::Decode, Some(&times[index]), || match cli.frame {
+                    _ if cli.from_clipboard => frames::still(
+                        clipboard::read_image(),
+                        cli.frame.unwrap_or(FrameSelection::Index(0)),
+                    ),
+                    // The first image may already be decoded, to estimate the space its tiles take.
+                    // Only its decoder looks, so the others don't wait on the lock.
+                    None => {
+                        let sampled = (index == 0).then(|| sample.lock().unwrap().take());
+                        frames::still(
+                            sampled.flatten().map_or_else(|| open(path), Ok),
+                            FrameSelection::Index(0),
+                        )
+                    }
+                    Some(selection) => frames::decode(path, selection, open),
+                });
+
+        // Finishes the image once the split stage drops its last frame.
+        let source = Arc::new(DecodedImage::new(
+            index,
+            source_path,
+            attrs,
+            sha256,
+            mask,
+            reserved,
+            &finished,
+        ));
+        while let Some(frame) = settings
+            .stats
+            .time(Stage::Decode, Some(&times[index]), || frames.next())
+        {
+            if settings.policy.stopped() || source.rejected() {
+                return;
+            }
+
+            let (frame, img) = match frame {
+                Ok(frame) => frame,
+                Err(err) => {
+                    skipped.push(source_path.clone(), err);
+                    settings.policy.record(None);
+                    return;
+                }
             };
+            let frame = DecodedFrame {
+                source: Arc::clone(&source),
+                frame,
+                img,
+            };
+            if decoded.send(frame).is_err() {
+                return;
+            }
+        }
+    };
+
+    let split = |frame: DecodedFrame| {
+        let DecodedFrame {
+            source: decoded,
+            frame,
+            mut img,
+        } = frame;
+        let (index, path, attrs, sha256) =
+            (decoded.index, decoded.path, &decoded.attrs, &decoded.sha256);
+        let (layouts, preset) = options(index);
+
+        if settings.policy.stopped() || decoded.rejected() {
+            return;
+        }
+
+        let regions = match &cli.regions {
+            Some(template) => {
+                let regions_path = mask::path(template, &stems[index]);
+                match Regions::read(&regions_path, img.dimensions()) {
+                    Ok(regions) => Some(regions),
+                    Err(err) => {
+                        if decoded.reject() {
+                            skipped.push(
+                                path.clone(),
+                                format!(
+                                    "Failed to read regions {}: {}",
+                                    regions_path.display(),
+                                    err
+                                ),
+                            );
+                            settings.policy.record(None);
+                        }
+                        return;
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let split_time = Some(&times[index]);
+        img = settings.stats.time(Stage::Split, split_time, || {
+            if let Some(aspect) = preset.and_then(|preset| preset.aspect) {
+                img = resize::crop_to_aspect(&img, aspect);
+            }
+            match canvas {
+                Some((width, height)) => resampler.resize_to_fill(&img, width, height),
+                None => img,
+            }
+        });
 
-            let crop_width = if j == cols.len() - 1 {
-                width - x
+        let (min_width, min_height) = min_size(layouts, img.width(), img.height());
+        if img.width() < min_width || img.height() < min_height {
+            let policy = if cli.upscale_to_fit {
+                SmallImagePolicy::Upscale
             } else {
-                col_width * cols[j]
+                cli.small_image
+            };
+
+            match policy {
+                SmallImagePolicy::Skip => {
+                    if decoded.reject() {
+                        eprintln!(
+                            "{}",
+                            i18n::message("skipped-small", &[("path", &path.display())])
+                        );
+                    }
+                    return;
+                }
+                SmallImagePolicy::Error => {
+                    if decoded.reject() {
+                        skipped.push_with_help(
+                            path.clone(),
+                            format!(
+                                "The image is {}x{}, but the grid needs at least {}x{}",
+                                img.width(),
+                                img.height(),
+                                min_width,
+                                min_height
+                            ),
+                            "Use '--small-image pad' or '--small-image upscale' to split it anyway, or '--small-image skip' to leave it out",
+                        );
+                        settings.policy.record(None);
+                    }
+                    return;
+                }
+                SmallImagePolicy::Upscale => {
+                    img = settings.stats.time(Stage::Split, split_time, || {
+                        resize::upscale(&img, min_width, min_height, resampler)
+                    });
+                    if decoded.note() {
+                        eprintln!(
+                            "{}",
+                            i18n::message(
+                                "scaled-up-small",
+                                &[
+                                    ("path", &path.display()),
+                                    ("width", &img.width()),
+                                    ("height", &img.height()),
+                                ]
+                            )
+                        );
+                    }
+                }
+                SmallImagePolicy::Pad => {
+                    img = settings.stats.time(Stage::Split, split_time, || {
+                        resize::pad(img, min_width, min_height)
+                    });
+                    if decoded.note() {
+                        eprintln!(
+                            "{}",
+                            i18n::message(
+                                "padded-small",
+                                &[
+                                    ("path", &path.display()),
+                                    ("width", &img.width()),
+                                    ("height", &img.height()),
+                                ]
+                            )
+                        );
+                    }
+                }
+                SmallImagePolicy::AsIs if decoded.note() => match random_crops {
+                    Some((crops, _)) => {
+                        let fitted = crops.fit(img.dimensions());
+                        eprintln!(
+                            "splix: Cut {} crop{} of {}x{} from {} instead of {}x{}, since the crops are larger than the image",
+                            fitted.count,
+                            if fitted.count == 1 { "" } else { "s" },
+                            fitted.width,
+                            fitted.height,
+                            path.display(),
+                            crops.width,
+                            crops.height
+                        );
+                    }
+                    None => eprintln!(
+                        "splix: Split {} into fewer rows or columns than requested, since it's only {}x{}",
+                        path.display(),
+                        img.width(),
+                        img.height()
+                    ),
+                },
+                SmallImagePolicy::AsIs => {}
+            }
+        }
+
+        let (width, height) = img.dimensions();
+
+        // Pixel bands are cut at their exact size, so only the rest must divide evenly.
+        let indivisible = layouts
+            .iter()
+            .map(|(_, layout)| layout.sections(width, height))
+            .map(|((fixed_width, cols), (fixed_height, rows))| {
+                (
+                    width.saturating_sub(fixed_width),
+                    height.saturating_sub(fixed_height),
+                    cols,
+                    rows,
+                )
+            })
+            .find(|&(rest_width, rest_height, cols, rows)| {
+                rest_width % cols != 0 || rest_height % rows != 0
+            });
+        if let Some((rest_width, rest_height, cols, rows)) =
+            indivisible.filter(|_| cli.strict_divisible)
+        {
+            if decoded.reject() {
+                let rest = if (rest_width, rest_height) == (width, height) {
+                    ",".to_string()
+                } else {
+                    format!(
+                        ", leaving {}x{} besides the pixel bands,",
+                        rest_width, rest_height
+                    )
+                };
+                skipped.push_with_help(
+                    path.clone(),
+                    format!(
+                        "The image is {}x{}{} which doesn't divide evenly into {} row and {} column sections",
+                        width, height, rest, rows, cols
+                    ),
+                    "Leave out '--strict-divisible' to let some tiles be larger than others",
+                );
+                settings.policy.record(None);
+            }
+            return;
+        }
+        let misaligned = cli
+            .align
+            .filter(|&align| width % align != 0 || height % align != 0);
+        if let Some(align) = misaligned.filter(|_| cli.strict_divisible) {
+            if decoded.reject() {
+                skipped.push_with_help(
+                    path.clone(),
+                    format!(
+                        "The image is {}x{}, which isn't a whole number of {} pixel blocks",
+                        width, height, align
+                    ),
+                    "Leave out '--strict-divisible' to let the tiles at the right and bottom edges take what's left over",
+                );
+                settings.policy.record(None);
+            }
+            return;
+        }
+
+        if decoded.accept() {
+            settings.stats.processed();
+        }
+        // The block grid only lines up with the image at full size, as it was decoded and oriented.
+        let mcu = cli
+            .snap_to_mcu
+            .then(|| mcu::mcu_grid(&paths[index], !cli.no_auto_orient))
+            .flatten()
+            .filter(|grid| (grid.width, grid.height) == img.dimensions());
+
+        for level in 0..cli.levels.unwrap_or(1) {
+            // Each level is scaled down from the one before, so the image is only decoded once.
+            if level > 0 {
+                let (width, height) = (img.width().div_ceil(2), img.height().div_ceil(2));
+                let (min_width, min_height) = min_size(layouts, width, height);
+                if width < min_width || height < min_height {
+                    break;
+                }
+                img = settings.stats.time(Stage::Split, split_time, || {
+                    resampler.resize_exact(&img, width, height)
+                });
+            }
+            let (width, height) = img.dimensions();
+            let planes = cli.channels.map(|channels| {
+                settings.stats.time(Stage::Split, split_time, || {
+                    channels::planes(&img, channels)
+                })
+            });
+            for (grid, layout) in layouts {
+                let mut cells = layout.cells(width, height);
+                // Pages that aren't double-page spreads are kept whole.
+                if preset.is_some_and(|preset| preset.spreads) && width <= height {
+                    cells = vec![whole_image((width, height))];
+                }
+                // Seeded by the image's position, so its cuts don't depend on which thread splits it first.
+                if let Some((amount, seed)) = jitter {
+                    let mut rng = Rng::new(seed.wrapping_add(index as u64));
+                    cells = jitter::jitter(&cells, (width, height), amount, &mut rng);
+                }
+                if let Some(max_height) = cli.max_height.filter(|_| cli.smart_break) {
+                    cells = settings.stats.time(Stage::Split, split_time, || {
+                        breaks::smart_rows(&img, max_height)
+                    });
+                }
+                if cli.avoid_text {
+                    cells = settings.stats.time(Stage::Split, split_time, || {
+                        breaks::avoid_text(&img, &cells)
+                    });
+                }
+                if let Some(split) = cli.ruled_lines {
+                    cells = settings
+                        .stats
+                        .time(Stage::Split, split_time, || lines::ruled_cells(&img, split));
+                }
+                if cli.detect_grid {
+                    cells = settings
+                        .stats
+                        .time(Stage::Split, split_time, || detect::detect_grid(&img));
+                }
+                if let Some((crops, seed)) = random_crops {
+                    let mut rng = Rng::new(seed.wrapping_add(index as u64));
+                    cells = crops.cells((width, height), &mut rng);
+                }
+                if let Some(regions) = &regions {
+                    cells = regions.cells(cells, cli.region_mode, (width, height));
+                }
+                if let Some(align) = cli.align {
+                    cells = align::align(&cells, (width, height), (align, align), (0, 0));
+                }
+                if let Some(grid) = mcu.filter(|_| level == 0) {
+                    cells = align::align(
+                        &cells,
+                        (width, height),
+                        (grid.mcu_width, grid.mcu_height),
+                        (grid.x_origin, grid.y_origin),
+                    );
+                }
+                if cli.rtl {
+                    cells = grid::right_to_left(cells);
+                }
+                let img_file_name = &stems[index];
+                let (img_format, img_format_str) = &formats[index];
+
+                // Drawn once for each frame, level, and grid, before the image is cut into channels.
+                if cli.emit_grid_overlay {
+                    let mut name = img_file_name.clone();
+                    if matches!(cli.frame, Some(FrameSelection::All)) {
+                        name = format!("{}-f{}", name, frame);
+                    }
+                    if cli.levels.is_some() {
+                        name = format!("{}-l{}", name, level);
+                    }
+                    name = format!("{}-grid.png", name);
+                    if !grid.is_empty() {
+                        name = format!("{}/{}", grid, name);
+                    }
+                    let overlay = overlay::draw_grid(&img, &cells);
+                    let result =
+                        encode::encode(&overlay, ImageFormat::Png, &EncodeOptions::default())
+                            .map_err(io::Error::other)
+                            .and_then(|bytes| {
+                                settings.write(
+                                    PathBuf::from(&name),
+                                    bytes,
+                                    "image/png",
+                                    attrs,
+                                    None,
+                                )
+                            });
+                    if let Err(err) = result {
+                        eprintln!("splix: Failed to save grid overlay {}: {}", name, err);
+                        settings.policy.record(Some(err.kind()));
+                    }
+                }
+
+                let source = SourceInfo {
+                    path,
+                    stem: img_file_name,
+                    format: *img_format,
+                    ext: img_format_str,
+                    index,
+                    frame,
+                    zoom: 0,
+                    grid,
+                    level,
+                    channel: "",
+                    mask: decoded.mask.as_ref(),
+                    preset,
+                    attrs,
+                    times: &times[index],
+                };
+
+                if let (Some(scheme), Some(pyramids)) = (cli.map_tiles, &pyramids) {
+                    let max_zoom = maptiles::max_zoom(width, height);
+                    let alpha = maptiles::keeps_alpha(*img_format);
+                    for zoom in 0..=max_zoom {
+                        let (level, (width, height)) =
+                            settings.stats.time(Stage::Split, split_time, || {
+                                maptiles::level(&img, zoom, max_zoom, scheme, resampler, alpha)
+                            });
+                        let mut cells = layout.cells(level.width(), level.height());
+                        maptiles::number_rows(&mut cells, scheme);
+                        let tiles =
+                            save_images(&level, &cells, &settings, &SourceInfo { zoom, ..source });
+
+                        manifest.push(SourceEntry {
+                            path: path.clone(),
+                            width,
+                            height,
+                            frame: cli.frame.map(|_| frame),
+                            zoom: Some(zoom),
+                            grid: None,
+                            level: None,
+                            channel: None,
+                            sha256: sha256.clone(),
+                            downscaled: downscaled.lock().unwrap().get(&paths[index]).copied(),
+                            duplicate_of: None,
+                            tiles,
+                        });
+                    }
+
+                    let (dir, url) = &pyramids[index];
+                    let title = path.file_name().unwrap_or_default().to_string_lossy();
+                    let mut files = vec![(
+                        maptiles::TILE_MAP_RESOURCE,
+                        maptiles::tile_map_resource(
+                            &title,
+                            (width, height),
+                            max_zoom,
+                            (img_format_str, img_format.to_mime_type()),
+                        ),
+                        "text/xml",
+                    )];
+                    if cli.viewer {
+                        files.push((
+                            maptiles::VIEWER,
+                            maptiles::viewer_html(&title, (width, height), max_zoom, scheme, url),
+                            "text/html",
+                        ));
+                    }
+
+                    for (name, contents, content_type) in files {
+                        let name = dir.join(name);
+                        if let Err(err) = settings.write(
+                            name.clone(),
+                            contents.into_bytes(),
+                            content_type,
+                            attrs,
+                            None,
+                        ) {
+                            eprintln!("splix: Failed to save {}: {}", name.display(), err);
+                            settings.policy.record(Some(err.kind()));
+                        }
+                    }
+                    continue;
+                }
+
+                let planes = match &planes {
+                    Some(planes) => planes.iter().map(|(name, plane)| (*name, plane)).collect(),
+                    None => vec![("", &img)],
+                };
+                for (channel, plane) in planes {
+                    let tiles =
+                        save_images(plane, &cells, &settings, &SourceInfo { channel, ..source });
+
+                    manifest.push(SourceEntry {
+                        path: path.clone(),
+                        width,
+                        height,
+                        frame: cli.frame.map(|_| frame),
+                        zoom: None,
+                        grid: Some(grid.clone()).filter(|grid| !grid.is_empty()),
+                        level: cli.levels.map(|_| level),
+                        channel: Some(channel.to_string()).filter(|channel| !channel.is_empty()),
+                        sha256: sha256.clone(),
+                        downscaled: downscaled.lock().unwrap().get(&paths[index]).copied(),
+                        duplicate_of: None,
+                        tiles,
+                    });
+                }
+            }
+        }
+    };
+
+    let next = AtomicUsize::new(0);
+    interrupt::install();
+    thread::scope(|scope| {
+        for _ in 0..write_jobs {
+            scope.spawn(|| settings.run_writes());
+        }
+
+        // The split stage stops once every decoder has finished and the frames they queued are split.
+        let closer = Arc::new(decoded.closer());
+        for _ in 0..decode_jobs {
+            let (closer, next, decode, paths) = (Arc::clone(&closer), &next, &decode, &paths);
+            scope.spawn(move || {
+                let _closer = closer;
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= paths.len() {
+                        break;
+                    }
+                    decode(index);
+                }
+            });
+        }
+        drop(closer);
+
+        // Frames are taken from the queue on this thread and split on the rayon pool, so no pool thread waits
+        // on the decoders. Only as many are split at once as the pool has threads, so the queue stays bounded.
+        let _writes = settings.pending.closer();
+        let splitting = Semaphore::new(rayon::current_num_threads());
+        let split = &split;
+        rayon::in_place_scope(|splits| loop {
+            let permit = splitting.acquire();
+            let Some(frame) = decoded.recv() else {
+                break;
             };
+            splits.spawn(move |_| {
+                let _permit = permit;
+                split(frame);
+            });
+        });
+    });
+
+    let unwritten = settings.unwritten.into_inner().unwrap();
+    if !unwritten.is_empty() {
+        manifest.forget_files(&unwritten);
+    }
 
-            let sub_img = img.crop(x, y, crop_width, crop_height);
-            split_images.push(sub_img);
+    #[cfg(feature = "async")]
+    if let Some(writes) = settings.writes {
+        for err in writes.finish() {
+            eprintln!("{}", err);
+            settings.policy.record(Some(err.kind()));
         }
     }
 
-    split_images
-}
+    if let Some(dedupe) = settings.dedupe {
+        dedupe.finish();
+    }
 
-/// Saves the split images to the specified directory.
-///
-/// # Arguments
-///
-/// * `split_images` - A reference to a vector containing the split images.
-/// * `output_directory` - Path to the directory where split images will be saved.
-/// * `img_file_name` - Path of image excluding parent directories and extension.
-/// * `img_format` - Format of the image.
-/// * `img_format_str` - String representation of the image format.
-/// * `num_rows` - Number of rows the image was split into.
-/// * `num_cols` _ Number of columns the image was split into.
-fn save_images(
-    split_images: &Vec<DynamicImage>,
-    output_directory: PathBuf,
-    img_file_name: &str,
-    img_format: &ImageFormat,
-    img_format_str: &str,
-    num_cols: usize,
-) {
-    if !output_directory.exists() {
-        if let Err(err) = fs::create_dir_all(&output_directory) {
+    if let Some(mode) = cli.dedupe_sources {
+        let copies: Vec<SourceEntry> = manifest
+            .sources()
+            .iter()
+            .flat_map(|source| {
+                duplicates
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, original)| {
+                        original.is_some_and(|original| sources[original] == source.path)
+                    })
+                    .map(move |(index, _)| (index, source))
+            })
+            .map(|(index, source)| {
+                let tiles = source
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, tile)| {
+                        let Some(original) = tile.file.as_ref() else {
+                            return tile.clone();
+                        };
+                        let name = settings.name_template.render(&TileName {
+                            stem: &stems[index],
+                            ext: tile.format.as_deref().unwrap_or(formats[index].1.as_str()),
+                            row: tile.row,
+                            col: tile.col,
+                            index: i,
+                            source_index: index,
+                            frame: source.frame.unwrap_or(0),
+                            zoom: source.zoom.unwrap_or(0),
+                            grid: source.grid.as_deref().unwrap_or(""),
+                            level: source.level.unwrap_or(0),
+                            channel: source.channel.as_deref().unwrap_or(""),
+                        });
+                        let file = settings.output.location(&name);
+                        let written =
+                            mode.writes_duplicates() && dedupe::link(original, &file, mode);
+                        if mode.writes_duplicates() && !written {
+                            settings.policy.record(None);
+                        }
+                        TileEntry {
+                            file: written.then_some(file),
+                            duplicate_of: Some(original.to_path_buf()),
+                            ..tile.clone()
+                        }
+                    })
+                    .collect();
+                SourceEntry {
+                    path: sources[index].clone(),
+                    duplicate_of: Some(source.path.clone()),
+                    tiles,
+                    ..source.clone()
+                }
+            })
+            .collect();
+
+        let count = duplicates
+            .iter()
+            .filter(|original| original.is_some())
+            .count();
+        if count > 0 {
             eprintln!(
-                "splix: Failed to create directory {}: {}",
-                output_directory.to_string_lossy(),
+                "splix: {} image{} duplicated an earlier one and {} split again",
+                count,
+                if count == 1 { "" } else { "s" },
+                if count == 1 { "wasn't" } else { "weren't" }
+            );
+        }
+        for copy in copies {
+            manifest.push(copy);
+        }
+    }
+
+    if let Some(cache) = settings.cache {
+        if !unwritten.is_empty() {
+            cache.forget_files(&unwritten);
+        }
+        if let Err(err) = cache.write() {
+            eprintln!(
+                "splix: update: Failed to write {}: {}",
+                cache::CACHE_FILE,
                 err
             );
+            settings.policy.record(Some(err.kind()));
+        }
+    }
+
+    if let Some(clipboard) = settings.clipboard {
+        if let Err(err) = clipboard.copy() {
+            eprintln!("{}", err);
+            settings.policy.record(None);
         }
     }
 
-    let output_directory = output_directory;
+    if let Some(scope) = cli.dicom_tags {
+        for (path, stem) in paths.iter().zip(&stems) {
+            if !dicom::is_dicom(path) {
+                continue;
+            }
+            let name = PathBuf::from(format!("{}.dicom.json", stem));
+            let written = dicom::tags(path, scope == DicomTags::All)
+                .map_err(io::Error::other)
+                .and_then(|json| {
+                    settings.output.write(
+                        &name,
+                        json.as_bytes(),
+                        "application/json",
+                        &TileAttrs::default(),
+                    )
+                });
+            if let Err(err) = written {
+                eprintln!("splix: Failed to write {}: {}", name.display(), err);
+                settings.policy.record(Some(err.kind()));
+            }
+        }
+    }
 
-    split_images.par_iter().enumerate().for_each(|(i, image)| {
-        let file_name = format!(
-            "{}-r{}c{}.{}",
-            img_file_name,
-            i / num_cols,
-            i % num_cols,
-            img_format_str
-        );
+    if let Some(dataset) = settings.dataset {
+        let finished = dataset.finish().and_then(|files| {
+            files.into_iter().try_for_each(|(name, bytes)| {
+                let content_type = match name.extension() {
+                    Some(ext) if ext == "tar" => "application/x-tar",
+                    _ => "application/json",
+                };
+                settings.stats.wrote(bytes.len() as u64, false);
+                settings
+                    .output
+                    .write(&name, &bytes, content_type, &TileAttrs::default())
+                    .map_err(|err| {
+                        io::Error::new(err.kind(), format!("{}: {}", name.display(), err))
+                    })
+            })
+        });
+        if let Err(err) = finished {
+            eprintln!("splix: Failed to finish the dataset: {}", err);
+            settings.policy.record(Some(err.kind()));
+        }
+    }
 
-        let file_path = &output_directory.join(file_name);
+    if cli.name_by_hash.is_some() {
+        let written = manifest.hash_map().and_then(|json| {
+            settings.output.write(
+                Path::new(HASH_MAP),
+                &json,
+                "application/json",
+                &TileAttrs::default(),
+            )
+        });
+        if let Err(err) = written {
+            eprintln!("splix: Failed to write {}: {}", HASH_MAP, err);
+            settings.policy.record(Some(err.kind()));
+        }
+    }
 
-        if file_path.exists() {
-            if let Err(err) = fs::remove_file(&file_path) {
-                eprintln!(
-                    "Failed to remove existing image {}: {}",
-                    file_path.file_stem().unwrap().to_string_lossy(),
-                    err
-                );
-                return;
+    if cli.sprite_css.is_some() || cli.engine_meta.is_some() {
+        let sources = manifest.sources();
+        let output_dir = settings.output.local_dir();
+        let sheets = export::sheets(&sources, output_dir, !cli.no_auto_orient);
+
+        let mut files = Vec::new();
+        if let Some(format) = cli.sprite_css {
+            files.push((
+                PathBuf::from(format.file_name()),
+                export::sprite_css(&sheets, format),
+                "text/css",
+            ));
+            files.push((
+                PathBuf::from(export::SPRITES_DEMO),
+                export::sprite_demo(&sheets),
+                "text/html",
+            ));
+        }
+        if let Some(format) = cli.engine_meta {
+            let content_type = match format {
+                EngineFormat::Godot => "text/plain",
+                EngineFormat::Unity => "application/json",
+            };
+            match export::engine_meta(&sheets, output_dir, format) {
+                Ok(meta) => files.extend(
+                    meta.into_iter()
+                        .map(|(name, contents)| (name, contents, content_type)),
+                ),
+                Err(err) => {
+                    eprintln!("splix: engine-meta: Failed to write the metadata: {}", err);
+                    settings.policy.record(None);
+                }
             }
         }
 
-        if let Err(err) = image.save_with_format(&file_path, *img_format) {
+        for (name, contents, content_type) in files {
+            if let Err(err) = settings.output.write(
+                &name,
+                contents.as_bytes(),
+                content_type,
+                &TileAttrs::default(),
+            ) {
+                eprintln!("splix: Failed to write {}: {}", name.display(), err);
+                settings.policy.record(Some(err.kind()));
+            }
+        }
+    }
+
+    if let Some(layout) = cli.html {
+        let html =
+            export::reassembly_html(&manifest.sources(), settings.output.local_dir(), layout);
+        if let Err(err) = settings.output.write(
+            Path::new(export::TILES_HTML),
+            html.as_bytes(),
+            "text/html",
+            &TileAttrs::default(),
+        ) {
+            eprintln!("splix: Failed to write {}: {}", export::TILES_HTML, err);
+            settings.policy.record(Some(err.kind()));
+        }
+    }
+
+    let output_dir = settings.output.local_dir().map(Path::to_path_buf);
+    if let Err(err) = Arc::into_inner(settings.output).map_or(Ok(()), Output::finish) {
+        eprintln!("splix: Failed to finish writing the output: {}", err);
+        settings.policy.record(Some(err.kind()));
+    }
+
+    let skipped = skipped.into_sorted();
+    report::print_skipped(&skipped);
+
+    if let Some(report_path) = &cli.skipped_report {
+        if let Err(err) = report::write_skipped(report_path, &skipped) {
             eprintln!(
-                "splix: Failed to save image {}: {}",
-                file_path.file_stem().unwrap().to_string_lossy(),
+                "splix: Failed to write skipped file report {}: {}",
+                report_path.display(),
                 err
             );
+            settings.policy.record(Some(err.kind()));
         }
-    });
-}
+    }
 
-fn main() {
-    let cli = Cli::parse();
+    if let Some(manifest_path) = &cli.manifest {
+        if let Err(err) = manifest.write(manifest_path, cli.seed) {
+            eprintln!(
+                "splix: Failed to write manifest {}: {}",
+                manifest_path.display(),
+                err
+            );
+            settings.policy.record(Some(err.kind()));
+        }
+    }
 
-    if let Err(err) = validate_args(&cli) {
-        eprintln!("{}", err);
-        return;
+    if let Some(progress) = &settings.progress {
+        progress.finish(settings.policy.errors());
     }
 
-    let img_dir = cli.images;
-    let rows = cli.rows.unwrap_or(vec![1]);
-    let cols = cli.cols.unwrap_or(vec![1]);
-    let output_directory = cli.output_dir.unwrap_or(PathBuf::from("splixed-images"));
+    if let Some(profile_path) = &cli.profile {
+        let files: Vec<(&Path, &FileTimes)> =
+            sources.iter().map(PathBuf::as_path).zip(&times).collect();
+        if let Err(err) = stats::write_profile(profile_path, &files) {
+            eprintln!(
+                "splix: Failed to write profile {}: {}",
+                profile_path.display(),
+                err
+            );
+            settings.policy.record(Some(err.kind()));
+        }
+    }
 
-    WalkDir::new(&img_dir)
-        .max_depth(if cli.recursive { usize::MAX } else { 1 })
-        .into_iter()
-        .par_bridge()
-        .filter_map(|entry| entry.ok().filter(|entry| entry.path().is_file()))
-        .for_each(|entry| {
-            if let Ok(img) = image::open(entry.path()) {
-                let split_images = split_image(img, &rows, &cols);
-                let img_file_name = &entry.path().file_stem().unwrap().to_string_lossy();
-                let img_format = &ImageFormat::from_path(entry.path()).unwrap();
-                let img_format_str = img_format.extensions_str()[0];
-
-                save_images(
-                    &split_images,
-                    output_directory.clone(),
-                    img_file_name,
-                    img_format,
-                    img_format_str,
-                    if cols.len() > 1 {
-                        cols.len()
-                    } else {
-                        cols[0] as usize
-                    },
-                );
+    let summary = settings.stats.summary(started.elapsed());
+    summary.print();
+    if let Some(stats_path) = &cli.stats {
+        if let Err(err) = summary.write(stats_path) {
+            eprintln!(
+                "splix: Failed to write statistics {}: {}",
+                stats_path.display(),
+                err
+            );
+            settings.policy.record(Some(err.kind()));
+        }
+    }
+
+    if let Some(journal) = settings.journal {
+        if interrupt::interrupted() || settings.policy.errors() > 0 {
+            eprintln!("{}", i18n::message("resume-hint", &[]));
+        } else if let Err(err) = journal.finish() {
+            eprintln!(
+                "splix: resume: Failed to remove {}: {}",
+                journal::JOURNAL_FILE,
+                err
+            );
+            settings.policy.record(Some(err.kind()));
+        }
+    }
+
+    if interrupt::interrupted() {
+        eprintln!("{}", i18n::message("interrupted", &[]));
+        return ExitCode::from(interrupt::EXIT_CODE);
+    }
+
+    if settings.policy.stopped() {
+        eprintln!("{}", i18n::message("stopped-early", &[]));
+    }
+
+    if settings.policy.errors() > 0 {
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(output_dir) = output_dir.filter(|_| cli.open) {
+        let preview = match &pyramids {
+            Some(pyramids) if cli.viewer => {
+                pyramids.first().map(|(dir, _)| dir.join(maptiles::VIEWER))
             }
-        });
+            _ if cli.html.is_some() => Some(PathBuf::from(export::TILES_HTML)),
+            _ if cli.sprite_css.is_some() => Some(PathBuf::from(export::SPRITES_DEMO)),
+            _ => None,
+        };
+        let path = preview.map_or(output_dir.clone(), |preview| output_dir.join(preview));
+        if let Err(err) = launch::open(&path) {
+            eprintln!("{}", err);
+        }
+    }
+
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Name of the file relating tiles named by their hash to where they came from, written with `--name-by-hash`.
+pub const HASH_MAP: &str = "hashes.json";
+
+/// A record of every source image processed in a run and the tiles it produced.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Manifest {
+    /// Seed of the run's random choices, given with `--seed` or picked for them, to make them again with `--seed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    pub sources: Vec<SourceEntry>,
+}
+
+impl Manifest {
+    /// Reads a manifest written by an earlier run.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the manifest file.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+/// A source image and the tiles that were split from it.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SourceEntry {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// Frame of an animated image the tiles were split from, if `--frame` was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<usize>,
+    /// Zoom level the tiles were split at, if `--map-tiles` was given.
+    /// The width and height are then those of the image scaled to that level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zoom: Option<u32>,
+    /// Grid of `--grid` the tiles were split into, such as `4x4`, if it was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid: Option<String>,
+    /// Resolution the tiles were split at, from 0 for full size, if `--levels` was given.
+    /// The width and height are then those of the image scaled to that level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<u32>,
+    /// Plane the tiles were cut from, such as `red`, if `--channels` was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// SHA-256 of the source file as hex, to check it hasn't changed since it was split.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// How many times smaller on each side the image was split, if `--downscale-oversized` had to shrink it to decode it.
+    /// The width and height are then those of the smaller image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downscaled: Option<u32>,
+    /// Path of an identical source file split earlier in the run, if `--dedupe-sources` found this one to be a copy.
+    /// Its tiles are then linked or copied from the earlier file's tiles, rather than split again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<PathBuf>,
+    pub tiles: Vec<TileEntry>,
+}
+
+/// A single tile split from a source image.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct TileEntry {
+    pub row: usize,
+    pub col: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Path the tile was written to, or `None` if it was not written.
+    pub file: Option<PathBuf>,
+    /// Path of the first identical tile, if this tile is a duplicate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<PathBuf>,
+    /// SHA-256 of the tile's file as hex, to check it hasn't been corrupted since it was written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Extension of the format chosen for the tile by `--ext auto`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Percentage of the tile marked as foreground by `--mask`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<f32>,
+    /// What the tile contains, if `--tile-stats` was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<TileStats>,
+}
+
+/// What a tile contains, measured from its source pixels, so tiles can be filtered or ordered without reading them.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct TileStats {
+    /// Average brightness, from 0 to 255.
+    pub mean: f32,
+    /// Standard deviation of the brightness, which is 0 for a flat tile.
+    pub stddev: f32,
+    /// Shannon entropy of the brightness in bits, from 0 for a flat tile to 8 for noise.
+    pub entropy: f32,
+    /// The most common color as `#rrggbb`, averaged over the pixels near it.
+    pub dominant_color: String,
+    /// Fraction of the pixels near the most common color, from near 0 for a busy tile to 1 for a blank one.
+    pub blankness: f32,
+}
+
+/// A tile written with `--name-by-hash`, in `hashes.json`.
+#[derive(Serialize)]
+struct HashEntry<'a> {
+    sha256: &'a str,
+    file: &'a Path,
+    source: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grid: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    level: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<&'a str>,
+    row: usize,
+    col: usize,
+}
+
+/// Hashes bytes with SHA-256.
+///
+/// # Returns
+///
+/// The hash as lowercase hex.
+pub fn sha256(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// Hashes a file with SHA-256, reading it a piece at a time.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file.
+///
+/// # Returns
+///
+/// The hash as lowercase hex, or the error that kept the file from being read.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Formats bytes as lowercase hex.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Collects manifest entries from parallel workers.
+#[derive(Default)]
+pub struct ManifestBuilder {
+    sources: Mutex<Vec<SourceEntry>>,
+}
+
+impl ManifestBuilder {
+    /// Adds a processed source image to the manifest.
+    pub fn push(&self, source: SourceEntry) {
+        self.sources.lock().unwrap().push(source);
+    }
+
+    /// Every source image added so far.
+    pub fn sources(&self) -> MutexGuard<'_, Vec<SourceEntry>> {
+        self.sources.lock().unwrap()
+    }
+
+    /// Clears the file of tiles that failed to be written, so the manifest only lists files that exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - Where the tiles would have been written.
+    pub fn forget_files(&self, files: &HashSet<PathBuf>) {
+        let mut sources = self.sources.lock().unwrap();
+        for tile in sources.iter_mut().flat_map(|source| &mut source.tiles) {
+            if tile.file.as_ref().is_some_and(|file| files.contains(file)) {
+                tile.file = None;
+                tile.sha256 = None;
+            }
+        }
+    }
+
+    /// Relates the hash of every written tile to the source image and grid position it came from,
+    /// ordered by hash, so identical tiles are listed together.
+    ///
+    /// # Returns
+    ///
+    /// The contents of `hashes.json`.
+    pub fn hash_map(&self) -> io::Result<Vec<u8>> {
+        let sources = self.sources.lock().unwrap();
+        let mut entries: Vec<HashEntry> = sources
+            .iter()
+            .flat_map(|source| {
+                source.tiles.iter().filter_map(move |tile| {
+                    Some(HashEntry {
+                        sha256: tile.sha256.as_deref()?,
+                        file: tile.file.as_deref()?,
+                        source: &source.path,
+                        frame: source.frame,
+                        grid: source.grid.as_deref(),
+                        level: source.level,
+                        channel: source.channel.as_deref(),
+                        row: tile.row,
+                        col: tile.col,
+                    })
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            (
+                a.sha256, a.source, a.frame, a.grid, a.level, a.channel, a.row, a.col,
+            )
+                .cmp(&(
+                    b.sha256, b.source, b.frame, b.grid, b.level, b.channel, b.row, b.col,
+                ))
+        });
+
+        let mut json = serde_json::to_vec_pretty(&entries)?;
+        json.push(b'\n');
+        Ok(json)
+    }
+
+    /// Writes the collected entries as JSON, ordered by source path, frame, zoom level, grid, and level.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the manifest file to write.
+    /// * `seed` - Seed of the run's random choices, if it was given or picked.
+    pub fn write(self, path: &Path, seed: Option<u64>) -> io::Result<()> {
+        let mut sources = self.sources.into_inner().unwrap();
+        sources.sort_by(|a, b| {
+            (&a.path, a.frame, a.zoom, &a.grid, a.level, &a.channel)
+                .cmp(&(&b.path, b.frame, b.zoom, &b.grid, b.level, &b.channel))
+        });
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, &Manifest { seed, sources })?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+}
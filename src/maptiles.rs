@@ -0,0 +1,283 @@
+use crate::marks;
+use crate::resize::{self, Resampler};
+use clap::ValueEnum;
+use image::{DynamicImage, ImageFormat};
+use splix::grid::Cell;
+
+/// Width and height of map tiles in pixels, the same as gdal2tiles.
+pub const TILE_SIZE: u32 = 256;
+
+/// The naming template used for map tiles when `--name` isn't specified, the same layout as gdal2tiles.
+pub const MAP_TEMPLATE: &str = "{z}/{x}/{y}.{ext}";
+
+/// Name of the file describing the tiles' bounds and zoom levels.
+pub const TILE_MAP_RESOURCE: &str = "tilemapresource.xml";
+
+/// Name of the page showing the tiles in a zoomable map.
+pub const VIEWER: &str = "viewer.html";
+
+/// How map tile rows are numbered.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TileScheme {
+    /// From the bottom of the image, as in the Tile Map Service spec. The default of gdal2tiles.
+    Tms,
+    /// From the top of the image, as in OpenStreetMap, Leaflet, and Google Maps. Same as `gdal2tiles --xyz`.
+    Xyz,
+}
+
+/// The highest zoom level of an image, at which it's tiled at full size.
+/// Each level below halves the image, down to level 0, where it fits in a single tile.
+///
+/// # Arguments
+///
+/// * `width` - Width of the image.
+/// * `height` - Height of the image.
+pub fn max_zoom(width: u32, height: u32) -> u32 {
+    let mut zoom = 0;
+    while width.max(height).div_ceil(1 << zoom) > TILE_SIZE {
+        zoom += 1;
+    }
+    zoom
+}
+
+/// Scales an image down to a zoom level, then pads it so it divides evenly into tiles,
+/// with transparency where the format keeps it. Like gdal2tiles, the tiles line up with the image's
+/// bottom left corner for TMS and its top left corner for XYZ, so the padding is on the opposite edges.
+///
+/// # Arguments
+///
+/// * `img` - Image at full size.
+/// * `zoom` - Zoom level to scale to.
+/// * `max_zoom` - Zoom level of the image at full size.
+/// * `scheme` - How tile rows are numbered.
+/// * `resampler` - How the image is resampled when scaling.
+/// * `alpha` - Whether the tiles are saved in a format with an alpha channel.
+///
+/// # Returns
+///
+/// The image at the zoom level, and its size before padding.
+pub fn level(
+    img: &DynamicImage,
+    zoom: u32,
+    max_zoom: u32,
+    scheme: TileScheme,
+    resampler: Resampler,
+    alpha: bool,
+) -> (DynamicImage, (u32, u32)) {
+    let scale = 1 << (max_zoom - zoom);
+    let (width, height) = (img.width().div_ceil(scale), img.height().div_ceil(scale));
+    let scaled = if scale == 1 {
+        img.clone()
+    } else {
+        resampler.resize_exact(img, width, height)
+    };
+
+    let (padded_width, padded_height) = padded_size((width, height));
+    if (padded_width, padded_height) == (width, height) {
+        return (scaled, (width, height));
+    }
+
+    let color = if alpha {
+        resize::with_alpha(scaled.color())
+    } else {
+        scaled.color()
+    };
+    let mut padded = DynamicImage::new(padded_width, padded_height, color);
+    let scaled = resize::to_color_type(scaled, color);
+    let top = match scheme {
+        TileScheme::Tms => padded_height - height,
+        TileScheme::Xyz => 0,
+    };
+    marks::copy_tile(&mut padded, &scaled, 0, top);
+
+    (padded, (width, height))
+}
+
+/// Whether tiles saved in a format keep an alpha channel, so padding can be transparent.
+pub fn keeps_alpha(format: ImageFormat) -> bool {
+    !matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Pnm | ImageFormat::Hdr
+    )
+}
+
+/// Finds the size of an image scaled to a zoom level, without scaling it.
+///
+/// # Arguments
+///
+/// * `(width, height)` - Size of the image at full size.
+/// * `zoom` - Zoom level to scale to.
+/// * `max_zoom` - Zoom level of the image at full size.
+///
+/// # Returns
+///
+/// The size of the image at the zoom level once padded to whole tiles.
+pub fn level_size((width, height): (u32, u32), zoom: u32, max_zoom: u32) -> (u32, u32) {
+    let scale = 1 << (max_zoom - zoom);
+    padded_size((width.div_ceil(scale), height.div_ceil(scale)))
+}
+
+/// Rounds a size up to whole tiles.
+fn padded_size((width, height): (u32, u32)) -> (u32, u32) {
+    (
+        width.div_ceil(TILE_SIZE) * TILE_SIZE,
+        height.div_ceil(TILE_SIZE) * TILE_SIZE,
+    )
+}
+
+/// Renumbers tile rows for a scheme. Cells are numbered from the top, as for XYZ tiles.
+///
+/// # Arguments
+///
+/// * `cells` - Tiles of a zoom level.
+/// * `scheme` - How rows should be numbered.
+pub fn number_rows(cells: &mut [Cell], scheme: TileScheme) {
+    let rows = cells.iter().map(|cell| cell.row + 1).max().unwrap_or(0);
+    if let TileScheme::Tms = scheme {
+        for cell in cells {
+            cell.row = rows - 1 - cell.row;
+        }
+    }
+}
+
+/// Describes an image's map tiles the way gdal2tiles does for images without geographic coordinates,
+/// measuring bounds in pixels of the full-size image, with y running down from 0 at the top edge.
+///
+/// # Arguments
+///
+/// * `title` - Title of the tile map, such as the image's file name.
+/// * `(width, height)` - Size of the image.
+/// * `max_zoom` - Zoom level of the image at full size.
+/// * `(ext, mime_type)` - Extension and MIME type of the tiles.
+///
+/// # Returns
+///
+/// The contents of `tilemapresource.xml`.
+pub fn tile_map_resource(
+    title: &str,
+    (width, height): (u32, u32),
+    max_zoom: u32,
+    (ext, mime_type): (&str, &str),
+) -> String {
+    let mut xml = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<TileMap version=\"1.0.0\" tilemapservice=\"http://tms.osgeo.org/1.0.0\">\n",
+            "  <Title>{}</Title>\n",
+            "  <Abstract></Abstract>\n",
+            "  <SRS></SRS>\n",
+            "  <BoundingBox minx=\"{:.14}\" miny=\"{:.14}\" maxx=\"{:.14}\" maxy=\"{:.14}\"/>\n",
+            "  <Origin x=\"{:.14}\" y=\"{:.14}\"/>\n",
+            "  <TileFormat width=\"{}\" height=\"{}\" mime-type=\"{}\" extension=\"{}\"/>\n",
+            "  <TileSets profile=\"raster\">\n",
+        ),
+        escape_xml(title),
+        0.0,
+        -(height as f64),
+        width as f64,
+        0.0,
+        0.0,
+        -(height as f64),
+        TILE_SIZE,
+        TILE_SIZE,
+        mime_type,
+        ext,
+    );
+
+    for zoom in 0..=max_zoom {
+        xml.push_str(&format!(
+            "    <TileSet href=\"{}\" units-per-pixel=\"{:.14}\" order=\"{}\"/>\n",
+            zoom,
+            (1u64 << (max_zoom - zoom)) as f64,
+            zoom
+        ));
+    }
+
+    xml.push_str("  </TileSets>\n</TileMap>\n");
+    xml
+}
+
+/// Builds a page that shows an image's map tiles in a zoomable Leaflet map,
+/// loading Leaflet from unpkg.com so it works without any other files.
+///
+/// # Arguments
+///
+/// * `title` - Title of the page, such as the image's file name.
+/// * `(width, height)` - Size of the image.
+/// * `max_zoom` - Zoom level of the image at full size.
+/// * `scheme` - How tile rows are numbered.
+/// * `url` - Path of the tiles relative to the page, with `{z}`, `{x}`, and `{y}` placeholders.
+///
+/// # Returns
+///
+/// The contents of `viewer.html`.
+pub fn viewer_html(
+    title: &str,
+    (width, height): (u32, u32),
+    max_zoom: u32,
+    scheme: TileScheme,
+    url: &str,
+) -> String {
+    // Map y runs up from the bottom of TMS tiles and down from the top of XYZ tiles,
+    // so Leaflet's rows above 0 are TMS rows counted from the bottom.
+    let (top, row) = match scheme {
+        TileScheme::Tms => (-(height as i64), "-coords.y - 1"),
+        TileScheme::Xyz => (0, "coords.y"),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>{title}</title>
+  <link rel="stylesheet" href="https://unpkg.com/leaflet@1.9.4/dist/leaflet.css">
+  <script src="https://unpkg.com/leaflet@1.9.4/dist/leaflet.js"></script>
+  <style>html, body, #map {{ height: 100%; margin: 0; background: #222; }}</style>
+</head>
+<body>
+  <div id="map"></div>
+  <script>
+    var maxZoom = {max_zoom};
+    var map = L.map("map", {{ crs: L.CRS.Simple, minZoom: 0, maxZoom: maxZoom + 2 }});
+    var bounds = L.latLngBounds(
+      map.unproject([0, {bottom}], maxZoom),
+      map.unproject([{width}, {top}], maxZoom)
+    );
+    var Tiles = L.TileLayer.extend({{
+      getTileUrl: function (coords) {{
+        return L.Util.template(this._url, {{ z: coords.z, x: coords.x, y: {row} }});
+      }}
+    }});
+    new Tiles({url}, {{
+      tileSize: {tile_size},
+      minZoom: 0,
+      maxZoom: maxZoom + 2,
+      maxNativeZoom: maxZoom,
+      bounds: bounds,
+      noWrap: true
+    }}).addTo(map);
+    map.fitBounds(bounds);
+  </script>
+</body>
+</html>
+"#,
+        title = escape_xml(title),
+        max_zoom = max_zoom,
+        bottom = top + height as i64,
+        width = width,
+        top = top,
+        row = row,
+        url = serde_json::to_string(url).unwrap(),
+        tile_size = TILE_SIZE,
+    )
+}
+
+/// Escapes text for use in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,216 @@
+use crate::font::{self, GLYPH_HEIGHT};
+use crate::poster::Poster;
+use image::{DynamicImage, GenericImage, Rgba};
+use splix::grid::Cell;
+
+/// Width of the blank margin around each page with `--poster-marks`, in millimetres.
+pub const MARGIN_MM: f64 = 10.0;
+
+/// Color of crop marks and labels.
+const INK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+/// Color of overlap guides, lighter so they aren't mistaken for crop marks.
+const GUIDE: Rgba<u8> = Rgba([128, 128, 128, 255]);
+/// Color of the margin.
+const PAPER: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Crop marks, overlap guides, and labels drawn in a margin around each poster page.
+pub struct PageMarks {
+    /// Width of the margin in pixels.
+    margin: u32,
+    /// Width of the overlap shared with neighbouring pages in pixels.
+    overlap: u32,
+    /// Thickness of marks in pixels.
+    line: u32,
+    /// Size of each font pixel of the label in pixels.
+    scale: u32,
+}
+
+impl PageMarks {
+    /// # Arguments
+    ///
+    /// * `poster` - Page size and resolution.
+    /// * `overlap` - Width of the overlap shared with neighbouring pages in pixels.
+    pub fn new(poster: &Poster, overlap: u32) -> Self {
+        let margin = poster.pixels(MARGIN_MM);
+
+        PageMarks {
+            margin,
+            overlap,
+            line: poster.pixels(0.25).max(1),
+            scale: (margin * 3 / 10 / GLYPH_HEIGHT).max(1),
+        }
+    }
+
+    /// Width of the margin in pixels, on each side of the page.
+    pub fn margin(&self) -> u32 {
+        self.margin
+    }
+
+    /// Places a tile on a page with a margin holding the marks.
+    /// The page keeps the tile's color type, so it can be encoded in the same format.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` - Tile cut from the image.
+    /// * `cell` - Cell the tile was cut from.
+    /// * `(rows, cols)` - Number of rows and columns of pages.
+    ///
+    /// # Returns
+    ///
+    /// The page.
+    pub fn decorate(
+        &self,
+        tile: &DynamicImage,
+        cell: &Cell,
+        (rows, cols): (usize, usize),
+    ) -> DynamicImage {
+        let m = self.margin;
+        let (width, height) = (tile.width(), tile.height());
+        let (page_width, page_height) = (width + 2 * m, height + 2 * m);
+        let mut page = DynamicImage::new(page_width, page_height, tile.color());
+        font::fill_rect(&mut page, 0, 0, page_width, page_height, PAPER);
+        copy_tile(&mut page, tile, m, m);
+
+        // Marks stay in the inner half of the margin, leaving the outer part for the label.
+        let gap = m / 10;
+        let length = m * 4 / 10;
+        let near = m - gap - length;
+        let far_x = m + width + gap;
+        let far_y = m + height + gap;
+
+        for x in [m, m + width] {
+            for y in [near, far_y] {
+                font::fill_rect(
+                    &mut page,
+                    x.saturating_sub(self.line / 2),
+                    y,
+                    self.line,
+                    length,
+                    INK,
+                );
+            }
+        }
+        for y in [m, m + height] {
+            for x in [near, far_x] {
+                font::fill_rect(
+                    &mut page,
+                    x,
+                    y.saturating_sub(self.line / 2),
+                    length,
+                    self.line,
+                    INK,
+                );
+            }
+        }
+
+        if self.overlap > 0 {
+            let mut guides_x = Vec::new();
+            if cell.col > 0 {
+                guides_x.push(m + self.overlap);
+            }
+            if cell.col + 1 < cols {
+                guides_x.push((m + width).saturating_sub(self.overlap));
+            }
+            for x in guides_x {
+                for y in [near, far_y] {
+                    self.dashed(&mut page, x, y, length, true);
+                }
+            }
+
+            let mut guides_y = Vec::new();
+            if cell.row > 0 {
+                guides_y.push(m + self.overlap);
+            }
+            if cell.row + 1 < rows {
+                guides_y.push((m + height).saturating_sub(self.overlap));
+            }
+            for y in guides_y {
+                for x in [near, far_x] {
+                    self.dashed(&mut page, x, y, length, false);
+                }
+            }
+        }
+
+        let label = format!(
+            "ROW {} OF {}  COL {} OF {}",
+            cell.row + 1,
+            rows,
+            cell.col + 1,
+            cols
+        );
+        let label_width = font::text_width(&label, self.scale);
+        font::draw_text(
+            &mut page,
+            page_width.saturating_sub(label_width) / 2,
+            page_height.saturating_sub(gap + GLYPH_HEIGHT * self.scale),
+            self.scale,
+            &label,
+            INK,
+        );
+
+        page
+    }
+
+    /// Draws a dashed line across the margin.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - Page to draw on.
+    /// * `(x, y)` - Where the line starts.
+    /// * `length` - Length of the line.
+    /// * `vertical` - Whether the line runs down instead of across.
+    fn dashed(&self, page: &mut DynamicImage, x: u32, y: u32, length: u32, vertical: bool) {
+        let dash = (self.line * 3).max(2);
+        for start in (0..length).step_by(dash as usize * 2) {
+            let dash = dash.min(length - start);
+            if vertical {
+                font::fill_rect(
+                    page,
+                    x.saturating_sub(self.line / 2),
+                    y + start,
+                    self.line,
+                    dash,
+                    GUIDE,
+                );
+            } else {
+                font::fill_rect(
+                    page,
+                    x + start,
+                    y.saturating_sub(self.line / 2),
+                    dash,
+                    self.line,
+                    GUIDE,
+                );
+            }
+        }
+    }
+}
+
+/// Copies a tile onto a page of the same color type without going through 8-bit pixels.
+pub fn copy_tile(page: &mut DynamicImage, tile: &DynamicImage, x: u32, y: u32) {
+    macro_rules! copy {
+        ($($variant:ident),*) => {
+            match (page, tile) {
+                $((DynamicImage::$variant(page), DynamicImage::$variant(tile)) => {
+                    let _ = page.copy_from(tile, x, y);
+                })*
+                (page, tile) => {
+                    let _ = page.copy_from(tile, x, y);
+                }
+            }
+        };
+    }
+
+    copy!(
+        ImageLuma8,
+        ImageLumaA8,
+        ImageRgb8,
+        ImageRgba8,
+        ImageLuma16,
+        ImageLumaA16,
+        ImageRgb16,
+        ImageRgba16,
+        ImageRgb32F,
+        ImageRgba32F
+    );
+}
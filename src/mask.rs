@@ -0,0 +1,90 @@
+use image::{GrayImage, ImageResult, Luma};
+use splix::grid::Cell;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Brightness and opacity a pixel of a mask must reach to mark the image beneath it as foreground.
+const FOREGROUND: u8 = 128;
+
+/// Path of the mask for an image, given to `--mask`.
+///
+/// # Arguments
+///
+/// * `template` - The path given, in which `{stem}` is replaced with the stem of the image.
+/// * `stem` - Stem of the image.
+pub fn path(template: &str, stem: &str) -> PathBuf {
+    PathBuf::from(template.replace("{stem}", stem))
+}
+
+/// Reads a mask, keeping only which of its pixels mark foreground.
+/// Pixels that are bright and opaque are foreground, and the rest are background.
+///
+/// # Returns
+///
+/// The mask, white where it marks foreground and black elsewhere.
+pub fn read(path: &Path) -> ImageResult<GrayImage> {
+    let mask = image::open(path)?.into_luma_alpha8();
+    let (width, height) = mask.dimensions();
+    Ok(GrayImage::from_fn(width, height, |x, y| {
+        let [luma, alpha] = mask.get_pixel(x, y).0;
+        Luma([if luma >= FOREGROUND && alpha >= FOREGROUND {
+            255
+        } else {
+            0
+        }])
+    }))
+}
+
+/// Measures how much of a tile a mask marks as foreground.
+/// The mask is stretched over the image, so it may be drawn at a lower resolution, as long as it has the same shape.
+///
+/// # Arguments
+///
+/// * `mask` - The mask, as read by [`read`].
+/// * `cell` - Region of the image the tile is cut from.
+/// * `size` - Width and height of the image.
+///
+/// # Returns
+///
+/// The percentage of the tile that's foreground, to two decimal places.
+pub fn coverage(mask: &GrayImage, cell: &Cell, (width, height): (u32, u32)) -> f32 {
+    if mask.width() == 0 || mask.height() == 0 {
+        return 0.0;
+    }
+    let (columns, rows) = beneath(mask.dimensions(), cell, (width, height));
+
+    let total = columns.len() as u64 * rows.len() as u64;
+    let foreground = rows
+        .flat_map(|y| columns.clone().map(move |x| (x, y)))
+        .filter(|&(x, y)| mask.get_pixel(x, y).0[0] > 0)
+        .count() as u64;
+    (foreground as f32 * 10000.0 / total as f32).round() / 100.0
+}
+
+/// Finds the pixels of a mask beneath a tile, when the mask is stretched over the image.
+///
+/// # Arguments
+///
+/// * `mask_size` - Width and height of the mask, neither of which may be 0.
+/// * `cell` - Region of the image the tile is cut from.
+/// * `size` - Width and height of the image.
+///
+/// # Returns
+///
+/// The columns and rows of the mask beneath the tile, at least one of each even if the tile is smaller than a pixel of the mask.
+pub fn beneath(
+    (mask_width, mask_height): (u32, u32),
+    cell: &Cell,
+    (width, height): (u32, u32),
+) -> (Range<u32>, Range<u32>) {
+    let span = |start: u32, len: u32, side: u32, mask_side: u32| {
+        let (mask_side, side) = (u64::from(mask_side), u64::from(side.max(1)));
+        let first = (u64::from(start) * mask_side / side).min(mask_side - 1);
+        let end = (u64::from(start + len) * mask_side).div_ceil(side);
+        first as u32..end.clamp(first + 1, mask_side) as u32
+    };
+    (
+        span(cell.x, cell.width, width, mask_width),
+        span(cell.y, cell.height, height, mask_height),
+    )
+}
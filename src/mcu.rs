@@ -0,0 +1,147 @@
+use image::metadata::Orientation;
+use image::{ImageDecoder, ImageReader};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+/// The block grid a JPEG is encoded in.
+#[derive(Clone, Copy)]
+pub struct McuGrid {
+    /// Width of the image.
+    pub width: u32,
+    /// Height of the image.
+    pub height: u32,
+    /// Width of each minimum coded unit, 8 pixels times the largest horizontal sampling factor.
+    pub mcu_width: u32,
+    /// Height of each minimum coded unit, 8 pixels times the largest vertical sampling factor.
+    pub mcu_height: u32,
+    /// Where the first whole column of blocks starts, which is 0 unless the image is flipped or turned so
+    /// the partial blocks at its right or bottom edge come first.
+    pub x_origin: u32,
+    /// Where the first whole row of blocks starts.
+    pub y_origin: u32,
+}
+
+impl McuGrid {
+    /// The block grid as the image is displayed once its EXIF orientation is applied.
+    /// Quarter turns swap the grid's sides, and a reversed side starts with the partial block that ended it.
+    ///
+    /// # Arguments
+    ///
+    /// * `orientation` - The image's orientation.
+    fn oriented(self, orientation: Orientation) -> McuGrid {
+        // Where each displayed side comes from in the stored image, and whether it's reversed.
+        let (transposed, reverse_x, reverse_y) = match orientation {
+            Orientation::NoTransforms => (false, false, false),
+            Orientation::FlipHorizontal => (false, true, false),
+            Orientation::FlipVertical => (false, false, true),
+            Orientation::Rotate180 => (false, true, true),
+            Orientation::Rotate90 => (true, true, false),
+            Orientation::Rotate270 => (true, false, true),
+            Orientation::Rotate90FlipH => (true, false, false),
+            Orientation::Rotate270FlipH => (true, true, true),
+        };
+        let (width, height, mcu_width, mcu_height) = if transposed {
+            (self.height, self.width, self.mcu_height, self.mcu_width)
+        } else {
+            (self.width, self.height, self.mcu_width, self.mcu_height)
+        };
+        let origin = |reverse: bool, length: u32, mcu: u32| if reverse { length % mcu } else { 0 };
+        McuGrid {
+            width,
+            height,
+            mcu_width,
+            mcu_height,
+            x_origin: origin(reverse_x, width, mcu_width),
+            y_origin: origin(reverse_y, height, mcu_height),
+        }
+    }
+}
+
+/// Reads the size of a JPEG's minimum coded units from its frame header, such as 16x16 for 4:2:0 chroma subsampling
+/// or 8x8 for greyscale and 4:4:4, without decoding it.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+/// * `auto_orient` - Whether the image is split as its EXIF Orientation tag says it should be displayed.
+///
+/// # Returns
+///
+/// The block grid, or `None` if the file isn't a JPEG or can't be read.
+pub fn mcu_grid(path: &Path, auto_orient: bool) -> Option<McuGrid> {
+    let grid = read_frame_header(&mut BufReader::new(File::open(path).ok()?)).ok()??;
+    if !auto_orient {
+        return Some(grid);
+    }
+    let mut decoder = ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    Some(grid.oriented(decoder.orientation().ok()?))
+}
+
+/// Reads markers up to the start of frame.
+fn read_frame_header(reader: &mut impl Read) -> io::Result<Option<McuGrid>> {
+    let mut soi = [0; 2];
+    reader.read_exact(&mut soi)?;
+    if soi != [0xFF, 0xD8] {
+        return Ok(None);
+    }
+
+    loop {
+        let mut marker = [0; 2];
+        reader.read_exact(&mut marker)?;
+        if marker[0] != 0xFF {
+            return Ok(None);
+        }
+        // Markers may be padded with any number of 0xFF bytes.
+        while marker[1] == 0xFF {
+            reader.read_exact(&mut marker[1..])?;
+        }
+
+        let mut length = [0; 2];
+        reader.read_exact(&mut length)?;
+        let length = (u16::from_be_bytes(length) as usize).saturating_sub(2);
+        let mut segment = vec![0; length];
+        reader.read_exact(&mut segment)?;
+
+        // Every start of frame marker except DHT, JPG, and DAC, which share the range.
+        let is_frame = matches!(marker[1], 0xC0..=0xCF) && !matches!(marker[1], 0xC4 | 0xC8 | 0xCC);
+        if !is_frame {
+            if marker[1] == 0xDA {
+                return Ok(None);
+            }
+            continue;
+        }
+
+        // Precision, height, width, component count, then 3 bytes per component with its sampling factors.
+        let [_, height_hi, height_lo, width_hi, width_lo, components, ..] = segment[..] else {
+            return Ok(None);
+        };
+        let sampling: Vec<u8> = segment[6..]
+            .chunks_exact(3)
+            .take(components as usize)
+            .map(|component| component[1])
+            .collect();
+        // A single component is always coded in 8x8 blocks, whatever its sampling factors say.
+        let (h_max, v_max) = if sampling.len() == 1 {
+            (1, 1)
+        } else {
+            sampling.iter().fold((1, 1), |(h, v), &factors| {
+                (h.max(factors >> 4), v.max(factors & 0x0F))
+            })
+        };
+
+        return Ok(Some(McuGrid {
+            width: u16::from_be_bytes([width_hi, width_lo]) as u32,
+            height: u16::from_be_bytes([height_hi, height_lo]) as u32,
+            mcu_width: 8 * h_max as u32,
+            mcu_height: 8 * v_max as u32,
+            x_origin: 0,
+            y_origin: 0,
+        }));
+    }
+}
@@ -0,0 +1,114 @@
+use splix::grid::{Cell, Layout};
+
+/// A monitor's resolution and position on the virtual desktop.
+#[derive(Clone, Copy)]
+pub struct Monitor {
+    pub width: u32,
+    pub height: u32,
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Parses a monitor for `--monitors`, written as `WIDTHxHEIGHT+X+Y` like X11 geometry,
+/// where either offset may be negative, such as `1920x1080-1920+0`.
+///
+/// # Arguments
+///
+/// * `monitor` - Monitor to parse.
+///
+/// # Returns
+///
+/// The monitor, or an error message if it isn't a valid monitor.
+pub fn parse_monitor(monitor: &str) -> Result<Monitor, String> {
+    let invalid = || {
+        format!(
+            "'{}' is not a monitor, such as 2560x1440+0+0 or 1920x1080-1920+0",
+            monitor
+        )
+    };
+
+    let monitor = monitor.trim();
+    let size_end = monitor.find(['+', '-']).ok_or_else(invalid)?;
+    let (size, offsets) = monitor.split_at(size_end);
+    let y_start = offsets[1..].find(['+', '-']).ok_or_else(invalid)? + 1;
+    let (x, y) = offsets.split_at(y_start);
+
+    let (width, height) = size
+        .split_once(['x', 'X'])
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .ok_or_else(invalid)?;
+
+    Ok(Monitor {
+        width,
+        height,
+        x: parse_offset(x).ok_or_else(invalid)?,
+        y: parse_offset(y).ok_or_else(invalid)?,
+    })
+}
+
+/// Parses a signed offset such as `+2560` or `-1920`.
+fn parse_offset(offset: &str) -> Option<i64> {
+    offset.strip_prefix('+').unwrap_or(offset).parse().ok()
+}
+
+/// Lays out monitors on a canvas covering the whole virtual desktop.
+/// With bezel compensation, every gap between monitors is widened by the bezel,
+/// so the part of the image hidden behind the bezels is skipped instead of shifting the picture.
+///
+/// # Arguments
+///
+/// * `monitors` - Monitors in the order their slices are numbered.
+/// * `bezel` - Width in pixels hidden behind the bezels between neighbouring monitors.
+///
+/// # Returns
+///
+/// A layout of one region per monitor, in column order with a single row.
+pub fn layout(monitors: &[Monitor], bezel: u32) -> Layout {
+    // Each monitor moves right by one bezel for every distinct monitor edge to its left, and likewise down.
+    let shift = |start: i64, ends: &[i64]| {
+        start + bezel as i64 * ends.iter().filter(|&&end| end <= start).count() as i64
+    };
+
+    let mut right_edges: Vec<i64> = monitors.iter().map(|m| m.x + m.width as i64).collect();
+    let mut bottom_edges: Vec<i64> = monitors.iter().map(|m| m.y + m.height as i64).collect();
+    right_edges.sort_unstable();
+    bottom_edges.sort_unstable();
+    right_edges.dedup();
+    bottom_edges.dedup();
+
+    let placed: Vec<(i64, i64, &Monitor)> = monitors
+        .iter()
+        .map(|m| (shift(m.x, &right_edges), shift(m.y, &bottom_edges), m))
+        .collect();
+
+    let left = placed.iter().map(|&(x, _, _)| x).min().unwrap_or(0);
+    let top = placed.iter().map(|&(_, y, _)| y).min().unwrap_or(0);
+    let right = placed
+        .iter()
+        .map(|&(x, _, m)| x + m.width as i64)
+        .max()
+        .unwrap_or(0);
+    let bottom = placed
+        .iter()
+        .map(|&(_, y, m)| y + m.height as i64)
+        .max()
+        .unwrap_or(0);
+
+    Layout::Regions {
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+        regions: placed
+            .iter()
+            .enumerate()
+            .map(|(col, &(x, y, m))| Cell {
+                row: 0,
+                col,
+                x: (x - left) as u32,
+                y: (y - top) as u32,
+                width: m.width,
+                height: m.height,
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,167 @@
+use crate::output::long_path;
+use crate::tiff_writer::{self, TiffCompression, TiffPage, ASCII, LONG, RATIONAL, SHORT};
+use image::{DynamicImage, ImageResult};
+use splix::grid::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where a page came from, to order the pages of a source: by frame, grid, level, plane, then tile.
+type PageKey = (usize, String, u32, String, usize);
+
+/// The stem of a source and the pages kept for it.
+type SourcePages = (String, Vec<(PageKey, TiffPage)>);
+
+/// Tiles kept for `--multipage-tiff`, each already encoded as a page, until every tile of their source is split
+/// and its TIFF is written.
+pub struct MultipageTiff {
+    /// Path of the TIFF, in which `{stem}` is replaced with the stem of each source.
+    path: String,
+    compression: TiffCompression,
+    /// The stem and pages of each source, by its position in the batch.
+    sources: Mutex<HashMap<usize, SourcePages>>,
+}
+
+/// Where a tile kept as a page came from.
+pub struct PageSource<'a> {
+    /// Stem of the source image.
+    pub stem: &'a str,
+    /// Position of the source image in the batch.
+    pub index: usize,
+    pub frame: usize,
+    pub grid: &'a str,
+    pub level: u32,
+    pub channel: &'a str,
+    /// Position of the tile in its grid.
+    pub tile: usize,
+    /// Name the tile would be saved as, recorded as the page's name.
+    pub name: &'a Path,
+}
+
+impl MultipageTiff {
+    /// # Arguments
+    ///
+    /// * `path` - Path of the TIFF, which must include `{stem}` if more than one image is split.
+    /// * `compression` - How to compress the pages.
+    pub fn new(path: String, compression: TiffCompression) -> Self {
+        MultipageTiff {
+            path,
+            compression,
+            sources: Mutex::default(),
+        }
+    }
+
+    /// Whether the path names a separate TIFF for each source.
+    pub fn per_source(&self) -> bool {
+        self.path.contains("{stem}")
+    }
+
+    /// Encodes a tile as a page, tagged with its name and where it was cut from.
+    /// Besides `PageName`, each page records its cell in `XPosition` and `YPosition`, counted in pixels,
+    /// and as JSON in `ImageDescription`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Where the tile came from.
+    /// * `cell` - Region of the source the tile was cut from.
+    /// * `image` - The tile, as it would be saved.
+    ///
+    /// # Returns
+    ///
+    /// An error if the tile couldn't be encoded.
+    pub fn add(&self, source: &PageSource, cell: &Cell, image: &DynamicImage) -> ImageResult<()> {
+        let ascii = |text: String| text.bytes().chain([0]).map(u32::from).collect::<Vec<_>>();
+        let description = serde_json::json!({
+            "row": cell.row,
+            "col": cell.col,
+            "x": cell.x,
+            "y": cell.y,
+            "width": cell.width,
+            "height": cell.height,
+        });
+
+        let mut page = tiff_writer::encode_page(image, self.compression)?;
+        page.add_tags([
+            // A page of a multi-page document.
+            (254, LONG, vec![2]),
+            (270, ASCII, ascii(description.to_string())),
+            (282, RATIONAL, vec![1, 1]),
+            (283, RATIONAL, vec![1, 1]),
+            (
+                285,
+                ASCII,
+                ascii(source.name.to_string_lossy().into_owned()),
+            ),
+            (286, RATIONAL, vec![cell.x, 1]),
+            (287, RATIONAL, vec![cell.y, 1]),
+            // No absolute unit, so a resolution of 1 makes positions count pixels.
+            (296, SHORT, vec![1]),
+        ]);
+
+        let key = (
+            source.frame,
+            source.grid.to_string(),
+            source.level,
+            source.channel.to_string(),
+            source.tile,
+        );
+        self.sources
+            .lock()
+            .unwrap()
+            .entry(source.index)
+            .or_insert_with(|| (source.stem.to_string(), Vec::new()))
+            .1
+            .push((key, page));
+        Ok(())
+    }
+
+    /// Writes the kept pages of a source as its TIFF once every tile of it has been split, and lets go of them.
+    /// Each page's `PageNumber` records its position and the number of pages.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Position of the source in the batch.
+    ///
+    /// # Returns
+    ///
+    /// The size of the TIFF written, an error message if it couldn't be, or `None` if no pages of the source were kept.
+    pub fn finish(&self, index: usize) -> Option<Result<u64, String>> {
+        let (stem, mut pages) = self.sources.lock().unwrap().remove(&index)?;
+        let path = PathBuf::from(self.path.replace("{stem}", &stem));
+        pages.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let total = pages.len() as u32;
+        let pages = pages
+            .into_iter()
+            .enumerate()
+            .map(|(number, (_, mut page))| {
+                page.add_tags([(297, SHORT, vec![number as u32, total])]);
+                page
+            })
+            .collect();
+
+        let bytes = tiff_writer::assemble(pages);
+        let len = bytes.len() as u64;
+        // Offsets in a TIFF are 32-bit.
+        if u32::try_from(bytes.len()).is_err() {
+            return Some(Err(format!(
+                "splix: multipage-tiff: {} would be larger than the 4 GiB a TIFF can hold",
+                path.display()
+            )));
+        }
+        let written = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map_or(Ok(()), |parent| fs::create_dir_all(long_path(parent)))
+            .and_then(|_| fs::write(long_path(&path), bytes))
+            .map_err(|err: io::Error| {
+                format!(
+                    "splix: multipage-tiff: Failed to write {}: {}",
+                    path.display(),
+                    err
+                )
+            });
+        Some(written.map(|_| len))
+    }
+}
@@ -0,0 +1,803 @@
+use crate::diagnostic;
+use crate::manifest;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use std::slice;
+
+/// The naming template used when `--name` isn't specified.
+pub const DEFAULT_TEMPLATE: &str = "{stem}-r{row}c{col}.{ext}";
+
+/// The naming template used when every frame is split and `--name` isn't specified.
+pub const FRAMES_TEMPLATE: &str = "{stem}-f{frame}-r{row}c{col}.{ext}";
+
+/// The naming template used with `--magick-compat` when `--name` isn't specified,
+/// numbering tiles the way ImageMagick numbers the images it writes.
+pub const MAGICK_TEMPLATE: &str = "{stem}-%d.{ext}";
+
+/// Placeholders templates may use, and what each is filled in with.
+const PLACEHOLDERS: [(&str, Segment); 14] = [
+    ("stem", Segment::Stem),
+    ("ext", Segment::Ext),
+    ("row", Segment::Row),
+    ("y", Segment::Row),
+    ("col", Segment::Col),
+    ("x", Segment::Col),
+    ("index", Segment::Index(0)),
+    ("n", Segment::SourceIndex),
+    ("frame", Segment::Frame),
+    ("z", Segment::Zoom),
+    ("grid", Segment::Grid),
+    ("level", Segment::Level),
+    ("channel", Segment::Channel),
+    ("eye", Segment::Eye),
+];
+
+/// How tiles of a numbered image sequence are laid out with `--sequence`.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SequenceLayout {
+    /// A folder for each tile position holding its frames, such as `r0c0/frame_0001.png`.
+    Cells,
+    /// Every tile in one folder, named after its frame first, such as `frame_0001_r0c0.png`.
+    Flat,
+}
+
+impl SequenceLayout {
+    /// The naming template used for the layout when `--name` isn't specified.
+    pub fn template(self) -> &'static str {
+        match self {
+            SequenceLayout::Cells => "r{row}c{col}/{stem}.{ext}",
+            SequenceLayout::Flat => "{stem}_r{row}c{col}.{ext}",
+        }
+    }
+}
+
+/// What tile names are made safe to save on, with `--sanitize`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Sanitize {
+    /// The system splix runs on.
+    #[default]
+    Host,
+    /// Windows and exFAT, wherever splix runs.
+    Portable,
+    /// Nothing, keeping names as they're generated.
+    Off,
+}
+
+/// Longest file or directory name most file systems allow, in bytes of UTF-8.
+/// Names this short are also short enough in the UTF-16 of Windows and exFAT.
+const MAX_NAME_BYTES: usize = 255;
+
+/// Longest extension kept when a name is shortened, so long dotted names aren't taken for one.
+const MAX_EXT_BYTES: usize = 16;
+
+/// Hex characters of the hash added to a shortened name.
+const SHORTENED_HASH_LEN: usize = 8;
+
+/// Characters Windows doesn't allow in file names, besides `/`.
+/// `\` is a separator on Windows, but can be part of a name made elsewhere.
+const WINDOWS_RESERVED_CHARS: [char; 8] = ['<', '>', ':', '"', '|', '?', '*', '\\'];
+
+/// Names of devices, which Windows doesn't allow as file names, even with an extension, such as `con.png`.
+const WINDOWS_DEVICE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// A part of a parsed name template.
+#[derive(Clone)]
+enum Segment {
+    Literal(String),
+    Stem,
+    Ext,
+    Row,
+    Col,
+    /// The tile's position, padded with zeros to a width.
+    Index(usize),
+    SourceIndex,
+    Frame,
+    Zoom,
+    Grid,
+    Level,
+    Channel,
+    /// The eye of a stereo pair the tile is, from its position.
+    Eye,
+}
+
+/// A template for the path of each tile, relative to the output directory.
+pub struct NameTemplate {
+    segments: Vec<Segment>,
+    sanitize: Sanitize,
+}
+
+/// The values a name template can refer to.
+pub struct TileName<'a> {
+    pub stem: &'a str,
+    pub ext: &'a str,
+    pub row: usize,
+    pub col: usize,
+    pub index: usize,
+    pub source_index: usize,
+    pub frame: usize,
+    pub zoom: u32,
+    pub grid: &'a str,
+    pub level: u32,
+    pub channel: &'a str,
+}
+
+impl NameTemplate {
+    /// Parses and validates a name template.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Template such as `{stem}/{row}/{col}.png`.
+    ///
+    /// # Returns
+    ///
+    /// The template, or an error message if it has unknown placeholders or would leave the output directory.
+    pub fn new(template: &str) -> Result<Self, String> {
+        let segments = segments(template, "name")?;
+
+        let path = Path::new(template);
+        if path
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(format!(
+                "splix: name: The template '{}' must be a relative path inside the output directory",
+                template
+            ));
+        }
+
+        Ok(NameTemplate {
+            segments,
+            sanitize: Sanitize::default(),
+        })
+    }
+
+    /// Makes the names of tiles safe to save on a system, as [`sanitize_component`] describes.
+    pub fn with_sanitize(self, sanitize: Sanitize) -> Self {
+        NameTemplate { sanitize, ..self }
+    }
+
+    /// Numbers tiles wherever the template has an ImageMagick-style `%d`, or `%03d` for numbers padded with zeros,
+    /// the same as `{index}`. `%%` is a literal `%`.
+    pub fn with_magick_numbering(self) -> Self {
+        let mut segments = Vec::new();
+
+        for segment in self.segments {
+            let Segment::Literal(text) = segment else {
+                segments.push(segment);
+                continue;
+            };
+
+            let mut literal = String::new();
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find('%') {
+                literal.push_str(&rest[..start]);
+                rest = &rest[start + 1..];
+
+                if let Some(after) = rest.strip_prefix('%') {
+                    literal.push('%');
+                    rest = after;
+                    continue;
+                }
+
+                let digits = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                if rest[digits..].starts_with('d') {
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Index(rest[..digits].parse().unwrap_or(0)));
+                    rest = &rest[digits + 1..];
+                } else {
+                    literal.push('%');
+                }
+            }
+            literal.push_str(rest);
+
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(literal));
+            }
+        }
+
+        NameTemplate { segments, ..self }
+    }
+
+    /// Parses a template for text drawn on tiles, which unlike a path may contain anything.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Template such as `{row},{col}`.
+    /// * `arg` - Argument the template was given for, to name in error messages.
+    ///
+    /// # Returns
+    ///
+    /// The template, or an error message if it has unknown placeholders.
+    pub fn text(template: &str, arg: &str) -> Result<Self, String> {
+        Ok(NameTemplate {
+            segments: segments(template, arg)?,
+            sanitize: Sanitize::Off,
+        })
+    }
+
+    /// Whether the template refers to the frame of an animated image.
+    pub fn uses_frame(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Frame))
+    }
+
+    /// Builds the path of a tile, relative to the output directory.
+    /// Each part of the path is made safe to save, as [`sanitize_component`] describes.
+    pub fn render(&self, name: &TileName) -> PathBuf {
+        Path::new(&self.render_text(name))
+            .components()
+            .map(|component| match component {
+                Component::Normal(part) => {
+                    sanitize_component(&part.to_string_lossy(), self.sanitize).into()
+                }
+                component => component.as_os_str().to_os_string(),
+            })
+            .collect()
+    }
+
+    /// Whether the template refers to the zoom level of map tiles.
+    pub fn uses_zoom(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Zoom))
+    }
+
+    /// Fills in the template for a tile.
+    pub fn render_text(&self, name: &TileName) -> String {
+        render_segments(&self.segments, name)
+    }
+
+    /// Builds the path of the directory holding every zoom level of a source's map tiles,
+    /// which is the part of the template before `{z}`, up to its last '/'.
+    pub fn zoom_dir(&self, name: &TileName) -> PathBuf {
+        let end = self
+            .segments
+            .iter()
+            .position(|segment| matches!(segment, Segment::Zoom))
+            .unwrap_or(0);
+        let prefix = render_segments(&self.segments[..end], name);
+        PathBuf::from(prefix.rsplit_once('/').map_or("", |(dir, _)| dir))
+    }
+
+    /// Builds the path of a source's map tiles relative to its zoom directory,
+    /// leaving `{z}`, `{x}`, and `{y}` for a map viewer to fill in.
+    pub fn url_template(&self, name: &TileName) -> String {
+        let mut url = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Zoom => url.push_str("{z}"),
+                Segment::Col => url.push_str("{x}"),
+                Segment::Row => url.push_str("{y}"),
+                segment => url.push_str(&render_segments(slice::from_ref(segment), name)),
+            }
+        }
+
+        let dir = self.zoom_dir(name);
+        match url.strip_prefix(&format!("{}/", dir.to_string_lossy())) {
+            Some(relative) if !dir.as_os_str().is_empty() => relative.to_string(),
+            _ => url,
+        }
+    }
+}
+
+/// Fills in placeholders of a template for a tile.
+fn render_segments(segments: &[Segment], name: &TileName) -> String {
+    let mut rendered = String::new();
+
+    for segment in segments {
+        match segment {
+            Segment::Literal(text) => rendered.push_str(text),
+            Segment::Stem => rendered.push_str(name.stem),
+            Segment::Ext => rendered.push_str(name.ext),
+            Segment::Row => rendered.push_str(&name.row.to_string()),
+            Segment::Col => rendered.push_str(&name.col.to_string()),
+            Segment::Index(width) => {
+                rendered.push_str(&format!("{:0width$}", name.index, width = width))
+            }
+            Segment::SourceIndex => rendered.push_str(&name.source_index.to_string()),
+            Segment::Frame => rendered.push_str(&name.frame.to_string()),
+            Segment::Zoom => rendered.push_str(&name.zoom.to_string()),
+            Segment::Grid => rendered.push_str(name.grid),
+            Segment::Level => rendered.push_str(&name.level.to_string()),
+            Segment::Channel => rendered.push_str(name.channel),
+            Segment::Eye => match name.index {
+                0 => rendered.push_str("left"),
+                1 => rendered.push_str("right"),
+                index => rendered.push_str(&index.to_string()),
+            },
+        }
+    }
+
+    rendered
+}
+
+/// Splits a template into literal text and placeholders.
+///
+/// # Arguments
+///
+/// * `template` - Template to split.
+/// * `arg` - Argument the template was given for, to name in error messages.
+fn segments(template: &str, arg: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(format!(
+                "splix: {}: Unmatched '}}' in template '{}'",
+                arg, template
+            ));
+        }
+
+        let Some(len) = rest[start..].find('}') else {
+            return Err(format!(
+                "splix: {}: Unmatched '{{' in template '{}'",
+                arg, template
+            ));
+        };
+
+        if start > 0 {
+            segments.push(Segment::Literal(rest[..start].to_string()));
+        }
+
+        let placeholder = &rest[start + 1..start + len];
+        segments.push(
+            match PLACEHOLDERS.iter().find(|(name, _)| *name == placeholder) {
+                Some((_, segment)) => segment.clone(),
+                None => {
+                    let err = format!(
+                        "splix: {}: Unknown placeholder '{{{}}}' in template '{}'",
+                        arg, placeholder, template
+                    );
+                    let names = PLACEHOLDERS.iter().map(|(name, _)| *name);
+                    return Err(match diagnostic::closest(placeholder, names) {
+                        Some(closest) => {
+                            diagnostic::with_help(err, format!("Did you mean '{{{}}}'?", closest))
+                        }
+                        None => err,
+                    });
+                }
+            },
+        );
+
+        rest = &rest[start + len + 1..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+
+    Ok(segments)
+}
+
+/// Picks a stem for each source image so that no two images write tiles to the same path.
+/// Images whose tiles would collide, such as `a/photo.jpg` and `b/photo.jpg` in a recursive search,
+/// are named after their path relative to the searched directory instead, such as `a_photo` and `b_photo`.
+/// If that still collides, the source's extension is appended, such as `photo_jpg` and `photo_png`,
+/// and then its position in the batch, for names that only collide once they're made safe, such as `a:b` and `a?b`.
+///
+/// # Arguments
+///
+/// * `template` - Template used to name the tiles.
+/// * `root` - Directory that was searched for images.
+/// * `sources` - Path and output extension of each image, in batch order.
+///
+/// # Returns
+///
+/// The stem to use for each image.
+pub fn unique_stems(
+    template: &NameTemplate,
+    root: &Path,
+    sources: &[(&Path, &str)],
+) -> Vec<String> {
+    let mut stems: Vec<String> = sources.iter().map(|(path, _)| stem_of(path)).collect();
+
+    for attempt in 0..3 {
+        let colliding = colliding(template, &stems, sources);
+        if colliding.is_empty() {
+            break;
+        }
+
+        for i in colliding {
+            let path = sources[i].0;
+            let renamed = if attempt == 0 {
+                path.strip_prefix(root)
+                    .unwrap_or(path)
+                    .with_extension("")
+                    .components()
+                    .map(|component| text_of(component.as_os_str()))
+                    .collect::<Vec<_>>()
+                    .join("_")
+            } else if attempt == 1 {
+                format!(
+                    "{}_{}",
+                    stems[i],
+                    text_of(path.extension().unwrap_or_default())
+                )
+            } else {
+                format!("{}_{}", stems[i], i + 1)
+            };
+
+            if renamed != stems[i] {
+                eprintln!(
+                    "splix: Naming tiles of {} after '{}' so they don't overwrite tiles of another image",
+                    path.display(),
+                    renamed
+                );
+                stems[i] = renamed;
+            }
+        }
+    }
+
+    stems
+}
+
+/// The stem of an image's file name, as text for naming its tiles.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+///
+/// # Returns
+///
+/// The stem, with anything that isn't valid Unicode escaped as [`text_of`] describes.
+pub fn stem_of(path: &Path) -> String {
+    text_of(path.file_stem().unwrap_or_default())
+}
+
+/// Converts part of a path to text, escaping whatever isn't valid Unicode instead of replacing it
+/// with `�`, so names that differ only there still give tiles different names.
+/// Invalid bytes on Unix are escaped as `%` and two hex digits, such as `%FF`,
+/// and unpaired surrogates on Windows as `%u` and four, such as `%uD800`.
+pub fn text_of(text: &OsStr) -> String {
+    if let Some(text) = text.to_str() {
+        return text.to_string();
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let mut escaped = String::new();
+        for chunk in text.as_bytes().utf8_chunks() {
+            escaped.push_str(chunk.valid());
+            for byte in chunk.invalid() {
+                escaped.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        escaped
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        char::decode_utf16(text.encode_wide())
+            .map(|c| {
+                c.map_or_else(
+                    |err| format!("%u{:04X}", err.unpaired_surrogate()),
+                    String::from,
+                )
+            })
+            .collect()
+    }
+    #[cfg(not(any(unix, windows)))]
+    text.to_string_lossy().into_owned()
+}
+
+/// Makes a file or directory name of a tile safe to save on a system, for `--sanitize`.
+/// Names are made safe as [`portable_component`] describes, following the rules of Windows on Windows,
+/// or everywhere with [`Sanitize::Portable`], and then shortened as [`shorten`] describes.
+///
+/// # Arguments
+///
+/// * `name` - The name.
+/// * `sanitize` - The system to make the name safe to save on.
+///
+/// # Returns
+///
+/// The name, or a safe name close to it.
+pub fn sanitize_component(name: &str, sanitize: Sanitize) -> String {
+    match sanitize {
+        Sanitize::Host => shorten(&portable_component(name, cfg!(windows))),
+        Sanitize::Portable => shorten(&portable_component(name, true)),
+        Sanitize::Off => name.to_string(),
+    }
+}
+
+/// Shortens a name longer than file systems allow, such as a tile named after a long stem.
+/// The name is cut short, keeping whole characters and its extension, and the start of
+/// the SHA-256 of the whole name is added, so tiles whose names differ only past the cut,
+/// such as in their row and column, still get different names.
+pub fn shorten(name: &str) -> String {
+    if name.len() <= MAX_NAME_BYTES {
+        return name.to_string();
+    }
+    let ext = name
+        .rfind('.')
+        .map(|dot| &name[dot..])
+        .filter(|ext| ext.len() <= MAX_EXT_BYTES)
+        .unwrap_or_default();
+    let hash = &manifest::sha256(name.as_bytes())[..SHORTENED_HASH_LEN];
+    let mut end = MAX_NAME_BYTES - ext.len() - hash.len() - 1;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}~{}{}", &name[..end], hash, ext)
+}
+
+/// Makes a file or directory name safe to save. Control characters, which break terminals and
+/// line-based tools, and the characters that reorder text, which can disguise an extension,
+/// are replaced with `_`. On Windows, so are the characters it doesn't allow in names and
+/// a trailing dot or space, which it would drop, and `_` is added to the names of devices such as `CON` and `nul.png`.
+///
+/// # Arguments
+///
+/// * `name` - The name.
+/// * `windows` - Whether to also follow the rules of Windows.
+///
+/// # Returns
+///
+/// The name, or a safe name close to it.
+pub fn portable_component(name: &str, windows: bool) -> String {
+    let mut portable: String = name
+        .chars()
+        .map(|c| {
+            let reorders = matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}');
+            if c.is_control() || reorders || (windows && WINDOWS_RESERVED_CHARS.contains(&c)) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if !windows || portable == "." || portable == ".." {
+        return portable;
+    }
+
+    if portable.ends_with(['.', ' ']) {
+        portable.pop();
+        portable.push('_');
+    }
+    let base = portable.split('.').next().unwrap_or_default();
+    if WINDOWS_DEVICE_NAMES
+        .iter()
+        .any(|device| device.eq_ignore_ascii_case(base.trim_end()))
+    {
+        portable.insert(base.len(), '_');
+    }
+    portable
+}
+
+/// Splits a stem around its last number, such as `frame_0012` into `frame_`, `0012`, and an empty suffix.
+///
+/// # Returns
+///
+/// The text before the number, the number's digits, and the text after it, or `None` if the stem has no number.
+fn frame_number(stem: &str) -> Option<(&str, &str, &str)> {
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+
+    Some((&stem[..start], &stem[start..end], &stem[end..]))
+}
+
+/// Orders images of numbered sequences by their numbers rather than their names,
+/// so `frame_9` comes before `frame_10`. Images of different sequences, such as `a_001` and `b_001`,
+/// are kept apart, and images without a number sort by name.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+///
+/// # Returns
+///
+/// A key to sort the image by.
+pub fn sequence_key(path: &Path) -> (PathBuf, String, String, u128, PathBuf) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+    match frame_number(&stem) {
+        Some((prefix, digits, suffix)) => (
+            parent,
+            prefix.to_string(),
+            suffix.to_string(),
+            digits.parse().unwrap_or(u128::MAX),
+            path.to_path_buf(),
+        ),
+        None => (
+            parent,
+            stem.into_owned(),
+            String::new(),
+            0,
+            path.to_path_buf(),
+        ),
+    }
+}
+
+/// Renumbers the stems of each numbered sequence so its frames count up by one from its first,
+/// padded with zeros to the same width, so video tools reading the tiles of each position as a sequence
+/// find no gaps even if frames were missing or left out, such as with `--limit`.
+///
+/// # Arguments
+///
+/// * `stems` - Stem of each image, in the order of the sequence.
+///
+/// # Returns
+///
+/// The renumbered stems. Stems without a number are kept as they are.
+pub fn renumber_sequence(stems: &[String]) -> Vec<String> {
+    // The frames of each sequence, by the text around their numbers.
+    let mut sequences: HashMap<(&str, &str), Vec<(usize, &str)>> = HashMap::new();
+    for (i, stem) in stems.iter().enumerate() {
+        if let Some((prefix, digits, suffix)) = frame_number(stem) {
+            sequences
+                .entry((prefix, suffix))
+                .or_default()
+                .push((i, digits));
+        }
+    }
+
+    let mut renumbered = stems.to_vec();
+    for ((prefix, suffix), frames) in sequences {
+        let first: u128 = frames[0].1.parse().unwrap_or(0);
+        let last = first + frames.len() as u128 - 1;
+        let width = frames
+            .iter()
+            .map(|(_, digits)| digits.len())
+            .max()
+            .unwrap_or(0)
+            .max(last.to_string().len());
+
+        for (number, (i, _)) in (first..).zip(frames) {
+            renumbered[i] = format!("{}{:0width$}{}", prefix, number, suffix, width = width);
+        }
+    }
+
+    renumbered
+}
+
+/// Finds the sources whose first tile would be written to the same path as another source's,
+/// ignoring case for file systems that do too.
+fn colliding(template: &NameTemplate, stems: &[String], sources: &[(&Path, &str)]) -> Vec<usize> {
+    let mut first_tiles: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, stem) in stems.iter().enumerate() {
+        let first_tile = template.render(&TileName {
+            stem,
+            ext: sources[i].1,
+            row: 0,
+            col: 0,
+            index: 0,
+            source_index: i,
+            frame: 0,
+            zoom: 0,
+            grid: "",
+            level: 0,
+            channel: "",
+        });
+        first_tiles
+            .entry(first_tile.to_string_lossy().to_lowercase())
+            .or_default()
+            .push(i);
+    }
+
+    let mut colliding: Vec<usize> = first_tiles
+        .into_values()
+        .filter(|sources| sources.len() > 1)
+        .flatten()
+        .collect();
+    colliding.sort_unstable();
+    colliding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_and_reordering_characters_are_replaced() {
+        assert_eq!(portable_component("a\nb\tc", false), "a_b_c");
+        assert_eq!(
+            portable_component("photo\u{202E}gnp.exe", false),
+            "photo_gnp.exe"
+        );
+        assert_eq!(
+            portable_component("日本語 写真 📷", false),
+            "日本語 写真 📷"
+        );
+    }
+
+    #[test]
+    fn windows_reserved_characters_are_replaced() {
+        assert_eq!(portable_component("a:b?c*d", true), "a_b_c_d");
+        assert_eq!(portable_component("a:b", false), "a:b");
+        assert_eq!(portable_component("a\\b", true), "a_b");
+        assert_eq!(portable_component("a\\b", false), "a\\b");
+        assert_eq!(portable_component("scan. ", true), "scan._");
+    }
+
+    #[test]
+    fn windows_device_names_are_renamed() {
+        assert_eq!(portable_component("CON", true), "CON_");
+        assert_eq!(portable_component("nul.png", true), "nul_.png");
+        assert_eq!(portable_component("com1.tar.gz", true), "com1_.tar.gz");
+        assert_eq!(portable_component("console.png", true), "console.png");
+        assert_eq!(portable_component("con-r0c0.png", true), "con-r0c0.png");
+        assert_eq!(portable_component("con.png", false), "con.png");
+    }
+
+    #[test]
+    fn long_names_are_shortened() {
+        let name = format!("{}.png", "é".repeat(200));
+        let short = shorten(&name);
+        assert!(short.len() <= MAX_NAME_BYTES);
+        assert!(short.ends_with(".png"));
+        assert_eq!(shorten("short.png"), "short.png");
+        assert_ne!(
+            shorten(&format!("{}-r0c0.png", "a".repeat(250))),
+            shorten(&format!("{}-r0c1.png", "a".repeat(250)))
+        );
+    }
+
+    #[test]
+    fn sanitize_policies() {
+        assert_eq!(sanitize_component("a:b", Sanitize::Portable), "a_b");
+        assert_eq!(sanitize_component("a\nb", Sanitize::Off), "a\nb");
+        assert_eq!(
+            sanitize_component(&"x".repeat(300), Sanitize::Host).len(),
+            MAX_NAME_BYTES
+        );
+    }
+
+    #[test]
+    fn stems_are_unique_once_sanitized() {
+        let template = NameTemplate::new("{stem}-r{row}c{col}.{ext}")
+            .unwrap()
+            .with_sanitize(Sanitize::Portable);
+        let sources = [
+            (Path::new("scans/a:b.png"), "png"),
+            (Path::new("scans/a?b.png"), "png"),
+        ];
+        let stems = unique_stems(&template, Path::new("scans"), &sources);
+        assert_eq!(colliding(&template, &stems, &sources), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn rendered_paths_keep_their_directories() {
+        let template = NameTemplate::new("{stem}/{row}.{ext}").unwrap();
+        let path = template.render(&TileName {
+            stem: "a\u{7}b",
+            ext: "png",
+            row: 1,
+            col: 0,
+            index: 0,
+            source_index: 0,
+            frame: 0,
+            zoom: 0,
+            grid: "",
+            level: 0,
+            channel: "",
+        });
+        assert_eq!(path, Path::new("a_b").join("1.png"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn invalid_unicode_is_escaped() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(OsStr::from_bytes(b"caf\xE9 \xFF.png"));
+        assert_eq!(stem_of(path), "caf%E9 %FF");
+        assert_ne!(
+            stem_of(Path::new(OsStr::from_bytes(b"\xFE.png"))),
+            stem_of(Path::new(OsStr::from_bytes(b"\xFF.png")))
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::resize;
+use clap::ValueEnum;
+use image::{DynamicImage, Rgba};
+
+/// Histogram bins for images with 8 bits per channel, and for deeper images.
+const BINS_8: usize = 256;
+const BINS_16: usize = 4096;
+
+/// Contextual regions across and down a tile for CLAHE, fewer in tiles too small for them.
+const CLAHE_REGIONS: u32 = 8;
+/// Smallest side of a CLAHE region, in pixels.
+const CLAHE_MIN_REGION: u32 = 16;
+/// How many times the average count a bin of a region's histogram may reach before it's clipped.
+const CLAHE_CLIP: f32 = 2.0;
+
+/// How `--normalize` evens out the exposure of each tile.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Normalize {
+    /// Spread the tile's brightness evenly across the full range.
+    Histogram,
+    /// Equalize each region of the tile separately, limiting contrast so noise isn't amplified.
+    Clahe,
+    /// Stretch the tile's brightness so its darkest pixel is black and its brightest white.
+    Minmax,
+}
+
+/// Evens out the exposure of a tile, keeping its color type.
+/// Color tiles are adjusted in brightness alone, as luma, so their hues don't shift.
+/// Fully transparent pixels are left out when measuring the tile.
+///
+/// # Arguments
+///
+/// * `img` - The tile.
+/// * `mode` - How to even out its exposure.
+///
+/// # Returns
+///
+/// The adjusted tile. Floating-point tiles are clamped to the standard range.
+pub fn normalize(img: &DynamicImage, mode: Normalize) -> DynamicImage {
+    let color = img.color();
+    let bins = if color.bytes_per_pixel() > color.channel_count() {
+        BINS_16
+    } else {
+        BINS_8
+    };
+    let mut rgba = img.to_rgba16();
+    let (width, height) = rgba.dimensions();
+
+    let luma: Vec<f32> = rgba.pixels().map(luma).collect();
+    let opaque: Vec<bool> = rgba.pixels().map(|pixel| pixel[3] > 0).collect();
+    let adjusted: Vec<f32> = match mode {
+        Normalize::Minmax => {
+            let (min, max) = luma
+                .iter()
+                .zip(&opaque)
+                .filter(|(_, &opaque)| opaque)
+                .fold((f32::MAX, f32::MIN), |(min, max), (&value, _)| {
+                    (min.min(value), max.max(value))
+                });
+            if max <= min {
+                return img.clone();
+            }
+            luma.iter()
+                .map(|&value| (value - min) / (max - min) * 65535.0)
+                .collect()
+        }
+        Normalize::Histogram => {
+            let lut = equalize(
+                luma.iter()
+                    .zip(&opaque)
+                    .filter(|(_, &opaque)| opaque)
+                    .map(|(&value, _)| bin(value, bins)),
+                bins,
+                None,
+            );
+            luma.iter().map(|&value| lut[bin(value, bins)]).collect()
+        }
+        Normalize::Clahe => clahe(&luma, &opaque, (width, height), bins),
+    };
+
+    for ((pixel, &before), &after) in rgba.pixels_mut().zip(&luma).zip(&adjusted) {
+        *pixel = with_luma(*pixel, before, after);
+    }
+    resize::to_color_type(DynamicImage::ImageRgba16(rgba), color)
+}
+
+/// The histogram bin of a luma from 0 to 65535.
+fn bin(luma: f32, bins: usize) -> usize {
+    ((luma / 65535.0 * bins as f32) as usize).min(bins - 1)
+}
+
+/// Brightness of a pixel, as BT.601 luma.
+fn luma(pixel: &Rgba<u16>) -> f32 {
+    let [r, g, b, _] = pixel.0.map(f32::from);
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+/// Changes the luma of a pixel, keeping its chroma and alpha.
+fn with_luma(pixel: Rgba<u16>, before: f32, after: f32) -> Rgba<u16> {
+    let [r, _, b, a] = pixel.0.map(f32::from);
+    let (cr, cb) = (r - before, b - before);
+    let (r, b) = (after + cr, after + cb);
+    let g = (after - 0.299 * r - 0.114 * b) / 0.587;
+    let channel = |value: f32| value.round().clamp(0.0, 65535.0) as u16;
+    Rgba([channel(r), channel(g), channel(b), a as u16])
+}
+
+/// Builds the table equalizing a histogram, mapping each bin to a luma from 0 to 65535.
+///
+/// # Arguments
+///
+/// * `values` - Bin of each pixel measured.
+/// * `bins` - Number of bins.
+/// * `clip` - Multiple of the average count to clip each bin at, spreading the excess over every bin, if any.
+fn equalize(values: impl Iterator<Item = usize>, bins: usize, clip: Option<f32>) -> Vec<f32> {
+    let mut histogram = vec![0f32; bins];
+    for bin in values {
+        histogram[bin] += 1.0;
+    }
+    let total: f32 = histogram.iter().sum();
+    if total == 0.0 {
+        return (0..bins)
+            .map(|bin| bin as f32 / (bins - 1) as f32 * 65535.0)
+            .collect();
+    }
+
+    if let Some(clip) = clip {
+        let limit = (clip * total / bins as f32).max(1.0);
+        let excess: f32 = histogram
+            .iter()
+            .map(|&count| (count - limit).max(0.0))
+            .sum();
+        for count in &mut histogram {
+            *count = count.min(limit) + excess / bins as f32;
+        }
+    }
+
+    // Counting from the first occupied bin maps the darkest pixels to black.
+    let first = histogram
+        .iter()
+        .copied()
+        .find(|&count| count > 0.0)
+        .unwrap_or(0.0);
+    let mut cumulative = 0.0;
+    histogram
+        .iter()
+        .map(|&count| {
+            cumulative += count;
+            if total > first {
+                ((cumulative - first) / (total - first)).max(0.0) * 65535.0
+            } else {
+                cumulative / total * 65535.0
+            }
+        })
+        .collect()
+}
+
+/// Contrast-limited adaptive histogram equalization: each region of the image is equalized with a clipped histogram,
+/// and each pixel blends the tables of the four regions nearest it, so region edges don't show.
+fn clahe(luma: &[f32], opaque: &[bool], (width, height): (u32, u32), bins: usize) -> Vec<f32> {
+    let regions = |side: u32| (side / CLAHE_MIN_REGION).clamp(1, CLAHE_REGIONS);
+    let (across, down) = (regions(width), regions(height));
+    let bounds =
+        |region: u32, count: u32, side: u32| (region * side / count, (region + 1) * side / count);
+    let mut tables = Vec::with_capacity((across * down) as usize);
+    for row in 0..down {
+        let (top, bottom) = bounds(row, down, height);
+        for col in 0..across {
+            let (left, right) = bounds(col, across, width);
+            let values = (top..bottom).flat_map(|y| {
+                (left..right).filter_map(move |x| {
+                    let i = (y * width + x) as usize;
+                    opaque[i].then(|| bin(luma[i], bins))
+                })
+            });
+            tables.push(equalize(values, bins, Some(CLAHE_CLIP)));
+        }
+    }
+
+    // Position of a pixel among the regions' centres, as the two nearest regions and how far it is between them.
+    let between = |position: u32, count: u32, side: u32| {
+        let center = (position as f32 + 0.5) * count as f32 / side as f32 - 0.5;
+        let first = center.floor().clamp(0.0, (count - 1) as f32);
+        let second = (first + 1.0).min((count - 1) as f32);
+        (
+            first as usize,
+            second as usize,
+            (center - first).clamp(0.0, 1.0),
+        )
+    };
+
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let level = bin(luma[(y * width + x) as usize], bins);
+            let (row0, row1, dy) = between(y, down, height);
+            let (col0, col1, dx) = between(x, across, width);
+            let at = |row: usize, col: usize| tables[row * across as usize + col][level];
+            let top = at(row0, col0) * (1.0 - dx) + at(row0, col1) * dx;
+            let bottom = at(row1, col0) * (1.0 - dx) + at(row1, col1) * dx;
+            top * (1.0 - dy) + bottom * dy
+        })
+        .collect()
+}
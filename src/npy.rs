@@ -0,0 +1,359 @@
+use crate::multipage::PageSource;
+use crate::output::long_path;
+use image::{DynamicImage, ImageBuffer, Pixel, Primitive};
+use splix::grid::Cell;
+use splix::sink::{TileSink, ZipSink};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where a tile came from, to order the tiles of a source: by frame, grid, level, plane, then tile.
+type TileKey = (usize, String, u32, String, usize);
+
+/// The stem of a source and the tiles kept for it.
+type SourceTiles = (String, Vec<(TileKey, Tile)>);
+
+/// How `--npy-out` lays out the arrays of a source.
+#[derive(Clone, Copy, PartialEq)]
+enum Layout {
+    /// One `.npy` holding every tile, stacked along a first axis.
+    Stack,
+    /// One `.npz` holding a `.npy` for each tile, named after it.
+    Archive,
+    /// A directory holding a `.npy` for each tile.
+    Files,
+}
+
+/// A tile kept as an array.
+struct Tile {
+    /// Name the tile would be saved as.
+    name: String,
+    cell: Cell,
+    array: Array,
+}
+
+/// The pixels of a tile as a NumPy array of rows, columns, and channels.
+struct Array {
+    /// NumPy's description of the element type, such as `|u1`.
+    descr: &'static str,
+    shape: [u32; 3],
+    data: Vec<u8>,
+}
+
+/// Tiles kept for `--npy-out`, as arrays, until every tile of their source is split and they're written.
+pub struct NpyOut {
+    /// Path of the arrays, in which `{stem}` is replaced with the stem of each source.
+    path: String,
+    layout: Layout,
+    /// The stem and tiles of each source, by its position in the batch.
+    sources: Mutex<HashMap<usize, SourceTiles>>,
+}
+
+impl NpyOut {
+    /// # Arguments
+    ///
+    /// * `path` - A `.npy` to stack the tiles in, a `.npz` to save them in by name, or a directory to save them in
+    ///   as separate `.npy` files. It must include `{stem}` if more than one image is split.
+    pub fn new(path: String) -> Self {
+        let lower = path.to_lowercase();
+        let layout = if lower.ends_with(".npy") {
+            Layout::Stack
+        } else if lower.ends_with(".npz") {
+            Layout::Archive
+        } else {
+            Layout::Files
+        };
+        NpyOut {
+            path,
+            layout,
+            sources: Mutex::default(),
+        }
+    }
+
+    /// Whether the path names separate arrays for each source.
+    pub fn per_source(&self) -> bool {
+        self.path.contains("{stem}")
+    }
+
+    /// Keeps a tile as an array of its pixels, as it would be saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Where the tile came from.
+    /// * `cell` - Region of the source the tile was cut from.
+    /// * `image` - The tile, as it would be saved.
+    pub fn add(&self, source: &PageSource, cell: &Cell, image: &DynamicImage) {
+        let key = (
+            source.frame,
+            source.grid.to_string(),
+            source.level,
+            source.channel.to_string(),
+            source.tile,
+        );
+        let tile = Tile {
+            name: source.name.to_string_lossy().replace('\\', "/"),
+            cell: *cell,
+            array: Array::from_image(image),
+        };
+        self.sources
+            .lock()
+            .unwrap()
+            .entry(source.index)
+            .or_insert_with(|| (source.stem.to_string(), Vec::new()))
+            .1
+            .push((key, tile));
+    }
+
+    /// Writes the kept arrays of a source once every tile of it has been split, with a JSON index of the tiles they hold,
+    /// and lets go of them. The index of a `.npy` or `.npz` is saved beside it with a `.json` extension,
+    /// and the index of a directory is saved in it as `index.json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Position of the source in the batch.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes written, an error message if the arrays couldn't be written,
+    /// or `None` if no tiles of the source were kept.
+    pub fn finish(&self, index: usize) -> Option<Result<u64, String>> {
+        let (stem, mut tiles) = self.sources.lock().unwrap().remove(&index)?;
+        let path = PathBuf::from(self.path.replace("{stem}", &stem));
+        tiles.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let tiles: Vec<Tile> = tiles.into_iter().map(|(_, tile)| tile).collect();
+        Some(self.write(&path, &tiles))
+    }
+
+    /// Writes the arrays of a source's tiles, and their index.
+    fn write(&self, path: &Path, tiles: &[Tile]) -> Result<u64, String> {
+        let failed = |err: io::Error| {
+            format!(
+                "splix: npy-out: Failed to write {}: {}",
+                path.display(),
+                err
+            )
+        };
+
+        // Each tile's array is recorded as its position in the stack, its key in the archive, or its file.
+        let (index_path, arrays, len) = match self.layout {
+            Layout::Stack => {
+                let len = write_stack(path, tiles)?;
+                let arrays = (0..tiles.len()).map(serde_json::Value::from).collect();
+                (path.with_extension("json"), arrays, len)
+            }
+            Layout::Archive => {
+                let len = write_archive(path, tiles).map_err(failed)?;
+                let arrays = tiles.iter().map(|tile| array_name(tile).into()).collect();
+                (path.with_extension("json"), arrays, len)
+            }
+            Layout::Files => {
+                let mut len = 0;
+                let mut arrays = Vec::with_capacity(tiles.len());
+                for tile in tiles {
+                    let name = format!("{}.npy", array_name(tile));
+                    let bytes = tile.array.to_npy();
+                    create_parent(&path.join(&name))
+                        .and_then(|_| fs::write(long_path(&path.join(&name)), &bytes))
+                        .map_err(failed)?;
+                    len += bytes.len() as u64;
+                    arrays.push(name.into());
+                }
+                (path.join("index.json"), arrays, len)
+            }
+        };
+
+        let index = index_json(tiles, arrays);
+        create_parent(&index_path)
+            .and_then(|_| fs::write(long_path(&index_path), &index))
+            .map_err(failed)?;
+        Ok(len + index.len() as u64)
+    }
+}
+
+impl Array {
+    /// Copies the pixels of an image, keeping their bit depth: bytes for 8 bits per channel,
+    /// little-endian 16-bit integers for 16 bits, and little-endian 32-bit floats for floating point.
+    fn from_image(image: &DynamicImage) -> Self {
+        let channels = u32::from(image.color().channel_count());
+        let shape = [image.height(), image.width(), channels];
+        let (descr, data) = match image {
+            DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_) => ("|u1", image.as_bytes().to_vec()),
+            DynamicImage::ImageLuma16(buffer) => ("<u2", le_bytes(buffer)),
+            DynamicImage::ImageLumaA16(buffer) => ("<u2", le_bytes(buffer)),
+            DynamicImage::ImageRgb16(buffer) => ("<u2", le_bytes(buffer)),
+            DynamicImage::ImageRgba16(buffer) => ("<u2", le_bytes(buffer)),
+            DynamicImage::ImageRgb32F(buffer) => ("<f4", le_bytes(buffer)),
+            DynamicImage::ImageRgba32F(buffer) => ("<f4", le_bytes(buffer)),
+            _ => {
+                return Array {
+                    descr: "|u1",
+                    shape: [image.height(), image.width(), 4],
+                    data: image.to_rgba8().into_raw(),
+                }
+            }
+        };
+        Array { descr, shape, data }
+    }
+
+    /// Encodes the array as a `.npy` file.
+    fn to_npy(&self) -> Vec<u8> {
+        let mut bytes = npy_header(self.descr, &self.shape.map(u64::from));
+        bytes.extend(&self.data);
+        bytes
+    }
+}
+
+/// A sample wider than a byte, which arrays store in little-endian order.
+trait Sample: Primitive {
+    type Bytes: IntoIterator<Item = u8>;
+
+    fn le_bytes(self) -> Self::Bytes;
+}
+
+impl Sample for u16 {
+    type Bytes = [u8; 2];
+
+    fn le_bytes(self) -> [u8; 2] {
+        self.to_le_bytes()
+    }
+}
+
+impl Sample for f32 {
+    type Bytes = [u8; 4];
+
+    fn le_bytes(self) -> [u8; 4] {
+        self.to_le_bytes()
+    }
+}
+
+/// The samples of an image as little-endian bytes.
+fn le_bytes<P: Pixel<Subpixel = S>, S: Sample>(buffer: &ImageBuffer<P, Vec<S>>) -> Vec<u8> {
+    buffer.iter().flat_map(|&value| value.le_bytes()).collect()
+}
+
+/// Builds the header of a `.npy` file, version 1.0, padded so the array starts on a 64-byte boundary.
+/// The format is described at https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html.
+///
+/// # Arguments
+///
+/// * `descr` - NumPy's description of the element type, such as `|u1`.
+/// * `shape` - Length of each axis of the array.
+fn npy_header(descr: &str, shape: &[u64]) -> Vec<u8> {
+    let shape: Vec<String> = shape.iter().map(u64::to_string).collect();
+    let mut dict = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}), }}",
+        descr,
+        shape.join(", ")
+    );
+    // The magic string, version, and header length take 10 bytes, and the header ends with a newline.
+    let padding = (64 - (10 + dict.len() + 1) % 64) % 64;
+    dict.extend(std::iter::repeat_n(' ', padding));
+    dict.push('\n');
+
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend((dict.len() as u16).to_le_bytes());
+    header.extend(dict.into_bytes());
+    header
+}
+
+/// Name of a tile's array, which is its name without its extension.
+fn array_name(tile: &Tile) -> String {
+    Path::new(&tile.name)
+        .with_extension("")
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Writes every tile of a source stacked into one array, which needs them all to share a size and color type.
+fn write_stack(path: &Path, tiles: &[Tile]) -> Result<u64, String> {
+    let Some(first) = tiles.first() else {
+        return Ok(0);
+    };
+    if tiles
+        .iter()
+        .any(|tile| (tile.array.descr, tile.array.shape) != (first.array.descr, first.array.shape))
+    {
+        return Err(format!(
+            "splix: npy-out: The tiles of {} differ in size or color type, so they can't be stacked. Save them to a .npz or a directory instead",
+            path.display()
+        ));
+    }
+
+    let [height, width, channels] = first.array.shape.map(u64::from);
+    let header = npy_header(
+        first.array.descr,
+        &[tiles.len() as u64, height, width, channels],
+    );
+    let mut bytes = header;
+    for tile in tiles {
+        bytes.extend(&tile.array.data);
+    }
+    create_parent(path)
+        .and_then(|_| fs::write(long_path(path), &bytes))
+        .map_err(|err| {
+            format!(
+                "splix: npy-out: Failed to write {}: {}",
+                path.display(),
+                err
+            )
+        })?;
+    Ok(bytes.len() as u64)
+}
+
+/// Writes every tile of a source to a `.npz`, as a `.npy` named after the tile, as `numpy.savez_compressed` would.
+fn write_archive(path: &Path, tiles: &[Tile]) -> io::Result<u64> {
+    create_parent(path)?;
+    let mut archive = ZipSink::new(BufWriter::new(File::create(long_path(path))?));
+    for tile in tiles {
+        archive.add(&format!("{}.npy", array_name(tile)), &tile.array.to_npy())?;
+    }
+    archive.finish()?;
+    archive
+        .into_inner()
+        .into_inner()
+        .map_err(|err| err.into_error())?;
+    Ok(fs::metadata(long_path(path))?.len())
+}
+
+/// Describes the tiles of a source as JSON: where each was cut from, and the array or key it was saved as.
+fn index_json(tiles: &[Tile], arrays: Vec<serde_json::Value>) -> Vec<u8> {
+    let tiles: Vec<serde_json::Value> = tiles
+        .iter()
+        .zip(arrays)
+        .map(|(tile, array)| {
+            serde_json::json!({
+                "array": array,
+                "name": tile.name,
+                "row": tile.cell.row,
+                "col": tile.cell.col,
+                "x": tile.cell.x,
+                "y": tile.cell.y,
+                "width": tile.cell.width,
+                "height": tile.cell.height,
+                "shape": tile.array.shape,
+                "dtype": dtype(tile.array.descr),
+            })
+        })
+        .collect();
+    serde_json::to_vec_pretty(&serde_json::json!({ "tiles": tiles })).unwrap()
+}
+
+/// NumPy's name for an element type, such as `uint8`.
+fn dtype(descr: &str) -> &'static str {
+    match descr {
+        "<u2" => "uint16",
+        "<f4" => "float32",
+        _ => "uint8",
+    }
+}
+
+fn create_parent(path: &Path) -> io::Result<()> {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map_or(Ok(()), |parent| fs::create_dir_all(long_path(parent)))
+}
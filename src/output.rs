@@ -0,0 +1,394 @@
+use crate::archive::ArchiveKind;
+use crate::naming;
+#[cfg(feature = "s3")]
+use crate::s3::S3Output;
+use splix::sink::{TileSink, ZipSink};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of temporary files created so far, to give each one a unique name.
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Modification time of every file added to an archive, in seconds since the Unix epoch, if fixed by `--reproducible`.
+static ARCHIVE_TIME: OnceLock<u64> = OnceLock::new();
+
+/// Length of the longest path Windows opens without the `\\?\` prefix, less the 12 characters
+/// of an 8.3 file name it keeps free in directory paths.
+#[cfg_attr(not(windows), allow(dead_code))]
+const MAX_PATH: usize = 260 - 12;
+
+/// Where tiles are written.
+pub enum Output {
+    /// A local directory.
+    Dir(PathBuf),
+    /// A tar archive streamed to standard output, or a zip or tar archive file.
+    Archive(Mutex<ArchiveWriter>),
+    /// A bucket and key prefix in S3-compatible object storage.
+    #[cfg(feature = "s3")]
+    S3(S3Output),
+    /// Nowhere, when tiles are only copied to the clipboard.
+    Discard,
+}
+
+/// An archive tiles are written into.
+pub enum ArchiveWriter {
+    Tar(tar::Builder<Box<dyn Write + Send>>),
+    Zip(ZipSink<BufWriter<File>>),
+}
+
+/// Attributes copied from a source image to its tiles.
+#[derive(Clone, Default)]
+pub struct TileAttrs {
+    /// Modification time to give each tile, if `--preserve-times` was given.
+    pub modified: Option<SystemTime>,
+    /// Permissions to give each tile, if `--preserve-perms` was given.
+    pub permissions: Option<fs::Permissions>,
+}
+
+/// Options for outputs that upload tiles over the network.
+#[cfg_attr(not(feature = "s3"), allow(dead_code))]
+pub struct UploadOptions {
+    /// Maximum number of uploads that may run at once.
+    pub jobs: usize,
+    /// Number of times a failed upload is retried.
+    pub retries: u32,
+}
+
+impl Output {
+    /// Chooses an output from the `--output-dir` argument.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A local directory, `-` for standard output, a zip or tar archive, or an `s3://bucket/prefix` URL.
+    /// * `upload` - Options used if the output is remote.
+    ///
+    /// # Returns
+    ///
+    /// The output, or an error message if it isn't supported by this build or the archive couldn't be created.
+    #[cfg_attr(not(feature = "s3"), allow(unused_variables))]
+    pub fn new(path: PathBuf, upload: UploadOptions) -> Result<Self, String> {
+        match path.to_str().and_then(|path| path.strip_prefix("s3://")) {
+            #[cfg(feature = "s3")]
+            Some(url) => Ok(Output::S3(S3Output::new(url, upload)?)),
+            #[cfg(not(feature = "s3"))]
+            Some(_) => {
+                let _ = upload;
+                Err(crate::diagnostic::with_help(
+                    "splix: output-dir: S3 output requires splix to be built with the `s3` feature",
+                    crate::diagnostic::rebuild_with("s3"),
+                ))
+            }
+            None if path.as_os_str() == "-" => {
+                let stdout: Box<dyn Write + Send> = Box::new(BufWriter::new(io::stdout()));
+                Ok(Output::Archive(Mutex::new(ArchiveWriter::Tar(
+                    tar::Builder::new(stdout),
+                ))))
+            }
+            None => match ArchiveKind::from_path(&path) {
+                Some(ArchiveKind::TarGz) => Err(format!(
+                    "splix: output-dir: {} is a compressed tarball, which can't be written. Use .tar or .zip",
+                    path.display()
+                )),
+                Some(kind) => {
+                    let file = path
+                        .parent()
+                        .filter(|parent| !parent.as_os_str().is_empty())
+                        .map_or(Ok(()), |parent| fs::create_dir_all(long_path(parent)))
+                        .and_then(|_| File::create(long_path(&path)))
+                        .map_err(|err| {
+                            format!(
+                                "splix: output-dir: Failed to create archive {}: {}",
+                                path.display(),
+                                err
+                            )
+                        })?;
+                    let file = BufWriter::new(file);
+                    Ok(Output::Archive(Mutex::new(match kind {
+                        ArchiveKind::Zip => ArchiveWriter::Zip(ZipSink::new(file)),
+                        _ => ArchiveWriter::Tar(tar::Builder::new(Box::new(file))),
+                    })))
+                }
+                None => Ok(Output::Dir(path)),
+            },
+        }
+    }
+
+    /// The local directory tiles are written to, if any.
+    pub fn local_dir(&self) -> Option<&Path> {
+        match self {
+            Output::Dir(dir) => Some(dir),
+            Output::Archive(_) | Output::Discard => None,
+            #[cfg(feature = "s3")]
+            Output::S3(_) => None,
+        }
+    }
+
+    /// Describes where a tile is written, for messages and the manifest.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Path of the tile relative to the output.
+    pub fn location(&self, name: &Path) -> PathBuf {
+        match self {
+            Output::Dir(dir) => dir.join(name),
+            Output::Archive(_) | Output::Discard => name.to_path_buf(),
+            #[cfg(feature = "s3")]
+            Output::S3(s3) => PathBuf::from(s3.url(name)),
+        }
+    }
+
+    /// Writes a tile.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Path of the tile relative to the output.
+    /// * `bytes` - Encoded tile.
+    /// * `content_type` - MIME type of the encoded tile.
+    /// * `attrs` - Attributes to give the tile, where the output supports them.
+    ///
+    /// # Returns
+    ///
+    /// An error with a message describing which tile failed, keeping the kind of the underlying error.
+    #[cfg_attr(not(feature = "s3"), allow(unused_variables))]
+    pub fn write(
+        &self,
+        name: &Path,
+        bytes: &[u8],
+        content_type: &str,
+        attrs: &TileAttrs,
+    ) -> io::Result<()> {
+        match self {
+            Output::Dir(dir) => write_file(&dir.join(name), bytes, attrs),
+            Output::Archive(archive) => {
+                let result = match &mut *archive.lock().unwrap() {
+                    ArchiveWriter::Tar(builder) => {
+                        let mut header = tar::Header::new_gnu();
+                        header.set_size(bytes.len() as u64);
+                        header.set_mode(attrs.permissions.as_ref().map_or(0o644, mode));
+                        header.set_mtime(attrs.modified.map_or_else(archive_time, |time| {
+                            time.duration_since(UNIX_EPOCH)
+                                .map_or(0, |time| time.as_secs())
+                        }));
+                        builder.append_data(&mut header, name, bytes)
+                    }
+                    // Zip entries always separate directories with '/'.
+                    ArchiveWriter::Zip(zip) => zip.add(
+                        &name
+                            .iter()
+                            .map(|part| part.to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("/"),
+                        bytes,
+                    ),
+                };
+
+                result.map_err(|err| {
+                    context(
+                        err,
+                        format!(
+                            "splix: Failed to write image {} to the archive",
+                            name.display()
+                        ),
+                    )
+                })
+            }
+            #[cfg(feature = "s3")]
+            Output::S3(s3) => s3.put(name, bytes, content_type).map_err(io::Error::other),
+            Output::Discard => Ok(()),
+        }
+    }
+
+    /// Completes the output once every tile has been written.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Archive(archive) => match archive.into_inner().unwrap() {
+                ArchiveWriter::Tar(builder) => builder.into_inner()?.flush(),
+                ArchiveWriter::Zip(mut zip) => {
+                    zip.finish()?;
+                    zip.into_inner().flush()
+                }
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Checks whether an `--output-dir` argument is an archive, which holds tiles in the order they're written.
+pub fn is_archive(path: &Path) -> bool {
+    path.as_os_str() == "-" || ArchiveKind::from_path(path).is_some()
+}
+
+/// Fixes the modification time of every file added to an archive, for `--reproducible`:
+/// the time in the `SOURCE_DATE_EPOCH` environment variable if it's set, or otherwise the Unix epoch.
+pub fn fix_archive_time() {
+    let time = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|time| time.trim().parse().ok())
+        .unwrap_or(0);
+    let _ = ARCHIVE_TIME.set(time);
+}
+
+/// Modification time to give a file added to an archive, in seconds since the Unix epoch:
+/// the time fixed by [`fix_archive_time`], or otherwise now.
+pub fn archive_time() -> u64 {
+    ARCHIVE_TIME.get().copied().unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs())
+    })
+}
+
+/// Writes a tile to a local file, creating its directory and replacing any existing file.
+/// The tile is written to a temporary file next to it and renamed into place,
+/// so an interrupted run never leaves a truncated tile where a valid one used to be.
+fn write_file(file_path: &Path, bytes: &[u8], attrs: &TileAttrs) -> io::Result<()> {
+    let shown_path = file_path;
+    let file_path = &long_path(file_path);
+    let tile_directory = file_path.parent().unwrap();
+    if !tile_directory.exists() {
+        fs::create_dir_all(tile_directory).map_err(|err| {
+            context(
+                err,
+                format!(
+                    "splix: Failed to create directory {}",
+                    shown_path.parent().unwrap().display()
+                ),
+            )
+        })?;
+    }
+
+    // Shortened like tile names, since it's longer than the tile's.
+    let temp_path = tile_directory.join(naming::shorten(&format!(
+        ".{}.{}-{}.splix-tmp",
+        file_path.file_name().unwrap().to_string_lossy(),
+        process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    )));
+
+    fs::write(&temp_path, bytes)
+        .and_then(|_| set_attrs(&temp_path, attrs))
+        .and_then(|_| fs::rename(&temp_path, file_path))
+        .map_err(|err| {
+            let _ = fs::remove_file(&temp_path);
+            context(
+                err,
+                format!("splix: Failed to save image {}", shown_path.display()),
+            )
+        })
+}
+
+/// Lets Windows open a path longer than `MAX_PATH`, such as the tiles of a deep recursive split,
+/// by making it absolute and adding the `\\?\` prefix. Other paths, and paths elsewhere, are left as they are.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    match std::path::absolute(path) {
+        Ok(absolute) if absolute.as_os_str().len() >= MAX_PATH => match absolute.to_str() {
+            Some(text) => PathBuf::from(verbatim(text)),
+            None => absolute,
+        },
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Adds the `\\?\` prefix to an absolute Windows path, or `\\?\UNC\` to a network path such as `\\server\share`,
+/// so it isn't limited to `MAX_PATH`. Windows takes such paths as they are,
+/// so separators are made backslashes and `.` and `..` are resolved first.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn verbatim(path: &str) -> String {
+    if path.starts_with(r"\\?\") {
+        return path.to_string();
+    }
+    let path = path.replace('/', r"\");
+    // The drive, or the server and share of a network path, which `..` never leaves.
+    let (prefix, rest, root) = match path.strip_prefix(r"\\") {
+        Some(rest) => (r"\\?\UNC\", rest, 2),
+        None => (r"\\?\", path.as_str(), 1),
+    };
+
+    let mut parts: Vec<&str> = Vec::new();
+    for part in rest.split('\\') {
+        match part {
+            "" | "." => {}
+            ".." if parts.len() > root => {
+                parts.pop();
+            }
+            ".." => {}
+            part => parts.push(part),
+        }
+    }
+    format!("{}{}", prefix, parts.join(r"\"))
+}
+
+/// Gives a file the attributes of its source image.
+/// The modification time is set first, since preserved permissions may make the file read-only.
+fn set_attrs(path: &Path, attrs: &TileAttrs) -> io::Result<()> {
+    if let Some(modified) = attrs.modified {
+        File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(modified)?;
+    }
+
+    if let Some(permissions) = &attrs.permissions {
+        fs::set_permissions(path, permissions.clone())?;
+    }
+
+    Ok(())
+}
+
+/// The Unix mode bits of some permissions, for archive headers.
+#[cfg(unix)]
+fn mode(permissions: &fs::Permissions) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    permissions.mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(permissions: &fs::Permissions) -> u32 {
+    if permissions.readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+/// Prefixes an error's message while keeping its kind.
+fn context(err: io::Error, message: String) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {}", message, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_paths_are_made_verbatim() {
+        assert_eq!(verbatim(r"C:\tiles\a\b.png"), r"\\?\C:\tiles\a\b.png");
+        assert_eq!(verbatim("C:/tiles/./a/../b.png"), r"\\?\C:\tiles\b.png");
+        assert_eq!(verbatim(r"C:\..\..\b.png"), r"\\?\C:\b.png");
+        assert_eq!(verbatim(r"\\?\C:\tiles\b.png"), r"\\?\C:\tiles\b.png");
+    }
+
+    #[test]
+    fn network_paths_keep_their_share() {
+        assert_eq!(
+            verbatim(r"\\server\share\tiles\b.png"),
+            r"\\?\UNC\server\share\tiles\b.png"
+        );
+        assert_eq!(
+            verbatim(r"\\server\share\..\b.png"),
+            r"\\?\UNC\server\share\b.png"
+        );
+    }
+}
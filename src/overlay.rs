@@ -0,0 +1,68 @@
+use crate::font::{self, GLYPH_HEIGHT};
+use image::{DynamicImage, Rgba};
+use splix::grid::Cell;
+
+/// Color of the cut lines.
+const LINE: Rgba<u8> = Rgba([255, 0, 0, 255]);
+/// Color of the cell labels.
+const TEXT: Rgba<u8> = Rgba([255, 255, 255, 255]);
+/// Color of the box behind each cell label.
+const BACKGROUND: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// Draws the edges and position of every cell on a copy of an image,
+/// showing exactly where it's cut into tiles.
+///
+/// # Arguments
+///
+/// * `img` - Image as it's split, after any cropping or scaling.
+/// * `cells` - Cells the image is cut into.
+///
+/// # Returns
+///
+/// The copy of the image with the cells drawn on it.
+pub fn draw_grid(img: &DynamicImage, cells: &[Cell]) -> DynamicImage {
+    let mut overlay = DynamicImage::ImageRgba8(img.to_rgba8());
+    let line = (img.width().min(img.height()) / 500).max(1);
+
+    for cell in cells {
+        let (right, bottom) = (cell.x + cell.width, cell.y + cell.height);
+        font::fill_rect(&mut overlay, cell.x, cell.y, cell.width, line, LINE);
+        font::fill_rect(&mut overlay, cell.x, cell.y, line, cell.height, LINE);
+        font::fill_rect(
+            &mut overlay,
+            cell.x,
+            bottom.saturating_sub(line),
+            cell.width,
+            line,
+            LINE,
+        );
+        font::fill_rect(
+            &mut overlay,
+            right.saturating_sub(line),
+            cell.y,
+            line,
+            cell.height,
+            LINE,
+        );
+    }
+
+    // Labels are drawn after every line, so lines of overlapping cells don't cross them.
+    for cell in cells {
+        let text = format!("r{}c{}", cell.row, cell.col);
+        let scale = (cell.width.min(cell.height) / (GLYPH_HEIGHT * 12)).max(1);
+        let padding = scale * 2;
+        let (x, y) = (cell.x + line, cell.y + line);
+
+        font::fill_rect(
+            &mut overlay,
+            x,
+            y,
+            font::text_width(&text, scale) + padding * 2,
+            GLYPH_HEIGHT * scale + padding * 2,
+            BACKGROUND,
+        );
+        font::draw_text(&mut overlay, x + padding, y + padding, scale, &text, TEXT);
+    }
+
+    overlay
+}
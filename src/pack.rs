@@ -0,0 +1,273 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Name of the map written next to the atlases, relating each image to where it was packed.
+pub const ATLAS_MAP: &str = "atlas.json";
+
+/// A rectangle of free or used space in an atlas.
+#[derive(Clone, Copy)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        other.x < self.x + self.width
+            && self.x < other.x + other.width
+            && other.y < self.y + self.height
+            && self.y < other.y + other.height
+    }
+}
+
+/// An atlas and where each image was placed in it.
+pub struct Atlas {
+    pub width: u32,
+    pub height: u32,
+    /// Index of each image in the atlas and the offset of its top left corner.
+    pub placements: Vec<(usize, u32, u32)>,
+}
+
+/// Packs images into as few power-of-two atlases as possible, each as small as it can be.
+///
+/// # Arguments
+///
+/// * `sizes` - Width and height of each image.
+/// * `max_size` - Largest width and height of an atlas, a power of two.
+/// * `padding` - Empty pixels to leave between images.
+///
+/// # Returns
+///
+/// The atlases, or the index of an image too large to fit in an atlas at all.
+pub fn pack(sizes: &[(u32, u32)], max_size: u32, padding: u32) -> Result<Vec<Atlas>, usize> {
+    if let Some(index) = sizes
+        .iter()
+        .position(|&(width, height)| width > max_size || height > max_size)
+    {
+        return Err(index);
+    }
+
+    // Packing the largest images first leaves the small ones to fill the gaps.
+    let mut remaining: Vec<usize> = (0..sizes.len()).collect();
+    remaining.sort_by_key(|&index| {
+        let (width, height) = sizes[index];
+        (
+            std::cmp::Reverse(width.max(height)),
+            std::cmp::Reverse(u64::from(width) * u64::from(height)),
+        )
+    });
+
+    let mut atlases = Vec::new();
+    while !remaining.is_empty() {
+        let (placed, rest) = pack_bin(sizes, &remaining, max_size, max_size, padding);
+
+        // An atlas that isn't full is shrunk to the smallest size that still holds the same images.
+        let indexes: Vec<usize> = placed.iter().map(|&(index, _, _)| index).collect();
+        let atlas = candidate_sizes(sizes, &indexes, max_size)
+            .into_iter()
+            .find_map(|(width, height)| {
+                let (placed, rest) = pack_bin(sizes, &indexes, width, height, padding);
+                rest.is_empty().then_some(Atlas {
+                    width,
+                    height,
+                    placements: placed,
+                })
+            })
+            .unwrap_or(Atlas {
+                width: max_size,
+                height: max_size,
+                placements: placed,
+            });
+
+        atlases.push(atlas);
+        remaining = rest;
+    }
+
+    Ok(atlases)
+}
+
+/// Lists the power-of-two sizes an atlas of some images could be, smallest first.
+fn candidate_sizes(sizes: &[(u32, u32)], indexes: &[usize], max_size: u32) -> Vec<(u32, u32)> {
+    let area: u64 = indexes
+        .iter()
+        .map(|&index| u64::from(sizes[index].0) * u64::from(sizes[index].1))
+        .sum();
+    let widest = indexes
+        .iter()
+        .map(|&index| sizes[index].0)
+        .max()
+        .unwrap_or(1);
+    let tallest = indexes
+        .iter()
+        .map(|&index| sizes[index].1)
+        .max()
+        .unwrap_or(1);
+
+    let powers: Vec<u32> = (0..=max_size.trailing_zeros())
+        .map(|bit| 1 << bit)
+        .collect();
+    let mut candidates: Vec<(u32, u32)> = powers
+        .iter()
+        .flat_map(|&width| powers.iter().map(move |&height| (width, height)))
+        .filter(|&(width, height)| {
+            width >= widest && height >= tallest && u64::from(width) * u64::from(height) >= area
+        })
+        .collect();
+    // Squarer atlases are preferred among those of the same area.
+    candidates.sort_by_key(|&(width, height)| {
+        (u64::from(width) * u64::from(height), width.abs_diff(height))
+    });
+    candidates
+}
+
+/// Packs images into a single atlas with the MaxRects algorithm, placing each image where it leaves the least space on its shorter side.
+///
+/// # Arguments
+///
+/// * `sizes` - Width and height of each image.
+/// * `order` - Indexes of the images to pack, in the order to place them.
+/// * `width` - Width of the atlas.
+/// * `height` - Height of the atlas.
+/// * `padding` - Empty pixels to leave between images.
+///
+/// # Returns
+///
+/// The index and offset of each image placed, and the indexes of the images that didn't fit.
+fn pack_bin(
+    sizes: &[(u32, u32)],
+    order: &[usize],
+    width: u32,
+    height: u32,
+    padding: u32,
+) -> (Vec<(usize, u32, u32)>, Vec<usize>) {
+    // Padding is added to the right and bottom of every image, and the atlas grows by the same amount,
+    // so images only get padding between each other.
+    let mut free = vec![Rect {
+        x: 0,
+        y: 0,
+        width: width.saturating_add(padding),
+        height: height.saturating_add(padding),
+    }];
+    let mut placed = Vec::new();
+    let mut rest = Vec::new();
+
+    for &index in order {
+        let (image_width, image_height) = sizes[index];
+        let (Some(needed_width), Some(needed_height)) = (
+            image_width.checked_add(padding),
+            image_height.checked_add(padding),
+        ) else {
+            rest.push(index);
+            continue;
+        };
+
+        let best = free
+            .iter()
+            .filter(|rect| rect.width >= needed_width && rect.height >= needed_height)
+            .min_by_key(|rect| {
+                let (left_x, left_y) = (rect.width - needed_width, rect.height - needed_height);
+                (left_x.min(left_y), left_x.max(left_y))
+            })
+            .copied();
+        let Some(best) = best else {
+            rest.push(index);
+            continue;
+        };
+
+        let used = Rect {
+            x: best.x,
+            y: best.y,
+            width: needed_width,
+            height: needed_height,
+        };
+        placed.push((index, used.x, used.y));
+
+        let mut split = Vec::new();
+        free.retain(|rect| {
+            if !rect.intersects(&used) {
+                return true;
+            }
+            if used.x > rect.x {
+                split.push(Rect {
+                    width: used.x - rect.x,
+                    ..*rect
+                });
+            }
+            if used.x + used.width < rect.x + rect.width {
+                split.push(Rect {
+                    x: used.x + used.width,
+                    width: rect.x + rect.width - used.x - used.width,
+                    ..*rect
+                });
+            }
+            if used.y > rect.y {
+                split.push(Rect {
+                    height: used.y - rect.y,
+                    ..*rect
+                });
+            }
+            if used.y + used.height < rect.y + rect.height {
+                split.push(Rect {
+                    y: used.y + used.height,
+                    height: rect.y + rect.height - used.y - used.height,
+                    ..*rect
+                });
+            }
+            false
+        });
+        free.extend(split);
+
+        // Free rectangles inside others are redundant.
+        let mut i = 0;
+        while i < free.len() {
+            let redundant = free.iter().enumerate().any(|(j, other)| {
+                j != i && other.contains(&free[i]) && (!free[i].contains(other) || j < i)
+            });
+            if redundant {
+                free.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    (placed, rest)
+}
+
+/// The map of `atlas.json`.
+#[derive(Serialize)]
+pub struct AtlasMap {
+    pub atlases: Vec<AtlasEntry>,
+}
+
+/// An atlas in `atlas.json`.
+#[derive(Serialize)]
+pub struct AtlasEntry {
+    /// Name of the atlas's file, relative to the map.
+    pub file: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub sprites: Vec<SpriteEntry>,
+}
+
+/// An image packed into an atlas.
+#[derive(Serialize)]
+pub struct SpriteEntry {
+    /// Path of the image relative to the directory it was packed from, without its extension.
+    pub name: String,
+    /// Path of the image that was packed.
+    pub source: PathBuf,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
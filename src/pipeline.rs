@@ -0,0 +1,198 @@
+use crate::output::TileAttrs;
+use crate::semaphore::SemaphoreGuard;
+use image::{DynamicImage, GrayImage};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+
+/// A bounded channel between two stages of the pipeline, which any number of threads can send to and receive from.
+pub struct Queue<T> {
+    sender: Mutex<Option<SyncSender<T>>>,
+    receiver: Mutex<Receiver<T>>,
+}
+
+impl<T> Queue<T> {
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximum number of items waiting in the queue.
+    ///   Senders wait while it's full, so a slow stage holds back the ones before it and memory stays bounded.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+
+        Queue {
+            sender: Mutex::new(Some(sender)),
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    /// Adds an item, waiting while the queue is full.
+    ///
+    /// # Returns
+    ///
+    /// The item back if the queue has been closed.
+    pub fn send(&self, item: T) -> Result<(), T> {
+        let sender = self.sender.lock().unwrap().clone();
+
+        match sender {
+            Some(sender) => sender.send(item).map_err(|err| err.0),
+            None => Err(item),
+        }
+    }
+
+    /// Takes the next item, waiting while the queue is empty.
+    ///
+    /// # Returns
+    ///
+    /// The item, or `None` once the queue is closed and empty.
+    pub fn recv(&self) -> Option<T> {
+        self.receiver.lock().unwrap().recv().ok()
+    }
+
+    /// Closes the queue once the returned guard is dropped, even if the thread holding it panics,
+    /// so the stage receiving from it never waits forever. Items already queued are still received.
+    pub fn closer(&self) -> Closer<'_, T> {
+        Closer(self)
+    }
+}
+
+/// Closes a [`Queue`] when dropped.
+pub struct Closer<'a, T>(&'a Queue<T>);
+
+impl<T> Drop for Closer<'_, T> {
+    fn drop(&mut self) {
+        self.0.sender.lock().unwrap().take();
+    }
+}
+
+/// A source image read by the decode stage, shared by the frames decoded from it.
+pub struct DecodedImage<'a> {
+    /// Position of the image in the batch.
+    pub index: usize,
+    /// Path of the image.
+    pub path: &'a PathBuf,
+    /// Attributes of the image to copy to its tiles.
+    pub attrs: TileAttrs,
+    /// SHA-256 of the image file, if the manifest records it.
+    pub sha256: Option<String>,
+    /// Foreground of the image marked by `--mask`, if given.
+    pub mask: Option<GrayImage>,
+    /// Memory reserved for the image under `--memory-limit`, released once every frame has been split.
+    _memory: Option<SemaphoreGuard<'a>>,
+    /// Whether a frame was rejected, so the image's remaining frames are skipped.
+    rejected: AtomicBool,
+    /// Whether a frame was accepted for splitting.
+    accepted: AtomicBool,
+    /// Whether a message about the image has been printed.
+    noted: AtomicBool,
+    /// Called with the image's position once every frame has been split, such as to report it as finished.
+    finished: &'a (dyn Fn(usize) + Sync),
+}
+
+impl<'a> DecodedImage<'a> {
+    /// # Arguments
+    ///
+    /// * `index` - Position of the image in the batch.
+    /// * `path` - Path of the image.
+    /// * `attrs` - Attributes of the image to copy to its tiles.
+    /// * `sha256` - SHA-256 of the image file, if the manifest records it.
+    /// * `mask` - Foreground of the image marked by `--mask`, if given.
+    /// * `memory` - Memory reserved for the image, if limited.
+    /// * `finished` - Called with the image's position once every frame has been split.
+    pub fn new(
+        index: usize,
+        path: &'a PathBuf,
+        attrs: TileAttrs,
+        sha256: Option<String>,
+        mask: Option<GrayImage>,
+        memory: Option<SemaphoreGuard<'a>>,
+        finished: &'a (dyn Fn(usize) + Sync),
+    ) -> Self {
+        DecodedImage {
+            index,
+            path,
+            attrs,
+            sha256,
+            mask,
+            _memory: memory,
+            rejected: AtomicBool::new(false),
+            accepted: AtomicBool::new(false),
+            noted: AtomicBool::new(false),
+            finished,
+        }
+    }
+
+    /// Skips the frames of the image that haven't been split yet.
+    ///
+    /// # Returns
+    ///
+    /// Whether no other frame was rejected before, so the image is only reported once.
+    pub fn reject(&self) -> bool {
+        !self.rejected.swap(true, Ordering::Relaxed)
+    }
+
+    /// Marks a frame of the image as accepted for splitting.
+    ///
+    /// # Returns
+    ///
+    /// Whether no other frame was accepted before, so the image is only counted once.
+    pub fn accept(&self) -> bool {
+        !self.accepted.swap(true, Ordering::Relaxed)
+    }
+
+    /// Marks a message about the image as printed.
+    ///
+    /// # Returns
+    ///
+    /// Whether no message was printed before, so the image is only reported once rather than for every frame.
+    pub fn note(&self) -> bool {
+        !self.noted.swap(true, Ordering::Relaxed)
+    }
+
+    /// Whether a frame of the image was rejected.
+    pub fn rejected(&self) -> bool {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for DecodedImage<'_> {
+    fn drop(&mut self) {
+        (self.finished)(self.index);
+    }
+}
+
+/// A frame passed from the decode stage to the split stage.
+pub struct DecodedFrame<'a> {
+    /// The image the frame was decoded from.
+    pub source: Arc<DecodedImage<'a>>,
+    /// Number of the frame, or 0 for a still image.
+    pub frame: usize,
+    /// Pixels of the frame.
+    pub img: DynamicImage,
+}
+
+/// A file passed from the encode stage to the write stage.
+pub struct WriteJob {
+    /// Path of the file relative to the output.
+    pub name: PathBuf,
+    /// Contents of the file.
+    pub bytes: Vec<u8>,
+    /// MIME type of the contents.
+    pub content_type: &'static str,
+    /// Attributes to give the file.
+    pub attrs: TileAttrs,
+    /// The tile the file holds, or `None` for other files such as grid overlays.
+    pub tile: Option<WrittenTile>,
+}
+
+/// A tile to report once it has been written.
+pub struct WrittenTile {
+    /// Path of the image the tile was cut from.
+    pub source: PathBuf,
+    /// Where the tile is written.
+    pub file: PathBuf,
+    /// Row of the tile.
+    pub row: usize,
+    /// Column of the tile.
+    pub col: usize,
+}
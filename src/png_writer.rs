@@ -0,0 +1,246 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::{DynamicImage, ImageResult};
+use std::io::Write;
+
+/// PNG file signature.
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// The seven Adam7 passes, as the column and row each starts at and the steps between them.
+const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// The single pass of a PNG that isn't interlaced.
+const PROGRESSIVE_PASS: [(u32, u32, u32, u32); 1] = [(0, 0, 1, 1)];
+
+/// PNG filter type that predicts each byte from its left, upper, and upper left neighbours.
+const PAETH: u8 = 4;
+
+/// PNG filter type that leaves bytes as they are, which suits palette indices.
+const NONE: u8 = 0;
+
+/// An image reduced to a palette of colors, with one palette index per pixel.
+pub struct Indexed {
+    pub width: u32,
+    pub height: u32,
+    /// Colors of the palette as RGBA, at most 256 of them.
+    pub palette: Vec<[u8; 4]>,
+    /// Palette index of each pixel, left to right, top to bottom.
+    pub indices: Vec<u8>,
+}
+
+/// The layout of a PNG's pixels.
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    bytes_per_pixel: usize,
+    filter: u8,
+}
+
+/// Encodes an image as a PNG, which `image` does too, except that this can interlace it.
+/// Interlaced PNGs use Adam7, so viewers can show them coarsely before they've fully loaded.
+///
+/// # Arguments
+///
+/// * `img` - Image to encode. Float images are saved with 16 bits per channel.
+/// * `interlace` - Whether to interlace the image.
+///
+/// # Returns
+///
+/// The encoded image.
+pub fn encode(img: &DynamicImage, interlace: bool) -> ImageResult<Vec<u8>> {
+    let img = match img {
+        DynamicImage::ImageRgb32F(_) => DynamicImage::ImageRgb16(img.to_rgb16()),
+        DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgba16(img.to_rgba16()),
+        _ => img.clone(),
+    };
+    let color = img.color();
+
+    // PNG color types: 0 gray, 2 RGB, 4 gray with alpha, 6 RGBA.
+    let color_type = match (color.has_color(), color.has_alpha()) {
+        (false, false) => 0,
+        (true, false) => 2,
+        (false, true) => 4,
+        (true, true) => 6,
+    };
+    let bit_depth = color.bytes_per_pixel() / color.channel_count() * 8;
+
+    // 16-bit samples are stored big-endian.
+    let mut pixels = img.as_bytes().to_vec();
+    if bit_depth == 16 {
+        for sample in pixels.chunks_exact_mut(2) {
+            let value = u16::from_ne_bytes([sample[0], sample[1]]);
+            sample.copy_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    let header = Header {
+        width: img.width(),
+        height: img.height(),
+        bit_depth,
+        color_type,
+        bytes_per_pixel: color.bytes_per_pixel() as usize,
+        filter: PAETH,
+    };
+    write_png(&header, &pixels, &[], interlace)
+}
+
+/// Encodes an image reduced to a palette as a PNG with 8-bit palette indices.
+///
+/// # Arguments
+///
+/// * `img` - Image to encode.
+/// * `interlace` - Whether to interlace the image with Adam7.
+///
+/// # Returns
+///
+/// The encoded image.
+pub fn encode_indexed(img: &Indexed, interlace: bool) -> ImageResult<Vec<u8>> {
+    let palette: Vec<u8> = img
+        .palette
+        .iter()
+        .flat_map(|&[r, g, b, _]| [r, g, b])
+        .collect();
+
+    // Alpha of each palette entry, leaving off the opaque entries at the end.
+    let mut alpha: Vec<u8> = img.palette.iter().map(|color| color[3]).collect();
+    while alpha.last() == Some(&u8::MAX) {
+        alpha.pop();
+    }
+
+    let mut chunks = vec![(*b"PLTE", palette)];
+    if !alpha.is_empty() {
+        chunks.push((*b"tRNS", alpha));
+    }
+
+    let header = Header {
+        width: img.width,
+        height: img.height,
+        bit_depth: 8,
+        color_type: 3,
+        bytes_per_pixel: 1,
+        filter: NONE,
+    };
+    write_png(&header, &img.indices, &chunks, interlace)
+}
+
+/// Writes a PNG from its pixels, filtering each row and compressing them.
+///
+/// # Arguments
+///
+/// * `header` - Layout of the pixels.
+/// * `pixels` - Pixel bytes as stored in the PNG, left to right, top to bottom.
+/// * `chunks` - Chunks that go between the header and the pixels, such as the palette.
+/// * `interlace` - Whether to interlace the image with Adam7.
+///
+/// # Returns
+///
+/// The encoded image.
+fn write_png(
+    header: &Header,
+    pixels: &[u8],
+    chunks: &[([u8; 4], Vec<u8>)],
+    interlace: bool,
+) -> ImageResult<Vec<u8>> {
+    let (width, height) = (header.width, header.height);
+    let bytes_per_pixel = header.bytes_per_pixel;
+    let passes: &[(u32, u32, u32, u32)] = if interlace {
+        &ADAM7_PASSES
+    } else {
+        &PROGRESSIVE_PASS
+    };
+
+    let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+    let stride = width as usize * bytes_per_pixel;
+    for &(x_start, y_start, x_step, y_step) in passes {
+        if x_start >= width || y_start >= height {
+            continue;
+        }
+
+        let pass_width = (width - x_start).div_ceil(x_step) as usize;
+        let row_length = pass_width * bytes_per_pixel;
+        let mut previous = vec![0u8; row_length];
+        let mut row = vec![0u8; row_length];
+        let mut filtered = vec![0u8; row_length + 1];
+
+        for y in (y_start..height).step_by(y_step as usize) {
+            let source = &pixels[y as usize * stride..][..stride];
+            for (i, x) in (x_start..width).step_by(x_step as usize).enumerate() {
+                let x = x as usize * bytes_per_pixel;
+                row[i * bytes_per_pixel..][..bytes_per_pixel]
+                    .copy_from_slice(&source[x..x + bytes_per_pixel]);
+            }
+
+            filtered[0] = header.filter;
+            if header.filter == PAETH {
+                for i in 0..row_length {
+                    let (left, upper_left) = if i >= bytes_per_pixel {
+                        (row[i - bytes_per_pixel], previous[i - bytes_per_pixel])
+                    } else {
+                        (0, 0)
+                    };
+                    filtered[i + 1] = row[i].wrapping_sub(paeth(left, previous[i], upper_left));
+                }
+            } else {
+                filtered[1..].copy_from_slice(&row);
+            }
+
+            zlib.write_all(&filtered)?;
+            std::mem::swap(&mut previous, &mut row);
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // Bit depth, color type, deflate compression, adaptive filtering, and the interlace method.
+    ihdr.extend_from_slice(&[header.bit_depth, header.color_type, 0, 0, interlace as u8]);
+
+    let mut bytes = SIGNATURE.to_vec();
+    write_chunk(&mut bytes, b"IHDR", &ihdr);
+    for (kind, data) in chunks {
+        write_chunk(&mut bytes, kind, data);
+    }
+    write_chunk(&mut bytes, b"IDAT", &zlib.finish()?);
+    write_chunk(&mut bytes, b"IEND", &[]);
+    Ok(bytes)
+}
+
+/// Appends a PNG chunk with its length and checksum.
+pub fn write_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(data);
+
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    bytes.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+/// Predicts a byte from its neighbours, as defined for the PNG Paeth filter.
+fn paeth(left: u8, up: u8, upper_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - upper_left as i16;
+    let (to_left, to_up, to_upper_left) = (
+        (estimate - left as i16).abs(),
+        (estimate - up as i16).abs(),
+        (estimate - upper_left as i16).abs(),
+    );
+
+    if to_left <= to_up && to_left <= to_upper_left {
+        left
+    } else if to_up <= to_upper_left {
+        up
+    } else {
+        upper_left
+    }
+}
@@ -0,0 +1,99 @@
+/// Paper sizes that can be given to `--poster` by name, in millimetres, portrait.
+const PAPER_SIZES: [(&str, f64, f64); 10] = [
+    ("A0", 841.0, 1189.0),
+    ("A1", 594.0, 841.0),
+    ("A2", 420.0, 594.0),
+    ("A3", 297.0, 420.0),
+    ("A4", 210.0, 297.0),
+    ("A5", 148.0, 210.0),
+    ("A6", 105.0, 148.0),
+    ("Letter", 215.9, 279.4),
+    ("Legal", 215.9, 355.6),
+    ("Tabloid", 279.4, 431.8),
+];
+
+/// A printed page size and resolution.
+#[derive(Clone, Copy)]
+pub struct Poster {
+    pub width_mm: f64,
+    pub height_mm: f64,
+    pub dpi: f64,
+}
+
+impl Poster {
+    /// Converts a length on paper into pixels at the poster's resolution.
+    pub fn pixels(&self, mm: f64) -> u32 {
+        (mm / 25.4 * self.dpi).round() as u32
+    }
+}
+
+/// Parses a page for `--poster`, written as `PAPER@DPI`,
+/// where the paper is a name such as `A4` or `Letter`, or a size in millimetres such as `210x297mm`.
+///
+/// # Arguments
+///
+/// * `poster` - Page to parse.
+///
+/// # Returns
+///
+/// The page, or an error message if it isn't a valid page.
+pub fn parse_poster(poster: &str) -> Result<Poster, String> {
+    let invalid = || {
+        format!(
+            "'{}' is not a page, such as A4@300dpi or 210x297mm@300dpi",
+            poster
+        )
+    };
+
+    let (paper, dpi) = poster.split_once('@').ok_or_else(invalid)?;
+    let dpi: f64 = dpi
+        .trim()
+        .trim_end_matches("dpi")
+        .parse()
+        .map_err(|_| invalid())?;
+
+    let (width_mm, height_mm) = match PAPER_SIZES
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(paper.trim()))
+    {
+        Some(&(_, width, height)) => (width, height),
+        None => {
+            let (width, height) = paper.split_once(['x', 'X']).ok_or_else(invalid)?;
+            (
+                parse_length(width).map_err(|_| invalid())?,
+                parse_length(height).map_err(|_| invalid())?,
+            )
+        }
+    };
+
+    let page = Poster {
+        width_mm,
+        height_mm,
+        dpi,
+    };
+    if !(dpi > 0.0 && dpi.is_finite()) || page.pixels(width_mm) == 0 || page.pixels(height_mm) == 0
+    {
+        return Err(format!("'{}' has a page smaller than a pixel", poster));
+    }
+
+    Ok(page)
+}
+
+/// Parses a length in millimetres, such as `10` or `10mm`.
+///
+/// # Arguments
+///
+/// * `length` - Length to parse.
+///
+/// # Returns
+///
+/// The length in millimetres, or an error message if it isn't a valid length.
+pub fn parse_length(length: &str) -> Result<f64, String> {
+    length
+        .trim()
+        .trim_end_matches("mm")
+        .parse()
+        .ok()
+        .filter(|length: &f64| *length >= 0.0 && length.is_finite())
+        .ok_or_else(|| format!("'{}' is not a length in millimetres, such as 10mm", length))
+}
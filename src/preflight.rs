@@ -0,0 +1,109 @@
+use crate::aseprite;
+use crate::dicom;
+use crate::slide;
+use image::{ImageDecoder, ImageReader};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Most problems printed before the rest are only counted, so a large batch doesn't flood the terminal.
+pub const MAX_REPORTED: usize = 10;
+
+/// A tile the batch is going to write, worked out before any image is split.
+pub struct PlannedTile {
+    /// Position of the source image in the batch.
+    pub source: usize,
+    pub row: usize,
+    pub col: usize,
+    /// Where the tile is written, as given by `Output::location`.
+    pub path: PathBuf,
+}
+
+/// Reads the size of an image from its header, without decoding it.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+/// * `auto_orient` - Whether the image is rotated upright before splitting.
+///
+/// # Returns
+///
+/// The size the image is split at, or `None` if the header can't be read.
+pub fn header_size(path: &Path, auto_orient: bool) -> Option<(u32, u32)> {
+    if aseprite::is_aseprite(path) {
+        return aseprite::dimensions(path).ok();
+    }
+    if dicom::is_dicom(path) {
+        return dicom::dimensions(path).ok();
+    }
+    if slide::is_slide(path) {
+        return slide::dimensions(path).ok();
+    }
+
+    let mut decoder = ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    let (width, height) = decoder.dimensions();
+
+    // Orientations that turn the image a quarter turn swap its sides.
+    let turned = auto_orient
+        && decoder.orientation().is_ok_and(|orientation| {
+            matches!(
+                orientation,
+                image::metadata::Orientation::Rotate90
+                    | image::metadata::Orientation::Rotate270
+                    | image::metadata::Orientation::Rotate90FlipH
+                    | image::metadata::Orientation::Rotate270FlipH
+            )
+        });
+
+    Some(if turned {
+        (height, width)
+    } else {
+        (width, height)
+    })
+}
+
+/// Finds tiles that would be written to the same path as an earlier tile of the batch.
+///
+/// # Arguments
+///
+/// * `tiles` - Every tile of the batch.
+///
+/// # Returns
+///
+/// Each colliding tile, paired with the earlier tile it would overwrite.
+pub fn collisions(tiles: &[PlannedTile]) -> Vec<(&PlannedTile, &PlannedTile)> {
+    let mut seen: HashMap<&Path, &PlannedTile> = HashMap::new();
+    let mut collisions = Vec::new();
+
+    for tile in tiles {
+        match seen.get(tile.path.as_path()) {
+            Some(&first) => collisions.push((first, tile)),
+            None => {
+                seen.insert(&tile.path, tile);
+            }
+        }
+    }
+
+    collisions
+}
+
+/// Finds tiles that would overwrite files that already exist.
+///
+/// # Arguments
+///
+/// * `tiles` - Every tile of the batch, written to a local directory.
+///
+/// # Returns
+///
+/// The tiles whose paths already exist.
+pub fn existing(tiles: &[PlannedTile]) -> Vec<&PlannedTile> {
+    tiles
+        .par_iter()
+        .filter(|tile| tile.path.symlink_metadata().is_ok())
+        .collect()
+}
@@ -0,0 +1,190 @@
+use crate::diagnostic;
+use crate::resize::TileFit;
+
+/// Settings tuned for posting split images to a platform, chosen with `--preset`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Preset {
+    /// Aspect ratio, width over height, that images are cropped to around their centre before they're split, if any.
+    pub aspect: Option<f64>,
+    /// Rows and columns the preset splits images into, or `None` to use `--rows` and `--cols`.
+    pub grid: Option<(u32, u32)>,
+    /// Width and height each tile is scaled to, or `None` to keep the size it was cut at.
+    pub tile_size: Option<(u32, u32)>,
+    /// How tiles with a different aspect ratio are fitted to the tile size.
+    pub fit: TileFit,
+    /// How many times wider and taller each tile is stretched, such as to undo the squeeze of a half-width stereo pair.
+    pub stretch: Option<(u32, u32)>,
+    /// Extension, and so format, of the tiles, or `None` to keep each image's format.
+    pub ext: Option<&'static str>,
+    /// Encoder quality of the tiles, if the format is lossy.
+    pub quality: Option<u8>,
+    /// Template for the tiles' names, if the platform expects particular names.
+    pub name: Option<&'static str>,
+    /// Whether only landscape images are split with `grid`, as double-page spreads, and other images are kept whole.
+    pub spreads: bool,
+}
+
+impl Preset {
+    /// A preset of slides side by side, cropping the image so each slide has the tile's aspect ratio.
+    fn slides(cols: u32, tile_size: (u32, u32), quality: u8) -> Self {
+        Preset {
+            aspect: Some((tile_size.0 * cols) as f64 / tile_size.1 as f64),
+            grid: Some((1, cols)),
+            tile_size: Some(tile_size),
+            fit: TileFit::Fill,
+            stretch: None,
+            ext: Some("jpg"),
+            quality: Some(quality),
+            name: None,
+            spreads: false,
+        }
+    }
+
+    /// A preset splitting stereo pairs into an image for each eye, named `_left` and `_right`.
+    ///
+    /// # Arguments
+    ///
+    /// * `grid` - Rows and columns the pair is laid out in, with the left eye first.
+    /// * `stretch` - How many times wider and taller to stretch each eye, for pairs squeezed into the size of one image.
+    fn stereo(grid: (u32, u32), stretch: Option<(u32, u32)>) -> Self {
+        Preset {
+            aspect: None,
+            grid: Some(grid),
+            tile_size: None,
+            fit: TileFit::Fill,
+            stretch,
+            ext: None,
+            quality: None,
+            name: Some("{stem}_{eye}.{ext}"),
+            spreads: false,
+        }
+    }
+
+    /// Square emoji named so chat apps' bulk uploaders take each file name as the emoji's name.
+    fn emoji(size: u32) -> Self {
+        Preset {
+            aspect: None,
+            grid: None,
+            tile_size: Some((size, size)),
+            fit: TileFit::Pad,
+            stretch: None,
+            ext: Some("png"),
+            quality: None,
+            name: Some("{stem}_{row}_{col}.{ext}"),
+            spreads: false,
+        }
+    }
+
+    /// Comic and manga pages for an e-reader: double-page spreads are split into their two pages,
+    /// and every page is scaled to fit the screen, with the rest left as margins.
+    fn ereader(screen: (u32, u32)) -> Self {
+        Preset {
+            aspect: None,
+            grid: Some((1, 2)),
+            tile_size: Some(screen),
+            fit: TileFit::Pad,
+            stretch: None,
+            ext: Some("jpg"),
+            quality: Some(90),
+            name: Some("{stem}_{index}.{ext}"),
+            spreads: true,
+        }
+    }
+}
+
+/// Width of each slide of a carousel, the largest most platforms show at full resolution.
+const CAROUSEL_WIDTH: u32 = 1080;
+
+/// Size of custom emoji, the largest Slack and Discord accept without scaling them down.
+const EMOJI_SIZE: u32 = 128;
+
+/// Screen of the e-reader pages are fitted to by default, the 1404x1872 of the Kindle Oasis, Kobo Clara, and many other 7" readers.
+const EREADER_SIZE: (u32, u32) = (1404, 1872);
+
+/// A name of a preset, and the settings it chooses.
+type NamedPreset = (&'static str, fn() -> Preset);
+
+/// The presets by name, at their default sizes. `carousel`, `emoji`, and `ereader` also take a size of their own.
+const PRESETS: [NamedPreset; 10] = [
+    // Two posts side by side in a timeline, each shown at 7:8.
+    ("twitter-2up", || Preset::slides(2, (700, 800), 85)),
+    // Four posts in a 2x2 block, each shown at 16:9.
+    ("twitter-4up", || Preset {
+        aspect: Some(16.0 / 9.0),
+        grid: Some((2, 2)),
+        ..Preset::slides(2, (1200, 675), 85)
+    }),
+    // A panorama across three full-screen stories.
+    ("story", || Preset::slides(3, (1080, 1920), 90)),
+    ("carousel", || {
+        Preset::slides(3, (CAROUSEL_WIDTH, CAROUSEL_WIDTH), 90)
+    }),
+    ("emoji", || Preset::emoji(EMOJI_SIZE)),
+    ("ereader", || Preset::ereader(EREADER_SIZE)),
+    // Stereo pairs with the left eye on the left or on top, at full size or squeezed into the size of one image.
+    ("stereo-sbs", || Preset::stereo((1, 2), None)),
+    ("stereo-tb", || Preset::stereo((2, 1), None)),
+    ("stereo-half-sbs", || Preset::stereo((1, 2), Some((2, 1)))),
+    ("stereo-half-tb", || Preset::stereo((2, 1), Some((1, 2)))),
+];
+
+/// Parses a preset for `--preset`.
+///
+/// # Arguments
+///
+/// * `preset` - Name of the preset, with an aspect ratio for `carousel`, such as `carousel-4:5`,
+///   a size for `emoji`, such as `emoji:64`, or a screen for `ereader`, such as `ereader:1072x1448`.
+///
+/// # Returns
+///
+/// The preset, or an error message if there's no such preset.
+pub fn parse_preset(preset: &str) -> Result<Preset, String> {
+    let names: Vec<&str> = PRESETS.iter().map(|(name, _)| *name).collect();
+    let invalid = || {
+        format!(
+            "'{}' is not a preset: {}, or carousel-W:H such as carousel-4:5, emoji:SIZE such as emoji:64, or ereader:WxH such as ereader:1072x1448",
+            preset,
+            names.join(", ")
+        )
+    };
+
+    let name = preset.trim().to_ascii_lowercase();
+    if let Some((_, preset)) = PRESETS.iter().find(|(preset, _)| *preset == name) {
+        return Ok(preset());
+    }
+
+    if let Some(ratio) = name.strip_prefix("carousel-") {
+        let (width, height) = ratio
+            .split_once(':')
+            .and_then(|(width, height)| {
+                Some((width.parse::<f64>().ok()?, height.parse::<f64>().ok()?))
+            })
+            .filter(|&(width, height)| width > 0.0 && height > 0.0 && (width / height).is_finite())
+            .ok_or_else(invalid)?;
+
+        let height = (CAROUSEL_WIDTH as f64 * height / width).round().max(1.0);
+        return Ok(Preset::slides(3, (CAROUSEL_WIDTH, height as u32), 90));
+    }
+    if let Some(size) = name.strip_prefix("emoji:") {
+        let size = size
+            .parse()
+            .ok()
+            .filter(|&size| size > 0)
+            .ok_or_else(invalid)?;
+        return Ok(Preset::emoji(size));
+    }
+    if let Some(screen) = name.strip_prefix("ereader:") {
+        let screen = screen
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            .filter(|&(width, height)| width > 0 && height > 0)
+            .ok_or_else(invalid)?;
+        return Ok(Preset::ereader(screen));
+    }
+
+    let help = diagnostic::did_you_mean(&name, names.iter().copied());
+    Err(match help {
+        Some(help) => diagnostic::with_help(invalid(), help),
+        None => invalid(),
+    })
+}
@@ -0,0 +1,41 @@
+/// Lowers the CPU and I/O priority of the process, so other programs stay responsive.
+/// Must be called before any threads are started, since on Linux each thread inherits the priority of the thread that starts it.
+///
+/// # Returns
+///
+/// An error message if the priority couldn't be lowered.
+#[cfg(unix)]
+pub fn lower_priority() -> Result<(), String> {
+    // SAFETY: setpriority has no memory safety requirements.
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) } != 0 {
+        return Err(format!(
+            "splix: nice: Failed to lower the priority: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+        // SAFETY: ioprio_set takes only integers. Failing to set the I/O class isn't an error,
+        // since some file systems and schedulers don't support it.
+        unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn lower_priority() -> Result<(), String> {
+    Err("splix: nice: Lowering the priority is only supported on Unix".to_string())
+}
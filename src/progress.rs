@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// A progress notification, written as one line of JSON.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    /// The images have been found and splitting is starting.
+    Start { files: usize },
+    /// A tile was written.
+    Tile {
+        source: &'a Path,
+        file: &'a Path,
+        row: usize,
+        col: usize,
+    },
+    /// A source image is done, whether or not it could be split.
+    File {
+        path: &'a Path,
+        done: usize,
+        total: usize,
+        percent: f64,
+        /// Estimated seconds until every image is done.
+        eta_secs: f64,
+    },
+    /// Every image is done.
+    Finish {
+        files: usize,
+        errors: usize,
+        elapsed_secs: f64,
+    },
+}
+
+/// Reports progress as JSON lines on a side channel, for front-ends that wrap splix.
+pub struct Progress {
+    writer: Mutex<File>,
+    total: AtomicUsize,
+    done: AtomicUsize,
+    start: Instant,
+}
+
+impl Progress {
+    /// Opens a file descriptor inherited from the parent process for progress events.
+    ///
+    /// # Arguments
+    ///
+    /// * `fd` - File descriptor to write to, such as the write end of a pipe.
+    ///
+    /// # Returns
+    ///
+    /// The progress reporter, or an error message if the file descriptor can't be written to.
+    pub fn open(fd: u32) -> Result<Self, String> {
+        let writer = open_fd(fd).map_err(|err| {
+            format!(
+                "splix: progress-fd: Failed to open file descriptor {}: {}",
+                fd, err
+            )
+        })?;
+
+        Ok(Progress {
+            writer: Mutex::new(writer),
+            total: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+            start: Instant::now(),
+        })
+    }
+
+    /// Reports that splitting is starting.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - Number of images that will be split.
+    pub fn start(&self, files: usize) {
+        self.total.store(files, Ordering::Relaxed);
+        self.emit(&Event::Start { files });
+    }
+
+    /// Reports a written tile.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Path of the image the tile was split from.
+    /// * `file` - Where the tile was written.
+    /// * `row` - Row of the tile.
+    /// * `col` - Column of the tile.
+    pub fn tile(&self, source: &Path, file: &Path, row: usize, col: usize) {
+        self.emit(&Event::Tile {
+            source,
+            file,
+            row,
+            col,
+        });
+    }
+
+    /// Reports a finished source image, with an estimate of the time left.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the image.
+    pub fn file(&self, path: &Path) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let total = self.total.load(Ordering::Relaxed).max(done);
+        let elapsed = self.start.elapsed().as_secs_f64();
+
+        self.emit(&Event::File {
+            path,
+            done,
+            total,
+            percent: done as f64 * 100.0 / total as f64,
+            eta_secs: elapsed / done as f64 * (total - done) as f64,
+        });
+    }
+
+    /// Reports that every image is done.
+    ///
+    /// # Arguments
+    ///
+    /// * `errors` - Number of errors in the run.
+    pub fn finish(&self, errors: usize) {
+        self.emit(&Event::Finish {
+            files: self.done.load(Ordering::Relaxed),
+            errors,
+            elapsed_secs: self.start.elapsed().as_secs_f64(),
+        });
+    }
+
+    /// Writes an event as a line of JSON.
+    /// Failures are ignored, since a front-end that stops listening shouldn't stop the run.
+    fn emit(&self, event: &Event) {
+        let Ok(mut line) = serde_json::to_vec(event) else {
+            return;
+        };
+        line.push(b'\n');
+
+        let mut writer = self.writer.lock().unwrap();
+        let _ = writer.write_all(&line).and_then(|_| writer.flush());
+    }
+}
+
+/// Opens an inherited file descriptor for writing.
+#[cfg(unix)]
+fn open_fd(fd: u32) -> io::Result<File> {
+    File::options().append(true).open(format!("/dev/fd/{}", fd))
+}
+
+#[cfg(not(unix))]
+fn open_fd(_fd: u32) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Progress file descriptors are only supported on Unix",
+    ))
+}
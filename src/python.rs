@@ -0,0 +1,201 @@
+use crate::grid::{self, grid_cells, Cell};
+use image::{DynamicImage, ImageFormat, ImageReader};
+use numpy::{PyArray1, PyArrayMethods};
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// An image given to `split`, either as a path or as encoded bytes.
+#[derive(FromPyObject)]
+enum Source<'py> {
+    Bytes(Bound<'py, PyBytes>),
+    Path(PathBuf),
+}
+
+/// A row or column spec given to `split`, either a number of equal bands or the relative size of each band.
+#[derive(FromPyObject)]
+enum Spec {
+    Count(u32),
+    Sizes(Vec<f64>),
+}
+
+/// Splits an image into tiles.
+///
+/// # Arguments
+///
+/// * `source` - Path of the image, or its encoded bytes.
+/// * `rows` - Number of equal rows, or the relative height of each row. Default: 1.
+/// * `cols` - Number of equal columns, or the relative width of each column. Default: 1.
+/// * `tile_size` - Width and height of each tile, instead of `rows` and `cols`.
+///   Tiles in the last row and column are smaller if the image doesn't divide evenly.
+/// * `output_dir` - Directory to save the tiles in, named like the `splix` command names them.
+///
+/// # Returns
+///
+/// The tiles from left to right, top to bottom, as numpy arrays shaped `(height, width)` for grayscale images
+/// or `(height, width, channels)` otherwise, or the paths of the saved tiles if `output_dir` was given.
+#[pyfunction]
+#[pyo3(signature = (source, rows = None, cols = None, tile_size = None, output_dir = None))]
+fn split<'py>(
+    py: Python<'py>,
+    source: Source<'py>,
+    rows: Option<Spec>,
+    cols: Option<Spec>,
+    tile_size: Option<(u32, u32)>,
+    output_dir: Option<PathBuf>,
+) -> PyResult<Vec<Bound<'py, PyAny>>> {
+    if tile_size.is_some() && (rows.is_some() || cols.is_some()) {
+        return Err(PyValueError::new_err(
+            "splix: tile_size can't be combined with rows or cols",
+        ));
+    }
+
+    let (img, format, stem) = match source {
+        Source::Bytes(bytes) => {
+            let bytes = bytes.as_bytes();
+            let format = image::guess_format(bytes).map_err(to_value_error)?;
+            let img = py
+                .detach(|| image::load_from_memory_with_format(bytes, format))
+                .map_err(to_value_error)?;
+            (img, format, "image".to_string())
+        }
+        Source::Path(path) => {
+            let reader = ImageReader::open(&path)?
+                .with_guessed_format()
+                .map_err(PyOSError::new_err)?;
+            let format = reader.format().ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "splix: {} isn't an image in a supported format",
+                    path.display()
+                ))
+            })?;
+            let img = py.detach(|| reader.decode()).map_err(to_value_error)?;
+            let stem = path
+                .file_stem()
+                .map_or("image".into(), |stem| stem.to_string_lossy().into_owned());
+            (img, format, stem)
+        }
+    };
+
+    let (rows, cols) = match tile_size {
+        Some((0, _) | (_, 0)) => {
+            return Err(PyValueError::new_err(
+                "splix: tile_size must be greater than zero",
+            ))
+        }
+        Some((width, height)) => (
+            fixed_bands(img.height(), height),
+            fixed_bands(img.width(), width),
+        ),
+        None => (parse_spec(rows, "rows")?, parse_spec(cols, "cols")?),
+    };
+    let cells = grid_cells(img.width(), img.height(), &rows, &cols);
+
+    match output_dir {
+        Some(output_dir) => {
+            let format = if format.writing_enabled() {
+                format
+            } else {
+                ImageFormat::Png
+            };
+            let paths = py.detach(|| save_tiles(&img, &cells, format, &output_dir, &stem))?;
+
+            paths
+                .into_iter()
+                .map(|path| path.into_pyobject(py).map(Bound::into_any))
+                .collect()
+        }
+        None => cells.iter().map(|cell| to_array(py, &img, cell)).collect(),
+    }
+}
+
+/// Converts a row or column spec into whole numbers of sections, as the `splix` command does.
+fn parse_spec(spec: Option<Spec>, name: &str) -> PyResult<Vec<u32>> {
+    let spec = match spec {
+        None => vec![1.0],
+        Some(Spec::Count(count)) => vec![count as f64],
+        Some(Spec::Sizes(sizes)) => sizes,
+    };
+
+    if spec.is_empty() || spec.iter().any(|&size| !(size > 0.0 && size.is_finite())) {
+        return Err(PyValueError::new_err(format!(
+            "splix: {}: All sizes must be greater than zero",
+            name
+        )));
+    }
+
+    grid::parse_spec(&spec, name).map_err(PyValueError::new_err)
+}
+
+/// Divides a length into bands of a fixed size, with a smaller band at the end for any remainder.
+fn fixed_bands(length: u32, size: u32) -> Vec<u32> {
+    if length <= size {
+        return vec![1];
+    }
+
+    let mut sizes = vec![size; (length / size) as usize];
+    if !length.is_multiple_of(size) {
+        sizes.push(length % size);
+    }
+    sizes
+}
+
+/// Encodes and saves each tile, named `{stem}-r{row}c{col}.{ext}`.
+fn save_tiles(
+    img: &DynamicImage,
+    cells: &[Cell],
+    format: ImageFormat,
+    output_dir: &Path,
+    stem: &str,
+) -> PyResult<Vec<PathBuf>> {
+    std::fs::create_dir_all(output_dir)?;
+
+    cells
+        .iter()
+        .map(|cell| {
+            let path = output_dir.join(format!(
+                "{}-r{}c{}.{}",
+                stem,
+                cell.row,
+                cell.col,
+                format.extensions_str()[0]
+            ));
+
+            let mut bytes = Vec::new();
+            img.crop_imm(cell.x, cell.y, cell.width, cell.height)
+                .write_to(&mut Cursor::new(&mut bytes), format)
+                .map_err(to_value_error)?;
+            std::fs::write(&path, bytes)?;
+
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Copies a cell into a numpy array of 8-bit pixels.
+fn to_array<'py>(py: Python<'py>, img: &DynamicImage, cell: &Cell) -> PyResult<Bound<'py, PyAny>> {
+    let tile = img.crop_imm(cell.x, cell.y, cell.width, cell.height);
+    let (height, width) = (tile.height() as usize, tile.width() as usize);
+
+    let (pixels, shape) = match (tile.color().has_color(), tile.color().has_alpha()) {
+        (false, false) => (tile.into_luma8().into_raw(), vec![height, width]),
+        (true, false) => (tile.into_rgb8().into_raw(), vec![height, width, 3]),
+        (_, true) => (tile.into_rgba8().into_raw(), vec![height, width, 4]),
+    };
+
+    PyArray1::from_vec(py, pixels)
+        .reshape(shape)
+        .map(Bound::into_any)
+}
+
+fn to_value_error(err: image::ImageError) -> PyErr {
+    PyValueError::new_err(format!("splix: {}", err))
+}
+
+/// Python bindings for splix, the lightning-fast image splitter.
+#[pymodule]
+fn splix(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(split, module)?)
+}
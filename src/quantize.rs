@@ -0,0 +1,209 @@
+use crate::png_writer::Indexed;
+use clap::ValueEnum;
+use color_quant::NeuQuant;
+use image::DynamicImage;
+use std::collections::HashMap;
+
+/// How many pixels NeuQuant skips between the ones it learns from, trading accuracy for speed.
+/// 1 learns from every pixel, and 10 is the recommended balance.
+const SAMPLE_FACTOR: i32 = 10;
+
+/// 8x8 Bayer matrix, giving the order in which ordered dithering rounds pixels up.
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// How colors missing from the palette are approximated.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Dither {
+    /// Use the nearest palette color. Smooth gradients show bands.
+    #[default]
+    None,
+    /// Spread each pixel's error onto its neighbours. Hides banding behind fine noise.
+    FloydSteinberg,
+    /// Nudge pixels by a repeating pattern. Hides banding behind a regular texture that compresses well.
+    Ordered,
+}
+
+/// Reduces an image to a palette of at most the given number of colors.
+/// Images with no more colors than that keep their colors exactly;
+/// others are reduced with NeuQuant, which picks the palette that best fits the image.
+///
+/// # Arguments
+///
+/// * `img` - Image to reduce. Higher bit depths are reduced to 8 bits per channel.
+/// * `colors` - Largest number of colors in the palette, from 2 to 256.
+/// * `dither` - How to approximate colors missing from the palette.
+///
+/// # Returns
+///
+/// The image as a palette and an index into it for each pixel.
+pub fn quantize(img: &DynamicImage, colors: usize, dither: Dither) -> Indexed {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let pixels: Vec<[u8; 4]> = rgba.pixels().map(|pixel| pixel.0).collect();
+
+    if let Some((palette, indices)) = exact_palette(&pixels, colors) {
+        return Indexed {
+            width,
+            height,
+            palette,
+            indices,
+        };
+    }
+
+    let quantizer = NeuQuant::new(SAMPLE_FACTOR, colors, rgba.as_raw());
+    let palette: Vec<[u8; 4]> = quantizer
+        .color_map_rgba()
+        .chunks_exact(4)
+        .map(|color| [color[0], color[1], color[2], color[3]])
+        .collect();
+
+    let indices = match dither {
+        Dither::None => nearest(&quantizer, &pixels),
+        Dither::FloydSteinberg => floyd_steinberg(&quantizer, &palette, &pixels, width),
+        Dither::Ordered => ordered(&quantizer, &pixels, width, colors),
+    };
+
+    Indexed {
+        width,
+        height,
+        palette,
+        indices,
+    }
+}
+
+/// Maps each pixel to the nearest palette color.
+fn nearest(quantizer: &NeuQuant, pixels: &[[u8; 4]]) -> Vec<u8> {
+    // Neighbouring pixels often share a color, so remember the last lookup.
+    let mut last: Option<([u8; 4], u8)> = None;
+    pixels
+        .iter()
+        .map(|pixel| match last {
+            Some((color, index)) if color == *pixel => index,
+            _ => {
+                let index = quantizer.index_of(pixel) as u8;
+                last = Some((*pixel, index));
+                index
+            }
+        })
+        .collect()
+}
+
+/// Maps each pixel to a palette color with Floyd-Steinberg error diffusion,
+/// passing the difference between each pixel and its palette color on to the pixels not yet mapped.
+///
+/// # Arguments
+///
+/// * `quantizer` - Quantizer that chose the palette.
+/// * `palette` - Colors of the palette.
+/// * `pixels` - Pixels of the image.
+/// * `width` - Width of the image.
+fn floyd_steinberg(
+    quantizer: &NeuQuant,
+    palette: &[[u8; 4]],
+    pixels: &[[u8; 4]],
+    width: u32,
+) -> Vec<u8> {
+    let width = width as usize;
+    // Errors carried onto the current row and the next, with a pixel of padding on each side.
+    let mut current = vec![[0f32; 4]; width + 2];
+    let mut next = vec![[0f32; 4]; width + 2];
+    let mut indices = Vec::with_capacity(pixels.len());
+
+    for row in pixels.chunks_exact(width) {
+        for (x, pixel) in row.iter().enumerate() {
+            let mut wanted = [0u8; 4];
+            for c in 0..4 {
+                wanted[c] = (pixel[c] as f32 + current[x + 1][c])
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
+            }
+
+            let index = quantizer.index_of(&wanted);
+            indices.push(index as u8);
+
+            for c in 0..4 {
+                let error = wanted[c] as f32 - palette[index][c] as f32;
+                current[x + 2][c] += error * 7.0 / 16.0;
+                next[x][c] += error * 3.0 / 16.0;
+                next[x + 1][c] += error * 5.0 / 16.0;
+                next[x + 2][c] += error / 16.0;
+            }
+        }
+
+        std::mem::swap(&mut current, &mut next);
+        next.fill([0.0; 4]);
+    }
+
+    indices
+}
+
+/// Maps each pixel to a palette color with ordered dithering,
+/// offsetting pixels by a Bayer matrix tiled over the image before finding the nearest color.
+///
+/// # Arguments
+///
+/// * `quantizer` - Quantizer that chose the palette.
+/// * `pixels` - Pixels of the image.
+/// * `width` - Width of the image.
+/// * `colors` - Number of colors in the palette, which sets how far apart its colors roughly are.
+fn ordered(quantizer: &NeuQuant, pixels: &[[u8; 4]], width: u32, colors: usize) -> Vec<u8> {
+    // Half the spacing between palette colors if they were spread evenly over the RGB cube,
+    // since an adaptive palette puts its colors closer together where the image needs them.
+    let spread = 255.0 / (colors as f32).cbrt() / 2.0;
+
+    pixels
+        .iter()
+        .enumerate()
+        .map(|(i, pixel)| {
+            let (x, y) = (i % width as usize, i / width as usize);
+            let offset = ((BAYER[y % 8][x % 8] as f32 + 0.5) / 64.0 - 0.5) * spread;
+
+            let mut wanted = [0u8; 4];
+            for c in 0..4 {
+                wanted[c] = (pixel[c] as f32 + offset).round().clamp(0.0, 255.0) as u8;
+            }
+            quantizer.index_of(&wanted) as u8
+        })
+        .collect()
+}
+
+/// Builds a palette of every color in an image, if it has few enough.
+///
+/// # Arguments
+///
+/// * `pixels` - Pixels of the image.
+/// * `colors` - Largest number of colors in the palette.
+///
+/// # Returns
+///
+/// The palette and an index into it for each pixel, or `None` if the image has too many colors.
+fn exact_palette(pixels: &[[u8; 4]], colors: usize) -> Option<(Vec<[u8; 4]>, Vec<u8>)> {
+    let mut palette = Vec::new();
+    let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(pixels.len());
+
+    for pixel in pixels {
+        let index = match lookup.get(pixel) {
+            Some(&index) => index,
+            None if palette.len() < colors => {
+                let index = palette.len() as u8;
+                palette.push(*pixel);
+                lookup.insert(*pixel, index);
+                index
+            }
+            None => return None,
+        };
+        indices.push(index);
+    }
+
+    Some((palette, indices))
+}
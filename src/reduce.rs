@@ -0,0 +1,264 @@
+use image::error::{DecodingError, ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+use image::{
+    DynamicImage, ImageBuffer, ImageDecoder, ImageError, ImageFormat, ImageReader, ImageResult,
+    Limits,
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::mem;
+use std::path::Path;
+
+/// Largest factor JPEGs can be shrunk by while they're decoded.
+const MAX_JPEG_FACTOR: u32 = 8;
+
+/// Decodes an image too large to decode in full at a fraction of its size, for `--downscale-oversized`,
+/// without ever holding it at full size. JPEGs are shrunk by 2, 4, or 8 as they're decoded,
+/// and PNGs that aren't interlaced are shrunk row by row, averaging each block of pixels.
+/// Other images can't be decoded this way.
+///
+/// # Arguments
+///
+/// * `path` - Path of the image.
+/// * `budget` - Bytes the decoded image may take.
+/// * `auto_orient` - Whether to rotate the image upright according to its metadata.
+///
+/// # Returns
+///
+/// The image, and how many times smaller on each side it is than at full size,
+/// or an error if the image can't be decoded small enough to fit in the budget.
+pub fn open(path: &Path, budget: u64, auto_orient: bool) -> ImageResult<(DynamicImage, u32)> {
+    // The header is read without the memory limit, since the image is never decoded in full.
+    let mut reader = ImageReader::open(path)?.with_guessed_format()?;
+    reader.no_limits();
+    let format = reader.format();
+    let orientation = reader
+        .into_decoder()
+        .and_then(|mut decoder| decoder.orientation());
+
+    let (mut img, factor) = match format {
+        Some(ImageFormat::Jpeg) => open_jpeg(path, budget)?,
+        Some(ImageFormat::Png) => open_png(path, budget)?,
+        _ => return Err(unsupported(format)),
+    };
+    if let (Ok(orientation), true) = (orientation, auto_orient) {
+        img.apply_orientation(orientation);
+    }
+    Ok((img, factor))
+}
+
+/// Bytes an image is shrunk to fit in once it's too large to decode in full:
+/// the most the built-in decoders allocate by default.
+pub fn default_budget() -> u64 {
+    Limits::default().max_alloc.unwrap_or(u64::MAX)
+}
+
+/// Whether memory can be allocated for a decoded image of a size, which decoding it in full needs.
+/// The memory is given back at once, without being touched.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes the decoded image takes.
+pub fn fits_in_memory(bytes: u64) -> bool {
+    usize::try_from(bytes).is_ok_and(|bytes| Vec::<u8>::new().try_reserve_exact(bytes).is_ok())
+}
+
+/// Finds the smallest factor that shrinks an image to fit in a budget.
+///
+/// # Arguments
+///
+/// * `size` - Width and height of the image at full size.
+/// * `bytes_per_pixel` - Bytes each pixel takes once decoded.
+/// * `budget` - Bytes the decoded image may take.
+/// * `power_of_two` - Whether the factor must be a power of 2.
+fn factor_for(
+    (width, height): (u32, u32),
+    bytes_per_pixel: u64,
+    budget: u64,
+    power_of_two: bool,
+) -> u32 {
+    let fits = |factor: u32| {
+        u64::from(width.div_ceil(factor)) * u64::from(height.div_ceil(factor)) * bytes_per_pixel
+            <= budget
+    };
+    let mut factor = 2;
+    while !fits(factor) && factor < width.max(height) {
+        factor = if power_of_two { factor * 2 } else { factor + 1 };
+    }
+    factor
+}
+
+/// Decodes a JPEG at a half, a quarter, or an eighth of its size.
+fn open_jpeg(path: &Path, budget: u64) -> ImageResult<(DynamicImage, u32)> {
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.read_info().map_err(jpeg_error)?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| decoding_error(ImageFormat::Jpeg, "The header is missing"))?;
+    let size = (u32::from(info.width), u32::from(info.height));
+
+    let factor = factor_for(size, info.pixel_format.pixel_bytes() as u64, budget, true);
+    if factor > MAX_JPEG_FACTOR {
+        return Err(decoding_error(
+            ImageFormat::Jpeg,
+            "The image is too large to decode even at an eighth of its size",
+        ));
+    }
+    let (width, height) = decoder
+        .scale(
+            size.0.div_ceil(factor) as u16,
+            size.1.div_ceil(factor) as u16,
+        )
+        .map_err(jpeg_error)?;
+    let pixels = decoder.decode().map_err(jpeg_error)?;
+
+    let (width, height) = (u32::from(width), u32::from(height));
+    let img = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8)
+        }
+        jpeg_decoder::PixelFormat::L16 => {
+            ImageBuffer::from_raw(width, height, be_samples(&pixels)).map(DynamicImage::ImageLuma16)
+        }
+        jpeg_decoder::PixelFormat::RGB24 => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+        }
+        jpeg_decoder::PixelFormat::CMYK32 => return Err(unsupported(Some(ImageFormat::Jpeg))),
+    };
+    img.map(|img| (img, factor))
+        .ok_or_else(|| decoding_error(ImageFormat::Jpeg, "The decoded image is the wrong size"))
+}
+
+/// Decodes a PNG that isn't interlaced at a fraction of its size, a row at a time,
+/// averaging each square block of pixels into one.
+fn open_png(path: &Path, budget: u64) -> ImageResult<(DynamicImage, u32)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    if reader.info().interlaced {
+        return Err(unsupported(Some(ImageFormat::Png)));
+    }
+
+    let (width, height) = (reader.info().width, reader.info().height);
+    let (color, depth) = reader.output_color_type();
+    let channels = color.samples();
+    let sample_bytes = if depth == png::BitDepth::Sixteen {
+        2
+    } else {
+        1
+    };
+    let factor = factor_for(
+        (width, height),
+        (channels * sample_bytes) as u64,
+        budget,
+        false,
+    );
+    let (small_width, small_height) = (width.div_ceil(factor), height.div_ceil(factor));
+
+    // The sums of each sample of the block of pixels being averaged into each pixel of the row.
+    let mut sums = vec![0u64; small_width as usize * channels];
+    let mut samples: Vec<u16> = Vec::with_capacity(sums.len() * small_height as usize);
+    let mut y = 0;
+    while let Some(row) = reader.next_row().map_err(png_error)? {
+        let data = row.data();
+        for x in 0..width as usize {
+            let block = x / factor as usize * channels;
+            for channel in 0..channels {
+                let i = (x * channels + channel) * sample_bytes;
+                let sample = if sample_bytes == 2 {
+                    u16::from_be_bytes([data[i], data[i + 1]])
+                } else {
+                    u16::from(data[i])
+                };
+                sums[block + channel] += u64::from(sample);
+            }
+        }
+
+        y += 1;
+        if y % factor == 0 || y == height {
+            let rows = u64::from(y - (y - 1) / factor * factor);
+            for (block, sums) in sums.chunks_exact_mut(channels).enumerate() {
+                let columns = u64::from(factor.min(width - block as u32 * factor));
+                let count = rows * columns;
+                samples.extend(
+                    sums.iter_mut()
+                        .map(|sum| ((mem::take(sum) + count / 2) / count) as u16),
+                );
+            }
+        }
+    }
+
+    let img = match (color, sample_bytes) {
+        (png::ColorType::Grayscale, 1) => {
+            to_8_bits(small_width, small_height, samples).map(DynamicImage::ImageLuma8)
+        }
+        (png::ColorType::GrayscaleAlpha, 1) => {
+            to_8_bits(small_width, small_height, samples).map(DynamicImage::ImageLumaA8)
+        }
+        (png::ColorType::Rgb, 1) => {
+            to_8_bits(small_width, small_height, samples).map(DynamicImage::ImageRgb8)
+        }
+        (png::ColorType::Rgba, 1) => {
+            to_8_bits(small_width, small_height, samples).map(DynamicImage::ImageRgba8)
+        }
+        (png::ColorType::Grayscale, _) => {
+            ImageBuffer::from_raw(small_width, small_height, samples).map(DynamicImage::ImageLuma16)
+        }
+        (png::ColorType::GrayscaleAlpha, _) => {
+            ImageBuffer::from_raw(small_width, small_height, samples)
+                .map(DynamicImage::ImageLumaA16)
+        }
+        (png::ColorType::Rgb, _) => {
+            ImageBuffer::from_raw(small_width, small_height, samples).map(DynamicImage::ImageRgb16)
+        }
+        (png::ColorType::Rgba, _) => {
+            ImageBuffer::from_raw(small_width, small_height, samples).map(DynamicImage::ImageRgba16)
+        }
+        // Palettes are expanded to RGB or RGBA.
+        (png::ColorType::Indexed, _) => return Err(unsupported(Some(ImageFormat::Png))),
+    };
+    img.map(|img| (img, factor))
+        .ok_or_else(|| decoding_error(ImageFormat::Png, "The decoded image is the wrong size"))
+}
+
+/// Narrows samples averaged from 8-bit pixels back to 8 bits.
+fn to_8_bits<P: image::Pixel<Subpixel = u8>>(
+    width: u32,
+    height: u32,
+    samples: Vec<u16>,
+) -> Option<ImageBuffer<P, Vec<u8>>> {
+    ImageBuffer::from_raw(
+        width,
+        height,
+        samples.into_iter().map(|sample| sample as u8).collect(),
+    )
+}
+
+/// Reads big-endian 16-bit samples.
+fn be_samples(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+fn decoding_error(format: ImageFormat, err: impl Into<Box<dyn Error + Send + Sync>>) -> ImageError {
+    ImageError::Decoding(DecodingError::new(format.into(), err))
+}
+
+fn jpeg_error(err: jpeg_decoder::Error) -> ImageError {
+    decoding_error(ImageFormat::Jpeg, err)
+}
+
+fn png_error(err: png::DecodingError) -> ImageError {
+    decoding_error(ImageFormat::Png, err)
+}
+
+/// The error for an image that can't be decoded at a smaller size.
+fn unsupported(format: Option<ImageFormat>) -> ImageError {
+    let hint = format.map_or(ImageFormatHint::Unknown, ImageFormatHint::Exact);
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        hint,
+        UnsupportedErrorKind::GenericFeature("Decoding this image at a smaller size".to_string()),
+    ))
+}
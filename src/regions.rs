@@ -0,0 +1,346 @@
+use crate::mask;
+use clap::ValueEnum;
+use image::{ImageBuffer, Luma};
+use serde_json::Value;
+use splix::grid::Cell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// A labeled mask, where each value other than 0 marks a region.
+type Labels = ImageBuffer<Luma<u16>, Vec<u16>>;
+
+/// A ring of points, as fractions of the image's width and height, closed from its last point back to its first.
+type Ring = Vec<(f64, f64)>;
+
+/// A rectangle as fractions of the image's width and height: its left, top, right, and bottom edges.
+type Rect = [f64; 4];
+
+/// How `--region-mode` cuts tiles from `--regions`.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum RegionMode {
+    /// Keep the tiles of the grid that overlap a region.
+    Grid,
+    /// Cut one tile for each region, the smallest rectangle holding it.
+    Bbox,
+}
+
+/// A polygon: its outline, and any holes cut out of it.
+pub struct Polygon {
+    outline: Ring,
+    holes: Vec<Ring>,
+}
+
+/// Regions of an image to cut tiles from, for `--regions`.
+pub enum Regions {
+    /// A labeled mask, stretched over the image.
+    Labels(Labels),
+    /// The polygons making up each region.
+    Polygons(Vec<Vec<Polygon>>),
+}
+
+impl Regions {
+    /// Reads the regions of an image from a labeled mask, or from polygons in JSON.
+    /// Polygons may be GeoJSON features, geometries, or collections of them, such as those exported by QuPath,
+    /// or a list of polygons, each a list of `[x, y]` points. Their points are in pixels of the image.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the mask, or of the polygons if it ends in `.json` or `.geojson`.
+    /// * `size` - Width and height of the image the polygons are drawn on.
+    ///
+    /// # Returns
+    ///
+    /// The regions, or an error message if they can't be read.
+    pub fn read(path: &Path, (width, height): (u32, u32)) -> Result<Self, String> {
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        if !matches!(ext.as_deref(), Some("json" | "geojson")) {
+            let labels = image::open(path)
+                .map_err(|err| err.to_string())?
+                .into_luma16();
+            if labels.width() == 0 || labels.height() == 0 {
+                return Err("The mask is empty".to_string());
+            }
+            return Ok(Regions::Labels(labels));
+        }
+
+        let json = fs::read(path).map_err(|err| err.to_string())?;
+        let value: Value = serde_json::from_slice(&json).map_err(|err| err.to_string())?;
+        let scale = (f64::from(width.max(1)), f64::from(height.max(1)));
+        let mut regions = Vec::new();
+        collect(&value, scale, &mut regions)?;
+        Ok(Regions::Polygons(regions))
+    }
+
+    /// Cuts tiles from the regions.
+    ///
+    /// # Arguments
+    ///
+    /// * `cells` - Tiles of the grid the image is split into.
+    /// * `mode` - Whether to keep the tiles of the grid overlapping a region, or cut one tile for each region.
+    /// * `size` - Width and height of the image.
+    pub fn cells(&self, cells: Vec<Cell>, mode: RegionMode, size: (u32, u32)) -> Vec<Cell> {
+        match mode {
+            RegionMode::Grid => cells
+                .into_iter()
+                .filter(|cell| self.overlaps(cell, size))
+                .collect(),
+            RegionMode::Bbox => self
+                .bounds(size)
+                .into_iter()
+                .enumerate()
+                .map(|(col, (x, y, width, height))| Cell {
+                    row: 0,
+                    col,
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+                .collect(),
+        }
+    }
+
+    /// Finds the smallest rectangle holding each region, in pixels, leaving out regions outside the image.
+    /// Regions of a labeled mask are ordered by their value.
+    fn bounds(&self, (width, height): (u32, u32)) -> Vec<(u32, u32, u32, u32)> {
+        let rects: Vec<Rect> = match self {
+            Regions::Labels(labels) => {
+                let mut edges: BTreeMap<u16, [u32; 4]> = BTreeMap::new();
+                for (x, y, &Luma([label])) in labels.enumerate_pixels() {
+                    if label == 0 {
+                        continue;
+                    }
+                    let edges = edges.entry(label).or_insert([x, y, x, y]);
+                    *edges = [
+                        edges[0].min(x),
+                        edges[1].min(y),
+                        edges[2].max(x),
+                        edges[3].max(y),
+                    ];
+                }
+                let (mask_width, mask_height) =
+                    (f64::from(labels.width()), f64::from(labels.height()));
+                edges
+                    .into_values()
+                    .map(|[left, top, right, bottom]| {
+                        [
+                            f64::from(left) / mask_width,
+                            f64::from(top) / mask_height,
+                            f64::from(right + 1) / mask_width,
+                            f64::from(bottom + 1) / mask_height,
+                        ]
+                    })
+                    .collect()
+            }
+            Regions::Polygons(regions) => regions
+                .iter()
+                .map(|polygons| {
+                    polygons.iter().flat_map(|polygon| &polygon.outline).fold(
+                        [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+                        |[left, top, right, bottom], &(x, y)| {
+                            [left.min(x), top.min(y), right.max(x), bottom.max(y)]
+                        },
+                    )
+                })
+                .collect(),
+        };
+
+        let (width, height) = (f64::from(width), f64::from(height));
+        rects
+            .into_iter()
+            .filter_map(|[left, top, right, bottom]| {
+                let left = (left * width).floor().clamp(0.0, width) as u32;
+                let top = (top * height).floor().clamp(0.0, height) as u32;
+                let right = (right * width).ceil().clamp(0.0, width) as u32;
+                let bottom = (bottom * height).ceil().clamp(0.0, height) as u32;
+                (right > left && bottom > top).then_some((left, top, right - left, bottom - top))
+            })
+            .collect()
+    }
+
+    /// Checks whether any region overlaps a tile.
+    fn overlaps(&self, cell: &Cell, (width, height): (u32, u32)) -> bool {
+        match self {
+            Regions::Labels(labels) => {
+                let (columns, rows) = mask::beneath(labels.dimensions(), cell, (width, height));
+                rows.flat_map(|y| columns.clone().map(move |x| (x, y)))
+                    .any(|(x, y)| labels.get_pixel(x, y).0[0] != 0)
+            }
+            Regions::Polygons(regions) => {
+                let (width, height) = (f64::from(width.max(1)), f64::from(height.max(1)));
+                let rect = [
+                    f64::from(cell.x) / width,
+                    f64::from(cell.y) / height,
+                    f64::from(cell.x + cell.width) / width,
+                    f64::from(cell.y + cell.height) / height,
+                ];
+                regions.iter().flatten().any(|polygon| {
+                    meets(&polygon.outline, rect)
+                        && !polygon.holes.iter().any(|hole| encloses(hole, rect))
+                })
+            }
+        }
+    }
+}
+
+/// Gathers the regions of GeoJSON, or of a list of polygons, where each feature or polygon is a region.
+/// Geometries without an area, such as points and lines, are left out.
+fn collect(
+    value: &Value,
+    scale: (f64, f64),
+    regions: &mut Vec<Vec<Polygon>>,
+) -> Result<(), String> {
+    let invalid = || "Expected GeoJSON or a list of polygons".to_string();
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            for item in items {
+                collect(item, scale, regions)?;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                regions.push(vec![Polygon {
+                    outline: ring(item, scale)?,
+                    holes: Vec::new(),
+                }]);
+            }
+        }
+        Value::Object(object) => match object.get("type").and_then(Value::as_str) {
+            Some("FeatureCollection") => {
+                collect(
+                    object.get("features").unwrap_or(&Value::Null),
+                    scale,
+                    regions,
+                )?;
+            }
+            Some("Feature") => {
+                let polygons = geometry(object.get("geometry").unwrap_or(&Value::Null), scale)?;
+                if !polygons.is_empty() {
+                    regions.push(polygons);
+                }
+            }
+            Some(_) => {
+                let polygons = geometry(value, scale)?;
+                if !polygons.is_empty() {
+                    regions.push(polygons);
+                }
+            }
+            None => return Err(invalid()),
+        },
+        _ => return Err(invalid()),
+    }
+    Ok(())
+}
+
+/// Reads the polygons of a GeoJSON geometry.
+fn geometry(value: &Value, scale: (f64, f64)) -> Result<Vec<Polygon>, String> {
+    let coordinates = || value.get("coordinates").unwrap_or(&Value::Null);
+    let polygon = |rings: &Value| -> Result<Polygon, String> {
+        let mut rings = list(rings)?.iter().map(|points| ring(points, scale));
+        Ok(Polygon {
+            outline: rings.next().ok_or("A polygon has no outline")??,
+            holes: rings.collect::<Result<_, _>>()?,
+        })
+    };
+
+    match value.get("type").and_then(Value::as_str) {
+        Some("Polygon") => Ok(vec![polygon(coordinates())?]),
+        Some("MultiPolygon") => list(coordinates())?.iter().map(polygon).collect(),
+        Some("GeometryCollection") => {
+            let mut polygons = Vec::new();
+            for geometry in list(value.get("geometries").unwrap_or(&Value::Null))? {
+                polygons.extend(self::geometry(geometry, scale)?);
+            }
+            Ok(polygons)
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn list(value: &Value) -> Result<&Vec<Value>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("Expected a list, but found {}", value))
+}
+
+/// Reads a ring of `[x, y]` points in pixels, as fractions of the image's size.
+fn ring(value: &Value, (width, height): (f64, f64)) -> Result<Ring, String> {
+    let invalid = || format!("{} is not a list of [x, y] points", value);
+    let points: Ring = list(value)
+        .map_err(|_| invalid())?
+        .iter()
+        .map(|point| match point.as_array().map(Vec::as_slice) {
+            Some([x, y, ..]) => x.as_f64().zip(y.as_f64()),
+            _ => None,
+        })
+        .map(|point| point.map(|(x, y)| (x / width, y / height)))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    if points.len() < 3 {
+        return Err(format!("{} has fewer than 3 points", value));
+    }
+    Ok(points)
+}
+
+/// Checks whether a point is inside a ring, by counting how many of its edges a ray from the point crosses.
+fn contains(ring: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    edges(ring)
+        .filter(|&((x1, y1), (x2, y2))| {
+            (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1)
+        })
+        .count()
+        % 2
+        == 1
+}
+
+/// Edges of a ring, as pairs of points.
+fn edges(ring: &[(f64, f64)]) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+    ring.iter()
+        .copied()
+        .zip(ring.iter().copied().cycle().skip(1))
+}
+
+/// Checks whether an edge passes through a rectangle, by clipping it to each side in turn.
+fn crosses(
+    ((x1, y1), (x2, y2)): ((f64, f64), (f64, f64)),
+    [left, top, right, bottom]: Rect,
+) -> bool {
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let (mut enter, mut exit) = (0.0f64, 1.0f64);
+    for (direction, distance) in [
+        (-dx, x1 - left),
+        (dx, right - x1),
+        (-dy, y1 - top),
+        (dy, bottom - y1),
+    ] {
+        if direction == 0.0 {
+            if distance < 0.0 {
+                return false;
+            }
+        } else if direction < 0.0 {
+            enter = enter.max(distance / direction);
+        } else {
+            exit = exit.min(distance / direction);
+        }
+    }
+    enter <= exit
+}
+
+/// Center of a rectangle.
+fn center([left, top, right, bottom]: Rect) -> (f64, f64) {
+    ((left + right) / 2.0, (top + bottom) / 2.0)
+}
+
+/// Checks whether a ring and a rectangle overlap: either an edge of the ring passes through the rectangle,
+/// or the rectangle is inside the ring.
+fn meets(ring: &[(f64, f64)], rect: Rect) -> bool {
+    edges(ring).any(|edge| crosses(edge, rect)) || contains(ring, center(rect))
+}
+
+/// Checks whether a rectangle is entirely inside a ring.
+fn encloses(ring: &[(f64, f64)], rect: Rect) -> bool {
+    !edges(ring).any(|edge| crosses(edge, rect)) && contains(ring, center(rect))
+}
@@ -0,0 +1,129 @@
+use crate::diagnostic;
+use crate::i18n;
+use crate::interrupt;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// A file that was found but couldn't be split.
+#[derive(Serialize)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub error: String,
+    /// A suggestion of how to split the file, kept apart so the error stays on one line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+}
+
+/// Collects the files skipped by parallel workers.
+#[derive(Default)]
+pub struct SkippedFiles {
+    files: Mutex<Vec<SkippedFile>>,
+}
+
+impl SkippedFiles {
+    /// Records a file that couldn't be split.
+    pub fn push(&self, path: PathBuf, error: impl ToString) {
+        self.files.lock().unwrap().push(SkippedFile {
+            path,
+            error: error.to_string(),
+            help: None,
+        });
+    }
+
+    /// Records a file that couldn't be split, with a suggestion of how to split it.
+    pub fn push_with_help(&self, path: PathBuf, error: impl ToString, help: impl ToString) {
+        self.files.lock().unwrap().push(SkippedFile {
+            path,
+            error: error.to_string(),
+            help: Some(help.to_string()),
+        });
+    }
+
+    /// Takes the recorded files, ordered by path.
+    pub fn into_sorted(self) -> Vec<SkippedFile> {
+        let mut files = self.files.into_inner().unwrap();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files
+    }
+}
+
+/// Prints a list of skipped files to standard error.
+pub fn print_skipped(files: &[SkippedFile]) {
+    if files.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "{}",
+        i18n::message("skipped-files", &[("count", &files.len())])
+    );
+    for file in files {
+        let error = match &file.help {
+            Some(help) => diagnostic::with_help(&file.error, help),
+            None => file.error.clone(),
+        };
+        // Lines below the error, such as suggestions, are indented under it.
+        eprintln!("  {}: {}", file.path.display(), error.replace('\n', "\n  "));
+    }
+}
+
+/// Writes a list of skipped files as JSON.
+///
+/// # Arguments
+///
+/// * `path` - Path of the report file to write.
+/// * `files` - Skipped files to list.
+pub fn write_skipped(path: &Path, files: &[SkippedFile]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, files)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+/// Decides whether a run keeps going after an error, and counts the errors that occurred.
+pub struct ErrorPolicy {
+    fail_fast: bool,
+    errors: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+impl ErrorPolicy {
+    /// # Arguments
+    ///
+    /// * `fail_fast` - Whether to stop at the first error, rather than continuing and reporting at the end.
+    pub fn new(fail_fast: bool) -> Self {
+        ErrorPolicy {
+            fail_fast,
+            errors: AtomicUsize::new(0),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Records an error that kept a file or tile from being split or saved.
+    /// A full disk stops the run regardless of the policy, since no further tiles could be saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of the underlying I/O error, if any.
+    pub fn record(&self, kind: Option<io::ErrorKind>) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+
+        if self.fail_fast || kind == Some(io::ErrorKind::StorageFull) {
+            self.stopped.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether no further work should be started, because of an error or because the run was interrupted.
+    pub fn stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed) || interrupt::interrupted()
+    }
+
+    /// The number of errors recorded.
+    pub fn errors(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
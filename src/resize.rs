@@ -0,0 +1,354 @@
+use crate::marks;
+use clap::ValueEnum;
+use image::imageops::{self, FilterType};
+use image::{ColorType, DynamicImage, GenericImageView};
+use std::cmp;
+
+/// The filter used to resample an image when resizing it.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ResizeFilter {
+    /// Nearest neighbor. Keeps hard edges, such as in pixel art.
+    Nearest,
+    /// Linear interpolation.
+    Triangle,
+    /// Cubic interpolation.
+    #[value(name = "catmullrom")]
+    CatmullRom,
+    /// Gaussian blur.
+    Gaussian,
+    /// Lanczos with a window of 3. Sharpest for photos.
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// How images are resampled when they're resized.
+#[derive(Clone, Copy)]
+pub struct Resampler {
+    pub filter: ResizeFilter,
+    /// Whether to resample in linear light instead of directly on sRGB values,
+    /// which would darken fine detail such as text and thin lines.
+    pub linear: bool,
+}
+
+impl Resampler {
+    /// Scales an image to an exact size, ignoring its aspect ratio.
+    ///
+    /// # Arguments
+    ///
+    /// * `img` - Image to scale.
+    /// * `width` - Width to scale to.
+    /// * `height` - Height to scale to.
+    ///
+    /// # Returns
+    ///
+    /// The scaled image, in the same color type as the original.
+    pub fn resize_exact(&self, img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        // Nearest neighbor doesn't blend pixels, and float images are already linear.
+        if !self.linear
+            || matches!(self.filter, ResizeFilter::Nearest)
+            || matches!(img.color(), ColorType::Rgb32F | ColorType::Rgba32F)
+        {
+            return img.resize_exact(width, height, self.filter.into());
+        }
+
+        let mut linear = img.to_rgba32f();
+        for pixel in linear.pixels_mut() {
+            for value in &mut pixel.0[..3] {
+                *value = srgb_to_linear(*value);
+            }
+        }
+
+        let mut scaled = imageops::resize(&linear, width, height, self.filter.into());
+        for pixel in scaled.pixels_mut() {
+            for value in &mut pixel.0[..3] {
+                *value = linear_to_srgb(value.clamp(0.0, 1.0));
+            }
+            pixel.0[3] = pixel.0[3].clamp(0.0, 1.0);
+        }
+
+        to_color_type(DynamicImage::ImageRgba32F(scaled), img.color())
+    }
+
+    /// Scales an image to fit inside a size, keeping its aspect ratio.
+    ///
+    /// # Arguments
+    ///
+    /// * `img` - Image to scale.
+    /// * `width` - Largest width of the scaled image.
+    /// * `height` - Largest height of the scaled image.
+    ///
+    /// # Returns
+    ///
+    /// The scaled image.
+    pub fn resize(&self, img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        let (scaled_width, scaled_height) = scaled_size(img.dimensions(), (width, height), false);
+        self.resize_exact(img, scaled_width, scaled_height)
+    }
+
+    /// Scales an image to cover a size, keeping its aspect ratio, and crops what's left over around its centre.
+    ///
+    /// # Arguments
+    ///
+    /// * `img` - Image to scale.
+    /// * `width` - Width of the cropped image.
+    /// * `height` - Height of the cropped image.
+    ///
+    /// # Returns
+    ///
+    /// The scaled and cropped image.
+    pub fn resize_to_fill(&self, img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        let (scaled_width, scaled_height) = scaled_size(img.dimensions(), (width, height), true);
+        self.resize_exact(img, scaled_width, scaled_height)
+            .crop_imm(
+                (scaled_width - width) / 2,
+                (scaled_height - height) / 2,
+                width,
+                height,
+            )
+    }
+}
+
+/// Finds the size of an image scaled to fit inside or cover a size, keeping its aspect ratio,
+/// rounded the same way as the `image` crate.
+fn scaled_size(
+    (width, height): (u32, u32),
+    (to_width, to_height): (u32, u32),
+    cover: bool,
+) -> (u32, u32) {
+    let width_ratio = to_width as f64 / width as f64;
+    let height_ratio = to_height as f64 / height as f64;
+    let ratio = if cover {
+        width_ratio.max(height_ratio)
+    } else {
+        width_ratio.min(height_ratio)
+    };
+
+    let scaled_width = ((width as f64 * ratio).round() as u32).max(1);
+    let scaled_height = ((height as f64 * ratio).round() as u32).max(1);
+    if cover {
+        (scaled_width.max(to_width), scaled_height.max(to_height))
+    } else {
+        (scaled_width.min(to_width), scaled_height.min(to_height))
+    }
+}
+
+/// Converts an sRGB value from 0 to 1 to linear light.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a value in linear light from 0 to 1 to sRGB.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts an image to a color type, such as back to its original type after processing it in another.
+///
+/// # Arguments
+///
+/// * `img` - Image to convert.
+/// * `color` - Color type to convert to. Types the `image` crate can't store are converted to RGBA.
+///
+/// # Returns
+///
+/// The converted image.
+pub fn to_color_type(img: DynamicImage, color: ColorType) -> DynamicImage {
+    match color {
+        ColorType::L8 => DynamicImage::ImageLuma8(img.into_luma8()),
+        ColorType::La8 => DynamicImage::ImageLumaA8(img.into_luma_alpha8()),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(img.into_rgb8()),
+        ColorType::L16 => DynamicImage::ImageLuma16(img.into_luma16()),
+        ColorType::La16 => DynamicImage::ImageLumaA16(img.into_luma_alpha16()),
+        ColorType::Rgb16 => DynamicImage::ImageRgb16(img.into_rgb16()),
+        ColorType::Rgba16 => DynamicImage::ImageRgba16(img.into_rgba16()),
+        ColorType::Rgb32F => DynamicImage::ImageRgb32F(img.into_rgb32f()),
+        ColorType::Rgba32F => DynamicImage::ImageRgba32F(img.into_rgba32f()),
+        _ => DynamicImage::ImageRgba8(img.into_rgba8()),
+    }
+}
+
+/// The color type with an alpha channel added, if it doesn't have one, keeping its bit depth.
+pub fn with_alpha(color: ColorType) -> ColorType {
+    match color {
+        ColorType::L8 => ColorType::La8,
+        ColorType::Rgb8 => ColorType::Rgba8,
+        ColorType::L16 => ColorType::La16,
+        ColorType::Rgb16 => ColorType::Rgba16,
+        ColorType::Rgb32F => ColorType::Rgba32F,
+        color => color,
+    }
+}
+
+/// Scales an image up, keeping its aspect ratio, until it's at least a given size.
+/// Each side is then rounded up to a multiple of the minimum, so a grid of that many sections divides it evenly.
+///
+/// # Arguments
+///
+/// * `img` - Image to scale.
+/// * `min_width` - Smallest width the scaled image may have.
+/// * `min_height` - Smallest height the scaled image may have.
+/// * `resampler` - How the image is resampled.
+///
+/// # Returns
+///
+/// The scaled image.
+pub fn upscale(
+    img: &DynamicImage,
+    min_width: u32,
+    min_height: u32,
+    resampler: Resampler,
+) -> DynamicImage {
+    let (width, height) = upscaled_size(img.dimensions(), min_width, min_height);
+    resampler.resize_exact(img, width, height)
+}
+
+/// Pads an image with transparent pixels on its right and bottom edges until it's at least a given size,
+/// so a grid of that many sections fits without scaling it.
+///
+/// # Arguments
+///
+/// * `img` - Image to pad.
+/// * `min_width` - Smallest width the padded image may have.
+/// * `min_height` - Smallest height the padded image may have.
+///
+/// # Returns
+///
+/// The padded image.
+pub fn pad(img: DynamicImage, min_width: u32, min_height: u32) -> DynamicImage {
+    let color = with_alpha(img.color());
+    let mut padded = DynamicImage::new(
+        img.width().max(min_width),
+        img.height().max(min_height),
+        color,
+    );
+    marks::copy_tile(&mut padded, &to_color_type(img, color), 0, 0);
+    padded
+}
+
+/// Finds the size `upscale` scales an image to, without scaling it.
+///
+/// # Arguments
+///
+/// * `(width, height)` - Size of the image.
+/// * `min_width` - Smallest width the scaled image may have.
+/// * `min_height` - Smallest height the scaled image may have.
+///
+/// # Returns
+///
+/// The size of the scaled image.
+pub fn upscaled_size((width, height): (u32, u32), min_width: u32, min_height: u32) -> (u32, u32) {
+    let scale = f64::max(
+        min_width as f64 / width as f64,
+        min_height as f64 / height as f64,
+    );
+
+    (
+        cmp::max(min_width, (width as f64 * scale).ceil() as u32).next_multiple_of(min_width),
+        cmp::max(min_height, (height as f64 * scale).ceil() as u32).next_multiple_of(min_height),
+    )
+}
+
+/// Crops an image around its centre to an aspect ratio, keeping as much of it as possible.
+///
+/// # Arguments
+///
+/// * `img` - Image to crop.
+/// * `aspect` - Aspect ratio, width over height, to crop to.
+///
+/// # Returns
+///
+/// The cropped image.
+pub fn crop_to_aspect(img: &DynamicImage, aspect: f64) -> DynamicImage {
+    let (width, height) = (img.width(), img.height());
+    let (crop_width, crop_height) = aspect_size((width, height), aspect);
+
+    img.crop_imm(
+        (width - crop_width) / 2,
+        (height - crop_height) / 2,
+        crop_width,
+        crop_height,
+    )
+}
+
+/// Finds the size `crop_to_aspect` crops an image to, without cropping it.
+///
+/// # Arguments
+///
+/// * `(width, height)` - Size of the image.
+/// * `aspect` - Aspect ratio, width over height, to crop to.
+///
+/// # Returns
+///
+/// The size of the cropped image.
+pub fn aspect_size((width, height): (u32, u32), aspect: f64) -> (u32, u32) {
+    if width as f64 / height as f64 > aspect {
+        (
+            ((height as f64 * aspect).round() as u32).clamp(1, width),
+            height,
+        )
+    } else {
+        (
+            width,
+            ((width as f64 / aspect).round() as u32).clamp(1, height),
+        )
+    }
+}
+
+/// How a tile is fitted to a size with a different aspect ratio.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TileFit {
+    /// Scale the tile to cover the size and crop what's left over around its centre.
+    Fill,
+    /// Scale the tile to fit inside the size and pad it with transparency around its centre.
+    Pad,
+}
+
+/// Scales a tile to an exact size, keeping its bit depth.
+///
+/// # Arguments
+///
+/// * `img` - Tile to scale.
+/// * `(width, height)` - Size to scale the tile to.
+/// * `fit` - How the tile is fitted if its aspect ratio differs.
+/// * `resampler` - How the tile is resampled.
+///
+/// # Returns
+///
+/// The scaled tile.
+pub fn fit_tile(
+    img: &DynamicImage,
+    (width, height): (u32, u32),
+    fit: TileFit,
+    resampler: Resampler,
+) -> DynamicImage {
+    match fit {
+        TileFit::Fill => resampler.resize_to_fill(img, width, height),
+        TileFit::Pad => {
+            let scaled = resampler.resize(img, width, height);
+            let (x, y) = ((width - scaled.width()) / 2, (height - scaled.height()) / 2);
+            let color = with_alpha(scaled.color());
+            let mut padded = DynamicImage::new(width, height, color);
+            marks::copy_tile(&mut padded, &to_color_type(scaled, color), x, y);
+            padded
+        }
+    }
+}
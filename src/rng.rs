@@ -0,0 +1,55 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small, seedable random number generator (SplitMix64).
+/// The same seed produces the same numbers on every platform.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Picks a seed from the current time, for runs where no seed was specified.
+    pub fn random_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Picks `count` distinct items, keeping them in their original order.
+    pub fn sample<T>(&mut self, items: Vec<T>, count: usize) -> Vec<T> {
+        let mut indices: Vec<usize> = (0..items.len()).collect();
+        let count = count.min(items.len());
+
+        for i in 0..count {
+            let j = i + self.below((indices.len() - i) as u64) as usize;
+            indices.swap(i, j);
+        }
+
+        let mut chosen = vec![false; items.len()];
+        for &i in &indices[..count] {
+            chosen[i] = true;
+        }
+
+        items
+            .into_iter()
+            .zip(chosen)
+            .filter_map(|(item, chosen)| chosen.then_some(item))
+            .collect()
+    }
+}
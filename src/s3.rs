@@ -0,0 +1,236 @@
+use crate::output::UploadOptions;
+use crate::semaphore::Semaphore;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Uploads tiles to a bucket in S3-compatible object storage.
+///
+/// Credentials and endpoint are read from the standard AWS environment variables:
+/// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN`, `AWS_REGION`
+/// (or `AWS_DEFAULT_REGION`), and `AWS_ENDPOINT_URL` for services other than AWS.
+pub struct S3Output {
+    bucket: String,
+    prefix: String,
+    region: String,
+    /// Scheme and host requests are sent to.
+    endpoint: String,
+    /// Whether the bucket is part of the path rather than the host name.
+    path_style: bool,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    uploads: Semaphore,
+    retries: u32,
+}
+
+impl S3Output {
+    /// Configures uploads to a bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The `bucket/prefix` part of an `s3://` URL.
+    /// * `upload` - Concurrency and retry options.
+    pub fn new(url: &str, upload: UploadOptions) -> Result<Self, String> {
+        let (bucket, prefix) = url.split_once('/').unwrap_or((url, ""));
+        if bucket.is_empty() {
+            return Err("splix: output-dir: The S3 URL must include a bucket name".to_string());
+        }
+
+        let access_key = env::var("AWS_ACCESS_KEY_ID").map_err(|_| {
+            "splix: output-dir: AWS_ACCESS_KEY_ID must be set to upload to S3".to_string()
+        })?;
+        let secret_key = env::var("AWS_SECRET_ACCESS_KEY").map_err(|_| {
+            "splix: output-dir: AWS_SECRET_ACCESS_KEY must be set to upload to S3".to_string()
+        })?;
+        let region = env::var("AWS_REGION")
+            .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".to_string());
+        let custom_endpoint = env::var("AWS_ENDPOINT_URL").ok();
+
+        Ok(S3Output {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+            endpoint: match &custom_endpoint {
+                Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+                None => format!("https://{}.s3.{}.amazonaws.com", bucket, region),
+            },
+            path_style: custom_endpoint.is_some(),
+            region,
+            access_key,
+            secret_key,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+            uploads: Semaphore::new(upload.jobs),
+            retries: upload.retries,
+        })
+    }
+
+    /// Builds the object key of a tile.
+    fn key(&self, name: &Path) -> String {
+        let name = name.to_string_lossy().replace('\\', "/");
+        if self.prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", self.prefix, name)
+        }
+    }
+
+    /// The `s3://` URL of a tile.
+    pub fn url(&self, name: &Path) -> String {
+        format!("s3://{}/{}", self.bucket, self.key(name))
+    }
+
+    /// Uploads a tile, retrying with exponential backoff if the request fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Path of the tile relative to the prefix.
+    /// * `bytes` - Encoded tile.
+    /// * `content_type` - MIME type of the encoded tile.
+    pub fn put(&self, name: &Path, bytes: &[u8], content_type: &str) -> Result<(), String> {
+        let _upload = self.uploads.acquire();
+        let key = self.key(name);
+        let mut attempt = 0;
+
+        loop {
+            match self.try_put(&key, bytes, content_type) {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt >= self.retries => {
+                    return Err(format!(
+                        "splix: Failed to upload image {}: {}",
+                        self.url(name),
+                        err
+                    ))
+                }
+                Err(_) => {
+                    thread::sleep(Duration::from_millis(500 << attempt.min(6)));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Sends a single signed `PutObject` request.
+    fn try_put(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), String> {
+        let path = if self.path_style {
+            format!("/{}/{}", uri_encode(&self.bucket), uri_encode(key))
+        } else {
+            format!("/{}", uri_encode(key))
+        };
+        let host = self
+            .endpoint
+            .split_once("://")
+            .map_or(self.endpoint.as_str(), |(_, host)| host);
+
+        let (amz_date, date) = timestamp(SystemTime::now());
+        let payload_hash = hex(&Sha256::digest(bytes));
+
+        let mut headers = vec![
+            ("host", host.to_string()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let canonical_request = format!(
+            "PUT\n{}\n\n{}\n{}\n{}",
+            path, canonical_headers, signed_headers, payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let mut signing_key = format!("AWS4{}", self.secret_key).into_bytes();
+        for part in [date.as_str(), &self.region, "s3", "aws4_request"] {
+            signing_key = hmac(&signing_key, part.as_bytes());
+        }
+        let signature = hex(&hmac(&signing_key, string_to_sign.as_bytes()));
+
+        let mut request = ureq::put(&format!("{}{}", self.endpoint, path))
+            .set("Content-Type", content_type)
+            .set(
+                "Authorization",
+                &format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            );
+        for (name, value) in &headers[1..] {
+            request = request.set(name, value);
+        }
+
+        request
+            .send_bytes(bytes)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent-encodes a path as required by SigV4, leaving `/` separators intact.
+fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Formats a time as the `YYYYMMDDTHHMMSSZ` and `YYYYMMDD` strings used in SigV4.
+fn timestamp(time: SystemTime) -> (String, String) {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Civil-from-days conversion, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let amz_date = format!(
+        "{}T{:02}{:02}{:02}Z",
+        date,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    );
+
+    (amz_date, date)
+}
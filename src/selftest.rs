@@ -0,0 +1,102 @@
+use crate::compare;
+use crate::encode::{self, EncodeOptions};
+use image::{DynamicImage, ImageBuffer, ImageFormat, Rgba};
+use rayon::prelude::*;
+use splix::grid::Cell;
+
+/// The result of splitting an image and joining its tiles back together.
+pub struct RoundTrip {
+    /// Number of tiles the image was split into.
+    pub tiles: usize,
+    /// Problems with individual tiles, such as tiles that couldn't be decoded.
+    pub errors: Vec<String>,
+    /// Peak signal-to-noise ratio of the joined image against the original, in decibels.
+    /// Infinite if every pixel matches.
+    pub psnr: f64,
+}
+
+impl RoundTrip {
+    /// Whether the joined image matches the original, exactly or within a PSNR for lossy formats.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Format the tiles were encoded in.
+    /// * `min_psnr` - Lowest PSNR, in decibels, accepted from a lossy format.
+    pub fn passed(&self, format: ImageFormat, min_psnr: f64) -> bool {
+        self.errors.is_empty()
+            && if is_lossy(format) {
+                self.psnr >= min_psnr
+            } else {
+                self.psnr.is_infinite()
+            }
+    }
+}
+
+/// Whether tiles in a format may differ from the pixels they were encoded from.
+pub fn is_lossy(format: ImageFormat) -> bool {
+    matches!(format, ImageFormat::Jpeg | ImageFormat::Avif)
+}
+
+/// Encodes each cell of an image, decodes it again, and joins the decoded tiles in memory.
+///
+/// # Arguments
+///
+/// * `img` - Image to split.
+/// * `cells` - Cells of the image.
+/// * `format` - Format to encode the tiles in.
+/// * `options` - Encoder settings.
+///
+/// # Returns
+///
+/// How closely the joined image matches the original.
+pub fn round_trip(
+    img: &DynamicImage,
+    cells: &[Cell],
+    format: ImageFormat,
+    options: &EncodeOptions,
+) -> RoundTrip {
+    let tiles: Vec<Result<(&Cell, DynamicImage), String>> = cells
+        .par_iter()
+        .map(|cell| {
+            let name = format!("r{}c{}", cell.row, cell.col);
+            let tile = img.crop_imm(cell.x, cell.y, cell.width, cell.height);
+            let bytes = encode::encode(&tile, format, options)
+                .map_err(|err| format!("{}: Failed to encode the tile: {}", name, err))?;
+            let decoded = image::load_from_memory_with_format(&bytes, format)
+                .map_err(|err| format!("{}: Failed to decode the tile: {}", name, err))?;
+
+            if (decoded.width(), decoded.height()) == (cell.width, cell.height) {
+                Ok((cell, decoded))
+            } else {
+                Err(format!(
+                    "{}: Expected {}x{} pixels, but the tile decoded as {}x{}",
+                    name,
+                    cell.width,
+                    cell.height,
+                    decoded.width(),
+                    decoded.height()
+                ))
+            }
+        })
+        .collect();
+
+    let mut joined: ImageBuffer<Rgba<u16>, Vec<u16>> = ImageBuffer::new(img.width(), img.height());
+    let mut errors = Vec::new();
+    for tile in tiles {
+        match tile {
+            Ok((cell, decoded)) => {
+                let decoded = decoded.to_rgba16();
+                for (x, y, pixel) in decoded.enumerate_pixels() {
+                    joined.put_pixel(cell.x + x, cell.y + y, *pixel);
+                }
+            }
+            Err(err) => errors.push(err),
+        }
+    }
+
+    RoundTrip {
+        tiles: cells.len(),
+        errors,
+        psnr: compare::psnr(&img.to_rgba16(), &joined),
+    }
+}
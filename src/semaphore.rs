@@ -0,0 +1,53 @@
+use std::sync::{Condvar, Mutex};
+
+/// A counting semaphore for limiting how many threads may use a resource at once.
+pub struct Semaphore {
+    capacity: usize,
+    permits: Mutex<usize>,
+    available: Condvar,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Semaphore {
+            capacity: permits,
+            permits: Mutex::new(permits),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a permit is available, then holds it until the returned guard is dropped.
+    pub fn acquire(&self) -> SemaphoreGuard<'_> {
+        self.acquire_many(1)
+    }
+
+    /// Blocks until several permits are available, then holds them until the returned guard is dropped.
+    /// Requests for more permits than the semaphore has wait for all of them instead.
+    pub fn acquire_many(&self, permits: usize) -> SemaphoreGuard<'_> {
+        let permits = permits.min(self.capacity);
+
+        let mut available = self.permits.lock().unwrap();
+        while *available < permits {
+            available = self.available.wait(available).unwrap();
+        }
+        *available -= permits;
+
+        SemaphoreGuard {
+            semaphore: self,
+            permits,
+        }
+    }
+}
+
+/// Permits acquired from a [`Semaphore`], released on drop.
+pub struct SemaphoreGuard<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl Drop for SemaphoreGuard<'_> {
+    fn drop(&mut self) {
+        *self.semaphore.permits.lock().unwrap() += self.permits;
+        self.semaphore.available.notify_all();
+    }
+}
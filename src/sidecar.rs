@@ -0,0 +1,209 @@
+use crate::diagnostic;
+use crate::presets::{self, Preset};
+use splix::grid::{self, BandLength, BandSize, Layout};
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Suffix added to an image's file name to find its sidecar, such as `photo.jpg.splix.toml`.
+pub const SUFFIX: &str = ".splix.toml";
+
+/// Options read from an image's sidecar, which override the run's for that image.
+pub struct Sidecar {
+    /// Path of the sidecar, for messages.
+    pub path: PathBuf,
+    /// How the image is divided, as a single unnamed grid.
+    pub layouts: Vec<(String, Layout)>,
+    /// The preset the image is cut and sized with, if the sidecar chose one.
+    pub preset: Option<Preset>,
+}
+
+/// Finds the path of an image's sidecar, whether or not it exists.
+pub fn path(image: &Path) -> PathBuf {
+    let mut name = OsString::from(image.as_os_str());
+    name.push(SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Reads the sidecar next to an image, if it has one.
+/// A sidecar is a small TOML file of `key = value` lines, with `#` comments, setting any of:
+/// `rows` and `cols`, as a number, a string such as `"2,3,1"`, or an array such as `["100px", "auto"]`,
+/// written as they would be given to `--rows` and `--cols`, and `preset`, as a string such as `"carousel"`.
+///
+/// # Arguments
+///
+/// * `image` - Path of the image.
+/// * `rows` - Rows of the run, for images whose sidecar sets only the columns.
+/// * `cols` - Columns of the run, for images whose sidecar sets only the rows.
+///
+/// # Returns
+///
+/// The sidecar, `None` if the image doesn't have one, or an error message if it can't be read.
+pub fn read(
+    image: &Path,
+    rows: Option<&[BandSize]>,
+    cols: Option<&[BandSize]>,
+) -> Result<Option<Sidecar>, String> {
+    let path = path(image);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        // An image whose name is nearly as long as names can be has no room for a sidecar.
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::InvalidFilename
+            ) =>
+        {
+            return Ok(None)
+        }
+        Err(err) => return Err(format!("splix: {}: {}", path.display(), err)),
+    };
+
+    let (mut own_rows, mut own_cols, mut preset) = (None, None, None);
+    for (number, line) in text.lines().enumerate() {
+        let invalid =
+            |message: String| format!("splix: {}:{}: {}", path.display(), number + 1, message);
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(invalid(format!("'{}' isn't a `key = value` line", line)));
+        };
+        let values = parse_value(value.trim()).map_err(invalid)?;
+
+        match key.trim() {
+            "rows" => own_rows = Some(bands(&values).map_err(invalid)?),
+            "cols" => own_cols = Some(bands(&values).map_err(invalid)?),
+            "preset" => match &values[..] {
+                [name] => preset = Some(presets::parse_preset(name).map_err(invalid)?),
+                _ => return Err(invalid("The preset must be a single name".to_string())),
+            },
+            key => {
+                let err = invalid(format!(
+                    "'{}' isn't an option sidecars can set: rows, cols, or preset",
+                    key
+                ));
+                return Err(
+                    match diagnostic::did_you_mean(key, ["rows", "cols", "preset"]) {
+                        Some(help) => diagnostic::with_help(err, help),
+                        None => err,
+                    },
+                );
+            }
+        }
+    }
+
+    // A preset with its own grid replaces the run's rows and columns, as it does on the command line.
+    let preset_grid = preset
+        .and_then(|preset: Preset| preset.grid)
+        .map(|(rows, cols)| {
+            let band = |size: u32| {
+                vec![BandSize {
+                    length: BandLength::Relative(size as f64),
+                    skip: false,
+                }]
+            };
+            (band(rows), band(cols))
+        });
+    let (rows, cols) = match preset_grid {
+        Some(_) if own_rows.is_some() || own_cols.is_some() => {
+            return Err(format!(
+                "splix: {}: This preset chooses its own rows and columns, so they can't be set too",
+                path.display()
+            ))
+        }
+        Some((rows, cols)) => (rows, cols),
+        None => (
+            own_rows.unwrap_or_else(|| rows.map_or_else(one, <[BandSize]>::to_vec)),
+            own_cols.unwrap_or_else(|| cols.map_or_else(one, <[BandSize]>::to_vec)),
+        ),
+    };
+
+    let invalid = |err: String| format!("splix: {}: {}", path.display(), err);
+    let (rows, skip_rows) = grid::parse_bands(&rows, "rows").map_err(invalid)?;
+    let (cols, skip_cols) = grid::parse_bands(&cols, "cols").map_err(invalid)?;
+    Ok(Some(Sidecar {
+        layouts: vec![(
+            String::new(),
+            Layout::Grid {
+                rows,
+                cols,
+                skip_rows,
+                skip_cols,
+            },
+        )],
+        path,
+        preset,
+    }))
+}
+
+/// A single band taking the whole image, for a side the sidecar and the run leave undivided.
+fn one() -> Vec<BandSize> {
+    vec![BandSize {
+        length: BandLength::Relative(1.0),
+        skip: false,
+    }]
+}
+
+/// Parses the sizes of `rows` or `cols`, each given as `--rows` would take it.
+fn bands(values: &[String]) -> Result<Vec<BandSize>, String> {
+    values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(str::parse)
+        .collect()
+}
+
+/// Parses a TOML value: a number, a quoted string, or an array of them.
+///
+/// # Returns
+///
+/// The value, or each item of an array, as text.
+fn parse_value(value: &str) -> Result<Vec<String>, String> {
+    match value.strip_prefix('[') {
+        Some(items) => {
+            let items = items
+                .strip_suffix(']')
+                .ok_or_else(|| format!("'{}' is missing its closing ']'", value))?;
+            items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(parse_scalar)
+                .collect()
+        }
+        None => parse_scalar(value).map(|value| vec![value]),
+    }
+}
+
+/// Parses a number or a quoted string.
+fn parse_scalar(value: &str) -> Result<String, String> {
+    for quote in ['"', '\''] {
+        if let Some(text) = value.strip_prefix(quote) {
+            return text
+                .strip_suffix(quote)
+                .map(str::to_string)
+                .ok_or_else(|| format!("{} is missing its closing quote", value));
+        }
+    }
+    if value.is_empty() {
+        return Err("A value is missing".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Removes a `#` comment from a line, leaving any `#` inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('#', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
@@ -0,0 +1,307 @@
+//! Destinations for tiles split by the library, so tiles can be routed anywhere,
+//! such as a database or an HTTP upload, by implementing [`TileSink`].
+
+use crate::grid::{grid_cells, Cell, SplitStrategy};
+use crate::split::{self, SplitError};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use image::{DynamicImage, ImageFormat};
+use rayon::prelude::*;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a sink is told about each tile besides its bytes.
+#[derive(Clone, Debug)]
+pub struct TileMeta {
+    /// Name of the tile, such as `r0c1.png`, which sinks storing files use as its path.
+    pub name: String,
+    /// Where the tile was cut from in the image.
+    pub cell: Cell,
+    /// Format the tile is encoded in.
+    pub format: ImageFormat,
+}
+
+/// A destination for tiles.
+pub trait TileSink {
+    /// Stores a tile.
+    ///
+    /// # Arguments
+    ///
+    /// * `meta` - Name, position, and format of the tile.
+    /// * `bytes` - The encoded tile.
+    fn write(&mut self, meta: &TileMeta, bytes: &[u8]) -> io::Result<()>;
+
+    /// Finishes storing tiles once the last one has been written, such as by writing the end of an archive.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Splits an encoded image held in memory and writes each tile to a sink, then finishes the sink.
+/// Tiles are named `r{row}c{col}.{ext}`.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded image.
+/// * `rows` - A single number of equal rows, or the relative height of each row.
+/// * `cols` - A single number of equal columns, or the relative width of each column.
+/// * `sink` - Where to write the tiles.
+///
+/// # Returns
+///
+/// The error that stopped the split, if any.
+pub fn split_to_sink(
+    bytes: &[u8],
+    rows: &[u32],
+    cols: &[u32],
+    sink: &mut impl TileSink,
+) -> Result<(), SplitError> {
+    let grid = |width, height, _: &DynamicImage| grid_cells(width, height, rows, cols);
+    split_to_sink_with(bytes, &grid, sink)
+}
+
+/// Splits an encoded image held in memory into the tiles a strategy chooses and writes each to a sink,
+/// then finishes the sink. Tiles are named `r{row}c{col}.{ext}`, after the row and column of their cells,
+/// and encoded in parallel on rayon's thread pool, a few at a time, but written in the order the strategy chose them.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded image.
+/// * `strategy` - Chooses the tiles to cut from the decoded image.
+/// * `sink` - Where to write the tiles.
+///
+/// # Returns
+///
+/// The error that stopped the split, if any.
+pub fn split_to_sink_with(
+    bytes: &[u8],
+    strategy: &(impl SplitStrategy + ?Sized),
+    sink: &mut impl TileSink,
+) -> Result<(), SplitError> {
+    let (img, format) = split::decode(bytes)?;
+    let cells = split::strategy_cells(strategy, &img);
+
+    // Only as many tiles as there are threads are held at once.
+    for cells in cells.chunks(rayon::current_num_threads()) {
+        let tiles = cells
+            .par_iter()
+            .map(|cell| split::encode(&img, cell, format))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (cell, data) in cells.iter().zip(tiles) {
+            let meta = TileMeta {
+                name: format!("r{}c{}.{}", cell.row, cell.col, format.extensions_str()[0]),
+                cell: *cell,
+                format,
+            };
+            sink.write(&meta, &data).map_err(SplitError::Write)?;
+        }
+    }
+
+    sink.finish().map_err(SplitError::Write)
+}
+
+/// Writes tiles as files in a directory, which is created if it doesn't exist.
+pub struct DirSink {
+    dir: PathBuf,
+}
+
+impl DirSink {
+    /// # Arguments
+    ///
+    /// * `dir` - Directory to write the tiles to.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DirSink { dir: dir.into() }
+    }
+}
+
+impl TileSink for DirSink {
+    fn write(&mut self, meta: &TileMeta, bytes: &[u8]) -> io::Result<()> {
+        let path = self.dir.join(&meta.name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)
+    }
+}
+
+/// Keeps tiles in memory, in the order they were written.
+#[derive(Default)]
+pub struct MemorySink {
+    /// Each tile and its bytes.
+    pub tiles: Vec<(TileMeta, Vec<u8>)>,
+}
+
+impl TileSink for MemorySink {
+    fn write(&mut self, meta: &TileMeta, bytes: &[u8]) -> io::Result<()> {
+        self.tiles.push((meta.clone(), bytes.to_vec()));
+        Ok(())
+    }
+}
+
+/// Writes tiles as files in a tar archive.
+pub struct TarSink<W: Write> {
+    builder: tar::Builder<W>,
+}
+
+impl<W: Write> TarSink<W> {
+    /// # Arguments
+    ///
+    /// * `writer` - Where to write the archive.
+    pub fn new(writer: W) -> Self {
+        TarSink {
+            builder: tar::Builder::new(writer),
+        }
+    }
+
+    /// Finishes the archive if it hasn't been already, and returns its writer.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.builder.into_inner()
+    }
+}
+
+impl<W: Write> TileSink for TarSink<W> {
+    fn write(&mut self, meta: &TileMeta, bytes: &[u8]) -> io::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(unix_time());
+        self.builder.append_data(&mut header, &meta.name, bytes)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.builder.finish()
+    }
+}
+
+/// Writes tiles as files in a zip archive, compressed with deflate.
+/// Archives are limited to 65,535 tiles and 4 GiB, as zip64 isn't written.
+pub struct ZipSink<W: Write> {
+    writer: W,
+    /// Bytes written so far, which is the offset of the next entry.
+    offset: u64,
+    /// The central directory entry of each tile written so far.
+    directory: Vec<u8>,
+    entries: u16,
+}
+
+/// Signatures and fields of the zip format, from PKWARE's APPNOTE.TXT.
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP_END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP_VERSION: u16 = 20;
+const ZIP_DEFLATE: u16 = 8;
+/// Flag marking names as UTF-8.
+const ZIP_UTF8: u16 = 1 << 11;
+/// The MS-DOS date of 1980-01-01, the earliest a zip entry can have, used for every tile.
+const ZIP_DATE: u16 = (1 << 5) | 1;
+
+impl<W: Write> ZipSink<W> {
+    /// # Arguments
+    ///
+    /// * `writer` - Where to write the archive.
+    pub fn new(writer: W) -> Self {
+        ZipSink {
+            writer,
+            offset: 0,
+            directory: Vec::new(),
+            entries: 0,
+        }
+    }
+
+    /// Returns the writer, once the archive has been finished.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Adds a file to the archive, such as one that isn't a tile.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Path of the file in the archive, with directories separated by '/'.
+    /// * `bytes` - Contents of the file.
+    pub fn add(&mut self, name: &str, bytes: &[u8]) -> io::Result<()> {
+        let too_large = || io::Error::other("The zip archive is too large without zip64");
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes)?;
+        let compressed = encoder.finish()?;
+
+        let name = name.as_bytes();
+        let crc = crc32fast::hash(bytes);
+        let compressed_len = u32::try_from(compressed.len()).map_err(|_| too_large())?;
+        let len = u32::try_from(bytes.len()).map_err(|_| too_large())?;
+        let offset = u32::try_from(self.offset).map_err(|_| too_large())?;
+        let name_len = u16::try_from(name.len()).map_err(|_| too_large())?;
+        self.entries = self.entries.checked_add(1).ok_or_else(too_large)?;
+
+        // Fields shared by the local header and the central directory entry.
+        let mut common = Vec::with_capacity(26);
+        common.extend(ZIP_VERSION.to_le_bytes());
+        common.extend(ZIP_UTF8.to_le_bytes());
+        common.extend(ZIP_DEFLATE.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+        common.extend(ZIP_DATE.to_le_bytes());
+        common.extend(crc.to_le_bytes());
+        common.extend(compressed_len.to_le_bytes());
+        common.extend(len.to_le_bytes());
+        common.extend(name_len.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend(ZIP_LOCAL_HEADER.to_le_bytes());
+        local.extend(&common);
+        local.extend(name);
+        self.writer.write_all(&local)?;
+        self.writer.write_all(&compressed)?;
+
+        self.directory.extend(ZIP_CENTRAL_HEADER.to_le_bytes());
+        self.directory.extend(ZIP_VERSION.to_le_bytes());
+        self.directory.extend(&common);
+        // Comment length, disk number, internal and external attributes, then the local header's offset.
+        self.directory.extend(0u16.to_le_bytes());
+        self.directory.extend(0u16.to_le_bytes());
+        self.directory.extend(0u16.to_le_bytes());
+        self.directory.extend(0u32.to_le_bytes());
+        self.directory.extend(offset.to_le_bytes());
+        self.directory.extend(name);
+
+        self.offset += (local.len() + compressed.len()) as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write> TileSink for ZipSink<W> {
+    fn write(&mut self, meta: &TileMeta, bytes: &[u8]) -> io::Result<()> {
+        self.add(&meta.name, bytes)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let too_large = || io::Error::other("The zip archive is too large without zip64");
+        let offset = u32::try_from(self.offset).map_err(|_| too_large())?;
+        let directory_len = u32::try_from(self.directory.len()).map_err(|_| too_large())?;
+
+        let mut end = Vec::with_capacity(22);
+        end.extend(ZIP_END_OF_DIRECTORY.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+        end.extend(self.entries.to_le_bytes());
+        end.extend(self.entries.to_le_bytes());
+        end.extend(directory_len.to_le_bytes());
+        end.extend(offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+
+        self.writer.write_all(&self.directory)?;
+        self.writer.write_all(&end)?;
+        self.offset += (self.directory.len() + end.len()) as u64;
+        self.directory.clear();
+        self.writer.flush()
+    }
+}
+
+/// Seconds since the Unix epoch, for the modification time of archived tiles.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
@@ -0,0 +1,501 @@
+use image::error::{DecodingError, ImageFormatHint};
+#[cfg(feature = "openslide")]
+use image::{imageops::FilterType, RgbaImage};
+use image::{DynamicImage, GenericImageView, ImageError, ImageResult, Rgba};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Level chosen with `--slide-level`, which every slide in the run is read at.
+static LEVEL: OnceLock<SlideLevel> = OnceLock::new();
+
+/// Extensions of the whole-slide images OpenSlide reads, lowercase.
+/// Generic pyramidal TIFFs are left to `image`, which reads their full-size page.
+const SLIDE_EXTS: [&str; 8] = ["svs", "ndpi", "scn", "mrxs", "vms", "vmu", "bif", "svslide"];
+
+/// Brightness every channel of a pixel must reach for it to count as background, such as the bare glass of a slide.
+const BACKGROUND_BRIGHTNESS: u8 = 210;
+
+/// Which resolution of a whole-slide image to read, for `--slide-level`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlideLevel {
+    /// A level stored in the slide, from 0 for full resolution.
+    Level(u32),
+    /// An objective magnification, such as 20 for 20x, read from the nearest level at or above it.
+    Magnification(f64),
+}
+
+impl Default for SlideLevel {
+    fn default() -> Self {
+        SlideLevel::Level(0)
+    }
+}
+
+/// Parses a level for `--slide-level`.
+///
+/// # Arguments
+///
+/// * `level` - A level number, such as `2`, or a magnification ending in `x`, such as `20x`.
+///
+/// # Returns
+///
+/// The level, or an error message if it isn't valid.
+pub fn parse_level(level: &str) -> Result<SlideLevel, String> {
+    let level = level.trim().to_ascii_lowercase();
+    let parsed = match level.strip_suffix('x') {
+        Some(magnification) => magnification
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&magnification: &f64| magnification.is_finite() && magnification > 0.0)
+            .map(SlideLevel::Magnification),
+        None => level.parse().ok().map(SlideLevel::Level),
+    };
+    parsed.ok_or_else(|| {
+        format!(
+            "'{}' is not a slide level: a level number such as 2, or a magnification such as 20x",
+            level
+        )
+    })
+}
+
+/// Chooses the level every slide in the run is read at.
+pub fn set_level(level: SlideLevel) {
+    let _ = LEVEL.set(level);
+}
+
+/// Checks whether a file is a whole-slide image by its extension, if splix was built with the `openslide` feature.
+pub fn is_slide(path: &Path) -> bool {
+    cfg!(feature = "openslide")
+        && path.extension().is_some_and(|ext| {
+            SLIDE_EXTS
+                .iter()
+                .any(|slide| ext.eq_ignore_ascii_case(slide))
+        })
+}
+
+/// Checks whether an image is mostly background, such as the empty glass around the tissue on a slide.
+/// Pixels that are mostly transparent, or bright in every channel, are background.
+///
+/// # Arguments
+///
+/// * `img` - Image to check.
+/// * `min_content` - Percentage of pixels that must be content for the image not to be background.
+///
+/// # Returns
+///
+/// `true` if less than `min_content` percent of the image is content.
+pub fn is_background(img: &impl GenericImageView<Pixel = Rgba<u8>>, min_content: u8) -> bool {
+    let (width, height) = img.dimensions();
+    let total = u64::from(width) * u64::from(height);
+    let content = img
+        .pixels()
+        .filter(|(_, _, Rgba([r, g, b, a]))| {
+            *a >= 128 && [r, g, b].iter().any(|&&c| c < BACKGROUND_BRIGHTNESS)
+        })
+        .count() as u64;
+
+    content * 100 < u64::from(min_content) * total
+}
+
+/// Reads the width and height a slide is split at, at the level chosen with [`set_level`], without reading its pixels.
+pub fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    openslide::plan(path).map(|plan| plan.size)
+}
+
+/// Reads a slide at the level chosen with [`set_level`], which must fit in memory.
+/// A magnification between the slide's levels is scaled down from the level above it.
+pub fn decode(path: &Path) -> ImageResult<DynamicImage> {
+    openslide::read(path)
+}
+
+/// Checks whether slides can be read: splix was built with the `openslide` feature and OpenSlide is installed.
+///
+/// # Returns
+///
+/// An error message saying why slides can't be read, if they can't.
+pub fn check_library() -> Result<(), String> {
+    openslide::check_library()
+}
+
+fn invalid(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("whole-slide image".to_string()),
+        message,
+    ))
+}
+
+#[cfg(not(feature = "openslide"))]
+mod openslide {
+    use super::invalid;
+    use image::{DynamicImage, ImageError, ImageResult};
+    use std::path::Path;
+
+    pub struct Plan {
+        pub size: (u32, u32),
+    }
+
+    pub fn plan(_path: &Path) -> ImageResult<Plan> {
+        Err(unsupported())
+    }
+
+    pub fn read(_path: &Path) -> ImageResult<DynamicImage> {
+        Err(unsupported())
+    }
+
+    pub fn check_library() -> Result<(), String> {
+        Err("splix was built without the `openslide` feature".to_string())
+    }
+
+    fn unsupported() -> ImageError {
+        invalid("Slides require splix to be built with the `openslide` feature")
+    }
+}
+
+/// OpenSlide, loaded when the first slide is read, so splix runs without it until a slide is split.
+#[cfg(feature = "openslide")]
+mod openslide {
+    use super::{invalid, DynamicImage, FilterType, RgbaImage, SlideLevel, LEVEL};
+    use crate::reduce;
+    use image::ImageResult;
+    use libloading::Library;
+    use std::ffi::{c_char, c_void, CStr, CString};
+    use std::path::Path;
+    use std::sync::OnceLock;
+
+    /// Names OpenSlide's library is installed under, newest first.
+    #[cfg(target_os = "macos")]
+    const LIBRARY_NAMES: [&str; 2] = ["libopenslide.1.dylib", "libopenslide.0.dylib"];
+    #[cfg(windows)]
+    const LIBRARY_NAMES: [&str; 2] = ["libopenslide-1.dll", "libopenslide-0.dll"];
+    #[cfg(not(any(target_os = "macos", windows)))]
+    const LIBRARY_NAMES: [&str; 2] = ["libopenslide.so.1", "libopenslide.so.0"];
+
+    /// Rows read from a slide at a time, which bounds the buffer OpenSlide fills beside the image.
+    const BAND_ROWS: u32 = 512;
+
+    static API: OnceLock<Result<Api, String>> = OnceLock::new();
+
+    type Handle = *mut c_void;
+
+    /// The functions of OpenSlide splix calls, from openslide.h.
+    struct Api {
+        open: unsafe extern "C" fn(*const c_char) -> Handle,
+        close: unsafe extern "C" fn(Handle),
+        get_error: unsafe extern "C" fn(Handle) -> *const c_char,
+        get_level_count: unsafe extern "C" fn(Handle) -> i32,
+        get_level_dimensions: unsafe extern "C" fn(Handle, i32, *mut i64, *mut i64),
+        get_level_downsample: unsafe extern "C" fn(Handle, i32) -> f64,
+        get_property_value: unsafe extern "C" fn(Handle, *const c_char) -> *const c_char,
+        read_region: unsafe extern "C" fn(Handle, *mut u32, i64, i64, i32, i64, i64),
+        /// Kept loaded for as long as the functions above are called.
+        _library: Library,
+    }
+
+    impl Api {
+        fn load() -> Result<Api, String> {
+            for name in LIBRARY_NAMES {
+                // SAFETY: OpenSlide's library has no initializers with preconditions.
+                if let Ok(library) = unsafe { Library::new(name) } {
+                    return Api::bind(library);
+                }
+            }
+            Err(format!(
+                "OpenSlide isn't installed: none of {} could be loaded",
+                LIBRARY_NAMES.join(", ")
+            ))
+        }
+
+        fn bind(library: Library) -> Result<Api, String> {
+            // SAFETY: each symbol is given its signature from openslide.h.
+            unsafe {
+                Ok(Api {
+                    open: *symbol(&library, "openslide_open")?,
+                    close: *symbol(&library, "openslide_close")?,
+                    get_error: *symbol(&library, "openslide_get_error")?,
+                    get_level_count: *symbol(&library, "openslide_get_level_count")?,
+                    get_level_dimensions: *symbol(&library, "openslide_get_level_dimensions")?,
+                    get_level_downsample: *symbol(&library, "openslide_get_level_downsample")?,
+                    get_property_value: *symbol(&library, "openslide_get_property_value")?,
+                    read_region: *symbol(&library, "openslide_read_region")?,
+                    _library: library,
+                })
+            }
+        }
+    }
+
+    unsafe fn symbol<'a, T>(
+        library: &'a Library,
+        name: &str,
+    ) -> Result<libloading::Symbol<'a, T>, String> {
+        library
+            .get(name.as_bytes())
+            .map_err(|err| format!("OpenSlide's library is missing {}: {}", name, err))
+    }
+
+    /// An open slide, closed when dropped.
+    struct Slide {
+        api: &'static Api,
+        handle: Handle,
+    }
+
+    impl Slide {
+        fn open(path: &Path) -> ImageResult<Slide> {
+            let api = API
+                .get_or_init(Api::load)
+                .as_ref()
+                .map_err(|err| invalid(err))?;
+            let name = CString::new(path.to_string_lossy().into_owned())
+                .map_err(|_| invalid("The slide's path contains a NUL byte"))?;
+            // SAFETY: the name is a NUL-terminated string that outlives the call.
+            let handle = unsafe { (api.open)(name.as_ptr()) };
+            if handle.is_null() {
+                return Err(invalid("OpenSlide doesn't recognize the slide's format"));
+            }
+            let slide = Slide { api, handle };
+            slide.check()?;
+            Ok(slide)
+        }
+
+        /// Returns the error OpenSlide recorded, after which the slide can't be read any further.
+        fn check(&self) -> ImageResult<()> {
+            // SAFETY: the handle is open until the slide is dropped.
+            let error = unsafe { (self.api.get_error)(self.handle) };
+            if error.is_null() {
+                return Ok(());
+            }
+            // SAFETY: OpenSlide returns a NUL-terminated string that lives as long as the handle.
+            let error = unsafe { CStr::from_ptr(error) }.to_string_lossy();
+            Err(invalid(&format!(
+                "OpenSlide failed to read the slide: {}",
+                error
+            )))
+        }
+
+        fn level_count(&self) -> i32 {
+            // SAFETY: the handle is open until the slide is dropped.
+            unsafe { (self.api.get_level_count)(self.handle) }
+        }
+
+        fn level_size(&self, level: i32) -> (i64, i64) {
+            let (mut width, mut height) = (-1, -1);
+            // SAFETY: the handle is open, and both pointers are to live integers.
+            unsafe { (self.api.get_level_dimensions)(self.handle, level, &mut width, &mut height) };
+            (width, height)
+        }
+
+        fn downsample(&self, level: i32) -> f64 {
+            // SAFETY: the handle is open until the slide is dropped.
+            unsafe { (self.api.get_level_downsample)(self.handle, level) }
+        }
+
+        fn property(&self, name: &str) -> Option<String> {
+            let name = CString::new(name).ok()?;
+            // SAFETY: the handle is open, and the name is a NUL-terminated string that outlives the call.
+            let value = unsafe { (self.api.get_property_value)(self.handle, name.as_ptr()) };
+            // SAFETY: OpenSlide returns a NUL-terminated string that lives as long as the handle.
+            (!value.is_null()).then(|| {
+                unsafe { CStr::from_ptr(value) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+        }
+    }
+
+    impl Drop for Slide {
+        fn drop(&mut self) {
+            // SAFETY: the handle was opened by OpenSlide and is closed once.
+            unsafe { (self.api.close)(self.handle) };
+        }
+    }
+
+    /// The level a slide is read from, and the size it's scaled to.
+    pub struct Plan {
+        level: i32,
+        /// Size of the level, as it's read.
+        level_size: (u32, u32),
+        /// Ratio of the full-resolution level's size to this level's, to place regions read from it.
+        downsample: f64,
+        /// Size the level is scaled to, which is its own size unless a magnification between levels was chosen.
+        pub size: (u32, u32),
+    }
+
+    fn plan_slide(slide: &Slide) -> ImageResult<Plan> {
+        let count = slide.level_count();
+        if count < 1 {
+            slide.check()?;
+            return Err(invalid("The slide has no levels"));
+        }
+
+        let (level, magnification) = match LEVEL.get().copied().unwrap_or_default() {
+            SlideLevel::Level(level) => {
+                let level = i32::try_from(level)
+                    .ok()
+                    .filter(|&level| level < count)
+                    .ok_or_else(|| {
+                        invalid(&format!(
+                            "The slide has only {} levels, from 0 to {}",
+                            count,
+                            count - 1
+                        ))
+                    })?;
+                (level, None)
+            }
+            SlideLevel::Magnification(magnification) => {
+                let objective = slide
+                    .property("openslide.objective-power")
+                    .and_then(|power| power.trim().parse::<f64>().ok())
+                    .filter(|&power| power > 0.0)
+                    .ok_or_else(|| {
+                        invalid("The slide doesn't record its magnification, so choose a level by number")
+                    })?;
+                // The smallest level that still reaches the magnification, allowing for rounding in its downsample.
+                let level = (0..count)
+                    .rev()
+                    .find(|&level| objective / slide.downsample(level) >= magnification * 0.99)
+                    .ok_or_else(|| {
+                        invalid(&format!(
+                            "The slide was scanned at only {}x, below {}x",
+                            objective, magnification
+                        ))
+                    })?;
+                (level, Some(magnification / objective))
+            }
+        };
+
+        let fit = |(width, height): (i64, i64)| {
+            u32::try_from(width)
+                .ok()
+                .zip(u32::try_from(height).ok())
+                .filter(|&(width, height)| width > 0 && height > 0)
+                .ok_or_else(|| invalid("The slide's level is empty or too large to read"))
+        };
+        let level_size = fit(slide.level_size(level))?;
+        let size = match magnification {
+            Some(scale) => {
+                let (width, height) = fit(slide.level_size(0))?;
+                let scaled = |side: u32| ((f64::from(side) * scale).round() as u32).max(1);
+                let size = (scaled(width), scaled(height));
+                // Levels whose downsample rounds to the magnification are read as they are.
+                if size.0.abs_diff(level_size.0) <= 1 && size.1.abs_diff(level_size.1) <= 1 {
+                    level_size
+                } else {
+                    size
+                }
+            }
+            None => level_size,
+        };
+
+        Ok(Plan {
+            level,
+            level_size,
+            downsample: slide.downsample(level),
+            size,
+        })
+    }
+
+    pub fn plan(path: &Path) -> ImageResult<Plan> {
+        plan_slide(&Slide::open(path)?)
+    }
+
+    pub fn check_library() -> Result<(), String> {
+        API.get_or_init(Api::load)
+            .as_ref()
+            .map(|_| ())
+            .map_err(String::clone)
+    }
+
+    pub fn read(path: &Path) -> ImageResult<DynamicImage> {
+        let slide = Slide::open(path)?;
+        let plan = plan_slide(&slide)?;
+        let (width, height) = plan.level_size;
+        check_fits(&slide, &plan)?;
+
+        let mut image = RgbaImage::new(width, height);
+        let mut band = vec![0u32; width as usize * BAND_ROWS.min(height) as usize];
+        for top in (0..height).step_by(BAND_ROWS as usize) {
+            let rows = BAND_ROWS.min(height - top);
+            let band = &mut band[..width as usize * rows as usize];
+            // Regions are placed in the full-resolution level's coordinates, whatever level they're read from.
+            let y = (f64::from(top) * plan.downsample).round() as i64;
+            // SAFETY: the handle is open, and the band holds exactly the region's pixels.
+            unsafe {
+                (slide.api.read_region)(
+                    slide.handle,
+                    band.as_mut_ptr(),
+                    0,
+                    y,
+                    plan.level,
+                    i64::from(width),
+                    i64::from(rows),
+                )
+            };
+            slide.check()?;
+
+            for (pixel, &argb) in image
+                .chunks_exact_mut(4)
+                .skip(top as usize * width as usize)
+                .zip(band.iter())
+            {
+                pixel.copy_from_slice(&unpremultiply(argb));
+            }
+        }
+
+        let image = DynamicImage::ImageRgba8(image);
+        if plan.size == plan.level_size {
+            Ok(image)
+        } else {
+            Ok(image.resize_exact(plan.size.0, plan.size.1, FilterType::Triangle))
+        }
+    }
+
+    /// Checks that a slide's level, and the size it's scaled to, fit in memory once decoded,
+    /// since the whole level is read before it's split.
+    ///
+    /// # Returns
+    ///
+    /// An error naming the largest level that fits, if the chosen one doesn't.
+    fn check_fits(slide: &Slide, plan: &Plan) -> ImageResult<()> {
+        let bytes = |(width, height): (u32, u32)| u64::from(width) * u64::from(height) * 4;
+        let needed = bytes(plan.level_size).saturating_add(if plan.size == plan.level_size {
+            0
+        } else {
+            bytes(plan.size)
+        });
+        if reduce::fits_in_memory(needed) {
+            return Ok(());
+        }
+
+        let fits = (plan.level + 1..slide.level_count()).find(|&level| {
+            let (width, height) = slide.level_size(level);
+            u64::try_from(width)
+                .ok()
+                .zip(u64::try_from(height).ok())
+                .and_then(|(width, height)| width.checked_mul(height)?.checked_mul(4))
+                .is_some_and(reduce::fits_in_memory)
+        });
+        let (width, height) = plan.level_size;
+        Err(invalid(&match fits {
+            Some(level) => format!(
+                "Level {} of the slide is {}x{}, too large to read into memory; read a smaller level with '--slide-level {}'",
+                plan.level, width, height, level
+            ),
+            None => format!(
+                "Level {} of the slide is {}x{}, too large to read into memory, and no smaller level fits",
+                plan.level, width, height
+            ),
+        }))
+    }
+
+    /// Converts OpenSlide's premultiplied ARGB pixel, in native byte order, to straight RGBA.
+    fn unpremultiply(argb: u32) -> [u8; 4] {
+        let [a, r, g, b] = argb.to_be_bytes();
+        match a {
+            0 => [0, 0, 0, 0],
+            255 => [r, g, b, a],
+            _ => {
+                let straight = |c: u8| (u32::from(c) * 255 / u32::from(a)).min(255) as u8;
+                [straight(r), straight(g), straight(b), a]
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::path::Path;
+
+/// Space left on a volume.
+pub struct Space {
+    /// Bytes that can still be written.
+    pub available: u64,
+    /// Size of the blocks files are stored in, which each file takes up at least one of.
+    pub block_size: u64,
+}
+
+/// Finds how much space is left on the volume holding a directory.
+/// The directory doesn't have to exist yet, in which case its nearest existing parent is checked.
+///
+/// # Arguments
+///
+/// * `dir` - Directory that will be written to.
+///
+/// # Returns
+///
+/// The space left, or `None` if it can't be determined.
+#[cfg(unix)]
+pub fn available_space(dir: &Path) -> Option<Space> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = dir
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Path::new("."));
+    let path = CString::new(existing.as_os_str().as_bytes()).ok()?;
+
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid C string and `stats` is large enough for statvfs to fill in.
+    if unsafe { libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: statvfs succeeded, so it filled in `stats`.
+    let stats = unsafe { stats.assume_init() };
+
+    // The field types differ between platforms.
+    #[allow(clippy::unnecessary_cast)]
+    Some(Space {
+        available: stats.f_bavail as u64 * stats.f_frsize as u64,
+        block_size: stats.f_frsize as u64,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_dir: &Path) -> Option<Space> {
+    None
+}
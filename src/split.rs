@@ -0,0 +1,129 @@
+use crate::grid::{grid_cells, Cell, SplitStrategy};
+use image::{DynamicImage, ImageError, ImageFormat};
+use std::fmt;
+use std::io::{self, Cursor};
+use std::ops::ControlFlow;
+
+/// Why an encoded image couldn't be split.
+#[derive(Debug)]
+pub enum SplitError {
+    /// The bytes aren't an image in a supported format.
+    Decode(ImageError),
+    /// A tile couldn't be encoded.
+    Encode(ImageError),
+    /// A tile couldn't be written to its [`TileSink`](crate::sink::TileSink).
+    Write(io::Error),
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SplitError::Decode(err) => write!(f, "Failed to decode image: {}", err),
+            SplitError::Encode(err) => write!(f, "Failed to encode tile: {}", err),
+            SplitError::Write(err) => write!(f, "Failed to write tile: {}", err),
+        }
+    }
+}
+
+/// Splits an encoded image held in memory, without touching the file system or spawning threads.
+/// Tiles are encoded in the same format as the image, or as PNG if that format can't be written.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded image.
+/// * `rows` - A single number of equal rows, or the relative height of each row.
+/// * `cols` - A single number of equal columns, or the relative width of each column.
+/// * `tile` - Function called with each cell and its encoded tile, left to right, top to bottom.
+///   Returning `ControlFlow::Break` stops splitting.
+///
+/// # Returns
+///
+/// Whether every tile was passed to `tile`, or the error that stopped the split.
+pub fn split_encoded(
+    bytes: &[u8],
+    rows: &[u32],
+    cols: &[u32],
+    tile: impl FnMut(&Cell, &[u8]) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>, SplitError> {
+    let grid = |width, height, _: &DynamicImage| grid_cells(width, height, rows, cols);
+    split_with(bytes, &grid, tile)
+}
+
+/// Splits an encoded image held in memory into the tiles a strategy chooses,
+/// without touching the file system or spawning threads.
+/// Tiles are encoded in the same format as the image, or as PNG if that format can't be written.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded image.
+/// * `strategy` - Chooses the tiles to cut from the decoded image.
+/// * `tile` - Function called with each cell and its encoded tile, in the order the strategy chose them.
+///   Returning `ControlFlow::Break` stops splitting.
+///
+/// # Returns
+///
+/// Whether every tile was passed to `tile`, or the error that stopped the split.
+pub fn split_with(
+    bytes: &[u8],
+    strategy: &(impl SplitStrategy + ?Sized),
+    mut tile: impl FnMut(&Cell, &[u8]) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>, SplitError> {
+    let (img, format) = decode(bytes)?;
+
+    for cell in strategy_cells(strategy, &img) {
+        let data = encode(&img, &cell, format)?;
+        if tile(&cell, &data).is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Decodes an encoded image.
+///
+/// # Returns
+///
+/// The image, and the format its tiles are encoded in: its own, or PNG if that format can't be written.
+pub(crate) fn decode(bytes: &[u8]) -> Result<(DynamicImage, ImageFormat), SplitError> {
+    let format = image::guess_format(bytes).map_err(SplitError::Decode)?;
+    let img = image::load_from_memory_with_format(bytes, format).map_err(SplitError::Decode)?;
+    let format = if format.writing_enabled() {
+        format
+    } else {
+        ImageFormat::Png
+    };
+    Ok((img, format))
+}
+
+/// The cells a strategy chooses for an image, cut short at its edges, leaving out any outside it or without an area.
+pub(crate) fn strategy_cells(
+    strategy: &(impl SplitStrategy + ?Sized),
+    img: &DynamicImage,
+) -> Vec<Cell> {
+    let (width, height) = (img.width(), img.height());
+    strategy
+        .cells(width, height, img)
+        .into_iter()
+        .filter(|cell| cell.x < width && cell.y < height)
+        .map(|cell| Cell {
+            width: cell.width.min(width - cell.x),
+            height: cell.height.min(height - cell.y),
+            ..cell
+        })
+        .filter(|cell| cell.width > 0 && cell.height > 0)
+        .collect()
+}
+
+/// Encodes the tile of a cell.
+pub(crate) fn encode(
+    img: &DynamicImage,
+    cell: &Cell,
+    format: ImageFormat,
+) -> Result<Vec<u8>, SplitError> {
+    let mut data = Vec::new();
+    img.crop_imm(cell.x, cell.y, cell.width, cell.height)
+        .write_to(&mut Cursor::new(&mut data), format)
+        .map_err(SplitError::Encode)?;
+    Ok(data)
+}
@@ -0,0 +1,234 @@
+use crate::i18n;
+use crate::units;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// A stage of the pipeline whose time is measured.
+#[derive(Clone, Copy)]
+pub enum Stage {
+    /// Reading and decoding source images.
+    Decode,
+    /// Preparing images for cutting, such as scaling them to a canvas or down to each level.
+    Split,
+    /// Cutting, scaling, decorating, and encoding tiles.
+    Encode,
+    /// Writing files to the output.
+    Write,
+}
+
+/// Counts and timings collected from every stage of a run.
+#[derive(Default)]
+pub struct RunStats {
+    scanned: AtomicUsize,
+    processed: AtomicUsize,
+    tiles: AtomicUsize,
+    input_bytes: AtomicU64,
+    output_bytes: AtomicU64,
+    /// Nanoseconds spent in each stage, summed over every thread.
+    stage_nanos: [AtomicU64; 4],
+}
+
+impl RunStats {
+    /// Records the number of images found.
+    pub fn scanned(&self, files: usize) {
+        self.scanned.store(files, Ordering::Relaxed);
+    }
+
+    /// Records an image that was split.
+    pub fn processed(&self) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the size of a source image that was read.
+    pub fn read(&self, bytes: u64) {
+        self.input_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Records a file that was written.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Size of the file.
+    /// * `tile` - Whether the file is a tile, rather than another file such as a grid overlay.
+    pub fn wrote(&self, bytes: u64, tile: bool) {
+        self.output_bytes.fetch_add(bytes, Ordering::Relaxed);
+        if tile {
+            self.tiles.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Runs part of a stage, adding the time it takes to the stage's total.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - The stage the work is part of.
+    /// * `file` - Times of the source image the work is for, if any.
+    /// * `f` - The work.
+    pub fn time<T>(&self, stage: Stage, file: Option<&FileTimes>, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        let nanos = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+
+        self.stage_nanos[stage as usize].fetch_add(nanos, Ordering::Relaxed);
+        if let Some(file) = file {
+            file.nanos[stage as usize].fetch_add(nanos, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Sums up the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `elapsed` - Wall time of the run.
+    pub fn summary(&self, elapsed: Duration) -> Summary {
+        let scanned = self.scanned.load(Ordering::Relaxed);
+        let processed = self.processed.load(Ordering::Relaxed);
+        let secs = |stage: Stage| {
+            Duration::from_nanos(self.stage_nanos[stage as usize].load(Ordering::Relaxed))
+                .as_secs_f64()
+        };
+
+        Summary {
+            files_scanned: scanned,
+            files_processed: processed,
+            files_skipped: scanned.saturating_sub(processed),
+            tiles_written: self.tiles.load(Ordering::Relaxed),
+            input_bytes: self.input_bytes.load(Ordering::Relaxed),
+            output_bytes: self.output_bytes.load(Ordering::Relaxed),
+            wall_secs: elapsed.as_secs_f64(),
+            stage_secs: StageSecs {
+                decode: secs(Stage::Decode),
+                split: secs(Stage::Split),
+                encode: secs(Stage::Encode),
+                write: secs(Stage::Write),
+            },
+        }
+    }
+}
+
+/// What a run did, printed at its end and written with `--stats`.
+#[derive(Serialize)]
+pub struct Summary {
+    files_scanned: usize,
+    files_processed: usize,
+    files_skipped: usize,
+    tiles_written: usize,
+    input_bytes: u64,
+    output_bytes: u64,
+    wall_secs: f64,
+    stage_secs: StageSecs,
+}
+
+/// Seconds spent in each stage, summed over every thread, so they can add up to more than the wall time.
+#[derive(Serialize)]
+struct StageSecs {
+    decode: f64,
+    split: f64,
+    encode: f64,
+    write: f64,
+}
+
+impl Summary {
+    /// Prints the summary to standard error.
+    pub fn print(&self) {
+        let secs = |secs: f64| format!("{:.2}", secs);
+        eprintln!(
+            "{}",
+            i18n::message(
+                "split-summary",
+                &[
+                    ("processed", &self.files_processed),
+                    ("scanned", &self.files_scanned),
+                    ("tiles", &self.tiles_written),
+                    ("secs", &secs(self.wall_secs)),
+                ]
+            )
+        );
+        eprintln!(
+            "  {}",
+            i18n::message(
+                "split-bytes",
+                &[
+                    ("read", &units::format_size(self.input_bytes)),
+                    ("written", &units::format_size(self.output_bytes)),
+                ]
+            )
+        );
+        eprintln!(
+            "  {}",
+            i18n::message(
+                "split-stages",
+                &[
+                    ("decode", &secs(self.stage_secs.decode)),
+                    ("split", &secs(self.stage_secs.split)),
+                    ("encode", &secs(self.stage_secs.encode)),
+                    ("write", &secs(self.stage_secs.write)),
+                ]
+            )
+        );
+    }
+
+    /// Writes the summary as JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to write.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+}
+
+/// Time spent on one source image in each stage, summed over every thread, for `--profile`.
+#[derive(Default)]
+pub struct FileTimes {
+    nanos: [AtomicU64; 4],
+}
+
+impl FileTimes {
+    /// Seconds spent in a stage.
+    fn secs(&self, stage: Stage) -> f64 {
+        Duration::from_nanos(self.nanos[stage as usize].load(Ordering::Relaxed)).as_secs_f64()
+    }
+}
+
+/// Writes the time spent on each source image as CSV, slowest first,
+/// so the inputs that dominate a run's time stand out.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file to write.
+/// * `files` - Each source image and the time spent on it.
+pub fn write_profile(path: &Path, files: &[(&Path, &FileTimes)]) -> io::Result<()> {
+    let mut rows: Vec<(&Path, [f64; 3])> = files
+        .iter()
+        .map(|(path, times)| {
+            let secs = [Stage::Decode, Stage::Split, Stage::Encode].map(|stage| times.secs(stage));
+            (*path, secs)
+        })
+        .collect();
+    let total = |secs: &[f64; 3]| secs.iter().sum::<f64>();
+    rows.sort_by(|a, b| total(&b.1).total_cmp(&total(&a.1)).then(a.0.cmp(b.0)));
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "path,decode_secs,split_secs,encode_secs,total_secs")?;
+    for (path, secs) in rows {
+        writeln!(
+            writer,
+            "\"{}\",{:.6},{:.6},{:.6},{:.6}",
+            path.display().to_string().replace('"', "\"\""),
+            secs[0],
+            secs[1],
+            secs[2],
+            total(&secs)
+        )?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,40 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Limits how many bytes per second are read and written, shared by every thread.
+pub struct Throttle {
+    bytes_per_sec: u64,
+    /// When the bytes reserved so far will have been transferred at the limited rate.
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    /// # Arguments
+    ///
+    /// * `bytes_per_sec` - Maximum average rate of reads and writes.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Throttle {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Blocks until a transfer of some bytes fits within the rate, then lets it proceed.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Number of bytes about to be read or written.
+    pub fn take(&self, bytes: u64) {
+        let duration = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+
+        let start = {
+            let mut next = self.next.lock().unwrap();
+            let start = (*next).max(Instant::now());
+            *next = start + duration;
+            start
+        };
+
+        thread::sleep(start.saturating_duration_since(Instant::now()));
+    }
+}
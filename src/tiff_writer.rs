@@ -0,0 +1,308 @@
+use clap::ValueEnum;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::error::{EncodingError, ImageFormatHint};
+use image::{ColorType, DynamicImage, ImageError, ImageFormat, ImageResult};
+use std::io::Write;
+use weezl::encode::Encoder as LzwEncoder;
+use weezl::BitOrder;
+
+/// Uncompressed size each strip of pixels is kept under, so readers can decode them one at a time.
+const STRIP_SIZE: usize = 64 * 1024;
+
+/// TIFF field types.
+pub const ASCII: u16 = 2;
+pub const SHORT: u16 = 3;
+pub const LONG: u16 = 4;
+pub const RATIONAL: u16 = 5;
+
+/// Number, field type, and values of a tag. ASCII values are bytes, and rational values are pairs of numerator and denominator.
+pub type Tag = (u16, u16, Vec<u32>);
+
+/// An encoded image waiting to be written as a page of a TIFF, whose strips aren't placed in the file yet.
+pub struct TiffPage {
+    /// Compressed strips of pixels.
+    strips: Vec<Vec<u8>>,
+    /// Tags besides the offsets and lengths of the strips.
+    tags: Vec<Tag>,
+}
+
+/// How TIFF tiles are compressed.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum TiffCompression {
+    /// Store pixels as they are. Every TIFF reader can open these, but they're as large as the pixels.
+    #[default]
+    None,
+    /// LZW, which nearly every TIFF reader supports.
+    Lzw,
+    /// Deflate, usually smaller than LZW, and supported by most TIFF readers.
+    Deflate,
+    /// Zstandard, usually smaller and faster than Deflate, but only supported by recent readers such as libtiff 4.0.10 and later.
+    Zstd,
+}
+
+impl TiffCompression {
+    /// The value of the Compression tag.
+    fn tag(self) -> u32 {
+        match self {
+            TiffCompression::None => 1,
+            TiffCompression::Lzw => 5,
+            TiffCompression::Deflate => 8,
+            TiffCompression::Zstd => 50000,
+        }
+    }
+
+    /// Compresses a strip of pixels.
+    fn compress(self, strip: &[u8]) -> ImageResult<Vec<u8>> {
+        match self {
+            TiffCompression::None => Ok(strip.to_vec()),
+            TiffCompression::Lzw => LzwEncoder::with_tiff_size_switch(BitOrder::Msb, 8)
+                .encode(strip)
+                .map_err(encoding_error),
+            TiffCompression::Deflate => {
+                let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+                zlib.write_all(strip)?;
+                Ok(zlib.finish()?)
+            }
+            TiffCompression::Zstd => Ok(zstd::bulk::compress(strip, 0)?),
+        }
+    }
+}
+
+/// Encodes an image as a TIFF, which `image` does too, except that this can compress it.
+/// Compressed integer samples are stored as differences from their left neighbour,
+/// the horizontal predictor, which makes photos and scans compress far better.
+///
+/// # Arguments
+///
+/// * `img` - Image to encode.
+/// * `compression` - How to compress the pixels.
+///
+/// # Returns
+///
+/// The encoded image.
+pub fn encode(img: &DynamicImage, compression: TiffCompression) -> ImageResult<Vec<u8>> {
+    Ok(assemble(vec![encode_page(img, compression)?]))
+}
+
+/// Encodes an image as a page, to be written with others by [`assemble`].
+///
+/// # Arguments
+///
+/// * `img` - Image to encode.
+/// * `compression` - How to compress the pixels.
+///
+/// # Returns
+///
+/// The encoded page.
+pub fn encode_page(img: &DynamicImage, compression: TiffCompression) -> ImageResult<TiffPage> {
+    let color = img.color();
+    let (width, height) = (img.width(), img.height());
+    let samples = color.channel_count() as usize;
+    let sample_size = color.bytes_per_pixel() as usize / samples;
+    let float = matches!(color, ColorType::Rgb32F | ColorType::Rgba32F);
+    let predictor = compression != TiffCompression::None && !float;
+
+    let stride = width as usize * color.bytes_per_pixel() as usize;
+    let rows_per_strip = (STRIP_SIZE / stride.max(1)).clamp(1, height.max(1) as usize);
+
+    let mut strips = Vec::new();
+    for strip in img.as_bytes().chunks(stride * rows_per_strip) {
+        let mut strip = strip.to_vec();
+        for row in strip.chunks_exact_mut(stride) {
+            to_little_endian(row, sample_size);
+            if predictor {
+                differences(row, samples, sample_size);
+            }
+        }
+
+        strips.push(compression.compress(&strip)?);
+    }
+
+    let mut tags = vec![
+        (256, LONG, vec![width]),
+        (257, LONG, vec![height]),
+        (258, SHORT, vec![sample_size as u32 * 8; samples]),
+        (259, SHORT, vec![compression.tag()]),
+        // BlackIsZero for gray, or RGB.
+        (262, SHORT, vec![if color.has_color() { 2 } else { 1 }]),
+        (277, SHORT, vec![samples as u32]),
+        (278, LONG, vec![rows_per_strip as u32]),
+        // Samples of each pixel stored together.
+        (284, SHORT, vec![1]),
+        (317, SHORT, vec![if predictor { 2 } else { 1 }]),
+    ];
+    if color.has_alpha() {
+        // Unassociated alpha, which isn't premultiplied into the colors.
+        tags.push((338, SHORT, vec![2]));
+    }
+    // Unsigned integers or floats.
+    tags.push((339, SHORT, vec![if float { 3 } else { 1 }; samples]));
+
+    Ok(TiffPage { strips, tags })
+}
+
+impl TiffPage {
+    /// Adds tags to the page, such as to describe where it came from.
+    ///
+    /// # Arguments
+    ///
+    /// * `tags` - Tags to add, replacing any of the same number.
+    pub fn add_tags(&mut self, tags: impl IntoIterator<Item = Tag>) {
+        for tag in tags {
+            self.tags.retain(|(number, _, _)| *number != tag.0);
+            self.tags.push(tag);
+        }
+    }
+}
+
+/// Writes pages as one TIFF, in order.
+///
+/// # Arguments
+///
+/// * `pages` - Pages to write.
+///
+/// # Returns
+///
+/// The encoded file.
+pub fn assemble(pages: Vec<TiffPage>) -> Vec<u8> {
+    // Each page's strips are followed by its tags, and the header points to the first page's tags.
+    let mut bytes = vec![b'I', b'I', 42, 0, 0, 0, 0, 0];
+    let mut link = 4;
+    for page in pages {
+        let (mut offsets, mut counts) = (Vec::new(), Vec::new());
+        for strip in &page.strips {
+            offsets.push(bytes.len() as u32);
+            counts.push(strip.len() as u32);
+            bytes.extend_from_slice(strip);
+        }
+
+        let mut tags = page.tags;
+        tags.push((273, LONG, offsets));
+        tags.push((279, LONG, counts));
+        tags.sort_by_key(|(number, _, _)| *number);
+        link = write_tags(&mut bytes, &tags, link);
+    }
+    bytes
+}
+
+/// Appends an image file directory holding the tags, and points the header or the previous directory at it.
+///
+/// # Arguments
+///
+/// * `bytes` - The file so far.
+/// * `tags` - Number, field type, and values of each tag, in increasing order of number.
+/// * `link` - Offset of the pointer to the directory, in the header or the previous directory.
+///
+/// # Returns
+///
+/// The offset of this directory's pointer to the next.
+fn write_tags(bytes: &mut Vec<u8>, tags: &[Tag], link: usize) -> usize {
+    let size = |field_type: u16| match field_type {
+        ASCII => 1,
+        SHORT => 2,
+        _ => 4,
+    };
+    let put = |bytes: &mut Vec<u8>, field_type: u16, value: u32| match field_type {
+        ASCII => bytes.push(value as u8),
+        SHORT => bytes.extend_from_slice(&(value as u16).to_le_bytes()),
+        _ => bytes.extend_from_slice(&value.to_le_bytes()),
+    };
+
+    // Values that don't fit in their entry are stored before the directory, which starts on a word boundary.
+    let mut entries = Vec::new();
+    for (tag, field_type, values) in tags {
+        let offset = if values.len() * size(*field_type) > 4 {
+            bytes.resize(bytes.len().next_multiple_of(2), 0);
+            let offset = bytes.len() as u32;
+            for &value in values {
+                put(bytes, *field_type, value);
+            }
+            Some(offset)
+        } else {
+            None
+        };
+        entries.push((*tag, *field_type, values, offset));
+    }
+
+    bytes.resize(bytes.len().next_multiple_of(2), 0);
+    let directory = bytes.len() as u32;
+    bytes[link..link + 4].copy_from_slice(&directory.to_le_bytes());
+    bytes.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (tag, field_type, values, offset) in entries {
+        // Rationals count pairs of values.
+        let count = if field_type == RATIONAL {
+            values.len() / 2
+        } else {
+            values.len()
+        };
+        bytes.extend_from_slice(&tag.to_le_bytes());
+        bytes.extend_from_slice(&field_type.to_le_bytes());
+        bytes.extend_from_slice(&(count as u32).to_le_bytes());
+        match offset {
+            Some(offset) => bytes.extend_from_slice(&offset.to_le_bytes()),
+            None => {
+                let start = bytes.len();
+                for &value in values {
+                    put(bytes, field_type, value);
+                }
+                bytes.resize(start + 4, 0);
+            }
+        }
+    }
+    // No further directories, until another is linked here.
+    let next = bytes.len();
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    next
+}
+
+/// Reorders the bytes of each sample in a row from native to little-endian order.
+fn to_little_endian(row: &mut [u8], sample_size: usize) {
+    if cfg!(target_endian = "big") && sample_size > 1 {
+        for sample in row.chunks_exact_mut(sample_size) {
+            sample.reverse();
+        }
+    }
+}
+
+/// Replaces each integer sample in a little-endian row with its difference from the same channel of the pixel to its left.
+fn differences(row: &mut [u8], samples: usize, sample_size: usize) {
+    if sample_size == 1 {
+        for i in (samples..row.len()).rev() {
+            row[i] = row[i].wrapping_sub(row[i - samples]);
+        }
+    } else {
+        let step = samples * 2;
+        for i in (step..row.len()).step_by(2).rev() {
+            let value = u16::from_le_bytes([row[i], row[i + 1]]);
+            let left = u16::from_le_bytes([row[i - step], row[i - step + 1]]);
+            row[i..i + 2].copy_from_slice(&value.wrapping_sub(left).to_le_bytes());
+        }
+    }
+}
+
+/// Wraps an LZW error as an encoding error.
+fn encoding_error(err: weezl::LzwError) -> ImageError {
+    ImageError::Encoding(EncodingError::new(
+        ImageFormatHint::Exact(ImageFormat::Tiff),
+        err,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    #[test]
+    fn every_compression_decodes() {
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_fn(37, 21, |x, y| {
+            Rgb([(x * 7) as u8, (y * 11) as u8, ((x + y) * 3) as u8])
+        }));
+        for compression in TiffCompression::value_variants() {
+            let bytes = encode(&img, *compression).unwrap();
+            let decoded = image::load_from_memory_with_format(&bytes, ImageFormat::Tiff).unwrap();
+            assert_eq!(decoded.to_rgb8(), img.to_rgb8());
+        }
+    }
+}
@@ -0,0 +1,146 @@
+/// Parses a size in bytes, such as `512M` or `4GiB`.
+/// Suffixes are powers of 1024: `K`, `M`, `G`, and `T`, optionally followed by `B` or `iB`.
+///
+/// # Arguments
+///
+/// * `size` - Size to parse.
+///
+/// # Returns
+///
+/// The size in bytes, or an error message if it isn't a valid size.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let digits = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, suffix) = size.split_at(digits);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a size, such as 512M or 4G", size))?;
+
+    let multiplier: u64 = match suffix
+        .trim()
+        .trim_end_matches(['B', 'b'])
+        .trim_end_matches('i')
+        .to_ascii_uppercase()
+        .as_str()
+    {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "'{}' has an unknown unit, expected K, M, G, or T",
+                size
+            ))
+        }
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses dimensions written as `WIDTHxHEIGHT`, such as `640x480`.
+///
+/// # Arguments
+///
+/// * `dimensions` - Dimensions to parse.
+///
+/// # Returns
+///
+/// The width and height, or an error message if they aren't valid dimensions.
+pub fn parse_dimensions(dimensions: &str) -> Result<(u32, u32), String> {
+    dimensions
+        .split_once(['x', 'X'])
+        .and_then(|(width, height)| Some((width.trim().parse().ok()?, height.trim().parse().ok()?)))
+        .ok_or_else(|| format!("'{}' is not a size in pixels, such as 640x480", dimensions))
+}
+
+/// Parses a grid written as `COLSxROWS`, such as `4x3`.
+///
+/// # Arguments
+///
+/// * `grid` - Grid to parse.
+///
+/// # Returns
+///
+/// The number of columns and rows, or an error message if they aren't both whole numbers above zero.
+pub fn parse_grid(grid: &str) -> Result<(u32, u32), String> {
+    grid.split_once(['x', 'X'])
+        .and_then(|(cols, rows)| Some((cols.trim().parse().ok()?, rows.trim().parse().ok()?)))
+        .filter(|&(cols, rows)| cols > 0 && rows > 0)
+        .ok_or_else(|| {
+            format!(
+                "'{}' is not a number of columns and rows, such as 4x3",
+                grid
+            )
+        })
+}
+
+/// Parses a color written as hex, such as `#ff8800` or `#f80`, or as `white` or `black`.
+///
+/// # Arguments
+///
+/// * `color` - Color to parse.
+///
+/// # Returns
+///
+/// The red, green, and blue of the color, or an error message if it isn't a valid color.
+pub fn parse_color(color: &str) -> Result<[u8; 3], String> {
+    let invalid = || {
+        format!(
+            "'{}' is not a color, such as #ff8800, #f80, white, or black",
+            color
+        )
+    };
+
+    let hex = match color.trim().to_ascii_lowercase().as_str() {
+        "white" => return Ok([255, 255, 255]),
+        "black" => return Ok([0, 0, 0]),
+        hex => hex.trim_start_matches('#').to_string(),
+    };
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+    match hex.len() {
+        3 => {
+            let mut rgb = [0; 3];
+            for (value, digit) in rgb.iter_mut().zip(hex.chars()) {
+                *value = channel(&digit.to_string())? * 17;
+            }
+            Ok(rgb)
+        }
+        6 => Ok([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ]),
+        _ => Err(invalid()),
+    }
+}
+
+/// Formats a size in bytes for messages, such as `1.5 GiB`.
+///
+/// # Arguments
+///
+/// * `bytes` - Size to format.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Environment variable naming a directory to keep saved presets in instead of the user's config directory,
+/// such as a shared folder a team keeps its presets in.
+const CONFIG_DIR_VAR: &str = "SPLIX_CONFIG_DIR";
+
+/// A bundle of options saved with `splix preset save`.
+#[derive(Deserialize, Serialize)]
+struct SavedPreset {
+    /// Command line arguments, given before any others when the preset is used.
+    args: Vec<String>,
+}
+
+/// Finds the directory saved presets are kept in: `presets` inside `$SPLIX_CONFIG_DIR` if it's set,
+/// otherwise inside splix's directory of the platform's config directory.
+///
+/// # Returns
+///
+/// The directory, or an error message if there's no home directory to put it in.
+fn presets_dir() -> Result<PathBuf, String> {
+    let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+
+    let config_dir = if let Some(dir) = var(CONFIG_DIR_VAR) {
+        PathBuf::from(dir)
+    } else if cfg!(windows) {
+        var("APPDATA")
+            .map(|dir| PathBuf::from(dir).join("splix"))
+            .ok_or("splix: preset: %APPDATA% isn't set, so there's nowhere to keep presets")?
+    } else if cfg!(target_os = "macos") {
+        var("HOME")
+            .map(|dir| PathBuf::from(dir).join("Library/Application Support/splix"))
+            .ok_or("splix: preset: $HOME isn't set, so there's nowhere to keep presets")?
+    } else {
+        var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|dir| PathBuf::from(dir).join(".config")))
+            .map(|dir| dir.join("splix"))
+            .ok_or("splix: preset: $HOME isn't set, so there's nowhere to keep presets")?
+    };
+
+    Ok(config_dir.join("presets"))
+}
+
+/// Finds the file a preset is saved in.
+///
+/// # Arguments
+///
+/// * `name` - Name of the preset, made of letters, digits, '-', '_', and '.'.
+///
+/// # Returns
+///
+/// The path of the file, or an error message if the name isn't valid.
+fn preset_path(name: &str) -> Result<PathBuf, String> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "splix: preset: '{}' isn't a valid name. Use letters, digits, '-', '_', and '.'",
+            name
+        ));
+    }
+
+    Ok(presets_dir()?.join(format!("{}.json", name)))
+}
+
+/// Saves a preset, replacing any preset of the same name.
+///
+/// # Arguments
+///
+/// * `name` - Name of the preset.
+/// * `args` - Command line arguments the preset stands for.
+///
+/// # Returns
+///
+/// The path the preset was saved to, or an error message if it couldn't be saved.
+pub fn save(name: &str, args: &[String]) -> Result<PathBuf, String> {
+    let path = preset_path(name)?;
+    let failed = |err: &dyn fmt::Display| {
+        format!("splix: preset: Failed to save {}: {}", path.display(), err)
+    };
+
+    let json = serde_json::to_string_pretty(&SavedPreset {
+        args: args.to_vec(),
+    })
+    .map_err(|err| failed(&err))?;
+    fs::create_dir_all(path.parent().unwrap()).map_err(|err| failed(&err))?;
+    fs::write(&path, json + "\n").map_err(|err| failed(&err))?;
+
+    Ok(path)
+}
+
+/// Loads the arguments of a saved preset.
+///
+/// # Arguments
+///
+/// * `name` - Name of the preset.
+///
+/// # Returns
+///
+/// The preset's arguments, or an error message if there's no such preset or it can't be read.
+pub fn load(name: &str) -> Result<Vec<String>, String> {
+    let path = preset_path(name)?;
+    let json = fs::read_to_string(&path).map_err(|err| {
+        format!(
+            "splix: preset: Failed to read preset '{}' from {}: {}",
+            name,
+            path.display(),
+            err
+        )
+    })?;
+    let preset: SavedPreset = serde_json::from_str(&json).map_err(|err| {
+        format!(
+            "splix: preset: {} isn't a valid preset: {}",
+            path.display(),
+            err
+        )
+    })?;
+
+    Ok(preset.args)
+}
+
+/// Lists the saved presets.
+///
+/// # Returns
+///
+/// The name and arguments of each preset in alphabetical order, or an error message if the presets can't be read.
+pub fn list() -> Result<Vec<(String, Vec<String>)>, String> {
+    let dir = presets_dir()?;
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(format!(
+                "splix: preset: Failed to read directory {}: {}",
+                dir.display(),
+                err
+            ))
+        }
+    };
+
+    let mut presets = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        presets.push((name.clone(), load(&name)?));
+    }
+    presets.sort();
+
+    Ok(presets)
+}
@@ -0,0 +1,150 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::DirEntry;
+
+/// Files of ignore patterns read from each directory, in the order they're applied, so later files win.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".splixignore"];
+
+/// File and directory names created by operating systems and file managers, compared case-insensitively.
+const JUNK_NAMES: &[&str] = &[
+    ".ds_store",
+    ".spotlight-v100",
+    ".trashes",
+    ".fseventsd",
+    "__macosx",
+    "thumbs.db",
+    "ehthumbs.db",
+    "desktop.ini",
+    "$recycle.bin",
+    "system volume information",
+];
+
+/// Checks whether a file or directory is hidden.
+/// Names starting with `.` are hidden on every platform, as are files with the hidden attribute on Windows.
+pub fn is_hidden(entry: &DirEntry) -> bool {
+    if entry.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+
+        if let Ok(metadata) = entry.metadata() {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+
+    false
+}
+
+/// Checks whether a file or directory is operating system junk, such as `Thumbs.db`, `.DS_Store`,
+/// or the `._` resource fork files macOS leaves next to images on non-Apple file systems.
+pub fn is_junk(entry: &DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy().to_lowercase();
+    name.starts_with("._") || JUNK_NAMES.contains(&name.as_str())
+}
+
+/// The `.gitignore` and `.splixignore` files of the directories being searched, and of the directories above them
+/// up to the root of the Git repository they're in, each applying to the directory it's in and everything inside.
+pub struct IgnoreRules {
+    /// The directory being searched, as given.
+    root: PathBuf,
+    /// The same directory, resolved, so patterns of directories above it can be matched.
+    resolved: PathBuf,
+    /// Directories above the root whose patterns apply, nearest first.
+    ancestors: Vec<PathBuf>,
+    /// Patterns of each directory read so far.
+    matchers: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreRules {
+    /// Prepares to match the files of a directory against its ignore files.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory being searched.
+    pub fn new(root: &Path) -> Self {
+        let resolved = fs::canonicalize(root).unwrap_or(root.to_path_buf());
+
+        // Patterns above the directory only apply inside a repository, as they would for Git.
+        let ancestors: Vec<PathBuf> = resolved
+            .ancestors()
+            .position(|dir| dir.join(".git").exists())
+            .map(|top| {
+                resolved
+                    .ancestors()
+                    .skip(1)
+                    .take(top)
+                    .map(Path::to_path_buf)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        IgnoreRules {
+            root: root.to_path_buf(),
+            resolved,
+            ancestors,
+            matchers: HashMap::new(),
+        }
+    }
+
+    /// Checks whether a file or directory is matched by an ignore pattern.
+    /// Patterns of the nearest directory win, so a deeper `!pattern` can bring back a file ignored above it.
+    pub fn is_ignored(&mut self, entry: &DirEntry) -> bool {
+        let Ok(relative) = entry.path().strip_prefix(&self.root) else {
+            return false;
+        };
+        let path = self.resolved.join(relative);
+        let is_dir = entry.file_type().is_dir();
+
+        let dirs: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .take(relative.components().count())
+            .map(Path::to_path_buf)
+            .chain(self.ancestors.iter().cloned())
+            .collect();
+        for dir in dirs {
+            let matcher = self
+                .matchers
+                .entry(dir)
+                .or_insert_with_key(|dir| read_ignore_files(dir));
+            let matched = matcher.matched(&path, is_dir);
+            if !matched.is_none() {
+                return matched.is_ignore();
+            }
+        }
+        false
+    }
+}
+
+/// Reads the ignore patterns of a directory, reporting patterns that can't be parsed.
+fn read_ignore_files(dir: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    for name in IGNORE_FILES {
+        let path = dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(err) = builder.add(&path) {
+            eprintln!(
+                "splix: Skipping bad patterns in {}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+
+    builder.build().unwrap_or_else(|err| {
+        eprintln!(
+            "splix: Skipping the ignore files of {}: {}",
+            dir.display(),
+            err
+        );
+        Gitignore::empty()
+    })
+}
@@ -0,0 +1,34 @@
+use crate::split::split_encoded;
+use js_sys::{Array, Uint8Array};
+use std::ops::ControlFlow;
+use wasm_bindgen::prelude::*;
+
+/// Splits an encoded image into equal rows and columns, for use from JavaScript.
+/// Tiles are encoded in the same format as the image, or as PNG if that format can't be written.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded image.
+/// * `rows` - Number of equal rows to split the image into.
+/// * `cols` - Number of equal columns to split the image into.
+///
+/// # Returns
+///
+/// An array of `Uint8Array` tiles, left to right, top to bottom, or an error if the image couldn't be split.
+#[wasm_bindgen]
+pub fn split(bytes: &[u8], rows: u32, cols: u32) -> Result<Array, JsError> {
+    if rows == 0 || cols == 0 {
+        return Err(JsError::new(
+            "splix: The number of rows and columns must be greater than zero",
+        ));
+    }
+
+    let tiles = Array::new();
+    let _ = split_encoded(bytes, &[rows], &[cols], |_, data| {
+        tiles.push(&Uint8Array::from(data));
+        ControlFlow::Continue(())
+    })
+    .map_err(|err| JsError::new(&format!("splix: {}", err)))?;
+
+    Ok(tiles)
+}
@@ -0,0 +1,150 @@
+use crate::resize::{self, Resampler};
+use clap::ValueEnum;
+use image::imageops;
+use image::{DynamicImage, RgbaImage};
+
+/// Opacity of the watermark when none is given.
+const DEFAULT_OPACITY: f32 = 0.5;
+
+/// Where the watermark is placed on each tile.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Position {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// An image composited onto every tile, such as a logo.
+pub struct Watermark {
+    /// The watermark, with its alpha already scaled by the opacity.
+    image: RgbaImage,
+    position: Position,
+}
+
+impl Watermark {
+    /// Opens the watermark for `--watermark`, written as `PATH[:POSITION[:OPACITY]]`.
+    /// The opacity may be a fraction such as `0.3` or a percentage such as `30%`.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - Path of the watermark image, optionally followed by its position and opacity.
+    ///
+    /// # Returns
+    ///
+    /// The watermark, or an error message if the image can't be opened or the opacity isn't valid.
+    pub fn open(spec: &str) -> Result<Self, String> {
+        let mut path = spec;
+        let mut opacity = DEFAULT_OPACITY;
+        let mut position = Position::BottomRight;
+
+        // Options are split off from the end, so paths containing ':' still work.
+        if let Some((rest, value)) = path.rsplit_once(':') {
+            if let Some(value) = parse_opacity(value) {
+                opacity = value?;
+                path = rest;
+            }
+        }
+        if let Some((rest, value)) = path.rsplit_once(':') {
+            if let Ok(value) = Position::from_str(value, true) {
+                position = value;
+                path = rest;
+            }
+        }
+
+        let mut image = image::open(path)
+            .map_err(|err| format!("splix: watermark: {}: {}", path, err))?
+            .into_rgba8();
+        for pixel in image.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+        }
+
+        Ok(Watermark { image, position })
+    }
+
+    /// Composites the watermark onto a tile, shrinking it first if it's larger than the tile.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` - Tile to draw on.
+    /// * `resampler` - How the watermark is resampled if it's shrunk.
+    pub fn apply(&self, tile: &mut DynamicImage, resampler: Resampler) {
+        let (width, height) = (tile.width(), tile.height());
+        let scaled;
+        let image = if self.image.width() > width || self.image.height() > height {
+            scaled = resampler
+                .resize_exact(
+                    &DynamicImage::ImageRgba8(self.image.clone()),
+                    width
+                        .min(self.image.width() * height / self.image.height())
+                        .max(1),
+                    height
+                        .min(self.image.height() * width / self.image.width())
+                        .max(1),
+                )
+                .into_rgba8();
+            &scaled
+        } else {
+            &self.image
+        };
+
+        // Keep a small gap between the watermark and the edges it sits against.
+        let gap = width.min(height) / 50;
+        let free_x = width - image.width();
+        let free_y = height - image.height();
+        let (x, y) = match self.position {
+            Position::TopLeft => (gap, gap),
+            Position::Top => (free_x / 2, gap),
+            Position::TopRight => (free_x.saturating_sub(gap), gap),
+            Position::Left => (gap, free_y / 2),
+            Position::Center => (free_x / 2, free_y / 2),
+            Position::Right => (free_x.saturating_sub(gap), free_y / 2),
+            Position::BottomLeft => (gap, free_y.saturating_sub(gap)),
+            Position::Bottom => (free_x / 2, free_y.saturating_sub(gap)),
+            Position::BottomRight => (free_x.saturating_sub(gap), free_y.saturating_sub(gap)),
+        };
+
+        let (x, y) = (x.min(free_x) as i64, y.min(free_y) as i64);
+        let color = tile.color();
+        if color.bytes_per_pixel() == color.channel_count() {
+            imageops::overlay(tile, image, x, y);
+        } else {
+            // Blending through the tile's 8-bit pixels would round away the precision of deeper tiles.
+            let mut deep = tile.to_rgba32f();
+            imageops::overlay(
+                &mut deep,
+                &DynamicImage::ImageRgba8(image.clone()).into_rgba32f(),
+                x,
+                y,
+            );
+            *tile = resize::to_color_type(DynamicImage::ImageRgba32F(deep), color);
+        }
+    }
+}
+
+/// Parses an opacity such as `0.3` or `30%`.
+///
+/// # Returns
+///
+/// `None` if the value doesn't look like an opacity, or the opacity or an error message
+/// if it's out of range.
+fn parse_opacity(value: &str) -> Option<Result<f32, String>> {
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok()? / 100.0,
+        None => value.trim().parse::<f32>().ok()?,
+    };
+
+    Some(if (0.0..=1.0).contains(&opacity) {
+        Ok(opacity)
+    } else {
+        Err(format!(
+            "splix: watermark: '{}' is not an opacity from 0 to 1 or 0% to 100%",
+            value
+        ))
+    })
+}
@@ -0,0 +1,157 @@
+//! Checks that 16-bit images are split without being reduced to 8 bits.
+
+mod common;
+
+use common::TestDir;
+use image::{ColorType, DynamicImage, ImageBuffer, ImageFormat, Luma, Rgb, Rgba};
+use splix::split;
+use std::ops::ControlFlow;
+
+const WIDTH: u32 = 60;
+const HEIGHT: u32 = 40;
+
+/// A sample whose low byte differs from its high byte, so it can't survive a trip through 8 bits.
+fn sample(x: u32, y: u32, channel: u32) -> u16 {
+    ((x * 1031 + y * 257 + channel * 4099) % 65536) as u16 | 1
+}
+
+/// A 16-bit test image of a color type.
+fn image(color: ColorType) -> DynamicImage {
+    match color {
+        ColorType::L16 => DynamicImage::ImageLuma16(ImageBuffer::from_fn(WIDTH, HEIGHT, |x, y| {
+            Luma([sample(x, y, 0)])
+        })),
+        ColorType::Rgb16 => {
+            DynamicImage::ImageRgb16(ImageBuffer::from_fn(WIDTH, HEIGHT, |x, y| {
+                Rgb([sample(x, y, 0), sample(x, y, 1), sample(x, y, 2)])
+            }))
+        }
+        ColorType::Rgba16 => {
+            DynamicImage::ImageRgba16(ImageBuffer::from_fn(WIDTH, HEIGHT, |x, y| {
+                Rgba([
+                    sample(x, y, 0),
+                    sample(x, y, 1),
+                    sample(x, y, 2),
+                    sample(x, y, 3),
+                ])
+            }))
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Runs splix on an image and checks every tile has the source's exact pixels.
+fn check_split(color: ColorType, format: ImageFormat, ext: &str) {
+    let dir = TestDir::new(&format!("{:?}-{}", color, ext));
+    let source = image(color);
+    let path = dir.join(format!("source.{}", ext));
+    source.save_with_format(&path, format).unwrap();
+
+    let output = common::split(&path, &dir.join("tiles"), &["--rows", "2", "--cols", "3"]);
+    assert!(output.status.success());
+
+    for row in 0..2 {
+        for col in 0..3 {
+            let tile_path = dir.join(format!("tiles/source-r{}c{}.{}", row, col, ext));
+            let tile = image::open(&tile_path).unwrap();
+            let expected = source.crop_imm(col * 20, row * 20, 20, 20);
+            assert_eq!(tile.color(), color, "{}", tile_path.display());
+            assert_eq!(
+                tile.as_bytes(),
+                expected.as_bytes(),
+                "{}",
+                tile_path.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn luma16_png() {
+    check_split(ColorType::L16, ImageFormat::Png, "png");
+}
+
+#[test]
+fn rgb16_png() {
+    check_split(ColorType::Rgb16, ImageFormat::Png, "png");
+}
+
+#[test]
+fn rgba16_png() {
+    check_split(ColorType::Rgba16, ImageFormat::Png, "png");
+}
+
+#[test]
+fn rgb16_tiff() {
+    check_split(ColorType::Rgb16, ImageFormat::Tiff, "tiff");
+}
+
+#[test]
+fn rgba16_tiff() {
+    check_split(ColorType::Rgba16, ImageFormat::Tiff, "tiff");
+}
+
+#[test]
+fn scaled_tiles_stay_16_bit() {
+    for color in [ColorType::L16, ColorType::Rgb16, ColorType::Rgba16] {
+        let dir = TestDir::new(&format!("{:?}-scaled", color));
+        let path = dir.join("source.png");
+        image(color).save(&path).unwrap();
+
+        let output = common::split(
+            &path,
+            &dir.join("tiles"),
+            &["--preset", "emoji", "-r", "2", "-c", "2", "--levels", "2"],
+        );
+        assert!(output.status.success());
+
+        for level in 0..2 {
+            let tile = image::open(dir.join(format!("tiles/{}/source_0_0.png", level))).unwrap();
+            let bits = tile.color().bytes_per_pixel() / tile.color().channel_count() * 8;
+            assert_eq!(bits, 16, "{:?} at level {}", color, level);
+        }
+    }
+}
+
+#[test]
+fn split_encoded_keeps_16_bit() {
+    for color in [ColorType::L16, ColorType::Rgb16, ColorType::Rgba16] {
+        let source = image(color);
+        let mut bytes = Vec::new();
+        source
+            .write_to(&mut std::io::Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+
+        let mut tiles = 0;
+        let flow = split::split_encoded(&bytes, &[2], &[2], |cell, data| {
+            let tile = image::load_from_memory(data).unwrap();
+            let expected = source.crop_imm(cell.x, cell.y, cell.width, cell.height);
+            assert_eq!(tile.color(), color);
+            assert_eq!(tile.as_bytes(), expected.as_bytes());
+            tiles += 1;
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert!(flow.is_continue());
+        assert_eq!(tiles, 4);
+    }
+}
+
+#[cfg(feature = "hdr")]
+#[test]
+fn float_exr_stays_intact() {
+    let dir = TestDir::new("exr");
+    let source = DynamicImage::ImageRgba32F(ImageBuffer::from_fn(WIDTH, HEIGHT, |x, y| {
+        Rgba([x as f32 / 3.0, y as f32 / 7.0, -0.5, 0.25])
+    }));
+    let path = dir.join("source.exr");
+    source.save(&path).unwrap();
+
+    let output = common::split(&path, &dir.join("tiles"), &["--rows", "2", "--cols", "2"]);
+    assert!(output.status.success());
+
+    let tile = image::open(dir.join("tiles/source-r1c1.exr")).unwrap();
+    let expected = source.crop_imm(WIDTH / 2, HEIGHT / 2, WIDTH / 2, HEIGHT / 2);
+    assert_eq!(tile.color(), ColorType::Rgba32F);
+    assert_eq!(tile.as_bytes(), expected.as_bytes());
+}
@@ -0,0 +1,98 @@
+//! Fixtures shared by the integration tests.
+
+// Each test file is its own crate, and not all of them use every fixture.
+#![allow(dead_code)]
+
+use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output};
+
+/// An empty directory for a test's files, removed once the test ends, whether it passed or not.
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    /// # Arguments
+    ///
+    /// * `name` - Name of the test, unique within its file.
+    pub fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!(
+            "splix-{}-{}-{}",
+            env!("CARGO_CRATE_NAME"),
+            process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Saves a test image as a PNG, whatever its extension.
+///
+/// # Arguments
+///
+/// * `path` - Path to save the image to.
+/// * `(width, height)` - Size of the image.
+pub fn save_image(path: &Path, (width, height): (u32, u32)) {
+    let img = DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+        Rgb([x as u8, y as u8, 0])
+    }));
+    img.save_with_format(path, ImageFormat::Png).unwrap();
+}
+
+/// Runs splix on an image, without checking the disk has room for the tiles.
+///
+/// # Arguments
+///
+/// * `image` - Path of the image.
+/// * `output` - Directory to save the tiles to.
+/// * `args` - Options, such as how to split the image.
+///
+/// # Returns
+///
+/// The output of splix, whether it succeeded or not.
+pub fn split(image: &Path, output: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_splix"))
+        .arg(image)
+        .args(["--no-space-check", "-d"])
+        .arg(output)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// The names of the tiles in a directory, sorted, leaving out splix's own files such as its lock.
+///
+/// # Returns
+///
+/// The names, or none if the directory wasn't created.
+pub fn tile_names(output: &Path) -> Vec<OsString> {
+    let mut names: Vec<OsString> = fs::read_dir(output)
+        .map(|entries| {
+            entries
+                .map(|entry| entry.unwrap().file_name())
+                .filter(|name| !name.as_encoded_bytes().starts_with(b"."))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
@@ -0,0 +1,94 @@
+//! Checks that images with unusual names, and outputs with long paths, are split and saved.
+
+mod common;
+
+use common::TestDir;
+use std::ffi::OsString;
+use std::path::Path;
+
+/// Splits an image into 2x2 tiles.
+///
+/// # Returns
+///
+/// The names of the tiles written, leaving out splix's own files such as its lock.
+fn split(image: &Path, output: &Path, args: &[&str]) -> Vec<OsString> {
+    let args = [&["--rows", "2", "--cols", "2"], args].concat();
+    assert!(common::split(image, output, &args).status.success());
+    common::tile_names(output)
+}
+
+#[test]
+fn unicode_names_are_kept() {
+    let dir = TestDir::new("unicode");
+    let image = dir.join("日本語 写真 📷.png");
+    common::save_image(&image, (20, 10));
+
+    let names = split(&image, &dir.join("tiles"), &[]);
+    assert_eq!(names.len(), 4);
+    assert!(names.contains(&OsString::from("日本語 写真 📷-r1c1.png")));
+}
+
+#[cfg(unix)]
+#[test]
+fn control_characters_are_replaced() {
+    let dir = TestDir::new("control");
+    let image = dir.join("line\nbreak\u{202E}gnp.png");
+    common::save_image(&image, (20, 10));
+
+    let names = split(&image, &dir.join("tiles"), &[]);
+    assert!(names.contains(&OsString::from("line_break_gnp-r0c0.png")));
+}
+
+#[cfg(unix)]
+#[test]
+fn invalid_unicode_is_escaped() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = TestDir::new("invalid");
+    let image = dir.join(std::ffi::OsStr::from_bytes(b"caf\xE9.png"));
+    common::save_image(&image, (20, 10));
+
+    let names = split(&image, &dir.join("tiles"), &[]);
+    assert!(names.contains(&OsString::from("caf%E9-r0c0.png")));
+}
+
+#[cfg(unix)]
+#[test]
+fn portable_names_follow_windows_rules() {
+    let dir = TestDir::new("portable");
+    let image = dir.join("what? a:b.png");
+    common::save_image(&image, (20, 10));
+
+    let names = split(&image, &dir.join("portable"), &["--sanitize", "portable"]);
+    assert!(names.contains(&OsString::from("what_ a_b-r0c0.png")));
+    let names = split(&image, &dir.join("host"), &[]);
+    assert!(names.contains(&OsString::from("what? a:b-r0c0.png")));
+}
+
+#[test]
+fn long_names_are_shortened() {
+    let dir = TestDir::new("long-name");
+    // Short enough to save, but too long once the tile's row and column are added.
+    let image = dir.join(format!("{}.png", "a".repeat(250)));
+    common::save_image(&image, (20, 10));
+
+    let names = split(&image, &dir.join("tiles"), &[]);
+    assert_eq!(names.len(), 4);
+    assert!(names.iter().all(|name| name.len() <= 255));
+}
+
+#[test]
+#[cfg(windows)]
+fn long_output_paths_are_saved() {
+    let dir = TestDir::new("long");
+    let image = dir.join("source.png");
+    common::save_image(&image, (20, 10));
+
+    // Well past the 260 characters Windows allows without long path support.
+    let output = (0..12).fold(dir.join("tiles"), |path, depth| {
+        path.join(format!("level-{:02}-of-a-deep-recursive-split", depth))
+    });
+    assert!(output.as_os_str().len() > 400);
+    let names = split(&image, &output, &[]);
+    assert_eq!(names.len(), 4);
+}
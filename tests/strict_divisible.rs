@@ -0,0 +1,55 @@
+//! Checks which images `--strict-divisible` accepts for specs with pixel sizes and `auto`.
+
+mod common;
+
+use common::TestDir;
+use std::process::Output;
+
+/// Splits a 100x60 image into rows with `--strict-divisible`.
+///
+/// # Returns
+///
+/// The output of splix, and the heights of the tiles written, top to bottom.
+fn split_rows(name: &str, rows: &str) -> (Output, Vec<u32>) {
+    let dir = TestDir::new(name);
+    let image = dir.join("source.png");
+    common::save_image(&image, (100, 60));
+
+    let tiles = dir.join("tiles");
+    let output = common::split(&image, &tiles, &["-r", rows, "--strict-divisible"]);
+    let heights = common::tile_names(&tiles)
+        .iter()
+        .map(|name| image::image_dimensions(tiles.join(name)).unwrap().1)
+        .collect();
+    (output, heights)
+}
+
+#[test]
+fn pixel_bands_are_not_counted_as_sections() {
+    let (output, heights) = split_rows("pixels-auto-pixels", "20px,auto,20px");
+    assert!(output.status.success());
+    assert_eq!(heights, [20, 20, 20]);
+
+    let (output, heights) = split_rows("pixels-auto", "10px,auto");
+    assert!(output.status.success());
+    assert_eq!(heights, [10, 50]);
+}
+
+#[test]
+fn rest_after_pixel_bands_must_divide_evenly() {
+    let (output, heights) = split_rows("pixels-auto-auto", "7px,auto,auto");
+    assert!(!output.status.success());
+    assert!(heights.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "leaving 100x53 besides the pixel bands, which doesn't divide evenly into 2 row"
+        ),
+        "{}",
+        stderr
+    );
+
+    let (output, heights) = split_rows("auto-relative", "auto,2");
+    assert!(output.status.success());
+    assert_eq!(heights, [20, 40]);
+}